@@ -0,0 +1,5 @@
+// HoloSelf OS — headless CLI for scripts, cron and CI
+
+fn main() -> std::process::ExitCode {
+    holoself_os_lib::run_cli()
+}
@@ -0,0 +1,539 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::settings;
+use crate::commands::{vitamin_d, voice};
+use crate::services::agent::AgentService;
+use crate::services::clock::{SharedClock, SystemClock};
+use crate::services::context::ServiceContext;
+use crate::services::health::HealthService;
+use crate::services::labs::LabService;
+use crate::services::open_meteo::OpenMeteoClient;
+use crate::services::secrets;
+use crate::services::simulation::{self, SimulationOptions};
+use crate::services::time;
+
+// Headless access to the app's data for scripts, cron and CI.
+//
+// Every run opens the same database as the app (migrating it the same way), reads
+// settings.json and API keys from the app's config directory without changing anything
+// there, performs one operation and prints the result: plain text by default, JSON with --json. It can run next to the app;
+// SQLite's WAL mode and busy timeout serialize the writes. Writes are audited with source
+// "cli". Failures exit with 1, usage errors with 2.
+
+/// Bundle identifier from tauri.conf.json; the app keeps its data under it
+const APP_IDENTIFIER: &str = "com.holoself.os";
+
+/// Days the timeline goes back when --from is omitted
+const DEFAULT_TIMELINE_DAYS: i64 = 7;
+
+const USAGE: &str = "\
+holoself-cli [--data-dir DIR] [--config-dir DIR] [--json] [--check-integrity] <comando> [opções]
+
+Comandos:
+  log-supplement --name NOME --dosage DOSE [--category CAT] [--at DATA] [--notes TEXTO]
+  log-vital --type TIPO --value VALOR --unit UNIDADE [--at DATA] [--source ORIGEM]
+  import-labs FICHEIRO.pdf...
+  timeline [--from DATA] [--to DATA] [--types a,b] [--vital-types a,b] [--limit N] [--cursor C]
+  schedule [--save]
+  vitamin-d [--uv ÍNDICE] [--date AAAA-MM-DD]
+  transcribe ÁUDIO [--language pt] [--remember]
+  agent
+  simulate [--start AAAA-MM-DD] [--days N] [--seed N] [--adherence 0-1]
+
+Sem --data-dir/--config-dir são usadas as pastas da aplicação. --check-integrity verifica a
+base de dados antes do comando e falha se estiver danificada. `simulate` só lê as definições:
+corre numa base de dados em memória e nunca toca nos registos.";
+
+/// Flags without a value
+const SWITCHES: [&str; 5] = ["json", "save", "remember", "help", "check-integrity"];
+/// Flags every command accepts
+const GLOBAL_FLAGS: [&str; 5] = ["data-dir", "config-dir", "json", "help", "check-integrity"];
+
+/// Parsed command line: one command, positional arguments and `--flag [value]` pairs
+#[derive(Debug, Default)]
+struct Args {
+    command: Option<String>,
+    positional: Vec<String>,
+    flags: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    fn parse(raw: Vec<String>) -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut raw = raw.into_iter();
+        while let Some(arg) = raw.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                let (name, value) = match flag.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None if SWITCHES.contains(&flag) => (flag.to_string(), None),
+                    None => {
+                        let value = raw.next().ok_or_else(|| format!("Falta o valor de --{}", flag))?;
+                        (flag.to_string(), Some(value))
+                    }
+                };
+                args.flags.push((name, value));
+            } else if args.command.is_none() {
+                args.command = Some(arg);
+            } else {
+                args.positional.push(arg);
+            }
+        }
+        Ok(args)
+    }
+
+    /// Refuse flags the command doesn't know, so a typo in a cron line fails loudly
+    fn allow(&self, flags: &[&str]) -> Result<(), String> {
+        match self.flags.iter().find(|(name, _)| !flags.contains(&name.as_str()) && !GLOBAL_FLAGS.contains(&name.as_str())) {
+            Some((name, _)) => Err(format!("Opção desconhecida para este comando: --{}", name)),
+            None => Ok(()),
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.flags.iter().rev().find(|(n, _)| n == name).and_then(|(_, v)| v.as_deref())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.value(name).ok_or_else(|| format!("Falta --{}", name))
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.flags.iter().any(|(n, _)| n == name)
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name)
+            .map(|raw| raw.parse().map_err(|_| format!("Valor inválido para --{}: '{}'", name, raw)))
+            .transpose()
+    }
+
+    fn list(&self, name: &str) -> Option<Vec<String>> {
+        self.value(name).map(|raw| raw.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+    }
+
+    fn dir(&self, name: &str, default: Option<PathBuf>) -> Result<PathBuf, String> {
+        self.value(name)
+            .map(PathBuf::from)
+            .or_else(|| default.map(|d| d.join(APP_IDENTIFIER)))
+            .ok_or_else(|| format!("Pasta da aplicação desconhecida neste sistema: indique --{}", name))
+    }
+}
+
+/// Parse arguments, run one command and map the outcome to an exit code
+pub fn run(raw: Vec<String>) -> ExitCode {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).try_init();
+
+    let args = match Args::parse(raw) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    if args.switch("help") || args.command.is_none() {
+        println!("{}", USAGE);
+        return if args.command.is_none() && !args.switch("help") { ExitCode::from(2) } else { ExitCode::SUCCESS };
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Erro: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match runtime.block_on(dispatch(&args, std::sync::Arc::new(SystemClock))) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(e)) => usage_error(&e),
+        Err(Failure::Run(e)) => {
+            eprintln!("Erro: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+enum Failure {
+    Usage(String),
+    Run(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Run(message)
+    }
+}
+
+/// What every command works against
+struct Context {
+    services: ServiceContext,
+    json: bool,
+}
+
+impl Context {
+    fn open(args: &Args, clock: SharedClock) -> Result<Self, String> {
+        let config_dir = args.dir("config-dir", dirs::config_dir())?;
+        let data_dir = args.dir("data-dir", dirs::data_dir())?;
+        // Read-only on config: no dirs or key files created, no env changes, nothing renamed
+        match secrets::init_read_only(&config_dir) {
+            Ok(backend) => log::info!("Secret store backend: {}", backend),
+            Err(e) => log::warn!("Secret store unavailable: {}", e),
+        }
+        let settings = settings::load_settings_from(&config_dir);
+        let tz = settings::user_timezone(&settings);
+        // One short-lived process: reads share the writer
+        let state = crate::open_database(&data_dir, tz, 0, clock.now())?;
+        // A full scan is too slow for every cron run; here damage is fatal
+        if args.switch("check-integrity") {
+            state.write_blocking(|db| db.check_integrity())?;
+        }
+        Ok(Context { services: ServiceContext::live(state, clock, settings), json: args.switch("json") })
+    }
+
+    fn now(&self) -> String {
+        time::format_utc(self.services.clock.now())
+    }
+
+    /// --at read in the user's zone, or now
+    fn at(&self, args: &Args) -> Result<String, Failure> {
+        match args.value("at") {
+            Some(raw) => time::normalize(raw, self.services.tz()).map_err(Failure::Usage),
+            None => Ok(self.now()),
+        }
+    }
+
+    /// Print `value` as JSON, or as the text `render` makes of it
+    fn print<T: Serialize>(&self, value: &T, render: impl FnOnce(&T) -> String) -> Result<(), String> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(value).map_err(|e| e.to_string())?);
+        } else {
+            println!("{}", render(value));
+        }
+        Ok(())
+    }
+}
+
+/// Run the parsed command; every timestamp it writes comes from `clock`
+async fn dispatch(args: &Args, clock: SharedClock) -> Result<(), Failure> {
+    let command = args.command.as_deref().unwrap_or_default();
+    let allowed: &[&str] = match command {
+        "log-supplement" => &["name", "dosage", "category", "at", "notes"],
+        "log-vital" => &["type", "value", "unit", "at", "source"],
+        "import-labs" | "agent" => &[],
+        "timeline" => &["from", "to", "types", "vital-types", "limit", "cursor"],
+        "schedule" => &["save"],
+        "vitamin-d" => &["uv", "date"],
+        "transcribe" => &["language", "remember"],
+        "simulate" => &["start", "days", "seed", "adherence"],
+        other => return Err(Failure::Usage(format!("Comando desconhecido: {}", other))),
+    };
+    args.allow(allowed).map_err(Failure::Usage)?;
+    let needs_files = matches!(command, "import-labs" | "transcribe");
+    if needs_files && args.positional.is_empty() {
+        return Err(Failure::Usage(format!("{}: indique pelo menos um ficheiro", command)));
+    }
+    if !needs_files && !args.positional.is_empty() {
+        return Err(Failure::Usage(format!("Argumento inesperado: {}", args.positional[0])));
+    }
+
+    if command == "simulate" {
+        return simulate(args, clock).await;
+    }
+    let ctx = Context::open(args, clock)?;
+    match command {
+        "log-supplement" => log_supplement(&ctx, args).await,
+        "log-vital" => log_vital(&ctx, args).await,
+        "import-labs" => import_labs(&ctx, args).await,
+        "timeline" => timeline(&ctx, args).await,
+        "schedule" => schedule(&ctx, args).await,
+        "vitamin-d" => vitamin_d_recommendation(&ctx, args).await,
+        "transcribe" => transcribe(&ctx, args).await,
+        _ => run_agent(&ctx).await,
+    }
+}
+
+async fn log_supplement(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let entry = SupplementEntry {
+        id: None,
+        name: args.required("name").map_err(Failure::Usage)?.to_string(),
+        dosage: args.required("dosage").map_err(Failure::Usage)?.to_string(),
+        taken_at: ctx.at(args)?,
+        category: args.value("category").unwrap_or("as_needed").to_string(),
+        notes: args.value("notes").map(str::to_string),
+    };
+    let name = entry.name.clone();
+    let id = HealthService::new(&ctx.services).log_supplement(entry, "cli").await?;
+    Ok(ctx.print(&serde_json::json!({ "id": id }), |_| format!("{} registado (id {}).", name, id))?)
+}
+
+async fn log_vital(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let value = args.number::<f64>("value").map_err(Failure::Usage)?
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| Failure::Usage("Falta --value (número)".to_string()))?;
+    let entry = VitalEntry {
+        id: None,
+        vital_type: args.required("type").map_err(Failure::Usage)?.to_string(),
+        value,
+        unit: args.required("unit").map_err(Failure::Usage)?.to_string(),
+        recorded_at: ctx.at(args)?,
+        source: args.value("source").unwrap_or("manual").to_string(),
+    };
+    let label = format!("{} = {} {}", entry.vital_type, entry.value, entry.unit);
+    let id = HealthService::new(&ctx.services).log_vital(entry, "cli").await?;
+    Ok(ctx.print(&serde_json::json!({ "id": id }), |_| format!("{} registado (id {}).", label, id))?)
+}
+
+/// Import every file; one bad PDF doesn't stop the rest, but fails the run
+async fn import_labs(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let labs = LabService::new(&ctx.services);
+    let mut failed = 0;
+    for file in &args.positional {
+        match labs.import_pdf(file).await {
+            Ok(result) => ctx.print(&result, |r| {
+                let mut lines = vec![format!(
+                    "{}: {} marcador(es){}",
+                    file,
+                    r.markers.len(),
+                    r.date.as_deref().map(|d| format!(" de {}", d)).unwrap_or_default()
+                )];
+                lines.extend(r.markers.iter().map(|m| format!(
+                    "  {} {} {} (ref. {}) — {}", m.marker, m.value, m.unit, m.reference_range, m.status
+                )));
+                lines.join("\n")
+            })?,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        n => Err(Failure::Run(format!("{} de {} ficheiro(s) falharam", n, args.positional.len()))),
+    }
+}
+
+/// Scalar payload fields as `key=value`, for one-line events
+fn summarize(payload: &Value) -> String {
+    let Some(fields) = payload.as_object() else { return payload.to_string() };
+    fields.iter()
+        .filter(|(key, value)| *key != "id" && !value.is_null() && !value.is_object() && !value.is_array())
+        .map(|(key, value)| match value {
+            Value::String(s) => format!("{}={}", key, s),
+            other => format!("{}={}", key, other),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn timeline(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let health = HealthService::new(&ctx.services);
+    let today = ctx.services.clock.today(ctx.services.tz());
+    let from = args.value("from").map(str::to_string)
+        .unwrap_or_else(|| (today - chrono::Duration::days(DEFAULT_TIMELINE_DAYS)).format("%Y-%m-%d").to_string());
+    let to = args.value("to").map(str::to_string).unwrap_or_else(|| today.format("%Y-%m-%d").to_string());
+    let limit = args.number::<u32>("limit").map_err(Failure::Usage)?;
+    let query = health.timeline_query(
+        &from, &to, args.list("types").as_deref(), args.list("vital-types"), args.value("cursor"), limit,
+    ).map_err(Failure::Usage)?;
+
+    let page = health.timeline(query).await?;
+    Ok(ctx.print(&page, |page| {
+        let mut lines: Vec<String> = page.events.iter()
+            .map(|e| format!("{}  {:<16} {}", e.timestamp, e.event_type, summarize(&e.payload)))
+            .collect();
+        if lines.is_empty() {
+            lines.push("Sem eventos neste período.".to_string());
+        }
+        if let Some(cursor) = &page.next_cursor {
+            lines.push(format!("… mais eventos: --cursor '{}'", cursor));
+        }
+        lines.join("\n")
+    })?)
+}
+
+/// Predicted exams; with --save, those not already pending are added to the schedule
+async fn schedule(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let health = HealthService::new(&ctx.services);
+    let exams = health.predict_exams().await?;
+    let mut saved = Vec::new();
+    if args.switch("save") {
+        let pending: Vec<String> = ctx.services.db.read(|db| db.get_upcoming_exams()).await?
+            .into_iter()
+            .map(|(_, exam_type, ..)| exam_type)
+            .collect();
+        for exam in exams.iter().filter(|e| !pending.contains(&e.exam_type)) {
+            health.store_exam(exam.clone(), "cli").await?;
+            saved.push(exam.exam_type.clone());
+        }
+    }
+    let report = serde_json::json!({ "exams": exams, "saved": saved });
+    Ok(ctx.print(&report, |_| {
+        let mut lines: Vec<String> = exams.iter()
+            .map(|e| format!("{}  {} — {}", e.scheduled_date, e.exam_type, e.reason))
+            .collect();
+        if lines.is_empty() {
+            lines.push("Nenhum exame previsto.".to_string());
+        }
+        if args.switch("save") {
+            lines.push(format!("{} exame(s) novo(s) agendado(s).", saved.len()));
+        }
+        lines.join("\n")
+    })?)
+}
+
+async fn vitamin_d_recommendation(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    let settings = &ctx.services.config.settings;
+    let uv_index = match args.number::<f64>("uv").map_err(Failure::Usage)? {
+        Some(uv) => uv,
+        None => OpenMeteoClient::new(ctx.services.http.clone()).current_uv_index(settings.latitude, settings.longitude).await?,
+    };
+    let rec = vitamin_d::recommend(&ctx.services.db, settings, uv_index, args.value("date"), ctx.services.clock.now()).await?;
+    Ok(ctx.print(&rec, |r| format!(
+        "UV {:.1}: {} min de sol{}, meio-dia solar às {}. D3 se faltar sol: {} UI.\n{}",
+        r.uv_index,
+        r.optimal_minutes,
+        r.best_window.as_deref().map(|w| format!(" entre {}", w)).unwrap_or_default(),
+        r.solar_noon,
+        r.d3_iu_supplement,
+        r.note
+    ))?)
+}
+
+/// Transcribe files in order; --remember stores each transcript as voice input for `agent`
+async fn transcribe(ctx: &Context, args: &Args) -> Result<(), Failure> {
+    for file in &args.positional {
+        let tools = ctx.services.config.tools.clone();
+        let transcript = voice::transcribe_file(tools, file.clone(), args.value("language").map(str::to_string)).await?;
+        if args.switch("remember") && !transcript.trim().is_empty() {
+            AgentService::new(&ctx.services).remember_voice_input(transcript.clone()).await?;
+        }
+        ctx.print(&serde_json::json!({ "file": file, "transcript": transcript }), |_| transcript.clone())?;
+    }
+    Ok(())
+}
+
+/// One agent turn, as the app's poll would run it with the user at the screen
+async fn run_agent(ctx: &Context) -> Result<(), Failure> {
+    let message = AgentService::new(&ctx.services).next_message(false).await?;
+    Ok(ctx.print(&message, |m| match m {
+        Some(m) => format!("[{}/{}] {}", m.category, m.priority, m.text),
+        None => "Sem mensagem.".to_string(),
+    })?)
+}
+
+/// Replay synthetic days against the agent; the transcript goes to stdout for diffing
+async fn simulate(args: &Args, clock: SharedClock) -> Result<(), Failure> {
+    let config_dir = args.dir("config-dir", dirs::config_dir())?;
+    let settings = settings::load_settings_from(&config_dir);
+    let start = match args.value("start") {
+        Some(raw) => NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .map_err(|_| Failure::Usage(format!("Data inválida: '{}'", raw)))?,
+        None => clock.today(settings::user_timezone(&settings)),
+    };
+    let defaults = SimulationOptions::new(start);
+    let options = SimulationOptions {
+        days: args.number("days").map_err(Failure::Usage)?.unwrap_or(defaults.days),
+        seed: args.number("seed").map_err(Failure::Usage)?.unwrap_or(defaults.seed),
+        adherence: args.number("adherence").map_err(Failure::Usage)?.unwrap_or(defaults.adherence),
+        ..defaults
+    };
+    let transcript = simulation::run(settings, &options).await?;
+    if args.switch("json") {
+        println!("{}", serde_json::to_string_pretty(&transcript).map_err(|e| e.to_string())?);
+    } else {
+        println!("{}", transcript.render());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// A fresh path under the temp dir, not created
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("holoself-cli-{}-{}", name, uuid::Uuid::new_v4().simple()))
+    }
+
+    fn with_dirs(raw: &[&str], data: &std::path::Path, config: &std::path::Path) -> Vec<String> {
+        let mut all = args(raw);
+        all.extend(["--data-dir".to_string(), data.display().to_string(), "--config-dir".to_string(), config.display().to_string()]);
+        all
+    }
+
+    #[test]
+    fn parses_commands_flags_switches_and_files() {
+        let parsed = Args::parse(args(&["--json", "import-labs", "a.pdf", "--config-dir=/tmp/x", "b.pdf", "--limit", "5", "--limit", "6"])).unwrap();
+        assert_eq!(parsed.command.as_deref(), Some("import-labs"));
+        assert_eq!(parsed.positional, ["a.pdf", "b.pdf"]);
+        assert!(parsed.switch("json") && !parsed.switch("save"));
+        assert_eq!(parsed.value("config-dir"), Some("/tmp/x"));
+        // The last occurrence wins
+        assert_eq!(parsed.number::<u32>("limit").unwrap(), Some(6));
+        assert_eq!(parsed.required("name").unwrap_err(), "Falta --name");
+
+        let parsed = Args::parse(args(&["timeline", "--limit", "cinco", "--types", "vital, ,supplement"])).unwrap();
+        assert_eq!(parsed.number::<u32>("limit").unwrap_err(), "Valor inválido para --limit: 'cinco'");
+        assert_eq!(parsed.list("types").unwrap(), ["vital", "supplement"]);
+
+        assert_eq!(Args::parse(args(&["timeline", "--from"])).unwrap_err(), "Falta o valor de --from");
+    }
+
+    #[test]
+    fn commands_refuse_flags_they_do_not_know() {
+        let parsed = Args::parse(args(&["timeline", "--json", "--data-dir", "/d", "--form", "2026-07-01"])).unwrap();
+        assert_eq!(parsed.allow(&["from", "to"]).unwrap_err(), "Opção desconhecida para este comando: --form");
+        // Global flags are accepted everywhere
+        assert!(parsed.allow(&["form"]).is_ok());
+    }
+
+    #[test]
+    fn usage_errors_exit_with_2_and_failures_with_1() {
+        let (data, config) = (temp_path("data"), temp_path("config"));
+        assert_eq!(run(args(&[])), ExitCode::from(2));
+        assert_eq!(run(args(&["--help"])), ExitCode::SUCCESS);
+        assert_eq!(run(args(&["frobnicate"])), ExitCode::from(2));
+        assert_eq!(run(args(&["timeline", "--form", "2026-07-01"])), ExitCode::from(2));
+        assert_eq!(run(args(&["import-labs"])), ExitCode::from(2));
+        assert_eq!(run(args(&["agent", "extra"])), ExitCode::from(2));
+        assert_eq!(run(with_dirs(&["simulate", "--start", "01/07/2026"], &data, &config)), ExitCode::from(2));
+        assert_eq!(run(with_dirs(&["simulate", "--days", "muitos"], &data, &config)), ExitCode::from(2));
+
+        let vital = ["log-vital", "--type", "heart_rate", "--value", "61", "--unit", "bpm", "--at"];
+        assert_eq!(run(with_dirs(&[&vital[..], &["ontem"]].concat(), &data, &config)), ExitCode::from(2));
+        assert_eq!(run(with_dirs(&[&vital[..], &["2026-07-01T08:00"]].concat(), &data, &config)), ExitCode::SUCCESS);
+
+        // Reaching the command and failing there is a runtime error
+        let missing = data.join("missing.pdf");
+        assert_eq!(run(with_dirs(&["import-labs", missing.to_str().unwrap()], &data, &config)), ExitCode::FAILURE);
+        std::fs::remove_dir_all(data).unwrap();
+    }
+
+    #[test]
+    fn leaves_the_config_dir_as_it_found_it() {
+        let data = temp_path("data");
+        let missing = temp_path("config");
+        let supplement = ["log-supplement", "--name", "Winfit", "--dosage", "1"];
+        assert_eq!(run(with_dirs(&supplement, &data, &missing)), ExitCode::SUCCESS);
+        assert!(!missing.exists());
+
+        let config = temp_path("config");
+        std::fs::create_dir_all(&config).unwrap();
+        std::fs::write(config.join("settings.json"), "{\"skin_type\": ").unwrap();
+        assert_eq!(run(with_dirs(&supplement, &data, &config)), ExitCode::SUCCESS);
+        let left: Vec<_> = std::fs::read_dir(&config).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(left, ["settings.json"]);
+        std::fs::remove_dir_all(config).unwrap();
+        std::fs::remove_dir_all(data).unwrap();
+    }
+}
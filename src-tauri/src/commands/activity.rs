@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::settings;
+use crate::db::DbState;
+use crate::services::time;
+
+/// Continuous stretch in front of the screen (webcam presence detector)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresenceSession {
+    pub id: Option<i64>,
+    pub started_at: String,       // ISO 8601 datetime, stored as UTC
+    pub ended_at: Option<String>, // None while the session is still open
+    pub source: Option<String>,   // webcam (default) | keyboard
+}
+
+/// Time away from the screen between two presence sessions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BreakEntry {
+    pub id: Option<i64>,
+    pub started_at: String,
+    pub ended_at: String,
+    pub reason: Option<String>, // away | manual
+}
+
+/// Smoothed posture score (0-100) from the webcam posture monitor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostureSample {
+    pub recorded_at: String,
+    pub score: u8,
+    pub head_x: Option<f64>, // normalized 0-1
+    pub head_y: Option<f64>,
+}
+
+/// Open or close a presence session. Send without `id` when the user sits down,
+/// then again with the returned `id` and `ended_at` when they leave.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_presence_session(
+    state: State<'_, DbState>,
+    mut session: PresenceSession,
+    app_handle: tauri::AppHandle,
+) -> Result<i64, String> {
+    let tz = settings::current_timezone(&app_handle);
+    session.started_at = time::normalize(&session.started_at, tz)?;
+    if let Some(ended_at) = &session.ended_at {
+        let ended_at = time::normalize(ended_at, tz)?;
+        if ended_at < session.started_at {
+            return Err("Sessão termina antes de começar.".to_string());
+        }
+        session.ended_at = Some(ended_at);
+    }
+
+    state.write(move |db| db.upsert_presence_session(&session)).await
+}
+
+/// Record a completed break
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_break(
+    state: State<'_, DbState>,
+    mut entry: BreakEntry,
+    app_handle: tauri::AppHandle,
+) -> Result<i64, String> {
+    let tz = settings::current_timezone(&app_handle);
+    entry.started_at = time::normalize(&entry.started_at, tz)?;
+    entry.ended_at = time::normalize(&entry.ended_at, tz)?;
+    if entry.ended_at <= entry.started_at {
+        return Err("Pausa termina antes de começar.".to_string());
+    }
+
+    state.write(move |db| db.insert_break(&entry)).await
+}
+
+/// Record a batch of posture samples (the frontend flushes periodically)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_posture_samples(
+    state: State<'_, DbState>,
+    mut samples: Vec<PostureSample>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    let tz = settings::current_timezone(&app_handle);
+    for sample in &mut samples {
+        if sample.score > 100 {
+            return Err(format!("Pontuação de postura inválida: {}", sample.score));
+        }
+        sample.recorded_at = time::normalize(&sample.recorded_at, tz)?;
+    }
+
+    state.write(move |db| db.insert_posture_samples(&samples)).await
+}
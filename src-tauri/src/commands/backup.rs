@@ -0,0 +1,372 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(feature = "gui")]
+use tauri::{Manager, State};
+use crate::commands::settings::{self, AppSettings};
+use crate::db::{self, Database, DbState};
+use crate::services::backup::{self as archive, Manifest, ManifestFile};
+use crate::services::{secrets, time};
+
+const SCHEDULER_TICK_SECS: u64 = 15 * 60;
+const FILE_PREFIX: &str = "holoself-backup-";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Serializes backups and restores; remembers the last scheduled failure for the UI
+#[derive(Default)]
+pub struct BackupState {
+    busy: Mutex<()>,
+    last_error: Mutex<Option<String>>,
+}
+
+/// A backup file in the backup directory
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub created_at: Option<String>, // from the file name
+    pub reason: String,             // manual | scheduled | pre-restore
+    pub size_bytes: u64,
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupStatus {
+    pub directory: String,
+    pub passphrase_set: bool,
+    pub last_error: Option<String>,
+    pub backups: Vec<BackupInfo>, // newest first
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreSummary {
+    pub created_at: String,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub files: Vec<String>,
+    pub safety_backup: String, // state just before the restore
+}
+
+#[cfg(feature = "gui")]
+fn app_dirs(app_handle: &tauri::AppHandle) -> Result<(PathBuf, PathBuf), String> {
+    let data = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let config = app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok((data, config))
+}
+
+#[cfg(feature = "gui")]
+fn backup_dir(app_handle: &tauri::AppHandle, settings: &AppSettings) -> Result<PathBuf, String> {
+    let dir = if settings.backup_dir.is_empty() {
+        app_dirs(app_handle)?.0.join("backups")
+    } else {
+        PathBuf::from(&settings.backup_dir)
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Falha ao criar {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+/// Passphrase for a backup: an explicit one wins, else the stored one when encryption is on
+fn resolve_passphrase(explicit: Option<String>, settings: &AppSettings) -> Result<Option<String>, String> {
+    match explicit.filter(|p| !p.trim().is_empty()) {
+        Some(passphrase) => Ok(Some(passphrase)),
+        None if settings.backup_encrypt => secrets::get(secrets::BACKUP_PASSPHRASE)
+            .map(Some)
+            .ok_or_else(|| "Cópias cifradas ativas, mas sem palavra-passe definida.".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// "holoself-backup-20261018-031500-scheduled.hsbak" → (created_at, reason)
+fn parse_file_name(name: &str) -> Option<(String, String)> {
+    let stem = name.strip_prefix(FILE_PREFIX)?.strip_suffix(&format!(".{}", archive::EXTENSION))?;
+    let (stamp, reason) = (stem.get(..15)?, stem.get(16..)?);
+    let created = NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S").ok()?;
+    Some((time::format_utc(created.and_utc()), reason.to_string()))
+}
+
+fn list_backups(dir: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut backups: Vec<BackupInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == archive::EXTENSION))
+        .map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (created_at, reason) = parse_file_name(&name)
+                .map_or((None, "manual".to_string()), |(created, reason)| (Some(created), reason));
+            BackupInfo {
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                encrypted: archive::is_encrypted(&path).unwrap_or(false),
+                path: path.to_string_lossy().into_owned(),
+                created_at,
+                reason,
+            }
+        })
+        .collect();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.path.cmp(&a.path)));
+    backups
+}
+
+/// Snapshot the database, copy settings and .env, and write one archive to `dest`
+/// (default: the backup directory). The caller holds `BackupState::busy`.
+#[cfg(feature = "gui")]
+fn write_backup(
+    app_handle: &tauri::AppHandle,
+    reason: &str,
+    dest: Option<PathBuf>,
+    passphrase: Option<&str>,
+) -> Result<BackupInfo, String> {
+    let settings = settings::load_settings(app_handle)?;
+    let (data_dir, config_dir) = app_dirs(app_handle)?;
+    let now = super::system::clock(app_handle).now();
+    let dest = match dest {
+        Some(dest) => dest,
+        None => backup_dir(app_handle, &settings)?.join(format!(
+            "{}{}-{}.{}", FILE_PREFIX, now.format("%Y%m%d-%H%M%S"), reason, archive::EXTENSION
+        )),
+    };
+
+    // Everything is staged first so files can't change between checksum and archive
+    let staging = data_dir.join(format!("backup-staging-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+    let result = (|| {
+        let mut files = vec![(archive::DB_FILE, staging.join(archive::DB_FILE))];
+        Database::snapshot(&data_dir.join(db::DB_FILE_NAME), &files[0].1)
+            .map_err(|e| format!("Falha ao copiar a base de dados: {}", e))?;
+        for name in [archive::SETTINGS_FILE, archive::ENV_FILE] {
+            let source = config_dir.join(name);
+            if source.exists() {
+                std::fs::copy(&source, staging.join(name)).map_err(|e| format!("Falha ao copiar {}: {}", name, e))?;
+                files.push((name, staging.join(name)));
+            }
+        }
+
+        let (schema_version, _) = Database::inspect(&files[0].1).map_err(|e| e.to_string())?;
+        let mut manifest = Manifest {
+            format: archive::ARCHIVE_FORMAT,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: time::format_utc(now),
+            schema_version,
+            reason: reason.to_string(),
+            files: Vec::new(),
+        };
+        for (name, path) in &files {
+            let (size, sha256) = archive::checksum(path).map_err(|e| e.to_string())?;
+            manifest.files.push(ManifestFile { name: name.to_string(), size, sha256 });
+        }
+        archive::write_archive(&dest, &manifest, &files, passphrase)
+    })();
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        log::warn!("Failed to remove backup staging dir: {}", e);
+    }
+
+    Ok(BackupInfo {
+        size_bytes: result?,
+        encrypted: passphrase.is_some(),
+        path: dest.to_string_lossy().into_owned(),
+        created_at: Some(time::format_utc(now)),
+        reason: reason.to_string(),
+    })
+}
+
+/// Delete scheduled backups beyond the newest `keep`; manual ones are never touched
+fn prune(dir: &Path, keep: u32) {
+    let scheduled = list_backups(dir).into_iter().filter(|b| b.reason == "scheduled");
+    for old in scheduled.skip(keep as usize) {
+        match std::fs::remove_file(&old.path) {
+            Ok(()) => log::info!("Removed old backup {}", old.path),
+            Err(e) => log::warn!("Failed to remove old backup {}: {}", old.path, e),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+fn restore(app_handle: &tauri::AppHandle, src: &Path, passphrase: Option<&str>) -> Result<RestoreSummary, String> {
+    let (data_dir, config_dir) = app_dirs(app_handle)?;
+    let staging = data_dir.join(format!("restore-staging-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+    let result = (|| {
+        let manifest = archive::extract_archive(src, passphrase, &staging, |manifest| {
+            if !manifest.has(archive::DB_FILE) {
+                return Err("A cópia não contém a base de dados.".to_string());
+            }
+            if manifest.schema_version > db::CURRENT_SCHEMA_VERSION {
+                return Err(format!(
+                    "Esta cópia vem de uma versão mais recente do HoloSelf ({}, esquema v{}). Atualize a aplicação antes de restaurar.",
+                    manifest.app_version, manifest.schema_version
+                ));
+            }
+            Ok(())
+        })?;
+
+        let staged_db = staging.join(archive::DB_FILE);
+        let (schema_version, integrity) = Database::inspect(&staged_db).map_err(|e| e.to_string())?;
+        if integrity != "ok" {
+            return Err(format!("A base de dados da cópia está danificada: {}", integrity));
+        }
+        if schema_version > db::CURRENT_SCHEMA_VERSION {
+            return Err(format!("Esquema v{} não suportado por esta versão.", schema_version));
+        }
+
+        // Keep the current state recoverable before overwriting anything
+        let settings = settings::load_settings(app_handle)?;
+        // Encrypted backups stay encrypted: without the stored passphrase the restore stops here
+        let safety_passphrase = resolve_passphrase(None, &settings)
+            .map_err(|e| format!("Não foi possível guardar o estado atual antes de restaurar: {}", e))?;
+        let safety = write_backup(app_handle, "pre-restore", None, safety_passphrase.as_deref())?;
+
+        // Readers share the file, so they see the restored content on their next query
+        let now = super::system::clock(app_handle).now();
+        app_handle.state::<DbState>().write_blocking(|db| {
+            db.restore_from(&staged_db).map_err(|e| format!("Falha ao restaurar a base de dados: {}", e))?;
+            db.run_migrations(settings::user_timezone(&settings), now)
+        })?;
+
+        for name in [archive::SETTINGS_FILE, archive::ENV_FILE] {
+            if manifest.has(name) {
+                let target = config_dir.join(name);
+                let tmp = target.with_extension("restore.tmp");
+                std::fs::copy(staging.join(name), &tmp)
+                    .and_then(|_| std::fs::rename(&tmp, &target))
+                    .map_err(|e| format!("Falha ao restaurar {}: {}", name, e))?;
+            }
+        }
+        if manifest.has(archive::SETTINGS_FILE) {
+            settings::reload_settings(app_handle)?;
+        }
+
+        Ok(RestoreSummary {
+            created_at: manifest.created_at.clone(),
+            app_version: manifest.app_version.clone(),
+            schema_version: manifest.schema_version,
+            files: manifest.files.iter().map(|f| f.name.clone()).collect(),
+            safety_backup: safety.path,
+        })
+    })();
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        log::warn!("Failed to remove restore staging dir: {}", e);
+    }
+    result
+}
+
+/// Run `job` on the blocking pool while holding the backup lock
+#[cfg(feature = "gui")]
+async fn exclusive<T, F>(app_handle: tauri::AppHandle, job: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&tauri::AppHandle) -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<BackupState>();
+        let _guard = state.busy.try_lock()
+            .map_err(|_| "Já está a decorrer uma cópia de segurança ou um restauro.".to_string())?;
+        job(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Periodically take a scheduled backup when one is due, then apply retention
+#[cfg(feature = "gui")]
+pub fn spawn_backup_scheduler(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(SCHEDULER_TICK_SECS));
+        loop {
+            interval.tick().await;
+            let Ok(settings) = settings::load_settings(&app_handle) else { continue };
+            if !settings.backup_enabled {
+                continue;
+            }
+            let outcome = exclusive(app_handle.clone(), move |app| {
+                let dir = backup_dir(app, &settings)?;
+                let last = list_backups(&dir).into_iter()
+                    .find(|b| b.reason == "scheduled")
+                    .and_then(|b| b.created_at);
+                let due_before = time::format_utc(super::system::clock(app).now() - chrono::Duration::hours(settings.backup_interval_hours as i64));
+                if last.is_some_and(|last| last > due_before) {
+                    return Ok(None);
+                }
+                let passphrase = resolve_passphrase(None, &settings)?;
+                let info = write_backup(app, "scheduled", None, passphrase.as_deref())?;
+                prune(&dir, settings.backup_keep);
+                Ok(Some(info))
+            }).await;
+
+            let state = app_handle.state::<BackupState>();
+            let error = match outcome {
+                Ok(Some(info)) => {
+                    log::info!("Scheduled backup written to {}", info.path);
+                    None
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("Scheduled backup failed: {}", e);
+                    Some(e)
+                }
+            };
+            if let Ok(mut last_error) = state.last_error.lock() {
+                *last_error = error;
+            };
+        }
+    });
+}
+
+/// Back up the database, settings and .env into one archive.
+/// `passphrase` encrypts this backup; without one the stored passphrase is used when
+/// encryption is enabled in settings. `path` defaults to the backup directory.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn create_backup(
+    path: Option<String>,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<BackupInfo, String> {
+    let settings = settings::load_settings(&app_handle)?;
+    let passphrase = resolve_passphrase(passphrase, &settings)?;
+    exclusive(app_handle, move |app| {
+        write_backup(app, "manual", path.map(PathBuf::from), passphrase.as_deref())
+    }).await
+}
+
+/// Restore a backup after checking its manifest, checksums and schema version.
+/// The current state is saved as a "pre-restore" backup first.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn restore_backup(
+    path: String,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<RestoreSummary, String> {
+    let passphrase = passphrase
+        .filter(|p| !p.trim().is_empty())
+        .or_else(|| secrets::get(secrets::BACKUP_PASSPHRASE));
+    exclusive(app_handle, move |app| restore(app, Path::new(&path), passphrase.as_deref())).await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_backup_status(
+    backup: State<'_, BackupState>,
+    app_handle: tauri::AppHandle,
+) -> Result<BackupStatus, String> {
+    let settings = settings::load_settings(&app_handle)?;
+    let dir = backup_dir(&app_handle, &settings)?;
+    Ok(BackupStatus {
+        directory: dir.to_string_lossy().into_owned(),
+        passphrase_set: secrets::is_configured(secrets::BACKUP_PASSPHRASE),
+        last_error: backup.last_error.lock().map_err(|e| e.to_string())?.clone(),
+        backups: list_backups(&dir),
+    })
+}
+
+/// Store the passphrase for encrypted backups in the secret store (empty clears it)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn set_backup_passphrase(passphrase: String) -> Result<(), String> {
+    let passphrase = passphrase.trim();
+    if !passphrase.is_empty() && passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("A palavra-passe deve ter pelo menos {} caracteres.", MIN_PASSPHRASE_LEN));
+    }
+    secrets::set(secrets::BACKUP_PASSPHRASE, passphrase)
+}
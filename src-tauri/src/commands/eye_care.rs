@@ -0,0 +1,265 @@
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+#[cfg(feature = "gui")]
+use tauri::{Emitter, Manager, State};
+use crate::commands::settings;
+use crate::db::{Database, DbState};
+use crate::services::eye_care::{self, LowBlinkPeriod, RuleEvent, TwentyTwentyEngine};
+use crate::services::time;
+
+pub const EYE_CARE_REMINDER_EVENT: &str = "eye-care://reminder";
+const ENGINE_TICK_SECS: u64 = 30;
+const SCREEN_ACTIVITY_WINDOW_MINUTES: i64 = 3;
+
+/// Blinks counted during one minute by the webcam blink detector
+#[derive(Debug, Deserialize)]
+pub struct BlinkRateInput {
+    pub recorded_at: String, // start of the minute, ISO 8601
+    pub blinks_per_minute: f64,
+}
+
+/// Raw blink timestamps observed while tracking during [from, to)
+#[derive(Debug, Deserialize)]
+pub struct BlinkEventsInput {
+    pub from: String,
+    pub to: String,
+    pub events: Vec<String>,
+}
+
+/// A short look-away from the screen (at least 20 s)
+#[derive(Debug, Deserialize)]
+pub struct EyeRestInput {
+    pub started_at: String,
+    pub ended_at: String,
+    pub source: Option<String>, // presence (default) | manual
+}
+
+/// 20-20-20 compliance and blink health over a range
+#[derive(Debug, Serialize)]
+pub struct EyeCareStats {
+    pub reminders: u32,
+    pub complied: u32,
+    pub missed: u32,
+    pub compliance_percent: Option<u32>, // None when no reminder has been resolved yet
+    pub mean_blink_rate: Option<f64>,
+    pub low_blink_periods: Vec<LowBlinkPeriod>,
+}
+
+/// Payload of `eye-care://reminder`
+#[derive(Debug, Serialize, Clone)]
+pub struct EyeCareReminder {
+    pub text: String,
+    pub screen_minutes: i64,
+    pub emitted_at: String,
+}
+
+/// In-memory 20-20-20 state; reminders and their outcomes are persisted
+#[derive(Default)]
+pub struct EyeCareState(pub Mutex<TwentyTwentyEngine>);
+
+/// Low-blink periods whose samples fall within [from, to) (canonical UTC bounds)
+pub fn low_blink_between(db: &Database, from: &str, to: &str) -> Result<Vec<LowBlinkPeriod>, String> {
+    let points = db.get_blink_points(from, to).map_err(|e| e.to_string())?;
+    Ok(eye_care::detect_low_blink_periods(&points))
+}
+
+fn store_blink_rates(db: &Database, rates: &[(DateTime<Utc>, f64)], source: &str) -> Result<usize, String> {
+    let mut stored = 0;
+    for (at, rate) in rates {
+        if !rate.is_finite() || !(0.0..=60.0).contains(rate) {
+            return Err(format!("Taxa de piscadas inválida: {}", rate));
+        }
+        let minute = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(*at);
+        let inserted = db.insert_blink_rate(&time::format_utc(minute), rate.round(), source)
+            .map_err(|e| e.to_string())?;
+        stored += inserted as usize;
+    }
+    Ok(stored)
+}
+
+/// One 20-20-20 step: decide from screen activity and look-aways, persist, and notify
+#[cfg(feature = "gui")]
+fn engine_tick(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let (Some(db_state), Some(eye_state)) = (
+        app_handle.try_state::<DbState>(),
+        app_handle.try_state::<EyeCareState>(),
+    ) else {
+        return Ok(());
+    };
+    let enabled = settings::load_settings(app_handle)
+        .map(|s| s.eye_care_reminders)
+        .unwrap_or(true);
+    let now = super::system::clock(app_handle).now();
+    // The presence state machine knows best; before its first signal, guess from samples
+    let presence = crate::commands::presence::at_screen(app_handle);
+
+    let reminder = db_state.write_blocking(|db| {
+        let mut engine = eye_state.0.lock().map_err(|e| e.to_string())?;
+        if !enabled {
+            *engine = TwentyTwentyEngine::default();
+            return Ok(None);
+        }
+
+        let since = time::format_utc(now - chrono::Duration::minutes(SCREEN_ACTIVITY_WINDOW_MINUTES));
+        let at_screen = match presence {
+            Some(present) => present,
+            None => db.screen_active(&since).map_err(|e| e.to_string())?,
+        };
+        let last_rest = db.last_eye_rest_end()
+            .map_err(|e| e.to_string())?
+            .and_then(|end| time::parse_instant(&end, chrono_tz::UTC));
+        let screen_minutes = engine.screen_minutes(now);
+
+        Ok::<_, String>(match engine.tick(now, at_screen, last_rest) {
+            Some(RuleEvent::Remind) => {
+                let emitted_at = time::format_utc(now);
+                db.insert_eye_reminder(&emitted_at).map_err(|e| e.to_string())?;
+                Some(EyeCareReminder {
+                    text: format!(
+                        "{} min de ecrã seguidos. Olha para algo a 6 metros durante 20 segundos.",
+                        screen_minutes
+                    ),
+                    screen_minutes,
+                    emitted_at,
+                })
+            }
+            Some(RuleEvent::Complied) => {
+                db.resolve_eye_reminders("complied", &time::format_utc(now)).map_err(|e| e.to_string())?;
+                None
+            }
+            Some(RuleEvent::Missed) => {
+                db.resolve_eye_reminders("missed", &time::format_utc(now)).map_err(|e| e.to_string())?;
+                None
+            }
+            None => None,
+        })
+    })?; // writer and engine released before emitting
+
+    if let Some(reminder) = reminder {
+        app_handle.emit(EYE_CARE_REMINDER_EVENT, reminder).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Run the 20-20-20 engine for the lifetime of the app
+#[cfg(feature = "gui")]
+pub fn spawn_engine(app_handle: tauri::AppHandle) {
+    // Reminders left open by a previous run can no longer be judged
+    if let Some(db_state) = app_handle.try_state::<DbState>() {
+        let now = time::format_utc(super::system::clock(&app_handle).now());
+        if let Err(e) = db_state.write_blocking(|db| db.resolve_eye_reminders("missed", &now)) {
+            log::warn!("Failed to close stale eye-care reminders: {}", e);
+        }
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(ENGINE_TICK_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = engine_tick(&app_handle) {
+                log::warn!("Eye-care engine tick failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Store per-minute blink rates from the webcam detector
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_blink_rates(
+    state: State<'_, DbState>,
+    samples: Vec<BlinkRateInput>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let rates = samples.iter()
+        .map(|s| {
+            time::parse_instant(&s.recorded_at, tz)
+                .map(|at| (at, s.blinks_per_minute))
+                .ok_or_else(|| format!("Data/hora inválida: '{}'", s.recorded_at))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    state.write(move |db| store_blink_rates(db, &rates, "webcam")).await
+}
+
+/// Store raw blink events as per-minute rates. Only whole minutes inside the tracked
+/// window are kept, so a minute with no blinks is a real 0, not missing data.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_blink_events(
+    state: State<'_, DbState>,
+    input: BlinkEventsInput,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let parse = |raw: &str| time::parse_instant(raw, tz).ok_or_else(|| format!("Data/hora inválida: '{}'", raw));
+    let (from, to) = (parse(&input.from)?, parse(&input.to)?);
+    if to <= from || to - from > chrono::Duration::hours(24) {
+        return Err("Janela de piscadas inválida (máximo 24 h).".to_string());
+    }
+    let events = input.events.iter().map(|e| parse(e)).collect::<Result<Vec<_>, String>>()?;
+
+    let first_minute = from.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(from);
+    let mut minute = if first_minute < from { first_minute + chrono::Duration::minutes(1) } else { first_minute };
+    let mut rates = Vec::new();
+    while minute + chrono::Duration::minutes(1) <= to {
+        let next = minute + chrono::Duration::minutes(1);
+        let blinks = events.iter().filter(|e| **e >= minute && **e < next).count();
+        rates.push((minute, blinks as f64));
+        minute = next;
+    }
+
+    state.write(move |db| store_blink_rates(db, &rates, "webcam")).await
+}
+
+/// Record a look-away; resolves a pending 20-20-20 reminder on the next engine tick
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_eye_rest(
+    state: State<'_, DbState>,
+    rest: EyeRestInput,
+    app_handle: tauri::AppHandle,
+) -> Result<i64, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let parse = |raw: &str| time::parse_instant(raw, tz).ok_or_else(|| format!("Data/hora inválida: '{}'", raw));
+    let (started_at, ended_at) = (parse(&rest.started_at)?, parse(&rest.ended_at)?);
+    if (ended_at - started_at).num_seconds() < eye_care::RULE_REST_SECONDS {
+        return Err(format!("Pausa visual demasiado curta (mínimo {} s).", eye_care::RULE_REST_SECONDS));
+    }
+
+    state.write(move |db| db.insert_eye_rest(
+        &time::format_utc(started_at),
+        &time::format_utc(ended_at),
+        rest.source.as_deref().unwrap_or("presence"),
+    )).await
+}
+
+/// 20-20-20 compliance, mean blink rate and low-blink periods in a date range
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_eye_care_stats(
+    state: State<'_, DbState>,
+    from: String,
+    to: String,
+    app_handle: tauri::AppHandle,
+) -> Result<EyeCareStats, String> {
+    let (from, to) = time::normalize_range(&from, &to, settings::current_timezone(&app_handle))?;
+    let ((reminders, complied, missed), points) = state.read(move |db| {
+        Ok::<_, rusqlite::Error>((db.eye_reminder_counts(&from, &to)?, db.get_blink_points(&from, &to)?))
+    }).await?;
+    let resolved = complied + missed;
+    let mean_blink_rate = (!points.is_empty())
+        .then(|| points.iter().map(|p| p.rate).sum::<f64>() / points.len() as f64)
+        .map(|mean| (mean * 10.0).round() / 10.0);
+
+    Ok(EyeCareStats {
+        reminders,
+        complied,
+        missed,
+        compliance_percent: (resolved > 0).then(|| (complied as f64 / resolved as f64 * 100.0).round() as u32),
+        mean_blink_rate,
+        low_blink_periods: eye_care::detect_low_blink_periods(&points),
+    })
+}
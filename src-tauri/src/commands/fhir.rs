@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::{records, settings};
+use crate::db::DbState;
+use crate::services::fhir::{self, Imported};
+use crate::services::time;
+
+const MAX_REPORTED_ERRORS: usize = 20;
+
+#[derive(Debug, Serialize)]
+pub struct FhirExportSummary {
+    pub path: String,
+    pub observations: usize,
+    pub medication_statements: usize,
+    pub service_requests: usize,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct FhirImportSummary {
+    pub vitals: usize,
+    pub lab_results: usize,
+    pub supplements: usize,
+    pub exams: usize,
+    pub duplicates: usize,      // already present, left untouched
+    pub ignored: usize,         // resource types outside the record (Patient, ...)
+    pub errors: Vec<String>,    // unreadable resources (first few)
+}
+
+fn default_export_path(now: DateTime<Utc>) -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("holoself-fhir-{}.json", now.format("%Y%m%d-%H%M%S")))
+}
+
+/// Export the record as a FHIR R4 Bundle (JSON). `from`/`to` limit vitals and supplements;
+/// lab results and exams are always exported in full.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn export_fhir_bundle(
+    state: State<'_, DbState>,
+    path: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<FhirExportSummary, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let now = super::system::clock(&app_handle).now();
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => time::normalize_range(&from, &to, tz)?,
+        (None, None) => ("0000-01-01T00:00:00Z".to_string(), "9999-12-31T23:59:59Z".to_string()),
+        _ => return Err("Indique as duas datas (from e to) ou nenhuma.".to_string()),
+    };
+
+    let (labs, vitals, supplements, exams) = state.read(move |db| {
+        Ok::<_, rusqlite::Error>((
+            db.get_lab_results()?,
+            db.get_vitals(&from, &to)?,
+            db.get_supplements(&from, &to)?,
+            db.get_health_schedule()?,
+        ))
+    }).await?;
+
+    let summary_counts = (labs.len() + vitals.len(), supplements.len(), exams.len());
+    let resources = labs.iter().map(fhir::lab_observation)
+        .chain(vitals.iter().map(fhir::vital_observation))
+        .chain(supplements.iter().map(fhir::medication_statement))
+        .chain(exams.iter().map(fhir::service_request))
+        .collect();
+    let bundle = fhir::bundle(&time::format_utc(now), resources);
+
+    let path = path.map(PathBuf::from).unwrap_or_else(|| default_export_path(now));
+    let file = std::fs::File::create(&path).map_err(|e| format!("Falha ao criar {:?}: {}", path, e))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), &bundle).map_err(|e| e.to_string())?;
+
+    Ok(FhirExportSummary {
+        path: path.to_string_lossy().into_owned(),
+        observations: summary_counts.0,
+        medication_statements: summary_counts.1,
+        service_requests: summary_counts.2,
+    })
+}
+
+/// Import a FHIR R4 Bundle such as the one written by `export_fhir_bundle`.
+/// Rows that already exist are skipped, so importing the same file twice is harmless.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn import_fhir_bundle(
+    state: State<'_, DbState>,
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<FhirImportSummary, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let now = time::format_utc(super::system::clock(&app_handle).now());
+    let file = std::fs::File::open(&path).map_err(|e| format!("Falha ao abrir {}: {}", path, e))?;
+    let bundle: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("JSON inválido: {}", e))?;
+    if bundle["resourceType"] != "Bundle" {
+        return Err("O ficheiro não é um FHIR Bundle.".to_string());
+    }
+
+    let mut summary = FhirImportSummary::default();
+    let mut rows = Vec::new();
+    for (index, entry) in bundle["entry"].as_array().into_iter().flatten().enumerate() {
+        let parsed = fhir::parse_resource(&entry["resource"]).and_then(|row| match row {
+            // Stored instants are canonical UTC; calendar dates (labs, exams) stay as given
+            Some(Imported::Vital(mut v)) => {
+                v.recorded_at = time::normalize(&v.recorded_at, tz)?;
+                Ok(Some(Imported::Vital(v)))
+            }
+            Some(Imported::Supplement(mut s)) => {
+                s.taken_at = time::normalize(&s.taken_at, tz)?;
+                Ok(Some(Imported::Supplement(s)))
+            }
+            other => Ok(other),
+        });
+        match parsed {
+            Ok(Some(row)) => rows.push(row),
+            Ok(None) => summary.ignored += 1,
+            Err(e) if summary.errors.len() < MAX_REPORTED_ERRORS => summary.errors.push(format!("entry[{}]: {}", index, e)),
+            Err(_) => {}
+        }
+    }
+
+    state.write(move |db| db.transaction(|db| {
+        for row in &rows {
+            match row {
+                Imported::Vital(v) if db.vital_exists(v)? => summary.duplicates += 1,
+                Imported::Vital(v) => {
+                    db.insert_vital(v)?;
+                    summary.vitals += 1;
+                }
+                Imported::Supplement(s) if db.supplement_exists(s)? => summary.duplicates += 1,
+                Imported::Supplement(s) => {
+                    db.insert_supplement(s)?;
+                    summary.supplements += 1;
+                }
+                Imported::Lab(l) if db.lab_record_exists(l)? => summary.duplicates += 1,
+                Imported::Lab(l) => {
+                    db.insert_lab_record(l)?;
+                    summary.lab_results += 1;
+                }
+                Imported::Schedule(e) if db.schedule_record_exists(e)? => summary.duplicates += 1,
+                Imported::Schedule(e) => {
+                    db.insert_schedule_record(e)?;
+                    summary.exams += 1;
+                }
+            }
+        }
+        records::audit_import(db, &now, "vitals", &path, summary.vitals)?;
+        records::audit_import(db, &now, "supplements", &path, summary.supplements)?;
+        records::audit_import(db, &now, "lab_results", &path, summary.lab_results)?;
+        records::audit_import(db, &now, "health_schedule", &path, summary.exams)?;
+        Ok::<_, rusqlite::Error>(summary)
+    })).await
+}
@@ -1,13 +1,17 @@
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
 use tauri::State;
+use crate::commands::{records, settings};
 use crate::db::DbState;
+use crate::services::health::HealthService;
+use crate::services::timeline::{TimelineBucket, TimelinePage};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SupplementEntry {
     pub id: Option<i64>,
     pub name: String,
     pub dosage: String,
-    pub taken_at: String,   // ISO 8601 datetime
+    pub taken_at: String,   // ISO 8601 datetime, stored as UTC
     pub category: String,   // morning | night | as_needed
     pub notes: Option<String>,
 }
@@ -15,59 +19,90 @@ pub struct SupplementEntry {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VitalEntry {
     pub id: Option<i64>,
-    pub vital_type: String, // heart_rate | hrv | sleep_score | stress_level | wpm
+    pub vital_type: String, // heart_rate | resting_heart_rate | hrv | sleep | sleep_score | steps | stress_level | wpm | blink_rate
     pub value: f64,
     pub unit: String,
-    pub recorded_at: String,
+    pub recorded_at: String, // ISO 8601 datetime, stored as UTC
     pub source: String,     // manual | wearable | webcam
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct HealthTimelineEntry {
-    pub timestamp: String,
-    pub event_type: String,
-    pub label: String,
-    pub value: Option<f64>,
-}
-
-/// Log a supplement intake
+/// Log a supplement intake. `source` is "ui" (default) or "voice".
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn log_supplement(
     state: State<'_, DbState>,
     entry: SupplementEntry,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> Result<i64, String> {
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.insert_supplement(&entry).map_err(|e| e.to_string())
+    let source = records::parse_source(source.as_deref(), "ui")?;
+    let ctx = settings::services(&state, &app_handle);
+    HealthService::new(&ctx).log_supplement(entry, source).await
 }
 
 /// Get supplement log for a date range
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn get_supplement_log(
     state: State<'_, DbState>,
     from: String,
     to: String,
+    app_handle: tauri::AppHandle,
 ) -> Result<Vec<SupplementEntry>, String> {
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.get_supplements(&from, &to).map_err(|e| e.to_string())
+    let ctx = settings::services(&state, &app_handle);
+    HealthService::new(&ctx).supplement_log(&from, &to).await
 }
 
-/// Get unified health timeline
+/// Unified health timeline, newest first, one page at a time. `types` picks event kinds
+/// (default: all of `timeline::EVENT_TYPES`); `vital_types` narrows vitals to those
+/// types. Pass `next_cursor` back as `cursor` for the following page.
+#[cfg(feature = "gui")]
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_health_timeline(
     state: State<'_, DbState>,
     from: String,
     to: String,
-) -> Result<Vec<HealthTimelineEntry>, String> {
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.get_health_timeline(&from, &to).map_err(|e| e.to_string())
+    types: Option<Vec<String>>,
+    vital_types: Option<Vec<String>>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    app_handle: tauri::AppHandle,
+) -> Result<TimelinePage, String> {
+    let ctx = settings::services(&state, &app_handle);
+    let health = HealthService::new(&ctx);
+    let query = health.timeline_query(&from, &to, types.as_deref(), vital_types, cursor.as_deref(), limit)?;
+    health.timeline(query).await
+}
+
+/// Timeline grouped by local day or ISO week (`bucket` is "day" or "week"), oldest
+/// first: event counts per kind plus count, mean, min, max and sum per vital type.
+/// Unlike the event list, vitals include every type unless `vital_types` is given.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_timeline_buckets(
+    state: State<'_, DbState>,
+    from: String,
+    to: String,
+    bucket: String,
+    types: Option<Vec<String>>,
+    vital_types: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<TimelineBucket>, String> {
+    let ctx = settings::services(&state, &app_handle);
+    HealthService::new(&ctx).timeline_buckets(&from, &to, &bucket, types.as_deref(), vital_types).await
 }
 
-/// Log a vital sign measurement
+/// Log a vital sign measurement. `source` is "ui" (default) or "voice".
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn log_vital(
     state: State<'_, DbState>,
     entry: VitalEntry,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> Result<i64, String> {
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.insert_vital(&entry).map_err(|e| e.to_string())
+    let source = records::parse_source(source.as_deref(), "ui")?;
+    let ctx = settings::services(&state, &app_handle);
+    HealthService::new(&ctx).log_vital(entry, source).await
 }
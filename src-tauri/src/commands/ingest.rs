@@ -0,0 +1,492 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "gui")]
+use tauri::{Manager, State};
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::{records, settings};
+use crate::db::{Database, DbState};
+use crate::services::ingest_api::{
+    self, ApiRequest, ApiResponse, IngestApiState, IngestApiStatus, RateLimiter,
+    RejectionAudit, SupplementInput, VitalInput, MAX_RECORDS_PER_REQUEST,
+};
+use crate::services::time;
+
+const SOURCE: &str = "api";
+const DEFAULT_AUDIT_LIMIT: u32 = 100;
+
+/// A script or device allowed to post readings
+#[derive(Debug, Serialize)]
+pub struct IngestClient {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+/// Returned once on creation; the token itself is never stored
+#[derive(Debug, Serialize)]
+pub struct NewIngestClient {
+    pub client: IngestClient,
+    pub token: String,
+}
+
+/// One request that reached the API and what it did
+#[derive(Debug, Serialize)]
+pub struct IngestAuditEntry {
+    pub id: Option<i64>,
+    pub received_at: String,
+    pub client_id: Option<i64>,      // None when the token was missing or unknown
+    pub client_name: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub record_kind: Option<String>, // vital | supplement
+    pub record_ids: Vec<i64>,        // rows written by this request
+    pub detail: Option<String>,
+}
+
+/// Response plus what goes into the audit log
+struct Outcome {
+    response: ApiResponse,
+    record_kind: Option<&'static str>,
+    record_ids: Vec<i64>,
+    detail: Option<String>,
+}
+
+impl Outcome {
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self { response: ApiResponse::error(status, message.clone()), record_kind: None, record_ids: Vec::new(), detail: Some(message) }
+    }
+}
+
+/// Kept by the server between requests: rate limits and the cap on rejected-request audit rows
+#[derive(Debug, Default)]
+pub struct RequestGate {
+    limiter: RateLimiter,
+    rejections: RejectionAudit,
+}
+
+/// Start or stop the local ingestion API to match settings.
+/// Called at startup and after every settings save.
+#[cfg(feature = "gui")]
+pub fn apply_ingest_api(app_handle: &tauri::AppHandle) {
+    let Some(api) = app_handle.try_state::<IngestApiState>() else { return };
+    let (enabled, port) = settings::load_settings(app_handle)
+        .map(|s| (s.ingest_api_enabled, s.ingest_api_port))
+        .unwrap_or((false, ingest_api::DEFAULT_PORT));
+    if !enabled {
+        api.stop();
+        return;
+    }
+
+    let handle = app_handle.clone();
+    let mut gate = RequestGate::default();
+    let started = api.start(port, move |request| {
+        let Some(db_state) = handle.try_state::<DbState>() else {
+            return ApiResponse::error(503, "Base de dados indisponível.");
+        };
+        let tz = settings::current_timezone(&handle);
+        let now = super::system::clock(&handle).now();
+        handle_request(&db_state, tz, now, &mut gate, request)
+    });
+    match started {
+        Ok(()) => log::info!("Ingestion API listening on 127.0.0.1:{}", port),
+        Err(e) => log::warn!("Ingestion API unavailable: {}", e),
+    }
+}
+
+/// Authenticate, rate-limit, route and audit one request
+pub fn handle_request(
+    db_state: &DbState,
+    tz: chrono_tz::Tz,
+    now: DateTime<Utc>,
+    gate: &mut RequestGate,
+    request: ApiRequest,
+) -> ApiResponse {
+    let received_at = time::format_utc(now);
+
+    let client = match request.bearer.as_deref() {
+        Some(token) => {
+            let hash = ingest_api::hash_token(token);
+            db_state.write_blocking(|db| db.authenticate_ingest_client(&hash, &received_at))
+                .unwrap_or_else(|e| {
+                    log::warn!("Ingestion API auth lookup failed: {}", e);
+                    None
+                })
+        }
+        None => None,
+    };
+
+    // Failed auth is limited per remote IP (not per connection: every request comes
+    // from a fresh ephemeral port) so tokens can't be guessed quickly
+    let bucket = match &client {
+        Some((id, _)) => format!("client:{}", id),
+        None => format!("anonymous:{}", request.remote.map_or("?".to_string(), |ip| ip.to_string())),
+    };
+    let outcome = if let Err(retry_after) = gate.limiter.check(&bucket, now) {
+        let mut outcome = Outcome::error(429, format!("Demasiados pedidos. Tente novamente em {} s.", retry_after));
+        outcome.response.retry_after = Some(retry_after);
+        outcome
+    } else if client.is_none() {
+        Outcome::error(401, "Token em falta ou inválido (Authorization: Bearer <token>).")
+    } else {
+        route(db_state, tz, &request, now)
+    };
+
+    let mut detail = outcome.detail;
+    if matches!(outcome.response.status, 401 | 429) {
+        match gate.rejections.admit(&bucket, now) {
+            None => return outcome.response,
+            Some(0) => {}
+            Some(skipped) => {
+                let note = format!("+{} pedido(s) recusado(s) não registado(s)", skipped);
+                detail = Some(detail.map_or(note.clone(), |d| format!("{} ({})", d, note)));
+            }
+        }
+    }
+
+    let (client_id, client_name) = client.map_or((None, None), |(id, name)| (Some(id), Some(name)));
+    let entry = IngestAuditEntry {
+        id: None,
+        received_at,
+        client_id,
+        client_name,
+        method: request.method,
+        path: request.path,
+        status: outcome.response.status,
+        record_kind: outcome.record_kind.map(str::to_string),
+        record_ids: outcome.record_ids,
+        detail,
+    };
+    let audited = db_state.write_blocking(|db| db.insert_ingest_audit(&entry));
+    if let Err(e) = audited {
+        log::warn!("Failed to write ingestion audit entry: {}", e);
+    }
+    outcome.response
+}
+
+fn route(db_state: &DbState, tz: chrono_tz::Tz, request: &ApiRequest, now: DateTime<Utc>) -> Outcome {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/status") => Outcome {
+            response: ApiResponse::json(200, serde_json::json!({ "status": "ok" })),
+            record_kind: None,
+            record_ids: Vec::new(),
+            detail: None,
+        },
+        ("POST", "/v1/vitals") => ingest::<VitalInput, _>(db_state, &request.body, now, "vital", "vitals", |input, now| {
+            let (vital_type, value, unit, recorded_at) = ingest_api::validate_vital(input, tz, now)?;
+            let entry = VitalEntry {
+                id: None,
+                vital_type,
+                value,
+                unit,
+                recorded_at: time::format_utc(recorded_at),
+                source: SOURCE.to_string(),
+            };
+            Ok(Box::new(move |db: &Database| db.insert_vital(&entry)))
+        }),
+        ("POST", "/v1/supplements") => ingest::<SupplementInput, _>(db_state, &request.body, now, "supplement", "supplements", |input, now| {
+            let (name, dosage, taken_at, category) = ingest_api::validate_supplement(input, tz, now)?;
+            let entry = SupplementEntry {
+                id: None,
+                name,
+                dosage,
+                taken_at: time::format_utc(taken_at),
+                category,
+                notes: input.notes.clone(),
+            };
+            Ok(Box::new(move |db: &Database| db.insert_supplement(&entry)))
+        }),
+        (_, "/v1/status" | "/v1/vitals" | "/v1/supplements") => Outcome::error(405, "Método não permitido."),
+        _ => Outcome::error(404, "Endpoint desconhecido (use /v1/vitals ou /v1/supplements)."),
+    }
+}
+
+type Write = Box<dyn FnOnce(&Database) -> rusqlite::Result<i64>>;
+
+/// Parse and validate every record first; write only when all of them are valid.
+/// Each row gets its `audit_log` entry in the same transaction.
+fn ingest<T, V>(
+    db_state: &DbState,
+    body: &[u8],
+    now: DateTime<Utc>,
+    kind: &'static str,
+    table: &'static str,
+    validate: V,
+) -> Outcome
+where
+    T: DeserializeOwned,
+    V: Fn(&T, chrono::DateTime<Utc>) -> Result<Write, String>,
+{
+    // A single object or an array of them; records are decoded one by one so each
+    // schema error can point at its index
+    let records = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Array(records)) => records,
+        Ok(record @ serde_json::Value::Object(_)) => vec![record],
+        Ok(_) => return Outcome::error(400, "O corpo deve ser um objeto JSON ou uma lista de objetos."),
+        Err(e) => return Outcome::error(400, format!("JSON inválido: {}", e)),
+    };
+    if records.is_empty() {
+        return Outcome::error(422, "Nenhum registo enviado.");
+    }
+    if records.len() > MAX_RECORDS_PER_REQUEST {
+        return Outcome::error(413, format!("Máximo de {} registos por pedido.", MAX_RECORDS_PER_REQUEST));
+    }
+
+    let mut writes = Vec::with_capacity(records.len());
+    let mut errors = Vec::new();
+    for (index, record) in records.into_iter().enumerate() {
+        let checked = serde_json::from_value::<T>(record)
+            .map_err(|e| e.to_string())
+            .and_then(|record| validate(&record, now));
+        match checked {
+            Ok(write) => writes.push(write),
+            Err(error) => errors.push(serde_json::json!({ "index": index, "error": error })),
+        }
+    }
+    if !errors.is_empty() {
+        let detail = format!("{} de {} registos inválidos", errors.len(), errors.len() + writes.len());
+        return Outcome {
+            response: ApiResponse::json(422, serde_json::json!({ "error": detail, "errors": errors })),
+            record_kind: Some(kind),
+            record_ids: Vec::new(),
+            detail: Some(detail),
+        };
+    }
+
+    // All records or none: a failure part-way rolls back the ones before it
+    let at = time::format_utc(now);
+    let written = db_state.write_blocking(|db| {
+        db.transaction(|db| {
+            writes.into_iter()
+                .map(|write| {
+                    let id = write(db)?;
+                    records::audit_insert(db, &at, table, id, SOURCE)?;
+                    Ok(id)
+                })
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+    });
+    let ids = match written {
+        Ok(ids) => ids,
+        Err(e) => {
+            let mut outcome = Outcome::error(500, format!("Falha ao gravar: {}", e));
+            outcome.record_kind = Some(kind);
+            return outcome;
+        }
+    };
+    Outcome {
+        response: ApiResponse::json(201, serde_json::json!({ "inserted": ids.len(), "ids": ids })),
+        record_kind: Some(kind),
+        detail: Some(format!("{} registo(s)", ids.len())),
+        record_ids: ids,
+    }
+}
+
+/// Issue a token for a new client. The token is only ever returned here.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn create_ingest_client(
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+    name: String,
+) -> Result<NewIngestClient, String> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 60 {
+        return Err("O nome do cliente deve ter entre 1 e 60 caracteres.".to_string());
+    }
+    let token = ingest_api::generate_token();
+    let created_at = time::format_utc(super::system::clock(&app_handle).now());
+    let (stored_name, hash, stored_at) = (name.to_string(), ingest_api::hash_token(&token), created_at.clone());
+    let id = state.write(move |db| db.insert_ingest_client(&stored_name, &hash, &stored_at)).await?;
+    Ok(NewIngestClient {
+        client: IngestClient { id, name: name.to_string(), created_at, last_used_at: None, revoked_at: None },
+        token,
+    })
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn list_ingest_clients(state: State<'_, DbState>) -> Result<Vec<IngestClient>, String> {
+    state.read(|db| db.get_ingest_clients()).await
+}
+
+/// Revoke a client's token; its past writes stay in the audit log
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn revoke_ingest_client(
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+    id: i64,
+) -> Result<bool, String> {
+    let revoked_at = time::format_utc(super::system::clock(&app_handle).now());
+    state.write(move |db| db.revoke_ingest_client(id, &revoked_at)).await
+}
+
+/// Latest API requests: which client wrote what, and rejected attempts
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_ingest_audit(state: State<'_, DbState>, limit: Option<u32>) -> Result<Vec<IngestAuditEntry>, String> {
+    let limit = limit.unwrap_or(DEFAULT_AUDIT_LIMIT).min(1000);
+    state.read(move |db| db.get_ingest_audit(limit)).await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_ingest_api_status(api: State<'_, IngestApiState>) -> Result<IngestApiStatus, String> {
+    Ok(api.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::clock::{Clock, FrozenClock};
+    use crate::services::context::testing::context_at;
+    use crate::services::context::ServiceContext;
+    use std::sync::Arc;
+
+    const TOKEN: &str = "hs_test_token";
+
+    struct Server {
+        ctx: ServiceContext,
+        clock: Arc<FrozenClock>,
+        gate: RequestGate,
+    }
+
+    impl Server {
+        fn new() -> Self {
+            let (ctx, clock, _) = context_at("2026-07-01T09:00:00Z", None);
+            let hash = ingest_api::hash_token(TOKEN);
+            ctx.db.write_blocking(|db| db.insert_ingest_client("balança", &hash, "2026-06-01T00:00:00Z")).unwrap();
+            Server { ctx, clock, gate: RequestGate::default() }
+        }
+
+        fn send(&mut self, method: &str, path: &str, token: Option<&str>, body: serde_json::Value) -> ApiResponse {
+            let request = ApiRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                bearer: token.map(str::to_string),
+                remote: Some(std::net::Ipv4Addr::LOCALHOST.into()),
+                body: body.to_string().into_bytes(),
+            };
+            handle_request(&self.ctx.db, self.ctx.tz(), self.clock.now(), &mut self.gate, request)
+        }
+
+        fn audit(&self) -> Vec<IngestAuditEntry> {
+            self.ctx.db.read_blocking(|db| db.get_ingest_audit(100)).unwrap()
+        }
+
+        fn count(&self, table: &str) -> i64 {
+            let sql = format!("SELECT COUNT(*) FROM {}", table);
+            self.ctx.db.read_blocking(|db| db.query_row(&sql, &[], |row| row.get(0))).unwrap()
+        }
+    }
+
+    fn vital(value: f64) -> serde_json::Value {
+        serde_json::json!({ "vital_type": "weight", "value": value, "recorded_at": "2026-07-01T08:30" })
+    }
+
+    #[test]
+    fn requests_need_a_known_token() {
+        let mut server = Server::new();
+        assert_eq!(server.send("POST", "/v1/vitals", None, vital(72.0)).status, 401);
+        assert_eq!(server.send("POST", "/v1/vitals", Some("hs_guess"), vital(72.0)).status, 401);
+        assert_eq!(server.count("vitals"), 0);
+
+        let created = server.send("POST", "/v1/vitals", Some(TOKEN), vital(72.0));
+        assert_eq!(created.status, 201);
+        assert_eq!(created.body["inserted"], 1);
+        // Naive times are the user's wall clock (Lisbon, UTC+1 in July)
+        let recorded_at: String = server.ctx.db.read_blocking(|db| db.query_row("SELECT recorded_at FROM vitals", &[], |row| row.get(0))).unwrap();
+        assert_eq!(recorded_at, "2026-07-01T07:30:00Z");
+
+        let audit = server.audit();
+        assert_eq!(audit[0].status, 201);
+        assert_eq!(audit[0].client_name.as_deref(), Some("balança"));
+        assert_eq!(audit[0].record_ids.len(), 1);
+
+        // The record has its own audit trail entry, like any other insert
+        let log = server.ctx.db.read_blocking(|db| db.get_audit_log(Some("vitals"), Some(audit[0].record_ids[0]), 10)).unwrap();
+        assert_eq!((log[0].action.as_str(), log[0].source.as_str()), ("insert", SOURCE));
+        assert_eq!(log[0].after.as_ref().unwrap()["value"], 72.0);
+    }
+
+    #[test]
+    fn requests_are_routed_by_method_and_path() {
+        let mut server = Server::new();
+        let status = |r: ApiResponse| r.status;
+        assert_eq!(status(server.send("GET", "/v1/status", Some(TOKEN), serde_json::Value::Null)), 200);
+        assert_eq!(status(server.send("DELETE", "/v1/vitals", Some(TOKEN), serde_json::Value::Null)), 405);
+        assert_eq!(status(server.send("POST", "/v2/vitals", Some(TOKEN), vital(72.0))), 404);
+        assert_eq!(status(server.send("POST", "/v1/vitals", Some(TOKEN), serde_json::json!("72"))), 400);
+        assert_eq!(status(server.send("POST", "/v1/vitals", Some(TOKEN), serde_json::json!([]))), 422);
+
+        // One invalid record rejects the whole batch, pointing at its index
+        let rejected = server.send("POST", "/v1/vitals", Some(TOKEN), serde_json::json!([vital(72.0), vital(900.0)]));
+        assert_eq!(rejected.status, 422);
+        assert_eq!(rejected.body["errors"][0]["index"], 1);
+        assert_eq!(server.count("vitals"), 0);
+
+        let supplement = serde_json::json!({ "name": "Magnésio", "dosage": "400 mg", "category": "night" });
+        assert_eq!(status(server.send("POST", "/v1/supplements", Some(TOKEN), supplement)), 201);
+        assert_eq!(server.count("supplements"), 1);
+    }
+
+    #[test]
+    fn a_failed_write_rolls_back_the_whole_batch() {
+        let mut server = Server::new();
+        server.ctx.db.write_blocking(|db| db.execute(
+            "CREATE TEMP TRIGGER full_disk BEFORE INSERT ON vitals WHEN NEW.value = 99 BEGIN SELECT RAISE(ABORT, 'disco cheio'); END",
+            &[],
+        )).unwrap();
+
+        let failed = server.send("POST", "/v1/vitals", Some(TOKEN), serde_json::json!([vital(72.0), vital(99.0)]));
+        assert_eq!(failed.status, 500);
+        assert_eq!(server.count("vitals"), 0);
+        assert_eq!(server.count("audit_log"), 0);
+        assert!(server.audit()[0].record_ids.is_empty());
+
+        // Nor is a record kept when its audit entry can't be written
+        server.ctx.db.write_blocking(|db| db.execute(
+            "CREATE TEMP TRIGGER audit_full BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'disco cheio'); END",
+            &[],
+        )).unwrap();
+        assert_eq!(server.send("POST", "/v1/vitals", Some(TOKEN), vital(72.0)).status, 500);
+        assert_eq!(server.count("vitals"), 0);
+    }
+
+    #[test]
+    fn clients_are_rate_limited_and_refill_over_time() {
+        let mut server = Server::new();
+        for _ in 0..20 {
+            assert_eq!(server.send("GET", "/v1/status", Some(TOKEN), serde_json::Value::Null).status, 200);
+        }
+        let limited = server.send("GET", "/v1/status", Some(TOKEN), serde_json::Value::Null);
+        assert_eq!((limited.status, limited.retry_after), (429, Some(1)));
+
+        server.clock.advance(chrono::Duration::seconds(2));
+        assert_eq!(server.send("GET", "/v1/status", Some(TOKEN), serde_json::Value::Null).status, 200);
+    }
+
+    #[test]
+    fn rejected_requests_share_one_bucket_and_a_capped_audit_trail() {
+        let mut server = Server::new();
+        // 20 bad tokens, then the anonymous bucket for 127.0.0.1 is empty
+        let statuses: Vec<u16> = (0..30).map(|_| server.send("POST", "/v1/vitals", Some("hs_guess"), vital(72.0)).status).collect();
+        assert_eq!(statuses.iter().filter(|s| **s == 401).count(), 20);
+        assert_eq!(statuses.iter().filter(|s| **s == 429).count(), 10);
+        // A valid client is not held back by the guesser
+        assert_eq!(server.send("GET", "/v1/status", Some(TOKEN), serde_json::Value::Null).status, 200);
+
+        let rejected = |server: &Server| server.audit().into_iter().filter(|e| e.client_id.is_none()).collect::<Vec<_>>();
+        assert_eq!(rejected(&server).len(), 1);
+
+        server.clock.advance(chrono::Duration::minutes(1));
+        server.send("POST", "/v1/vitals", None, vital(72.0));
+        let rows = rejected(&server);
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].detail.as_deref().unwrap().ends_with("(+29 pedido(s) recusado(s) não registado(s))"), "{:?}", rows[0].detail);
+    }
+}
@@ -1,4 +1,9 @@
+// Tauri command handlers plus the types and helpers the services share with them. Without
+// the `gui` feature only the shared part is compiled; the handlers and their imports drop out.
+#![cfg_attr(not(feature = "gui"), allow(dead_code, unused_imports))]
+
 pub mod health;
+pub mod activity;
 pub mod agent;
 pub mod gemini;
 pub mod system;
@@ -7,3 +12,14 @@ pub mod scheduler;
 pub mod vitamin_d;
 pub mod settings;
 pub mod setup;
+pub mod typing;
+pub mod eye_care;
+pub mod presence;
+pub mod wearables;
+pub mod ingest;
+pub mod fhir;
+pub mod report;
+pub mod backup;
+pub mod tabular;
+pub mod records;
+pub mod simulation;
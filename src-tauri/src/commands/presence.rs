@@ -0,0 +1,272 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+#[cfg(feature = "gui")]
+use tauri::{Emitter, Manager, State};
+use crate::commands::activity::{BreakEntry, PresenceSession};
+use crate::commands::agent::AgentMessage;
+use crate::commands::settings;
+use crate::db::{Database, DbState};
+use crate::services::eye_care::RULE_REST_SECONDS;
+use crate::services::presence::{PresenceMachine, PresenceSignal, PresenceSnapshot, Transition, MIN_BREAK_SECONDS};
+use crate::services::time;
+
+pub const PRESENCE_CHANGED_EVENT: &str = "presence://changed";
+pub const WELCOME_BACK_EVENT: &str = "presence://welcome-back";
+const IDLE_CHECK_SECS: u64 = 30;
+const DIGEST_MAX_ITEMS: usize = 3;
+
+/// Agent message held back while the user was away
+#[derive(Debug, Serialize, Clone)]
+pub struct DeferredMessage {
+    pub created_at: String,
+    pub category: String,
+    pub priority: String,
+    pub text: String,
+}
+
+/// Presence event from the webcam detector or an input hook
+#[derive(Debug, Deserialize)]
+pub struct PresenceEventInput {
+    pub state: String,          // present | away | activity
+    pub at: Option<String>,     // defaults to now; for "away", when the user was last seen
+    pub source: Option<String>, // webcam | keyboard | evdev
+}
+
+/// Payload of `presence://welcome-back`: everything missed, delivered once on return
+#[derive(Debug, Serialize, Clone)]
+pub struct WelcomeBackDigest {
+    pub away_minutes: i64,
+    pub text: String,
+    pub messages: Vec<DeferredMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresenceStatus {
+    #[serde(flatten)]
+    pub presence: PresenceSnapshot,
+    pub deferred_messages: u32,
+}
+
+struct OpenSession {
+    id: i64,
+    started_at: String,
+    source: String,
+}
+
+#[derive(Default)]
+struct Tracker {
+    machine: PresenceMachine,
+    open_session: Option<OpenSession>, // the session being recorded
+}
+
+/// Backend owner of presence: the state machine plus the session it is recording.
+/// Lock order: this state first, then the database.
+#[derive(Default)]
+pub struct PresenceState(Mutex<Tracker>);
+
+/// Whether the user is at the screen; None until the first presence signal of this run
+#[cfg(feature = "gui")]
+pub fn at_screen(app_handle: &tauri::AppHandle) -> Option<bool> {
+    let state = app_handle.try_state::<PresenceState>()?;
+    let tracker = state.0.lock().ok()?;
+    tracker.machine.is_present()
+}
+
+#[cfg(feature = "gui")]
+pub fn is_away(app_handle: &tauri::AppHandle) -> bool {
+    at_screen(app_handle) == Some(false)
+}
+
+/// Pass a message through, or hold it for the welcome-back digest when the user is away
+/// (`is_away`, read before taking the writer to keep the lock order).
+/// High-priority messages (voice responses) are never deferred.
+pub fn deliver_or_defer(
+    db: &Database,
+    message: AgentMessage,
+    away: bool,
+    now: DateTime<Utc>,
+) -> Result<Option<AgentMessage>, String> {
+    if message.priority == "high" || !away {
+        return Ok(Some(message));
+    }
+    db.insert_deferred_message(&DeferredMessage {
+        created_at: time::format_utc(now),
+        category: message.category,
+        priority: message.priority,
+        text: message.text,
+    }).map_err(|e| e.to_string())?;
+    Ok(None)
+}
+
+fn welcome_back_digest(db: &mut Database, at: DateTime<Utc>, away_seconds: i64) -> Result<Option<WelcomeBackDigest>, String> {
+    let messages = db.take_deferred_messages(&time::format_utc(at)).map_err(|e| e.to_string())?;
+    if messages.is_empty() && away_seconds < MIN_BREAK_SECONDS {
+        return Ok(None);
+    }
+
+    let away_minutes = away_seconds / 60;
+    let mut text = format!("Bem-vindo de volta. Estiveste fora {} min.", away_minutes);
+    if messages.is_empty() {
+        text.push_str(" Nada pendente.");
+    } else {
+        let items: Vec<&str> = messages.iter().take(DIGEST_MAX_ITEMS).map(|m| m.text.as_str()).collect();
+        text.push_str(&format!(" Enquanto estavas fora: {}", items.join(" ")));
+        if messages.len() > DIGEST_MAX_ITEMS {
+            text.push_str(&format!(" (+{} no painel)", messages.len() - DIGEST_MAX_ITEMS));
+        }
+    }
+    Ok(Some(WelcomeBackDigest { away_minutes, text, messages }))
+}
+
+/// Record what a transition means: sessions, breaks or eye rests, and the digest on return
+fn apply_transition(
+    db: &mut Database,
+    tracker: &mut Tracker,
+    transition: Transition,
+    source: &str,
+) -> Result<Option<WelcomeBackDigest>, String> {
+    match transition {
+        Transition::Arrived { at, away_since } => {
+            let started_at = time::format_utc(at);
+            let id = db.upsert_presence_session(&PresenceSession {
+                id: None,
+                started_at: started_at.clone(),
+                ended_at: None,
+                source: Some(source.to_string()),
+            }).map_err(|e| e.to_string())?;
+            tracker.open_session = Some(OpenSession { id, started_at: started_at.clone(), source: source.to_string() });
+
+            let Some(since) = away_since else { return Ok(None) };
+            let away_seconds = (at - since).num_seconds();
+            if away_seconds >= MIN_BREAK_SECONDS {
+                db.insert_break(&BreakEntry {
+                    id: None,
+                    started_at: time::format_utc(since),
+                    ended_at: started_at,
+                    reason: Some("away".to_string()),
+                }).map_err(|e| e.to_string())?;
+            } else if away_seconds >= RULE_REST_SECONDS {
+                db.insert_eye_rest(&time::format_utc(since), &started_at, "presence")
+                    .map_err(|e| e.to_string())?;
+            }
+            welcome_back_digest(db, at, away_seconds)
+        }
+        Transition::Left { at } => {
+            if let Some(session) = tracker.open_session.take() {
+                db.upsert_presence_session(&PresenceSession {
+                    id: Some(session.id),
+                    started_at: session.started_at,
+                    ended_at: Some(time::format_utc(at)),
+                    source: Some(session.source),
+                }).map_err(|e| e.to_string())?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Feed one signal through the state machine, persist the outcome and notify the HUD
+#[cfg(feature = "gui")]
+pub fn report(
+    app_handle: &tauri::AppHandle,
+    signal: PresenceSignal,
+    at: DateTime<Utc>,
+    source: &str,
+) -> Result<PresenceSnapshot, String> {
+    advance(app_handle, |machine| machine.signal(signal, at), source)
+}
+
+#[cfg(feature = "gui")]
+fn advance<F>(app_handle: &tauri::AppHandle, step: F, source: &str) -> Result<PresenceSnapshot, String>
+where
+    F: FnOnce(&mut PresenceMachine) -> Option<Transition>,
+{
+    let (Some(presence), Some(db_state)) = (
+        app_handle.try_state::<PresenceState>(),
+        app_handle.try_state::<DbState>(),
+    ) else {
+        return Err("Presença indisponível.".to_string());
+    };
+
+    let (transition, snapshot, digest) = {
+        let mut tracker = presence.0.lock().map_err(|e| e.to_string())?;
+        let transition = step(&mut tracker.machine);
+        let digest = match transition {
+            Some(t) => db_state.write_blocking(|db| apply_transition(db, &mut tracker, t, source))?,
+            None => None,
+        };
+        (transition, tracker.machine.snapshot(), digest)
+    }; // locks released before emitting
+
+    if transition.is_some() {
+        if let Err(e) = app_handle.emit(PRESENCE_CHANGED_EVENT, &snapshot) {
+            log::warn!("Failed to broadcast presence change: {}", e);
+        }
+    }
+    if let Some(digest) = digest {
+        if let Err(e) = app_handle.emit(WELCOME_BACK_EVENT, digest) {
+            log::warn!("Failed to deliver welcome-back digest: {}", e);
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Close sessions left open by a previous run and start idle detection
+#[cfg(feature = "gui")]
+pub fn spawn_idle_monitor(app_handle: tauri::AppHandle) {
+    if let Some(db_state) = app_handle.try_state::<DbState>() {
+        match db_state.write_blocking(|db| db.close_stale_presence_sessions()) {
+            Ok(0) => {}
+            Ok(n) => log::info!("Closed {} presence session(s) left open by the last run", n),
+            Err(e) => log::warn!("Failed to close stale presence sessions: {}", e),
+        }
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(IDLE_CHECK_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = advance(&app_handle, |machine| machine.tick(super::system::clock(&app_handle).now()), "idle") {
+                log::warn!("Idle check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Presence event from the HUD: "present" (also as a heartbeat), "away" or "activity"
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn report_presence(
+    event: PresenceEventInput,
+    app_handle: tauri::AppHandle,
+) -> Result<PresenceSnapshot, String> {
+    let signal = PresenceSignal::parse(&event.state)
+        .ok_or_else(|| format!("Estado de presença desconhecido: '{}'", event.state))?;
+    let now = super::system::clock(&app_handle).now();
+    let at = match &event.at {
+        Some(raw) => time::parse_instant(raw, settings::current_timezone(&app_handle))
+            .ok_or_else(|| format!("Data/hora inválida: '{}'", raw))?
+            .min(now),
+        None => now,
+    };
+    let source = event.source.as_deref().unwrap_or(match signal {
+        PresenceSignal::Activity => "keyboard",
+        _ => "webcam",
+    });
+    report(&app_handle, signal, at, source)
+}
+
+/// Current presence state and how many messages are waiting for the user's return
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_presence_status(
+    state: State<'_, DbState>,
+    presence: State<'_, PresenceState>,
+) -> Result<PresenceStatus, String> {
+    let snapshot = presence.0.lock().map_err(|e| e.to_string())?.machine.snapshot();
+    Ok(PresenceStatus {
+        presence: snapshot,
+        deferred_messages: state.read(|db| db.count_deferred_messages()).await?,
+    })
+}
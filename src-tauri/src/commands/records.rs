@@ -0,0 +1,415 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::settings;
+use crate::db::{Database, DbState, RECORD_TABLES};
+use crate::services::fhir::{LabRecord, ScheduleRecord};
+use crate::services::time;
+
+// Edits, soft deletes and the audit trail for health records.
+//
+// Changes to supplements, vitals, lab_results and health_schedule land in `audit_log`
+// in the same transaction, with the row as JSON before and after. Single-record writes
+// get one entry each, as do records posted to the local API (which also keeps its own
+// per-request `ingest_audit`); bulk imports get one summary entry per table. Changes made
+// by the agent, or by voice through it, can be undone newest first.
+
+/// Who made a change
+pub const SOURCES: [&str; 6] = ["ui", "voice", "agent", "import", "cli", "api"];
+const DEFAULT_AUDIT_LIMIT: u32 = 100;
+
+/// One `audit_log` row
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: Option<i64>,
+    pub changed_at: String,
+    pub record_type: String,    // table name
+    pub record_id: Option<i64>, // None for bulk imports
+    pub action: String,         // insert | update | delete | restore | import | undo
+    pub source: String,         // ui | voice | agent | import | cli | api
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub undone_at: Option<String>,
+}
+
+/// Validate a caller-supplied source, falling back to `default`
+pub fn parse_source(source: Option<&str>, default: &'static str) -> Result<&'static str, String> {
+    match source {
+        None => Ok(default),
+        Some(raw) => SOURCES.iter().copied()
+            .find(|s| *s == raw)
+            .ok_or_else(|| format!("Origem desconhecida '{}' (aceites: {})", raw, SOURCES.join(", "))),
+    }
+}
+
+fn parse_record_type(raw: &str) -> Result<&'static str, String> {
+    RECORD_TABLES.iter().copied()
+        .find(|t| *t == raw)
+        .ok_or_else(|| format!("Tipo de registo desconhecido '{}' (aceites: {})", raw, RECORD_TABLES.join(", ")))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_audit(
+    db: &Database,
+    at: &str,
+    record_type: &str,
+    record_id: Option<i64>,
+    action: &str,
+    source: &str,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> rusqlite::Result<i64> {
+    db.insert_audit(&AuditEntry {
+        id: None,
+        changed_at: at.to_string(),
+        record_type: record_type.to_string(),
+        record_id,
+        action: action.to_string(),
+        source: source.to_string(),
+        before,
+        after,
+        undone_at: None,
+    })
+}
+
+/// Audit a freshly inserted row at `at`; call inside the inserting transaction
+pub fn audit_insert(db: &Database, at: &str, record_type: &str, id: i64, source: &str) -> rusqlite::Result<()> {
+    let after = db.record_snapshot(record_type, id)?;
+    write_audit(db, at, record_type, Some(id), "insert", source, None, after)?;
+    Ok(())
+}
+
+/// One summary entry for a bulk import into `record_type`
+pub fn audit_import(db: &Database, at: &str, record_type: &str, file: &str, inserted: usize) -> rusqlite::Result<()> {
+    if inserted > 0 {
+        let after = json!({ "file": file, "inserted": inserted });
+        write_audit(db, at, record_type, None, "import", "import", None, Some(after))?;
+    }
+    Ok(())
+}
+
+/// The app clock's current instant, stamped on audit entries
+#[cfg(feature = "gui")]
+fn now(app_handle: &tauri::AppHandle) -> String {
+    time::format_utc(super::system::clock(app_handle).now())
+}
+
+/// Snapshot, edit, snapshot and audit in one transaction
+async fn apply_update<F>(
+    state: &DbState,
+    at: String,
+    record_type: &'static str,
+    id: i64,
+    source: &'static str,
+    update: F,
+) -> Result<(), String>
+where
+    F: FnOnce(&Database) -> rusqlite::Result<bool> + Send + 'static,
+{
+    let updated = state.write(move |db| db.transaction(|db| {
+        let before = db.record_snapshot(record_type, id)?;
+        if !update(db)? {
+            return Ok(false);
+        }
+        let after = db.record_snapshot(record_type, id)?;
+        write_audit(db, &at, record_type, Some(id), "update", source, before, after)?;
+        Ok::<_, rusqlite::Error>(true)
+    })).await?;
+    if updated { Ok(()) } else { Err("Registo não encontrado ou apagado.".to_string()) }
+}
+
+/// Correct a logged supplement intake
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn update_supplement(
+    state: State<'_, DbState>,
+    id: i64,
+    mut entry: SupplementEntry,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let source = parse_source(source.as_deref(), "ui")?;
+    if entry.name.trim().is_empty() {
+        return Err("O nome não pode ficar vazio.".to_string());
+    }
+    entry.taken_at = time::normalize(&entry.taken_at, settings::current_timezone(&app_handle))?;
+    apply_update(&state, now(&app_handle), "supplements", id, source, move |db| db.update_supplement(id, &entry)).await
+}
+
+/// Correct a vital reading
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn update_vital(
+    state: State<'_, DbState>,
+    id: i64,
+    mut entry: VitalEntry,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let source = parse_source(source.as_deref(), "ui")?;
+    if !entry.value.is_finite() {
+        return Err("Valor inválido.".to_string());
+    }
+    entry.recorded_at = time::normalize(&entry.recorded_at, settings::current_timezone(&app_handle))?;
+    apply_update(&state, now(&app_handle), "vitals", id, source, move |db| db.update_vital(id, &entry)).await
+}
+
+/// Correct a lab result (e.g. a value misread by OCR)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn update_lab_result(
+    state: State<'_, DbState>,
+    id: i64,
+    entry: LabRecord,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let source = parse_source(source.as_deref(), "ui")?;
+    if entry.marker.trim().is_empty() || !entry.value.is_finite() {
+        return Err("Marcador e valor são obrigatórios.".to_string());
+    }
+    if let Some(date) = entry.test_date.as_deref() {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("Data inválida: '{}'", date))?;
+    }
+    apply_update(&state, now(&app_handle), "lab_results", id, source, move |db| db.update_lab_record(id, &entry)).await
+}
+
+/// Reschedule, rename or complete an exam
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn update_scheduled_exam(
+    state: State<'_, DbState>,
+    id: i64,
+    entry: ScheduleRecord,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let source = parse_source(source.as_deref(), "ui")?;
+    chrono::NaiveDate::parse_from_str(&entry.scheduled_date, "%Y-%m-%d")
+        .map_err(|_| format!("Data inválida: '{}'", entry.scheduled_date))?;
+    apply_update(&state, now(&app_handle), "health_schedule", id, source, move |db| db.update_schedule_record(id, &entry)).await
+}
+
+/// Soft-delete or restore one record with its audit entry
+async fn set_deleted(state: &DbState, at: String, record_type: &'static str, id: i64, delete: bool, source: &'static str) -> Result<(), String> {
+    let changed = state.write(move |db| db.transaction(|db| {
+        let before = db.record_snapshot(record_type, id)?;
+        if !db.set_deleted(record_type, id, delete.then_some(at.as_str()))? {
+            return Ok(false);
+        }
+        let after = db.record_snapshot(record_type, id)?;
+        let action = if delete { "delete" } else { "restore" };
+        write_audit(db, &at, record_type, Some(id), action, source, before, after)?;
+        Ok::<_, rusqlite::Error>(true)
+    })).await?;
+    match (changed, delete) {
+        (true, _) => Ok(()),
+        (false, true) => Err("Registo não encontrado ou já apagado.".to_string()),
+        (false, false) => Err("Registo não encontrado ou não está apagado.".to_string()),
+    }
+}
+
+/// Soft-delete a record: it disappears from every view but stays restorable.
+/// `record_type` is supplements | vitals | lab_results | health_schedule.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn delete_record(
+    state: State<'_, DbState>,
+    record_type: String,
+    id: i64,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let record_type = parse_record_type(&record_type)?;
+    set_deleted(&state, now(&app_handle), record_type, id, true, parse_source(source.as_deref(), "ui")?).await
+}
+
+/// Bring back a soft-deleted record
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn restore_record(
+    state: State<'_, DbState>,
+    record_type: String,
+    id: i64,
+    source: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let record_type = parse_record_type(&record_type)?;
+    set_deleted(&state, now(&app_handle), record_type, id, false, parse_source(source.as_deref(), "ui")?).await
+}
+
+/// Change history, newest first; filter by table and/or record
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_audit_log(
+    state: State<'_, DbState>,
+    record_type: Option<String>,
+    record_id: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<AuditEntry>, String> {
+    let record_type = record_type.as_deref().map(parse_record_type).transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, 1000);
+    state.read(move |db| db.get_audit_log(record_type, record_id, limit)).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoSummary {
+    pub message: String,
+    pub undone: AuditEntry, // the agent change that was reverted
+}
+
+/// Revert the most recent change made by the agent that hasn't been undone.
+/// Refused when the record was changed again afterwards, so a later manual
+/// correction is never overwritten.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn undo_last_agent_action(state: State<'_, DbState>, app_handle: tauri::AppHandle) -> Result<UndoSummary, String> {
+    let now = now(&app_handle);
+    state.write(move |db| undo_last(db, &now)).await?
+}
+
+/// `undo_last_agent_action` at `now`, in one transaction; the inner error is the user-facing refusal
+pub fn undo_last(db: &Database, now: &str) -> rusqlite::Result<Result<UndoSummary, String>> {
+    db.transaction(|db| {
+        let Some(entry) = db.last_agent_change()? else {
+            return Ok(Err("Não há ações do agente por desfazer.".to_string()));
+        };
+        let (Some(id), Some(audit_id)) = (entry.record_id, entry.id) else {
+            return Ok(Err("Entrada de auditoria incompleta.".to_string()));
+        };
+        let table = entry.record_type.as_str();
+        if db.changed_after(table, id, audit_id)? {
+            return Ok(Err("O registo foi alterado depois desta ação; corrija-o manualmente.".to_string()));
+        }
+
+        let before = db.record_snapshot(table, id)?;
+        let reverted = match entry.action.as_str() {
+            "insert" | "restore" => db.set_deleted(table, id, Some(now))?,
+            "delete" => db.set_deleted(table, id, None)?,
+            "update" => match &entry.before {
+                Some(snapshot) => db.restore_snapshot(table, id, snapshot)?,
+                None => false,
+            },
+            _ => false,
+        };
+        if !reverted {
+            return Ok(Err("Já não é possível desfazer esta ação.".to_string()));
+        }
+        let after = db.record_snapshot(table, id)?;
+        write_audit(db, now, table, Some(id), "undo", "ui", before, after)?;
+        db.mark_audit_undone(audit_id, now)?;
+
+        let label = match entry.action.as_str() {
+            "insert" => "registo removido",
+            "update" => "alteração revertida",
+            "delete" => "registo reposto",
+            _ => "reposição revertida",
+        };
+        Ok(Ok(UndoSummary {
+            message: format!("Ação do agente desfeita: {}.", label),
+            undone: entry,
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::context::testing::context_at;
+
+    const AT: &str = "2026-07-01T09:00:00Z";
+
+    fn weight(value: f64) -> VitalEntry {
+        VitalEntry {
+            id: None,
+            vital_type: "weight".to_string(),
+            value,
+            unit: "kg".to_string(),
+            recorded_at: "2026-07-01T07:30:00Z".to_string(),
+            source: "manual".to_string(),
+        }
+    }
+
+    /// A vital logged by `source`, audited like the agent's own writes
+    fn logged(state: &DbState, value: f64, source: &str) -> i64 {
+        state.write_blocking(|db| db.transaction(|db| {
+            let id = db.insert_vital(&weight(value))?;
+            audit_insert(db, AT, "vitals", id, source)?;
+            Ok::<_, rusqlite::Error>(id)
+        })).unwrap()
+    }
+
+    fn value(state: &DbState, id: i64) -> (f64, Option<String>) {
+        state.read_blocking(|db| db.query_row(
+            "SELECT value, deleted_at FROM vitals WHERE id = ?1",
+            &[&id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )).unwrap()
+    }
+
+    fn undo(state: &DbState, now: &str) -> Result<UndoSummary, String> {
+        state.write_blocking(|db| undo_last(db, now)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn delete_and_restore_are_audited_once() {
+        let (ctx, _, _) = context_at(AT, None);
+        let id = logged(&ctx.db, 72.0, "ui");
+
+        set_deleted(&ctx.db, AT.to_string(), "vitals", id, true, "ui").await.unwrap();
+        assert_eq!(value(&ctx.db, id).1.as_deref(), Some(AT));
+        let again = set_deleted(&ctx.db, AT.to_string(), "vitals", id, true, "ui").await.unwrap_err();
+        assert_eq!(again, "Registo não encontrado ou já apagado.");
+
+        set_deleted(&ctx.db, AT.to_string(), "vitals", id, false, "ui").await.unwrap();
+        assert_eq!(value(&ctx.db, id).1, None);
+        let missing = set_deleted(&ctx.db, AT.to_string(), "vitals", id + 1, false, "ui").await.unwrap_err();
+        assert_eq!(missing, "Registo não encontrado ou não está apagado.");
+
+        let log = ctx.db.read_blocking(|db| db.get_audit_log(Some("vitals"), Some(id), 10)).unwrap();
+        let actions: Vec<&str> = log.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["restore", "delete", "insert"]);
+        assert_eq!(log[1].before.as_ref().unwrap()["deleted_at"], Value::Null);
+        assert_eq!(log[1].after.as_ref().unwrap()["deleted_at"], AT);
+    }
+
+    #[tokio::test]
+    async fn agent_changes_are_undone_newest_first() {
+        let (ctx, _, _) = context_at(AT, None);
+        let inserted = logged(&ctx.db, 72.0, "agent");
+        let edited = logged(&ctx.db, 80.0, "ui");
+        apply_update(&ctx.db, AT.to_string(), "vitals", edited, "voice", move |db| db.update_vital(edited, &weight(81.5)))
+            .await
+            .unwrap();
+
+        // The voice edit goes back to the snapshot taken before it
+        let summary = undo(&ctx.db, "2026-07-01T10:00:00Z").unwrap();
+        assert_eq!(summary.message, "Ação do agente desfeita: alteração revertida.");
+        assert_eq!(value(&ctx.db, edited), (80.0, None));
+
+        // Then the agent's insert is soft-deleted, and nothing is left
+        let summary = undo(&ctx.db, "2026-07-01T10:05:00Z").unwrap();
+        assert_eq!(summary.undone.record_id, Some(inserted));
+        assert_eq!(value(&ctx.db, inserted).1.as_deref(), Some("2026-07-01T10:05:00Z"));
+        assert_eq!(undo(&ctx.db, "2026-07-01T10:10:00Z").unwrap_err(), "Não há ações do agente por desfazer.");
+
+        let log = ctx.db.read_blocking(|db| db.get_audit_log(None, None, 10)).unwrap();
+        assert_eq!(log.iter().filter(|e| e.action == "undo").count(), 2);
+        assert!(log.iter().filter(|e| e.source == "agent" || e.source == "voice").all(|e| e.undone_at.is_some()));
+    }
+
+    #[tokio::test]
+    async fn undo_leaves_later_manual_corrections_alone() {
+        let (ctx, _, _) = context_at(AT, None);
+        let id = logged(&ctx.db, 72.0, "agent");
+        apply_update(&ctx.db, AT.to_string(), "vitals", id, "ui", move |db| db.update_vital(id, &weight(71.2)))
+            .await
+            .unwrap();
+
+        let refused = undo(&ctx.db, "2026-07-01T10:00:00Z").unwrap_err();
+        assert_eq!(refused, "O registo foi alterado depois desta ação; corrija-o manualmente.");
+        assert_eq!(value(&ctx.db, id), (71.2, None));
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::agent::PROTOCOLS;
+use crate::commands::settings;
+use crate::db::DbState;
+use crate::services::health_report::{self, Language, ProtocolAdherence, ReportData};
+use crate::services::time;
+
+const WINDOW_DAYS: u32 = 90;
+const LAB_LOOKBACK_DAYS: i64 = 730; // markers last measured before this are left out
+
+#[derive(Debug, Serialize)]
+pub struct HealthReportSummary {
+    pub path: String,
+    pub pages: usize,
+    pub markers: usize,
+    pub protocols: usize,
+    pub exams: usize,
+}
+
+fn default_report_path(lang: Language, now: DateTime<Utc>) -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir);
+    let stem = match lang {
+        Language::Pt => "holoself-relatorio",
+        Language::En => "holoself-report",
+    };
+    dir.join(format!("{}-{}.pdf", stem, now.format("%Y%m%d")))
+}
+
+/// Write a printable PDF for a medical appointment: lab markers with reference ranges
+/// and trends, 90-day supplement adherence, a vitals summary and pending exams.
+/// `language` is "pt" (default) or "en".
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn generate_health_report(
+    state: State<'_, DbState>,
+    language: Option<String>,
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<HealthReportSummary, String> {
+    let lang = language.as_deref().map(Language::parse).transpose()?.unwrap_or(Language::Pt);
+    let tz = settings::current_timezone(&app_handle);
+    let now = super::system::clock(&app_handle).now();
+    let today = now.with_timezone(&tz).date_naive();
+    let window_start = today - Duration::days(WINDOW_DAYS as i64 - 1);
+    let (from, _) = time::local_day_bounds(window_start, tz);
+    let (_, to) = time::local_day_bounds(today, tz);
+
+    let (labs, intakes, vitals, schedule) = state.read(move |db| {
+        Ok::<_, rusqlite::Error>((
+            db.get_lab_results()?,
+            db.get_supplements(&from, &to)?,
+            db.get_vital_summaries(&from, &to, tz)?,
+            db.get_health_schedule()?,
+        ))
+    }).await?;
+
+    // Only markers measured recently, but with their full history for the trend
+    let lab_cutoff = (today - Duration::days(LAB_LOOKBACK_DAYS)).format("%Y-%m-%d").to_string();
+    let recent: BTreeSet<String> = labs.iter()
+        .filter(|lab| lab.test_date.as_deref().is_some_and(|d| d >= lab_cutoff.as_str()))
+        .map(|lab| lab.marker.trim().to_lowercase())
+        .collect();
+    let labs = labs.into_iter()
+        .filter(|lab| recent.contains(&lab.marker.trim().to_lowercase()))
+        .collect();
+
+    // Adherence counts local calendar days with at least one intake of the protocol
+    let adherence: Vec<ProtocolAdherence> = PROTOCOLS.iter()
+        .map(|protocol| {
+            let days: BTreeSet<_> = intakes.iter()
+                .filter(|intake| intake.name == protocol.name)
+                .filter_map(|intake| time::parse_instant(&intake.taken_at, tz))
+                .map(|instant| instant.with_timezone(&tz).date_naive())
+                .collect();
+            let since = days.first().copied();
+            let days_expected = since.map_or(WINDOW_DAYS, |first| (today - first).num_days() as u32 + 1);
+            ProtocolAdherence {
+                name: protocol.name.to_string(),
+                dosage: protocol.dosage.to_string(),
+                days_taken: days.len() as u32,
+                days_expected,
+                since,
+            }
+        })
+        .collect();
+
+    let exams: Vec<_> = schedule.into_iter().filter(|exam| !exam.completed).collect();
+
+    let data = ReportData {
+        generated_on: today,
+        window_days: WINDOW_DAYS,
+        labs,
+        adherence,
+        vitals,
+        exams,
+    };
+    let report = health_report::render(&data, lang);
+
+    let path = path.map(PathBuf::from).unwrap_or_else(|| default_report_path(lang, now));
+    std::fs::write(&path, &report.bytes).map_err(|e| format!("Falha ao gravar {:?}: {}", path, e))?;
+
+    Ok(HealthReportSummary {
+        path: path.to_string_lossy().into_owned(),
+        pages: report.pages,
+        markers: report.markers,
+        protocols: data.adherence.len(),
+        exams: data.exams.len(),
+    })
+}
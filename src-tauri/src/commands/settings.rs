@@ -1,35 +1,305 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+#[cfg(feature = "gui")]
+use tauri::{Emitter, Manager};
+use crate::db::DbState;
+use crate::services::context::{Config, ServiceContext};
+use crate::services::secrets;
+
+/// Current settings.json schema version. Bump it and extend `upgrade` when a
+/// field is renamed or its meaning changes; plain additions only need a default.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Event broadcast (with masked keys) after settings are saved
+pub const SETTINGS_CHANGED_EVENT: &str = "settings://changed";
+
+const DEFAULT_TIMEZONE: &str = "Europe/Lisbon";
+const BODY_AREAS: [&str; 4] = ["face_hands", "face_arms", "arms_legs", "swimsuit"];
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppSettings {
+    pub version: u32,
     pub gemini_api_key: String,
     pub cartesia_api_key: String,
     pub cartesia_voice_id: String,
     pub skin_type: u8,
     pub latitude: f64,
     pub longitude: f64,
-    pub timezone: String,
-    pub sleep_anchor_hour: u8,  // Hour to start sleep protocol (default: 2 = 02:00)
+    pub timezone: String,          // IANA name, e.g. "Europe/Lisbon"
+    pub sleep_anchor_hour: u8,     // Hour to start sleep protocol (default: 2 = 02:00)
+    pub exposed_body_area: String, // face_hands | face_arms | arms_legs | swimsuit
+    pub keyboard_cadence: bool,    // opt-in system-wide typing cadence (Linux evdev)
+    pub eye_care_reminders: bool,  // 20-20-20 reminders
+    pub ingest_api_enabled: bool,  // opt-in local HTTP API for sensor scripts
+    pub ingest_api_port: u16,      // listens on 127.0.0.1 only
+    pub backup_enabled: bool,      // scheduled automatic backups
+    pub backup_interval_hours: u32,
+    pub backup_keep: u32,          // scheduled backups kept; older ones are deleted
+    pub backup_encrypt: bool,      // with the passphrase from the secret store
+    pub backup_dir: String,        // empty = <app data>/backups
+    pub whisper_binary_path: String, // saved by setup; empty = search the usual places
+    pub whisper_model_path: String,
+    pub ffmpeg_path: String,
+    /// Where an unreadable settings.json was set aside when these defaults replaced it.
+    /// Shown to the user once loaded; never read from or written to the file.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub corrupt_backup: Option<String>,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             gemini_api_key: String::new(),
             cartesia_api_key: String::new(),
             cartesia_voice_id: "a0e99841-438c-4a64-b679-ae501e7d6091".to_string(),
             skin_type: 4,
             latitude: 38.7223,
             longitude: -9.1393,
-            timezone: chrono::Local::now().format("%Z").to_string(),
+            timezone: detect_timezone(),
             sleep_anchor_hour: 2,
+            exposed_body_area: "face_arms".to_string(),
+            keyboard_cadence: false,
+            eye_care_reminders: true,
+            ingest_api_enabled: false,
+            ingest_api_port: crate::services::ingest_api::DEFAULT_PORT,
+            backup_enabled: false,
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            backup_encrypt: false,
+            backup_dir: String::new(),
+            whisper_binary_path: String::new(),
+            whisper_model_path: String::new(),
+            ffmpeg_path: String::new(),
+            corrupt_backup: None,
         }
     }
 }
 
-fn settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+impl AppSettings {
+    /// Check every field and report all problems at once (PT, shown in the settings panel)
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            errors.push("latitude deve estar entre -90 e 90".to_string());
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            errors.push("longitude deve estar entre -180 e 180".to_string());
+        }
+        if !(1..=6).contains(&self.skin_type) {
+            errors.push("tipo de pele deve estar entre 1 e 6".to_string());
+        }
+        if self.sleep_anchor_hour > 23 {
+            errors.push("hora âncora de sono deve estar entre 0 e 23".to_string());
+        }
+        if self.timezone.parse::<chrono_tz::Tz>().is_err() {
+            errors.push(format!("fuso horário desconhecido \"{}\" (use um nome IANA, ex. Europe/Lisbon)", self.timezone));
+        }
+        if !BODY_AREAS.contains(&self.exposed_body_area.as_str()) {
+            errors.push(format!("área exposta desconhecida \"{}\"", self.exposed_body_area));
+        }
+        if self.ingest_api_port < 1024 {
+            errors.push("porta da API local deve estar entre 1024 e 65535".to_string());
+        }
+        if !(1..=720).contains(&self.backup_interval_hours) {
+            errors.push("intervalo das cópias de segurança deve estar entre 1 e 720 horas".to_string());
+        }
+        if !(1..=100).contains(&self.backup_keep) {
+            errors.push("número de cópias a manter deve estar entre 1 e 100".to_string());
+        }
+        if !self.backup_dir.is_empty() && !Path::new(&self.backup_dir).is_absolute() {
+            errors.push("pasta das cópias de segurança deve ser um caminho absoluto".to_string());
+        }
+        for (path, what) in self.tool_paths() {
+            if !path.is_empty() && !Path::new(path).is_absolute() {
+                errors.push(format!("caminho do {} deve ser absoluto", what));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Configurações inválidas: {}.", errors.join("; ")))
+        }
+    }
+
+    /// Replace invalid fields with defaults so a hand-edited file can't break the backend
+    fn sanitize(mut self) -> Self {
+        let defaults = Self::default();
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            self.latitude = defaults.latitude;
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            self.longitude = defaults.longitude;
+        }
+        if !(1..=6).contains(&self.skin_type) {
+            self.skin_type = defaults.skin_type;
+        }
+        if self.sleep_anchor_hour > 23 {
+            self.sleep_anchor_hour = defaults.sleep_anchor_hour;
+        }
+        if self.timezone.parse::<chrono_tz::Tz>().is_err() {
+            self.timezone = defaults.timezone;
+        }
+        if !BODY_AREAS.contains(&self.exposed_body_area.as_str()) {
+            self.exposed_body_area = defaults.exposed_body_area;
+        }
+        if self.ingest_api_port < 1024 {
+            self.ingest_api_port = defaults.ingest_api_port;
+        }
+        if !(1..=720).contains(&self.backup_interval_hours) {
+            self.backup_interval_hours = defaults.backup_interval_hours;
+        }
+        if !(1..=100).contains(&self.backup_keep) {
+            self.backup_keep = defaults.backup_keep;
+        }
+        if !self.backup_dir.is_empty() && !Path::new(&self.backup_dir).is_absolute() {
+            self.backup_dir = defaults.backup_dir;
+        }
+        for path in [&mut self.whisper_binary_path, &mut self.whisper_model_path, &mut self.ffmpeg_path] {
+            if !Path::new(path.as_str()).is_absolute() {
+                path.clear();
+            }
+        }
+        self
+    }
+
+    fn tool_paths(&self) -> [(&String, &'static str); 3] {
+        [
+            (&self.whisper_binary_path, "motor de transcrição"),
+            (&self.whisper_model_path, "modelo de voz"),
+            (&self.ffmpeg_path, "conversor de áudio"),
+        ]
+    }
+
+    /// Copy with API keys replaced by their masks (the only form that leaves the backend)
+    fn masked(&self) -> Self {
+        let mut settings = self.clone();
+        settings.gemini_api_key = secrets::get(secrets::GEMINI_API_KEY)
+            .map(|k| secrets::mask(&k))
+            .unwrap_or_default();
+        settings.cartesia_api_key = secrets::get(secrets::CARTESIA_API_KEY)
+            .map(|k| secrets::mask(&k))
+            .unwrap_or_default();
+        settings
+    }
+}
+
+/// Live settings shared by every command; replaced on save so changes apply without a restart
+pub struct SettingsState(pub RwLock<AppSettings>);
+
+impl SettingsState {
+    pub fn load(config_dir: &Path) -> Self {
+        Self(RwLock::new(read_settings_file(&config_dir.join("settings.json"), true)))
+    }
+}
+
+/// Settings from the settings.json in `config_dir`, for callers without an app handle.
+/// Read-only: a corrupt file is left where it is for the app to deal with.
+pub fn load_settings_from(config_dir: &Path) -> AppSettings {
+    read_settings_file(&config_dir.join("settings.json"), false)
+}
+
+/// Best-effort IANA zone of the machine: $TZ, then the /etc/localtime symlink
+fn detect_timezone() -> String {
+    let from_env = std::env::var("TZ").ok()
+        .map(|tz| tz.trim_start_matches(':').to_string());
+    let from_localtime = std::fs::read_link("/etc/localtime").ok()
+        .and_then(|target| {
+            let target = target.to_string_lossy().into_owned();
+            target.split_once("zoneinfo/").map(|(_, name)| name.to_string())
+        });
+
+    from_env.into_iter()
+        .chain(from_localtime)
+        .find(|name| name.parse::<chrono_tz::Tz>().is_ok())
+        .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string())
+}
+
+/// Bring an older settings.json up to `SETTINGS_VERSION` before typed parsing
+fn upgrade(mut raw: serde_json::Value) -> serde_json::Value {
+    let version = raw["version"].as_u64().unwrap_or(0);
+
+    if version < 1 {
+        // v0 files stored `chrono::Local`'s abbreviation ("WET", "+01"), not an IANA name.
+        // Western European time is Portugal's; anything else is detected again.
+        let timezone = match raw["timezone"].as_str() {
+            Some("WET" | "WEST") => Some(DEFAULT_TIMEZONE.to_string()),
+            Some(tz) if (tz.contains('/') || tz == "UTC") && tz.parse::<chrono_tz::Tz>().is_ok() => Some(tz.to_string()),
+            _ => None,
+        };
+        if let Some(obj) = raw.as_object_mut() {
+            match timezone {
+                Some(tz) => obj.insert("timezone".to_string(), tz.into()),
+                None => obj.remove("timezone"),
+            };
+        }
+    }
+
+    if let Some(obj) = raw.as_object_mut() {
+        obj.insert("version".to_string(), SETTINGS_VERSION.into());
+    }
+    raw
+}
+
+/// Parse, upgrade and sanitize a settings file. A missing file yields defaults; with
+/// `set_aside` an unreadable one is moved to settings.json.bak so the user can recover it,
+/// and the defaults say so in `corrupt_backup`.
+fn read_settings_file(path: &Path, set_aside: bool) -> AppSettings {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return AppSettings::default(),
+        Err(e) => {
+            log::warn!("Failed to read settings {:?}, using defaults: {}", path, e);
+            return AppSettings::default();
+        }
+    };
+
+    let parsed = serde_json::from_str::<serde_json::Value>(&data)
+        .and_then(|raw| serde_json::from_value::<AppSettings>(upgrade(raw)));
+    match parsed {
+        Ok(settings) => {
+            if let Err(e) = settings.validate() {
+                log::warn!("Resetting invalid settings fields to defaults: {}", e);
+            }
+            settings.sanitize()
+        }
+        Err(e) => {
+            log::warn!("settings.json is corrupt (line {}, column {}): {} — using defaults", e.line(), e.column(), e);
+            if !set_aside {
+                return AppSettings::default();
+            }
+            let backup = path.with_extension("json.bak");
+            let kept_at = match std::fs::rename(path, &backup) {
+                Ok(()) => backup,
+                Err(e) => {
+                    log::warn!("Failed to back up corrupt settings: {}", e);
+                    path.to_path_buf()
+                }
+            };
+            AppSettings { corrupt_backup: Some(kept_at.display().to_string()), ..AppSettings::default() }
+        }
+    }
+}
+
+/// Write via a temp file + rename so a crash never leaves a half-written settings.json
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let tmp = path.with_extension("json.tmp");
+    let mut file = std::fs::File::create(&tmp)
+        .map_err(|e| format!("Failed to write settings: {}", e))?;
+    file.write_all(data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write settings: {}", e))?;
+    std::fs::rename(&tmp, path)
+        .map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+#[cfg(feature = "gui")]
+pub(crate) fn settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     let config_dir = app_handle
         .path()
         .app_config_dir()
@@ -39,46 +309,258 @@ fn settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(config_dir.join("settings.json"))
 }
 
-/// Load settings from disk, or return defaults
+/// Current settings without env overrides applied.
+/// Used by backend commands that need the user's profile (skin type, location, timezone).
+#[cfg(feature = "gui")]
+pub fn load_settings(app_handle: &tauri::AppHandle) -> Result<AppSettings, String> {
+    if let Some(state) = app_handle.try_state::<SettingsState>() {
+        return state.0.read()
+            .map(|s| s.clone())
+            .map_err(|e| e.to_string());
+    }
+    Ok(read_settings_file(&settings_path(app_handle)?, true))
+}
+
+/// Parse the configured timezone as a tz database name (e.g. "Europe/Lisbon", "WET")
+pub fn resolve_timezone(settings: &AppSettings) -> Option<chrono_tz::Tz> {
+    settings.timezone.parse::<chrono_tz::Tz>().ok()
+}
+
+/// The user's zone for "today" calculations. Loaded settings are always sanitized,
+/// so the UTC fallback only covers a file that could not be read at all.
+pub fn user_timezone(settings: &AppSettings) -> chrono_tz::Tz {
+    resolve_timezone(settings).unwrap_or(chrono_tz::UTC)
+}
+
+/// Shorthand for commands that only need the user's zone
+#[cfg(feature = "gui")]
+pub fn current_timezone(app_handle: &tauri::AppHandle) -> chrono_tz::Tz {
+    load_settings(app_handle)
+        .map(|s| user_timezone(&s))
+        .unwrap_or(chrono_tz::UTC)
+}
+
+/// Current settings with the API keys and tool paths, for commands without the database
+#[cfg(feature = "gui")]
+pub fn config(app_handle: &tauri::AppHandle) -> Config {
+    Config::load(load_settings(app_handle).unwrap_or_default())
+}
+
+/// Change some settings from the backend (e.g. tool paths found by setup), save and publish them
+#[cfg(feature = "gui")]
+pub fn update_settings(app_handle: &tauri::AppHandle, change: impl FnOnce(&mut AppSettings)) -> Result<(), String> {
+    let mut settings = load_settings(app_handle)?;
+    change(&mut settings);
+    settings.validate()?;
+    settings.corrupt_backup = None;
+    let data = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    write_atomic(&settings_path(app_handle)?, data.as_bytes())?;
+    publish(app_handle, settings)
+}
+
+/// Core services over the managed database, with the current settings and API keys
+#[cfg(feature = "gui")]
+pub fn services(state: &DbState, app_handle: &tauri::AppHandle) -> ServiceContext {
+    ServiceContext::live(state.clone(), super::system::clock(app_handle), load_settings(app_handle).unwrap_or_default())
+}
+
+/// Load settings from disk, or return defaults.
+/// API keys come from the secret store and only ever leave the backend masked.
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn get_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, String> {
-    let path = settings_path(&app_handle)?;
-
-    if path.exists() {
-        let data = std::fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read settings: {}", e))?;
-        let mut settings: AppSettings = serde_json::from_str(&data)
-            .map_err(|e| format!("Failed to parse settings: {}", e))?;
+    Ok(load_settings(&app_handle)?.masked())
+}
 
-        // Override with env vars if set (env vars take priority)
-        if let Ok(key) = std::env::var("GEMINI_API_KEY") {
-            if !key.is_empty() { settings.gemini_api_key = key; }
-        }
-        if let Ok(key) = std::env::var("CARTESIA_API_KEY") {
-            if !key.is_empty() { settings.cartesia_api_key = key; }
-        }
+/// Validate and save settings, then broadcast `settings://changed`.
+/// API keys go to the secret store, never into settings.json;
+/// a key field still holding the mask we sent out means "unchanged", and an empty one
+/// with no key stored is left alone. A key the store rejects doesn't block the other
+/// settings: they are saved and the error names the key.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn save_settings(mut settings: AppSettings, app_handle: tauri::AppHandle) -> Result<(), String> {
+    settings.version = SETTINGS_VERSION;
+    settings.validate()?;
 
-        Ok(settings)
-    } else {
-        let mut settings = AppSettings::default();
-        // Check env vars
-        if let Ok(key) = std::env::var("GEMINI_API_KEY") {
-            settings.gemini_api_key = key;
-        }
-        if let Ok(key) = std::env::var("CARTESIA_API_KEY") {
-            settings.cartesia_api_key = key;
+    let mut key_errors = Vec::new();
+    for (value, name) in [
+        (&mut settings.gemini_api_key, secrets::GEMINI_API_KEY),
+        (&mut settings.cartesia_api_key, secrets::CARTESIA_API_KEY),
+    ] {
+        let unchanged = secrets::is_mask_of(value, name)
+            || (value.trim().is_empty() && !secrets::is_configured(name));
+        if !unchanged {
+            if let Err(e) = secrets::set(name, value) {
+                key_errors.push(format!("{}: {}", name, e));
+            }
         }
-        Ok(settings)
+        value.clear();
     }
-}
 
-/// Save settings to disk
-#[tauri::command]
-pub async fn save_settings(settings: AppSettings, app_handle: tauri::AppHandle) -> Result<(), String> {
     let path = settings_path(&app_handle)?;
     let data = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    std::fs::write(&path, data)
-        .map_err(|e| format!("Failed to write settings: {}", e))?;
+    write_atomic(&path, data.as_bytes())?;
+    publish(&app_handle, settings)?;
+
+    if key_errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Settings saved, but API keys could not be stored ({})", key_errors.join("; ")))
+    }
+}
+
+/// Re-read settings.json after it was replaced on disk (backup restore)
+#[cfg(feature = "gui")]
+pub fn reload_settings(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    publish(app_handle, read_settings_file(&settings_path(app_handle)?, true))
+}
+
+/// Make `settings` live: shared state, opt-in services, and the change broadcast
+#[cfg(feature = "gui")]
+fn publish(app_handle: &tauri::AppHandle, settings: AppSettings) -> Result<(), String> {
+    if let Some(state) = app_handle.try_state::<SettingsState>() {
+        *state.0.write().map_err(|e| e.to_string())? = settings.clone();
+    }
+    crate::commands::typing::apply_input_cadence(app_handle);
+    crate::commands::ingest::apply_ingest_api(app_handle);
+    if let Err(e) = app_handle.emit(SETTINGS_CHANGED_EVENT, settings.masked()) {
+        log::warn!("Failed to broadcast settings change: {}", e);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("holoself-settings-{}-{}", name, uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn upgraded_timezone(raw: serde_json::Value) -> Option<String> {
+        upgrade(raw)["timezone"].as_str().map(str::to_string)
+    }
+
+    #[test]
+    fn version_0_abbreviations_become_iana_zones() {
+        assert_eq!(upgraded_timezone(serde_json::json!({ "timezone": "WET" })).as_deref(), Some("Europe/Lisbon"));
+        assert_eq!(upgraded_timezone(serde_json::json!({ "timezone": "WEST" })).as_deref(), Some("Europe/Lisbon"));
+        assert_eq!(upgraded_timezone(serde_json::json!({ "timezone": "America/Sao_Paulo" })).as_deref(), Some("America/Sao_Paulo"));
+        // Offsets and other abbreviations are detected again on load
+        assert_eq!(upgraded_timezone(serde_json::json!({ "timezone": "+01" })), None);
+        assert_eq!(upgraded_timezone(serde_json::json!({ "timezone": "CET" })), None);
+
+        // Current files are left alone, whatever they hold
+        let current = upgrade(serde_json::json!({ "version": SETTINGS_VERSION, "timezone": "WET" }));
+        assert_eq!((current["version"].as_u64(), current["timezone"].as_str()), (Some(SETTINGS_VERSION as u64), Some("WET")));
+    }
+
+    #[test]
+    fn validation_reports_every_field_out_of_bounds() {
+        assert!(AppSettings::default().validate().is_ok());
+        let edges = AppSettings { latitude: -90.0, longitude: 180.0, skin_type: 6, sleep_anchor_hour: 23, ingest_api_port: 1024, backup_interval_hours: 720, backup_keep: 100, ..AppSettings::default() };
+        assert!(edges.validate().is_ok());
+
+        let bad = AppSettings {
+            whisper_model_path: "models/ggml-base.bin".to_string(),
+            latitude: 90.5,
+            longitude: -181.0,
+            skin_type: 0,
+            sleep_anchor_hour: 24,
+            timezone: "Mars/Olympus".to_string(),
+            exposed_body_area: "feet".to_string(),
+            ingest_api_port: 80,
+            backup_interval_hours: 0,
+            backup_keep: 101,
+            backup_dir: "relative/backups".to_string(),
+            ..AppSettings::default()
+        };
+        let message = bad.validate().unwrap_err();
+        assert_eq!(message.matches("; ").count(), 10, "{}", message);
+        assert!(message.ends_with("caminho do modelo de voz deve ser absoluto."));
+        assert!(message.starts_with("Configurações inválidas: latitude deve estar entre -90 e 90; longitude"));
+    }
+
+    #[test]
+    fn sanitize_resets_only_the_invalid_coordinate() {
+        let settings = AppSettings { latitude: 41.15, longitude: 200.0, skin_type: 9, ..AppSettings::default() }.sanitize();
+        assert_eq!((settings.latitude, settings.longitude, settings.skin_type), (41.15, -9.1393, 4));
+
+        let settings = AppSettings { latitude: -95.0, longitude: -8.61, ..AppSettings::default() }.sanitize();
+        assert_eq!((settings.latitude, settings.longitude), (38.7223, -8.61));
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_and_leaves_no_temp_behind() {
+        let dir = temp_dir("write");
+        let path = dir.join("settings.json");
+        std::fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"{\"skin_type\": 2}").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"skin_type\": 2}");
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // A failed write keeps the old file whole
+        let missing = dir.join("gone").join("settings.json");
+        assert!(write_atomic(&missing, b"{}").is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_corrupt_file_is_set_aside_and_flagged() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("settings.json");
+        std::fs::write(&path, "{\"skin_type\": 3,").unwrap();
+
+        // Callers that must not touch the app's files only get the defaults
+        assert_eq!(load_settings_from(&dir).corrupt_backup, None);
+        assert!(path.exists());
+
+        let settings = read_settings_file(&path, true);
+        let backup = path.with_extension("json.bak");
+        assert_eq!(settings.corrupt_backup.as_deref(), backup.to_str());
+        assert_eq!(settings.skin_type, AppSettings::default().skin_type);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "{\"skin_type\": 3,");
+        assert!(!path.exists());
+
+        // The flag goes to the UI but never back into the file
+        let json = serde_json::to_value(&settings).unwrap();
+        assert!(json["corrupt_backup"].is_string());
+        let saved: AppSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(saved.corrupt_backup, None);
+        assert!(serde_json::to_value(&saved).unwrap().get("corrupt_backup").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_hand_edited_file_is_upgraded_and_sanitized() {
+        let dir = temp_dir("upgrade");
+        let path = dir.join("settings.json");
+        std::fs::write(&path, r#"{"timezone": "WET", "latitude": 120.0, "longitude": -8.61, "backup_keep": 0}"#).unwrap();
+
+        let settings = read_settings_file(&path, true);
+        assert_eq!((settings.version, settings.timezone.as_str()), (SETTINGS_VERSION, "Europe/Lisbon"));
+        assert_eq!((settings.latitude, settings.longitude, settings.backup_keep), (38.7223, -8.61, 7));
+        assert_eq!(settings.corrupt_backup, None);
+        assert!(read_settings_file(&dir.join("missing.json"), true).validate().is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saved_tool_paths_reach_the_config() {
+        let settings = AppSettings {
+            whisper_binary_path: "/opt/whisper/whisper-cli".to_string(),
+            ffmpeg_path: "bin/ffmpeg".to_string(),
+            ..AppSettings::default()
+        }.sanitize();
+        let tools = Config::load(settings).tools;
+        assert_eq!(tools.whisper_binary, Some(PathBuf::from("/opt/whisper/whisper-cli")));
+        // Empty or relative: search the usual places
+        assert_eq!((tools.whisper_model, tools.ffmpeg), (None, None));
+    }
+}
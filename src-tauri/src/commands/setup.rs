@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 #[cfg(unix)]
 use std::os::unix::fs as unix_fs;
-use tauri::Manager;
+use crate::services::context::ToolPaths;
+use crate::services::secrets;
 
 #[derive(serde::Serialize)]
 pub struct SetupStatus {
@@ -15,18 +16,15 @@ pub struct SetupStatus {
 }
 
 /// Check which components are configured
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn check_setup_status() -> Result<SetupStatus, String> {
-    let gemini_key = std::env::var("GEMINI_API_KEY")
-        .map(|k| !k.is_empty() && k != "your_gemini_api_key_here")
-        .unwrap_or(false);
+pub async fn check_setup_status(app_handle: tauri::AppHandle) -> Result<SetupStatus, String> {
+    let gemini_key = secrets::is_configured(secrets::GEMINI_API_KEY);
+    let cartesia_key = secrets::is_configured(secrets::CARTESIA_API_KEY);
 
-    let cartesia_key = std::env::var("CARTESIA_API_KEY")
-        .map(|k| !k.is_empty() && k != "your_cartesia_key_here")
-        .unwrap_or(false);
-
-    let whisper_binary = find_whisper_binary().is_some();
-    let whisper_model = find_whisper_model().is_some();
+    let tools = super::settings::config(&app_handle).tools;
+    let whisper_binary = find_whisper_binary(&tools).is_some();
+    let whisper_model = find_whisper_model(&tools).is_some();
 
     // Camera/mic permission can only be tested from frontend (WebRTC)
     // We return true optimistically here; the frontend verifies
@@ -52,22 +50,23 @@ pub struct DependencyHealth {
     pub message: String,
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn check_dependencies() -> Result<DependencyHealth, String> {
+pub async fn check_dependencies(app_handle: tauri::AppHandle) -> Result<DependencyHealth, String> {
     let home = dirs::home_dir().unwrap_or_default();
     let holoself_dir = home.join(".holoself");
 
-    let whisper_binary = find_whisper_binary().is_some();
-    let whisper_model = find_whisper_model().is_some();
+    let tools = super::settings::config(&app_handle).tools;
+    let whisper_binary = find_whisper_binary(&tools).is_some();
+    let whisper_model = find_whisper_model(&tools).is_some();
 
-    // Check ffmpeg: bundled → PATH
-    let ffmpeg = holoself_dir.join("bin/ffmpeg").exists()
+    // Check ffmpeg: setup path → bundled → PATH
+    let ffmpeg = tools.ffmpeg.as_ref().is_some_and(|p| p.exists())
+        || holoself_dir.join("bin/ffmpeg").exists()
         || Command::new("which").arg("ffmpeg").output()
             .map(|o| o.status.success()).unwrap_or(false);
 
-    let gemini_key = std::env::var("GEMINI_API_KEY")
-        .map(|k| !k.is_empty() && k != "your_gemini_api_key_here")
-        .unwrap_or(false);
+    let gemini_key = secrets::is_configured(secrets::GEMINI_API_KEY);
 
     let needs_repair = !whisper_binary || !whisper_model || !ffmpeg;
 
@@ -92,56 +91,34 @@ pub async fn check_dependencies() -> Result<DependencyHealth, String> {
 }
 
 /// Repair missing dependencies — reinstalls only what's missing
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn repair_dependencies() -> Result<String, String> {
+pub async fn repair_dependencies(app_handle: tauri::AppHandle) -> Result<String, String> {
     // Just call the same install function — it skips what's already installed
-    install_whisper_auto().await
+    install_whisper_auto(app_handle).await
 }
 
-/// Save API keys to .env file in the app data directory
+/// Save API keys to the secret store (OS keyring or encrypted file)
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn save_api_keys(
     gemini_key: Option<String>,
     cartesia_key: Option<String>,
-    app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    // Get app data dir
-    let config_dir = app_handle
-        .path()
-        .app_config_dir()
-        .map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-
-    let env_path = config_dir.join(".env");
-
-    // Read existing or start fresh
-    let mut env_content = std::fs::read_to_string(&env_path).unwrap_or_default();
-
-    // Update/add Gemini key
-    if let Some(key) = &gemini_key {
-        if !key.is_empty() {
-            update_env_var(&mut env_content, "GEMINI_API_KEY", key);
-            std::env::set_var("GEMINI_API_KEY", key);
-        }
+    if let Some(key) = gemini_key.filter(|k| !k.is_empty()) {
+        secrets::set(secrets::GEMINI_API_KEY, &key)?;
     }
-
-    // Update/add Cartesia key
-    if let Some(key) = &cartesia_key {
-        if !key.is_empty() {
-            update_env_var(&mut env_content, "CARTESIA_API_KEY", key);
-            std::env::set_var("CARTESIA_API_KEY", key);
-        }
+    if let Some(key) = cartesia_key.filter(|k| !k.is_empty()) {
+        secrets::set(secrets::CARTESIA_API_KEY, &key)?;
     }
-
-    std::fs::write(&env_path, &env_content).map_err(|e| e.to_string())?;
-
     Ok(())
 }
 
 /// Auto-install whisper.cpp + ffmpeg (download pre-built binaries + model)
 /// No compilation, no terminal, no dev tools needed.
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn install_whisper_auto() -> Result<String, String> {
+pub async fn install_whisper_auto(app_handle: tauri::AppHandle) -> Result<String, String> {
     let home = dirs::home_dir().ok_or("Não foi possível encontrar a pasta do utilizador")?;
     let holoself_dir = home.join(".holoself");
     std::fs::create_dir_all(&holoself_dir).map_err(|e| format!("Erro ao criar pasta: {}", e))?;
@@ -217,41 +194,22 @@ pub async fn install_whisper_auto() -> Result<String, String> {
         }
     };
 
-    // Set env vars for this session
-    std::env::set_var("WHISPER_CPP_PATH", &whisper_path);
-    std::env::set_var("WHISPER_MODEL_PATH", &model_path);
-    if ffmpeg_path.exists() {
-        std::env::set_var("HOLOSELF_FFMPEG_PATH", &ffmpeg_path);
-    }
+    // Remember where everything went; voice commands read it through Config
+    super::settings::update_settings(&app_handle, |settings| {
+        settings.whisper_binary_path = whisper_path.display().to_string();
+        settings.whisper_model_path = model_path.display().to_string();
+        settings.ffmpeg_path = if ffmpeg_path.exists() { ffmpeg_path.display().to_string() } else { String::new() };
+    })?;
 
     Ok("Componentes de voz instalados com sucesso!".to_string())
 }
 
 // === Helpers ===
 
-fn update_env_var(content: &mut String, key: &str, value: &str) {
-    let prefix = format!("{}=", key);
-    let new_line = format!("{}={}", key, value);
-
-    if let Some(pos) = content.find(&prefix) {
-        // Replace existing line
-        let end = content[pos..].find('\n').map(|i| pos + i).unwrap_or(content.len());
-        content.replace_range(pos..end, &new_line);
-    } else {
-        // Append
-        if !content.is_empty() && !content.ends_with('\n') {
-            content.push('\n');
-        }
-        content.push_str(&new_line);
-        content.push('\n');
-    }
-}
-
-fn find_whisper_binary() -> Option<PathBuf> {
-    // Check env var first
-    if let Ok(path) = std::env::var("WHISPER_CPP_PATH") {
-        let p = PathBuf::from(&path);
-        if p.exists() { return Some(p); }
+fn find_whisper_binary(tools: &ToolPaths) -> Option<PathBuf> {
+    // Path recorded by setup first
+    if let Some(p) = tools.whisper_binary.as_ref().filter(|p| p.exists()) {
+        return Some(p.clone());
     }
 
     let home = dirs::home_dir()?;
@@ -279,7 +237,7 @@ fn find_whisper_binary() -> Option<PathBuf> {
     None
 }
 
-fn find_whisper_binary_in(dir: &PathBuf) -> Option<PathBuf> {
+fn find_whisper_binary_in(dir: &Path) -> Option<PathBuf> {
     // Check bin/ directory (HoloSelf standard location)
     let bin_dir_names = ["bin", "build/bin"];
     for bd in &bin_dir_names {
@@ -463,10 +421,9 @@ fn find_file_recursive(dir: &PathBuf, name: &str) -> Option<PathBuf> {
     None
 }
 
-fn find_whisper_model() -> Option<PathBuf> {
-    if let Ok(path) = std::env::var("WHISPER_MODEL_PATH") {
-        let p = PathBuf::from(&path);
-        if p.exists() { return Some(p); }
+fn find_whisper_model(tools: &ToolPaths) -> Option<PathBuf> {
+    if let Some(p) = tools.whisper_model.as_ref().filter(|p| p.exists()) {
+        return Some(p.clone());
     }
 
     let home = dirs::home_dir()?;
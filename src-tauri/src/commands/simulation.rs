@@ -0,0 +1,30 @@
+use chrono::NaiveDate;
+use crate::commands::settings;
+use crate::services::simulation::{self, SimulationOptions, Transcript};
+
+/// Replay synthetic days against the agent in accelerated time; runs on a throwaway
+/// in-memory database, so the user's records are never touched.
+/// `start` defaults to today, `days` to 1.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn run_agent_simulation(
+    start: Option<String>,
+    days: Option<u32>,
+    seed: Option<u64>,
+    adherence: Option<f64>,
+    app_handle: tauri::AppHandle,
+) -> Result<Transcript, String> {
+    let settings = settings::load_settings(&app_handle)?;
+    let start = match start.as_deref() {
+        Some(raw) => NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| format!("Data inválida: '{}'", raw))?,
+        None => super::system::clock(&app_handle).today(settings::user_timezone(&settings)),
+    };
+    let defaults = SimulationOptions::new(start);
+    let options = SimulationOptions {
+        days: days.unwrap_or(defaults.days),
+        seed: seed.unwrap_or(defaults.seed),
+        adherence: adherence.unwrap_or(defaults.adherence),
+        ..defaults
+    };
+    simulation::run(settings, &options).await
+}
@@ -1,4 +1,7 @@
 use serde::Serialize;
+#[cfg(feature = "gui")]
+use tauri::Manager;
+use crate::services::clock::{ClockState, SharedClock, SystemClock};
 
 #[derive(Debug, Serialize)]
 pub struct SystemStatus {
@@ -11,10 +14,11 @@ pub struct SystemStatus {
 }
 
 /// Get current system status
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn get_system_status() -> Result<SystemStatus, String> {
-    let gemini_configured = std::env::var("GEMINI_API_KEY").is_ok();
-    let tz = chrono::Local::now().format("%Z").to_string();
+pub async fn get_system_status(app_handle: tauri::AppHandle) -> Result<SystemStatus, String> {
+    let gemini_configured = crate::services::secrets::is_configured(crate::services::secrets::GEMINI_API_KEY);
+    let tz = super::settings::current_timezone(&app_handle).name().to_string();
 
     Ok(SystemStatus {
         version: env!("CARGO_PKG_VERSION").to_string(),
@@ -25,3 +29,11 @@ pub async fn get_system_status() -> Result<SystemStatus, String> {
         uptime_seconds: 0, // TODO: Track uptime
     })
 }
+
+/// The app's clock; the system clock until setup has managed one
+#[cfg(feature = "gui")]
+pub fn clock(app_handle: &tauri::AppHandle) -> SharedClock {
+    app_handle.try_state::<ClockState>()
+        .map(|state| state.0.clone())
+        .unwrap_or_else(|| std::sync::Arc::new(SystemClock))
+}
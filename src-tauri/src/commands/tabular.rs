@@ -0,0 +1,385 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::{records, settings};
+use crate::db::{Database, DbState};
+use crate::services::tabular::{self, Format, Parsed, Table};
+use crate::services::time;
+
+const MAX_REPORTED_ERRORS: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct TableExport {
+    pub table: String,
+    pub path: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    pub line: u64, // line in the source file (CSV header is line 1)
+    pub message: String,
+}
+
+/// Outcome of an import; with `dry_run` the counts are what a real import would do
+#[derive(Debug, Serialize)]
+pub struct TabularImportReport {
+    pub table: String,
+    pub format: String,
+    pub dry_run: bool,
+    pub columns: Vec<String>,             // columns found in the file
+    pub mapping: BTreeMap<String, String>, // field → source column actually used
+    pub read: usize,
+    pub inserted: usize,
+    pub duplicates: usize,                // already present, left untouched
+    pub invalid: usize,
+    pub errors: Vec<RowError>,            // first few invalid rows
+}
+
+fn default_export_dir() -> PathBuf {
+    dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Export tables (default: all four) for a date range, one file per table.
+/// `format` is "csv" or "ndjson"; `from`/`to` are both given or both omitted.
+/// Lab results are filtered by their local test date.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn export_records(
+    state: State<'_, DbState>,
+    format: String,
+    tables: Option<Vec<String>>,
+    from: Option<String>,
+    to: Option<String>,
+    directory: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<TableExport>, String> {
+    let format = Format::parse(&format)?;
+    let tables = match tables {
+        Some(names) => names.iter().map(|name| Table::parse(name)).collect::<Result<Vec<_>, _>>()?,
+        None => Table::ALL.to_vec(),
+    };
+    let tz = settings::current_timezone(&app_handle);
+    let ranged = from.is_some();
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => time::normalize_range(&from, &to, tz)?,
+        (None, None) => ("0000-01-01T00:00:00Z".to_string(), "9999-12-31T23:59:59Z".to_string()),
+        _ => return Err("Indique as duas datas (from e to) ou nenhuma.".to_string()),
+    };
+    // Local calendar days covered by [from, to] for lab test dates
+    let local_day = |instant: &str, shift: Duration| {
+        time::parse_instant(instant, tz)
+            .map(|at| (at + shift).with_timezone(&tz).format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let (first_day, last_day) = (local_day(&from, Duration::zero()), local_day(&to, Duration::seconds(-1)));
+
+    let exports: Vec<(Table, Vec<tabular::Row>)> = state.read(move |db| {
+        tables.iter().map(|&table| {
+            let rows = match table {
+                Table::Supplements => db.get_supplements(&from, &to)
+                    .map(|rows| rows.iter().rev().map(tabular::supplement_row).collect()),
+                Table::Vitals => db.get_vitals(&from, &to)
+                    .map(|rows| rows.iter().map(tabular::vital_row).collect()),
+                Table::LabResults => db.get_lab_results().map(|rows| rows.iter()
+                    .filter(|lab| !ranged || lab.test_date.as_deref()
+                        .is_some_and(|d| d >= first_day.as_str() && d <= last_day.as_str()))
+                    .map(tabular::lab_row)
+                    .collect()),
+                Table::AgentMemory => db.get_agent_memory(&from, &to)
+                    .map(|rows| rows.iter().map(tabular::memory_row).collect()),
+            };
+            rows.map(|rows| (table, rows))
+        }).collect::<Result<Vec<_>, _>>()
+    }).await?;
+
+    let dir = directory.map(PathBuf::from).unwrap_or_else(default_export_dir);
+    let stamp = super::system::clock(&app_handle).now().format("%Y%m%d-%H%M%S");
+    let mut written = Vec::new();
+    for (table, rows) in exports {
+        let path = dir.join(format!("holoself-{}-{}.{}", table.name(), stamp, format.name()));
+        let file = std::fs::File::create(&path).map_err(|e| format!("Falha ao criar {:?}: {}", path, e))?;
+        let rows = tabular::write_rows(file, table, format, &rows)?;
+        written.push(TableExport {
+            table: table.name().to_string(),
+            path: path.to_string_lossy().into_owned(),
+            rows,
+        });
+    }
+    Ok(written)
+}
+
+/// Store validated rows, skipping those already present
+fn store(db: &Database, rows: &[Parsed], report: &mut TabularImportReport) -> rusqlite::Result<()> {
+    for row in rows {
+        let exists = match row {
+            Parsed::Supplement(s) => db.supplement_exists(s)?,
+            Parsed::Vital(v) => db.vital_exists(v)?,
+            Parsed::Lab(l) => db.lab_record_exists(l)?,
+            Parsed::Memory(m) => db.agent_memory_exists(m)?,
+        };
+        if exists {
+            report.duplicates += 1;
+            continue;
+        }
+        match row {
+            Parsed::Supplement(s) => db.insert_supplement(s)?,
+            Parsed::Vital(v) => db.insert_vital(v)?,
+            Parsed::Lab(l) => db.insert_lab_record(l)?,
+            Parsed::Memory(m) => db.insert_agent_memory(m)?,
+        };
+        report.inserted += 1;
+    }
+    Ok(())
+}
+
+/// Import a CSV or NDJSON file into one table. `mapping` maps fields to source columns
+/// (by default a column with the field's name). Invalid rows are reported by line and
+/// skipped; the rest is stored in one transaction. `dry_run` validates and counts
+/// without writing anything.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn import_records(
+    state: State<'_, DbState>,
+    table: String,
+    path: String,
+    format: Option<String>,
+    mapping: Option<BTreeMap<String, String>>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<TabularImportReport, String> {
+    let table = Table::parse(&table)?;
+    let format = match format {
+        Some(format) => Format::parse(&format)?,
+        None => Format::from_path(Path::new(&path))
+            .ok_or("Formato não reconhecido pela extensão: indique csv ou ndjson.")?,
+    };
+    let tz = settings::current_timezone(&app_handle);
+    let now = super::system::clock(&app_handle).now();
+    let mapping = mapping.unwrap_or_default();
+    let (report, rows) = read_import(table, Path::new(&path), format, &mapping, dry_run.unwrap_or(false), tz, now)?;
+    let audited_at = time::format_utc(now);
+    state.write(move |db| apply_import(db, report, &rows, &path, &audited_at)).await
+}
+
+/// Read and validate a file for `import_records`; invalid rows are counted and reported
+/// by line, the valid ones returned for `apply_import`
+fn read_import(
+    table: Table,
+    path: &Path,
+    format: Format,
+    mapping: &BTreeMap<String, String>,
+    dry_run: bool,
+    tz: chrono_tz::Tz,
+    now: DateTime<Utc>,
+) -> Result<(TabularImportReport, Vec<Parsed>), String> {
+    let source = tabular::read_file(path, format)?;
+    let mapping = tabular::resolve_mapping(table, &source.columns, mapping)?;
+
+    let mut report = TabularImportReport {
+        table: table.name().to_string(),
+        format: format.name().to_string(),
+        dry_run,
+        columns: source.columns,
+        mapping,
+        read: source.records.len(),
+        inserted: 0,
+        duplicates: 0,
+        invalid: 0,
+        errors: Vec::new(),
+    };
+    let mut rows = Vec::with_capacity(source.records.len());
+    for (line, record) in source.records {
+        match record.and_then(|record| tabular::parse_record(table, &record, &report.mapping, tz, now)) {
+            Ok(row) => rows.push(row),
+            Err(message) => {
+                report.invalid += 1;
+                if report.errors.len() < MAX_REPORTED_ERRORS {
+                    report.errors.push(RowError { line, message });
+                }
+            }
+        }
+    }
+    Ok((report, rows))
+}
+
+/// Store the rows in one transaction, or with `dry_run` count them and roll back
+fn apply_import(
+    db: &Database,
+    mut report: TabularImportReport,
+    rows: &[Parsed],
+    path: &str,
+    audited_at: &str,
+) -> rusqlite::Result<TabularImportReport> {
+    if report.dry_run {
+        db.rehearse(|db| store(db, rows, &mut report))?;
+    } else {
+        db.transaction(|db| {
+            store(db, rows, &mut report)?;
+            if report.table != Table::AgentMemory.name() {
+                records::audit_import(db, audited_at, &report.table, path, report.inserted)?;
+            }
+            Ok::<_, rusqlite::Error>(())
+        })?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::health::{SupplementEntry, VitalEntry};
+
+    const TZ: chrono_tz::Tz = chrono_tz::Europe::Lisbon;
+    const AUDITED_AT: &str = "2026-07-01T09:00:00Z";
+
+    fn now() -> DateTime<Utc> {
+        AUDITED_AT.parse().unwrap()
+    }
+
+    fn open() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db.run_migrations(TZ, now()).unwrap();
+        db
+    }
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("holoself-tabular-{}-{}", uuid::Uuid::new_v4().simple(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn vital(vital_type: &str, value: f64, unit: &str, recorded_at: &str, source: &str) -> VitalEntry {
+        VitalEntry {
+            id: None,
+            vital_type: vital_type.to_string(),
+            value,
+            unit: unit.to_string(),
+            recorded_at: recorded_at.to_string(),
+            source: source.to_string(),
+        }
+    }
+
+    fn import(db: &Database, table: Table, path: &Path, dry_run: bool) -> TabularImportReport {
+        let format = Format::from_path(path).unwrap();
+        let (report, rows) = read_import(table, path, format, &BTreeMap::new(), dry_run, TZ, now()).unwrap();
+        apply_import(db, report, &rows, &path.to_string_lossy(), AUDITED_AT).unwrap()
+    }
+
+    fn vitals(db: &Database) -> Vec<(String, f64, String, String, String)> {
+        db.get_vitals("2000-01-01T00:00:00Z", "2100-01-01T00:00:00Z").unwrap()
+            .into_iter()
+            .map(|v| (v.vital_type, v.value, v.unit, v.recorded_at, v.source))
+            .collect()
+    }
+
+    fn count(db: &Database, table: &str) -> i64 {
+        db.query_row(&format!("SELECT COUNT(*) FROM {}", table), &[], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn exported_files_import_back_unchanged() {
+        let source = open();
+        source.insert_vital(&vital("heart_rate", 61.0, "bpm", "2026-06-30T06:15:00Z", "wearable")).unwrap();
+        source.insert_vital(&vital("wpm", 72.5, "wpm", "2026-06-30T14:00:00Z", "typing")).unwrap();
+        source.insert_supplement(&SupplementEntry {
+            id: None,
+            name: "Vitamina D3".to_string(),
+            dosage: "2000 IU".to_string(),
+            taken_at: "2026-06-30T08:00:00Z".to_string(),
+            category: "morning".to_string(),
+            notes: Some("com \"pequeno-almoço\", gordura".to_string()),
+        }).unwrap();
+
+        for format in [Format::Csv, Format::Ndjson] {
+            let target = open();
+            for table in [Table::Vitals, Table::Supplements] {
+                let rows: Vec<tabular::Row> = match table {
+                    Table::Vitals => source.get_vitals("2000-01-01T00:00:00Z", "2100-01-01T00:00:00Z").unwrap()
+                        .iter().map(tabular::vital_row).collect(),
+                    _ => source.get_supplements("2000-01-01T00:00:00Z", "2100-01-01T00:00:00Z").unwrap()
+                        .iter().map(tabular::supplement_row).collect(),
+                };
+                let path = temp_file(&format!("{}.{}", table.name(), format.name()), "");
+                tabular::write_rows(std::fs::File::create(&path).unwrap(), table, format, &rows).unwrap();
+
+                let report = import(&target, table, &path, false);
+                assert_eq!((report.read, report.inserted, report.invalid), (rows.len(), rows.len(), 0), "{:?}", report.errors);
+                // A second import finds everything already there
+                assert_eq!(import(&target, table, &path, false).duplicates, rows.len());
+                std::fs::remove_file(path).unwrap();
+            }
+            assert_eq!(vitals(&target), vitals(&source));
+            let supplements = |db: &Database| db.get_supplements("2000-01-01T00:00:00Z", "2100-01-01T00:00:00Z").unwrap()
+                .into_iter()
+                .map(|s| (s.name, s.dosage, s.taken_at, s.category, s.notes))
+                .collect::<Vec<_>>();
+            assert_eq!(supplements(&target), supplements(&source));
+        }
+    }
+
+    #[test]
+    fn a_dry_run_reports_the_counts_and_writes_nothing() {
+        let db = open();
+        db.insert_vital(&vital("weight", 72.0, "kg", "2026-06-30T06:00:00Z", "manual")).unwrap();
+        let path = temp_file("peso.csv", "vital_type;value;recorded_at\nweight;72;30/06/2026 07:00\nweight;71,6;01/07/2026 07:00\n");
+
+        let report = import(&db, Table::Vitals, &path, true);
+        assert!(report.dry_run);
+        assert_eq!((report.read, report.inserted, report.duplicates, report.invalid), (2, 1, 1, 0));
+        assert_eq!(count(&db, "vitals"), 1);
+        assert_eq!(count(&db, "audit_log"), 0);
+
+        let report = import(&db, Table::Vitals, &path, false);
+        assert_eq!((report.inserted, report.duplicates), (1, 1));
+        assert_eq!(vitals(&db)[1], ("weight".to_string(), 71.6, "kg".to_string(), "2026-07-01T06:00:00Z".to_string(), "manual".to_string()));
+        let audit = db.get_audit_log(Some("vitals"), None, 10).unwrap();
+        assert_eq!((audit.len(), audit[0].action.as_str()), (1, "import"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_by_line_and_the_rest_is_stored() {
+        let db = open();
+        let path = temp_file("vitals.csv", "\
+vital_type,value,unit,recorded_at
+heart_rate,58,bpm,2026-06-30T07:00
+heart_rate,muitos,bpm,2026-06-30T07:05
+heart_rate,300,bpm,2026-06-30T07:10
+heart_rate,60,bpm,2026-07-01T12:00
+heart_rate,59,bpm,2026-06-30T07:15,extra
+pulse,60,bpm,2026-06-30T07:20
+heart_rate,61,bpm,2026-06-30T07:25
+");
+        let report = import(&db, Table::Vitals, &path, false);
+        assert_eq!((report.read, report.inserted, report.invalid), (7, 2, 5));
+        let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4, 5, 6, 7]);
+        assert!(report.errors[0].message.contains("número inválido 'muitos'"));
+        assert!(report.errors[1].message.contains("fora do intervalo"));
+        assert!(report.errors[2].message.contains("no futuro"));
+        assert!(report.errors[3].message.contains("colunas"));
+        assert!(report.errors[4].message.contains("vital_type desconhecido 'pulse'"));
+        assert_eq!(count(&db, "vitals"), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rows_already_stored_are_skipped_even_with_another_value() {
+        let db = open();
+        db.insert_vital(&vital("heart_rate", 62.0, "bpm", "2026-06-30T06:15:00Z", "wearable")).unwrap();
+        let path = temp_file("vitals.ndjson", r#"{"vital_type":"heart_rate","value":64,"unit":"bpm","recorded_at":"2026-06-30T06:15:00Z","source":"wearable"}
+{"vital_type":"heart_rate","value":64,"unit":"bpm","recorded_at":"2026-06-30T06:15:00Z","source":"manual"}
+"#);
+        let report = import(&db, Table::Vitals, &path, false);
+        assert_eq!((report.inserted, report.duplicates), (1, 1));
+        // The stored wearable reading keeps its value
+        assert_eq!(vitals(&db).iter().map(|v| (v.1, v.4.as_str())).collect::<Vec<_>>(), vec![(62.0, "wearable"), (64.0, "manual")]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+#[cfg(feature = "gui")]
+use tauri::{Manager, State};
+use crate::commands::{presence, settings};
+use crate::db::{Database, DbState};
+use crate::services::input_cadence::{CadenceStatus, InputCadenceState};
+use crate::services::presence::PresenceSignal;
+use crate::services::time;
+use crate::services::typing::{self, FatigueAssessment, FatigueEpisode};
+
+/// WPM reading from a typing detector (HUD keydown hook or the input-device service)
+#[derive(Debug, Deserialize)]
+pub struct WpmSnapshotInput {
+    pub recorded_at: String, // ISO 8601 datetime
+    pub wpm: f64,
+    pub source: Option<String>, // keyboard (default) | evdev
+}
+
+/// Down-sample readings to one mean per minute and store them with their baseline update.
+/// Idle minutes (0 WPM) are dropped so breaks don't drag the baseline down. Each minute is
+/// judged against the baseline as it stands, and fatigued minutes stay out of it.
+pub fn record_wpm(
+    db: &mut Database,
+    tz: chrono_tz::Tz,
+    readings: &[(DateTime<Utc>, f64)],
+    source: &str,
+    now: DateTime<Utc>,
+) -> Result<usize, String> {
+    let mut minutes: BTreeMap<DateTime<Utc>, (f64, u32)> = BTreeMap::new();
+    for (at, wpm) in readings {
+        if !wpm.is_finite() || *wpm <= 0.0 || *wpm > 300.0 {
+            continue;
+        }
+        let minute = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(*at);
+        let slot = minutes.entry(minute).or_default();
+        slot.0 += wpm;
+        slot.1 += 1;
+    }
+    let (Some(first), Some(last)) = (minutes.keys().next().copied(), minutes.keys().next_back().copied()) else {
+        return Ok(0);
+    };
+
+    // Stored minutes that share a smoothing window with this batch
+    let mut recent = db.get_wpm_points(
+        &time::format_utc(first - chrono::Duration::minutes(typing::SMOOTHING_MINUTES)),
+        &time::format_utc(last + chrono::Duration::minutes(1)),
+    ).map_err(|e| e.to_string())?;
+    let mut table = db.get_wpm_baselines().map_err(|e| e.to_string())?;
+
+    let updated_at = time::format_utc(now);
+    let mut stored = 0;
+    for (minute, (sum, count)) in minutes {
+        let local = minute.with_timezone(&tz);
+        let cell = (local.weekday().num_days_from_monday(), local.hour());
+        let wpm = (sum / count as f64).round();
+        let baseline_wpm = table.lookup(cell.0, cell.1).map(|b| b.mean);
+        recent.push(typing::WpmPoint { time: minute, wpm, baseline_wpm });
+        let fatigued = baseline_wpm.zip(typing::smoothed_wpm(&recent, minute))
+            .and_then(|(baseline, smoothed)| typing::fatigued_level(smoothed, baseline))
+            .is_some();
+
+        let learn = (!fatigued).then_some(cell);
+        let inserted = db.insert_wpm_snapshot(&time::format_utc(minute), wpm, source, baseline_wpm, learn, &updated_at)
+            .map_err(|e| e.to_string())?;
+        if !inserted {
+            recent.pop();
+        } else if !fatigued {
+            table.cells.entry(cell).or_default().add(wpm);
+        }
+        stored += inserted as usize;
+    }
+    Ok(stored)
+}
+
+/// Fatigue at `now` from the last few minutes of typing vs. the baseline for this hour
+pub fn current_fatigue(db: &Database, tz: chrono_tz::Tz, now: DateTime<Utc>) -> Result<FatigueAssessment, String> {
+    let from = time::format_utc(now - chrono::Duration::minutes(typing::SMOOTHING_MINUTES));
+    let points = db.get_wpm_points(&from, &time::format_utc(now + chrono::Duration::minutes(1)))
+        .map_err(|e| e.to_string())?;
+    let baseline = db.get_wpm_baselines()
+        .map_err(|e| e.to_string())?
+        .lookup_at(&now.with_timezone(&tz));
+    let recent = typing::smoothed_wpm(&points, now).unwrap_or(0.0);
+    Ok(typing::assess(recent, baseline))
+}
+
+/// Fatigue episodes whose snapshots fall within [from, to) (canonical UTC bounds)
+pub fn episodes_between(db: &Database, from: &str, to: &str) -> Result<Vec<FatigueEpisode>, String> {
+    let points = db.get_wpm_points(from, to).map_err(|e| e.to_string())?;
+    Ok(typing::detect_episodes(&points))
+}
+
+/// Start or stop the evdev cadence service to match `keyboard_cadence` in settings.
+/// Called at startup and after every settings save.
+#[cfg(feature = "gui")]
+pub fn apply_input_cadence(app_handle: &tauri::AppHandle) {
+    let Some(cadence) = app_handle.try_state::<InputCadenceState>() else { return };
+    let enabled = settings::load_settings(app_handle)
+        .map(|s| s.keyboard_cadence)
+        .unwrap_or(false);
+    if !enabled {
+        cadence.stop();
+        return;
+    }
+
+    let handle = app_handle.clone();
+    let started = cadence.start(move |minutes| {
+        let tz = settings::current_timezone(&handle);
+        let Some(state) = handle.try_state::<DbState>() else { return };
+        let now = super::system::clock(&handle).now();
+        let result = state.write_blocking(|db| record_wpm(db, tz, &minutes, "evdev", now));
+        if let Err(e) = result {
+            log::warn!("Failed to store typing cadence: {}", e);
+        }
+        // Typing in the last flushed minute means the user is at the desk
+        if let Some((minute, _)) = minutes.last() {
+            let at = (*minute + chrono::Duration::minutes(1)).min(now);
+            if let Err(e) = presence::report(&handle, PresenceSignal::Activity, at, "evdev") {
+                log::warn!("Failed to report typing activity: {}", e);
+            }
+        }
+    });
+    match started {
+        Ok(()) => log::info!("Input cadence service running"),
+        Err(e) => log::warn!("Input cadence service unavailable: {}", e),
+    }
+}
+
+/// Store WPM snapshots (the HUD sends one per minute)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn record_wpm_snapshots(
+    state: State<'_, DbState>,
+    snapshots: Vec<WpmSnapshotInput>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    let tz = settings::current_timezone(&app_handle);
+    // The evdev service already sees every keystroke, including those typed into the HUD
+    let evdev_running = app_handle.try_state::<InputCadenceState>()
+        .is_some_and(|c| c.is_running());
+    let mut by_source: BTreeMap<String, Vec<(DateTime<Utc>, f64)>> = BTreeMap::new();
+    for s in &snapshots {
+        let at = time::parse_instant(&s.recorded_at, tz)
+            .ok_or_else(|| format!("Data/hora inválida: '{}'", s.recorded_at))?;
+        let source = s.source.clone().unwrap_or_else(|| "keyboard".to_string());
+        if evdev_running && source == "keyboard" {
+            continue;
+        }
+        by_source.entry(source).or_default().push((at, s.wpm));
+    }
+
+    let last_typed = by_source.values()
+        .flatten()
+        .filter(|(_, wpm)| *wpm > 0.0)
+        .map(|(at, _)| *at)
+        .max();
+
+    let now = super::system::clock(&app_handle).now();
+    let stored = state.write(move |db| {
+        let mut stored = 0;
+        for (source, readings) in by_source {
+            stored += record_wpm(db, tz, &readings, &source, now)?;
+        }
+        Ok::<_, String>(stored)
+    }).await?;
+
+    if let Some(at) = last_typed {
+        presence::report(&app_handle, PresenceSignal::Activity, at.min(now), "keyboard")?;
+    }
+    Ok(stored)
+}
+
+/// Current typing fatigue against the personal weekday/hour baseline
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_typing_fatigue(
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+) -> Result<FatigueAssessment, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let now = super::system::clock(&app_handle).now();
+    state.read(move |db| current_fatigue(db, tz, now)).await
+}
+
+/// Sustained fatigue episodes in a date range
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_fatigue_episodes(
+    state: State<'_, DbState>,
+    from: String,
+    to: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<FatigueEpisode>, String> {
+    let tz = settings::current_timezone(&app_handle);
+    let (from, to) = time::normalize_range(&from, &to, tz)?;
+    state.read(move |db| episodes_between(db, &from, &to)).await
+}
+
+/// Whether system-wide typing cadence is running, on which keyboards, or why not
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_input_cadence_status(cadence: State<'_, InputCadenceState>) -> Result<CadenceStatus, String> {
+    Ok(cadence.status())
+}
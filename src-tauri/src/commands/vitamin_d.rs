@@ -1,46 +1,349 @@
-use crate::services::vitamin_d::{self, VitaminDRecommendation};
+use chrono::{DateTime, NaiveDate, Offset, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+use crate::commands::settings::{self, AppSettings};
+use crate::db::{Database, DbState};
+use crate::services::open_meteo::OpenMeteoClient;
+use crate::services::time;
+use crate::services::vitamin_d::{
+    self, DailyExposurePlan, IntakeBudget, IntakeHistory, LabResponseSample, ResponseModel, VitaminDRecommendation,
+};
 
-/// Get Vitamin D recommendation based on current UV index
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SunExposureEntry {
+    pub id: Option<i64>,
+    pub started_at: String,         // ISO 8601 datetime, stored as UTC
+    pub duration_minutes: u32,
+    pub body_area: String,          // face_hands | face_arms | arms_legs | swimsuit
+    pub spf: Option<f64>,           // None = no sunscreen
+    pub uv_index: Option<f64>,      // Looked up from the forecast cache when omitted
+    pub estimated_iu: Option<u32>,  // Computed on insert
+}
+
+/// UTC offset in effect at local noon on `date` for the user's timezone
+fn utc_offset_for(settings: &AppSettings, date: NaiveDate) -> chrono::FixedOffset {
+    let noon = date.and_hms_opt(12, 0, 0).unwrap_or_default();
+    settings::user_timezone(settings)
+        .from_local_datetime(&noon)
+        .earliest()
+        .map(|dt| dt.offset().fix())
+        .unwrap_or_else(|| chrono::Utc.fix())
+}
+
+/// Sun IU and individual D3 supplement doses logged between two canonical UTC instants
+fn intake_between(db: &Database, from: &str, to: &str) -> Result<(u32, Vec<u32>), String> {
+    let sun_iu: u32 = db.get_sun_exposures(from, to)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter_map(|s| s.estimated_iu)
+        .sum();
+    let doses: Vec<u32> = db.get_supplements(from, to)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter_map(|s| vitamin_d::parse_d3_iu(&s.name, &s.dosage))
+        .collect();
+    Ok((sun_iu, doses))
+}
+
+/// Sun + supplement IU logged over the rolling budget window ending at `now`
+fn rolling_intake(db: &Database, skin_type: u8, now: DateTime<Utc>) -> Result<(IntakeBudget, bool), String> {
+    let from = time::format_utc(now - chrono::Duration::days(vitamin_d::BUDGET_WINDOW_DAYS));
+    let to = time::format_utc(now);
+
+    let (sun_iu, doses) = intake_between(db, &from, &to)?;
+    let has_history = sun_iu > 0 || !doses.is_empty();
+    Ok((vitamin_d::intake_budget(sun_iu, doses.iter().sum(), skin_type), has_history))
+}
+
+/// Sun + supplement IU per day over whole local days `from..to`
+fn average_daily_intake(db: &Database, from: NaiveDate, to: NaiveDate, tz: chrono_tz::Tz) -> Result<f64, String> {
+    let days = (to - from).num_days().max(1);
+    let (from, to) = time::normalize_range(&from.format("%Y-%m-%d").to_string(), &to.format("%Y-%m-%d").to_string(), tz)?;
+    let (sun_iu, doses) = intake_between(db, &from, &to)?;
+    Ok((sun_iu + doses.iter().sum::<u32>()) as f64 / days as f64)
+}
+
+/// Personal 25-OH-D response model from lab history. None with fewer than two labs or when
+/// intake never changed between them, in which case the recommendation falls back to the budget/skin-type table.
+fn load_response_model(db: &Database, tz: chrono_tz::Tz) -> Result<Option<ResponseModel>, String> {
+    let mut labs: Vec<(NaiveDate, f64)> = db.get_lab_history(vitamin_d::VITAMIN_D_LAB_MARKERS)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter_map(|(value, unit, date)| {
+            let day = NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()?;
+            Some((day, vitamin_d::to_ng_ml(*value, unit)))
+        })
+        .collect();
+    // One level per test date: the same PDF imported twice is one lab, not a pair
+    labs.dedup_by_key(|(day, _)| *day);
+
+    if labs.len() < 2 {
+        return Ok(None);
+    }
+
+    let mut samples = Vec::new();
+    for pair in labs.windows(2) {
+        let ((start_day, start_ng_ml), (end_day, end_ng_ml)) = (pair[0], pair[1]);
+        let days = (end_day - start_day).num_days();
+
+        // Intake over the previous 8-12 weeks, bounded by the earlier lab, whole local days
+        let window_start = start_day.max(end_day - chrono::Duration::weeks(12));
+        // and over the 12 weeks that led up to the earlier lab
+        let prior_start = start_day - chrono::Duration::weeks(12);
+
+        samples.push(LabResponseSample {
+            weeks: days as f64 / 7.0,
+            start_ng_ml,
+            end_ng_ml,
+            avg_daily_iu: average_daily_intake(db, window_start, end_day, tz)?,
+            prior_daily_iu: average_daily_intake(db, prior_start, start_day, tz)?,
+        });
+    }
+
+    let latest_date = labs.last().map(|(d, _)| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    Ok(vitamin_d::fit_response_model(&samples, &latest_date))
+}
+
+/// Get Vitamin D recommendation based on current UV index and the user's profile
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn get_vitamin_d_recommendation(
     uv_index: f64,
-    month: Option<u32>,
+    date: Option<String>,
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<VitaminDRecommendation, String> {
-    let current_month = month.unwrap_or_else(|| {
-        chrono::Local::now().format("%m").to_string().parse().unwrap_or(1)
-    });
+    let settings = settings::load_settings(&app_handle)?;
+    let now = super::system::clock(&app_handle).now();
+    recommend(&state, &settings, uv_index, date.as_deref(), now).await
+}
 
-    // Skin type 4 (Lightskin Afro-Brazilian + Euro-Portuguese)
-    // Latitude: Portugal (38.7°N)
-    Ok(vitamin_d::calculate(uv_index, Some(4), Some(38.7), current_month))
+/// Recommendation for `date` (default: the user's today at `now`) from the profile in
+/// `settings`, the logged intake and the lab history
+pub async fn recommend(
+    state: &DbState,
+    settings: &AppSettings,
+    uv_index: f64,
+    date: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<VitaminDRecommendation, String> {
+    let skin_type = settings.skin_type;
+    let tz = settings::user_timezone(settings);
+    let history = state.read(move |db| {
+        let (budget, has_history) = rolling_intake(db, skin_type, now)?;
+        Ok::<_, String>(IntakeHistory {
+            budget: has_history.then_some(budget),
+            response: load_response_model(db, tz)?,
+        })
+    }).await?;
+
+    let day = match date {
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date '{}': {}", d, e))?,
+        None => now.with_timezone(&settings::user_timezone(settings)).date_naive(),
+    };
+    let offset = utc_offset_for(settings, day);
+
+    Ok(vitamin_d::calculate(
+        uv_index,
+        Some(settings.skin_type),
+        Some(settings.latitude),
+        Some(settings.longitude),
+        day,
+        offset,
+        &history,
+    ))
 }
 
 /// Fetch current UV index from Open-Meteo API (free, no key needed)
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn get_current_uv_index(
     latitude: Option<f64>,
     longitude: Option<f64>,
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<f64, String> {
-    let lat = latitude.unwrap_or(38.7223);   // Lisbon default
-    let lon = longitude.unwrap_or(-9.1393);
-
-    let url = format!(
-        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=uv_index",
-        lat, lon
-    );
-
-    let client = reqwest::Client::new();
-    let response = client.get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Weather API error: {}", e))?;
-
-    let data: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse weather data: {}", e))?;
-
-    data["current"]["uv_index"]
-        .as_f64()
-        .ok_or_else(|| "UV index not available.".to_string())
+    // Default to the user's configured location
+    let ctx = settings::services(&state, &app_handle);
+    let lat = latitude.unwrap_or(ctx.config.settings.latitude);
+    let lon = longitude.unwrap_or(ctx.config.settings.longitude);
+
+    OpenMeteoClient::new(ctx.http.clone()).current_uv_index(lat, lon).await
+}
+
+/// `today`'s plan from the cached forecast only — no network, so it can run inside a database call
+pub fn cached_plan_for_today(db: &Database, settings: &AppSettings, today: NaiveDate) -> Option<DailyExposurePlan> {
+    let tz = settings::user_timezone(settings);
+    let (start, _) = time::local_day_bounds(today, tz);
+    let forecast = db.get_uv_forecast(settings.latitude, settings.longitude, &start).ok()?;
+    vitamin_d::plan_exposure(&forecast, settings.skin_type, &settings.exposed_body_area, tz, today)
+        .into_iter()
+        .find(|p| p.date == today.format("%Y-%m-%d").to_string())
+}
+
+/// Multi-day sun exposure plan from the hourly UV forecast.
+/// Fresh forecasts are cached in SQLite; when offline the cached hours are used instead.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_vitamin_d_plan(
+    days: Option<u32>,
+    body_area: Option<String>,
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<DailyExposurePlan>, String> {
+    let ctx = settings::services(&state, &app_handle);
+    let settings = &ctx.config.settings;
+    let days = days.unwrap_or(3).clamp(1, 7);
+    let body_area = body_area.unwrap_or_else(|| settings.exposed_body_area.clone());
+    let tz = ctx.tz();
+    let now = ctx.clock.now();
+    let today = now.with_timezone(&tz).date_naive();
+    let (start, _) = time::local_day_bounds(today, tz);
+
+    let forecast = OpenMeteoClient::new(ctx.http.clone())
+        .cached_hourly_forecast(&ctx.db, settings.latitude, settings.longitude, days, start, now)
+        .await?;
+
+    if forecast.is_empty() {
+        return Err("Previsão UV indisponível (sem ligação e sem cache).".to_string());
+    }
+
+    let mut plan = vitamin_d::plan_exposure(&forecast, settings.skin_type, &body_area, tz, today);
+    plan.truncate(days as usize);
+    Ok(plan)
+}
+
+/// Log a sun exposure session. The UV index at the session midpoint comes from the
+/// forecast cache, fetching that day's history from Open-Meteo on a cache miss.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn log_sun_exposure(
+    mut entry: SunExposureEntry,
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+) -> Result<SunExposureEntry, String> {
+    let settings = settings::load_settings(&app_handle)?;
+
+    if entry.duration_minutes == 0 || entry.duration_minutes > 12 * 60 {
+        return Err("Duração inválida (1 min a 12 h).".to_string());
+    }
+    if entry.spf.is_some_and(|spf| !(0.0..=100.0).contains(&spf)) {
+        return Err("SPF inválido (0-100).".to_string());
+    }
+    let started = time::parse_instant(&entry.started_at, settings::user_timezone(&settings))
+        .ok_or_else(|| format!("Data/hora inválida: '{}'", entry.started_at))?;
+    entry.started_at = time::format_utc(started);
+    let midpoint = started + chrono::Duration::minutes(entry.duration_minutes as i64 / 2);
+
+    let uv_index = match entry.uv_index {
+        Some(uv) => Some(uv),
+        None => {
+            let ctx = settings::services(&state, &app_handle);
+            OpenMeteoClient::new(ctx.http.clone())
+                .uv_index_at(&ctx.db, settings.latitude, settings.longitude, midpoint, ctx.clock.now())
+                .await?
+        }
+    };
+
+    let uv_index = uv_index
+        .ok_or_else(|| "Índice UV desconhecido para esse horário. Indique-o manualmente.".to_string())?;
+
+    entry.uv_index = Some(uv_index);
+    entry.estimated_iu = Some(vitamin_d::estimate_session_iu(
+        uv_index,
+        settings.skin_type,
+        entry.duration_minutes,
+        &entry.body_area,
+        entry.spf,
+    ));
+
+    state.write(move |db| {
+        entry.id = Some(db.insert_sun_exposure(&entry)?);
+        Ok::<_, rusqlite::Error>(entry)
+    }).await
+}
+
+/// Get sun exposure log for a date range
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_sun_exposure_log(
+    state: State<'_, DbState>,
+    from: String,
+    to: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SunExposureEntry>, String> {
+    let (from, to) = time::normalize_range(&from, &to, settings::current_timezone(&app_handle))?;
+    state.read(move |db| db.get_sun_exposures(&from, &to)).await
+}
+
+/// Rolling 7-day vitamin D intake (sun sessions + logged D3 doses)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_vitamin_d_budget(
+    state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
+) -> Result<IntakeBudget, String> {
+    let settings = settings::load_settings(&app_handle)?;
+    let skin_type = settings.skin_type;
+    let now = super::system::clock(&app_handle).now();
+    state.read(move |db| rolling_intake(db, skin_type, now).map(|(budget, _)| budget)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::health::SupplementEntry;
+    use crate::services::fhir::LabRecord;
+    use crate::services::context::testing::{context_at, instant, supplement};
+
+    fn vitamin_d_lab(db: &Database, date: &str, value: f64) {
+        db.insert_lab_record(&LabRecord {
+            id: None,
+            marker: "Vitamina D (25-OH)".to_string(),
+            value,
+            unit: "ng/mL".to_string(),
+            reference_range: Some("30-100".to_string()),
+            status: "normal".to_string(),
+            lab_name: Some("Synlab".to_string()),
+            test_date: Some(date.to_string()),
+            pdf_source: None,
+        }).unwrap();
+    }
+
+    #[test]
+    fn two_labs_give_a_personal_model_and_one_does_not() {
+        let db = Database::open_in_memory().unwrap();
+        db.run_migrations(chrono_tz::Europe::Lisbon, "2026-07-01T09:00:00Z".parse().unwrap()).unwrap();
+        vitamin_d_lab(&db, "2026-03-01", 20.0);
+        assert!(load_response_model(&db, chrono_tz::Europe::Lisbon).unwrap().is_none());
+
+        // 2000 IU every day for the 12 weeks up to the second lab, nothing logged before
+        let first = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        for day in first.iter_days().take(84) {
+            let taken_at = format!("{}T08:00:00Z", day.format("%Y-%m-%d"));
+            db.insert_supplement(&SupplementEntry { dosage: "2000 IU".to_string(), ..supplement("Vitamina D3", &taken_at) }).unwrap();
+        }
+        vitamin_d_lab(&db, "2026-05-24", 30.0);
+
+        let model = load_response_model(&db, chrono_tz::Europe::Lisbon).unwrap().unwrap();
+        assert_eq!((model.lab_pairs, model.latest_ng_ml, model.latest_date.as_str()), (1, 30.0, "2026-05-24"));
+        assert!((model.ng_ml_per_1000_iu - 5.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn without_a_date_the_day_is_today_in_the_users_zone() {
+        // 00:30 on the 25th in Lisbon, still the 24th in UTC; Lisbon leaves summer time
+        // that night, so the two days have their solar noon an hour apart on the clock
+        let now = "2026-10-24T23:30:00Z";
+        let (ctx, _, _) = context_at(now, None);
+        let settings = &ctx.config.settings;
+        let noon = |rec: VitaminDRecommendation| rec.solar_noon;
+
+        let today = noon(recommend(&ctx.db, settings, 3.0, None, instant(now)).await.unwrap());
+        assert_eq!(today, noon(recommend(&ctx.db, settings, 3.0, Some("2026-10-25"), instant(now)).await.unwrap()));
+        assert_ne!(today, noon(recommend(&ctx.db, settings, 3.0, Some("2026-10-24"), instant(now)).await.unwrap()));
+        assert!(today.starts_with("12:"), "{}", today);
+    }
 }
@@ -1,22 +1,29 @@
+#[cfg(feature = "gui")]
 use tauri::State;
 use crate::db::DbState;
+use crate::services::agent::AgentService;
 use crate::services::cartesia::{self, CartesiaConfig};
-use crate::services::{native_tts, whisper};
+use crate::services::context::ToolPaths;
+use crate::services::{native_tts, secrets, whisper};
 
 /// Synthesize speech: tries Cartesia API first, falls back to macOS native TTS
-async fn synthesize_speech(text: &str) -> Result<Vec<u8>, String> {
+#[cfg(feature = "gui")]
+async fn synthesize_speech(text: &str, app_handle: &tauri::AppHandle) -> Result<Vec<u8>, String> {
     // 1. Try Cartesia if API key is configured
-    if let Ok(api_key) = std::env::var("CARTESIA_API_KEY") {
-        if !api_key.is_empty() && api_key != "your_cartesia_key_here" {
-            let config = CartesiaConfig {
-                api_key,
-                ..Default::default()
-            };
-            match cartesia::synthesize(text, &config).await {
-                Ok(bytes) => return Ok(bytes),
-                Err(e) => {
-                    log::warn!("Cartesia TTS failed, trying native fallback: {}", e);
-                }
+    if let Some(api_key) = secrets::get(secrets::CARTESIA_API_KEY) {
+        let mut config = CartesiaConfig {
+            api_key,
+            ..Default::default()
+        };
+        if let Ok(settings) = super::settings::load_settings(app_handle) {
+            if !settings.cartesia_voice_id.is_empty() {
+                config.voice_id = settings.cartesia_voice_id;
+            }
+        }
+        match cartesia::synthesize(text, &config).await {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) => {
+                log::warn!("Cartesia TTS failed, trying native fallback: {}", e);
             }
         }
     }
@@ -31,35 +38,47 @@ async fn synthesize_speech(text: &str) -> Result<Vec<u8>, String> {
 }
 
 /// Speak text and return audio bytes
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn speak(
     text: String,
+    app_handle: tauri::AppHandle,
 ) -> Result<Vec<u8>, String> {
-    synthesize_speech(&text).await
+    synthesize_speech(&text, &app_handle).await
 }
 
 /// Speak the latest agent message
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn speak_agent_message(
     state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Vec<u8>, String> {
-    let message = super::agent::get_agent_message(state).await?;
-    synthesize_speech(&message.text).await
+    let message = super::agent::get_agent_message(state, app_handle.clone()).await?
+        .ok_or("Utilizador ausente — mensagem adiada para o regresso.")?;
+    synthesize_speech(&message.text, &app_handle).await
+}
+
+/// Transcribe an audio file with Whisper.cpp on the blocking pool (default language: pt)
+pub async fn transcribe_file(tools: ToolPaths, audio_path: String, language: Option<String>) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        whisper::transcribe(&tools, &audio_path, language.as_deref())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
 }
 
 /// Transcribe audio file using Whisper.cpp, then cleanup temp file
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn process_voice_input(
     audio_path: String,
     language: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let path_clone = audio_path.clone();
-    let result = tokio::task::spawn_blocking(move || {
-        whisper::transcribe(&path_clone, language.as_deref())
-            .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?;
+    let tools = super::settings::config(&app_handle).tools;
+    let result = transcribe_file(tools, audio_path.clone(), language).await;
 
     // Cleanup temp audio file after transcription
     let _ = std::fs::remove_file(&audio_path);
@@ -69,9 +88,11 @@ pub async fn process_voice_input(
 
 /// Save temporary audio data from frontend for Whisper processing.
 /// Accepts WebM (Opus) from MediaRecorder and converts to WAV via ffmpeg/afconvert.
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn save_temp_audio(
     audio_data: Vec<u8>,
+    app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
     let temp_dir = std::env::temp_dir().join("holoself");
     std::fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
@@ -83,7 +104,7 @@ pub async fn save_temp_audio(
     std::fs::write(&webm_path, &audio_data).map_err(|e| e.to_string())?;
 
     // Convert WebM → WAV (16kHz mono PCM16 — Whisper optimal format)
-    let converted = convert_to_wav(&webm_path, &wav_path);
+    let converted = convert_to_wav(&super::settings::config(&app_handle).tools, &webm_path, &wav_path);
 
     // Cleanup source WebM
     let _ = std::fs::remove_file(&webm_path);
@@ -95,9 +116,9 @@ pub async fn save_temp_audio(
 }
 
 /// Convert audio file to WAV 16kHz mono PCM16
-fn convert_to_wav(input: &std::path::Path, output: &std::path::Path) -> Result<(), String> {
-    // Find ffmpeg: HoloSelf bundled → PATH
-    let ffmpeg_bin = find_ffmpeg();
+fn convert_to_wav(tools: &ToolPaths, input: &std::path::Path, output: &std::path::Path) -> Result<(), String> {
+    // Find ffmpeg: setup path → HoloSelf bundled → PATH
+    let ffmpeg_bin = find_ffmpeg(tools);
 
     let ffmpeg = std::process::Command::new(&ffmpeg_bin)
         .arg("-y")
@@ -137,12 +158,11 @@ fn convert_to_wav(input: &std::path::Path, output: &std::path::Path) -> Result<(
     Err("Conversor de áudio não encontrado. Execute a instalação automática novamente nas configurações.".to_string())
 }
 
-/// Find ffmpeg binary: HoloSelf bundled → env var → PATH
-fn find_ffmpeg() -> std::path::PathBuf {
-    // 1. Env var from setup
-    if let Ok(path) = std::env::var("HOLOSELF_FFMPEG_PATH") {
-        let p = std::path::PathBuf::from(&path);
-        if p.exists() { return p; }
+/// Find ffmpeg binary: setup path → HoloSelf bundled → PATH
+fn find_ffmpeg(tools: &ToolPaths) -> std::path::PathBuf {
+    // 1. Path recorded by setup
+    if let Some(p) = tools.ffmpeg.as_ref().filter(|p| p.exists()) {
+        return p.clone();
     }
     // 2. Bundled in ~/.holoself/bin/
     if let Some(home) = dirs::home_dir() {
@@ -154,36 +174,31 @@ fn find_ffmpeg() -> std::path::PathBuf {
 }
 
 /// Process voice command: transcribe + interpret via agent
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn process_voice_command(
     audio_path: String,
     state: State<'_, DbState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
     // 1. Transcribe
-    let transcript = tokio::task::spawn_blocking(move || {
-        whisper::transcribe(&audio_path, Some("pt"))
-            .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))??;
+    let ctx = super::settings::services(&state, &app_handle);
+    let transcript = transcribe_file(ctx.config.tools.clone(), audio_path, Some("pt".to_string())).await?;
 
     if transcript.trim().is_empty() {
         return Ok("".to_string());
     }
 
     // 2. Store in agent memory
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    let _ = db.execute(
-        "INSERT INTO agent_memory (key, value, timestamp) VALUES ('voice_input', ?1, ?2)",
-        rusqlite::params![&transcript, &chrono::Utc::now().to_rfc3339()],
-    );
+    let _ = AgentService::new(&ctx).remember_voice_input(transcript.clone()).await;
 
     // 3. Return transcript (agent will process on frontend)
     Ok(transcript)
 }
 
 /// Check whisper.cpp availability
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn get_whisper_status() -> Result<whisper::WhisperStatus, String> {
-    Ok(whisper::status())
+pub async fn get_whisper_status(app_handle: tauri::AppHandle) -> Result<whisper::WhisperStatus, String> {
+    Ok(whisper::status(&super::settings::config(&app_handle).tools))
 }
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use tauri::{Emitter, Manager};
+use crate::commands::health::VitalEntry;
+use crate::db::DbState;
+use crate::services::time;
+use crate::services::wearables::{self, Format, WearableSample};
+
+pub const IMPORT_PROGRESS_EVENT: &str = "wearables://progress";
+const BATCH_SIZE: usize = 2000;
+const SOURCE: &str = "wearable";
+
+/// Outcome of one import, for the HUD toast
+#[derive(Debug, Serialize, Default)]
+pub struct ImportSummary {
+    pub format: String,
+    pub read: usize,       // samples mapped to a vital
+    pub inserted: usize,   // new rows
+    pub duplicates: usize, // already imported before
+    pub skipped: usize,    // implausible values
+    pub by_type: BTreeMap<String, usize>,
+}
+
+/// Payload of `wearables://progress`, emitted after every stored batch
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportProgress {
+    pub read: usize,
+    pub inserted: usize,
+}
+
+fn to_entry(sample: WearableSample) -> VitalEntry {
+    VitalEntry {
+        id: None,
+        vital_type: sample.vital_type.to_string(),
+        value: sample.value,
+        unit: sample.unit.to_string(),
+        recorded_at: time::format_utc(sample.recorded_at),
+        source: SOURCE.to_string(),
+    }
+}
+
+/// Store one batch; the writer is held only for this transaction
+#[cfg(feature = "gui")]
+fn flush(app_handle: &tauri::AppHandle, batch: &mut Vec<VitalEntry>, summary: &mut ImportSummary) -> Result<(), String> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let db_state = app_handle.try_state::<DbState>().ok_or("Base de dados indisponível.")?;
+    let inserted = db_state.write_blocking(|db| db.insert_wearable_vitals(batch))?;
+    summary.inserted += inserted;
+    summary.duplicates += batch.len() - inserted;
+    batch.clear();
+
+    let progress = ImportProgress { read: summary.read, inserted: summary.inserted };
+    if let Err(e) = app_handle.emit(IMPORT_PROGRESS_EVENT, progress) {
+        log::warn!("Failed to report import progress: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gui")]
+fn import_file(app_handle: &tauri::AppHandle, path: &Path, format: Format) -> Result<ImportSummary, String> {
+    let mut summary = ImportSummary { format: format.name().to_string(), ..Default::default() };
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    wearables::parse_file(path, format, |sample| {
+        if !sample.is_plausible() {
+            summary.skipped += 1;
+            return Ok(());
+        }
+        summary.read += 1;
+        *summary.by_type.entry(sample.vital_type.to_string()).or_default() += 1;
+        batch.push(to_entry(sample));
+        if batch.len() >= BATCH_SIZE {
+            flush(app_handle, &mut batch, &mut summary)?;
+        }
+        Ok(())
+    })?;
+    flush(app_handle, &mut batch, &mut summary)?;
+
+    if let Some(db_state) = app_handle.try_state::<DbState>() {
+        let now = time::format_utc(super::system::clock(app_handle).now());
+        db_state.write_blocking(|db| {
+            crate::commands::records::audit_import(db, &now, "vitals", &path.to_string_lossy(), summary.inserted)
+        })?;
+    }
+
+    log::info!(
+        "Imported {} ({:?}): {} new, {} duplicates, {} skipped",
+        summary.format, path, summary.inserted, summary.duplicates, summary.skipped
+    );
+    Ok(summary)
+}
+
+/// Import heart rate, resting HR, HRV, sleep and steps from an Apple Health export.xml,
+/// a Google Fit / Health Connect JSON or a Garmin .fit file. Re-importing is harmless.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn import_wearable_file(
+    path: String,
+    format: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<ImportSummary, String> {
+    let path = PathBuf::from(path);
+    let format = match format.as_deref() {
+        Some(raw) => Format::parse(raw).ok_or_else(|| format!("Formato desconhecido: '{}'", raw))?,
+        None => Format::detect(&path)
+            .ok_or("Formato não reconhecido: use export.xml (Apple Health), .json (Google Fit / Health Connect) ou .fit (Garmin).")?,
+    };
+
+    tauri::async_runtime::spawn_blocking(move || import_file(&app_handle, &path, format))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,850 @@
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use chrono::{DateTime, Utc};
+use crate::services::time;
+use crate::services::typing::{Baseline, BaselineTable};
+
+// Ordered schema migrations with checksums, down steps and startup checks.
+//
+// Each step runs in its own transaction together with its `_migrations` row, so a failed
+// step leaves the database at the previous version. The SHA-256 of every applied up step
+// is stored next to it; a mismatch means a shipped migration was edited, and the database
+// is refused instead of drifting from what the code expects. A database written by a newer
+// app version is refused too. Down steps exist for rolling a copy back to an older schema;
+// the app itself only migrates up.
+
+/// What an up step runs
+pub enum Step {
+    Sql(&'static str),
+    /// SQL after a Rust pass that needs the user's zone; only the SQL is checksummed
+    WithZone(&'static str, fn(&Connection, Tz) -> rusqlite::Result<()>),
+}
+
+impl Step {
+    fn sql(&self) -> &'static str {
+        match self {
+            Step::Sql(sql) | Step::WithZone(sql, _) => sql,
+        }
+    }
+}
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: Step,
+    pub down: &'static str,
+}
+
+impl Migration {
+    /// Hex SHA-256 of the up SQL
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.up.sql().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Every migration, oldest first; versions are 1, 2, 3, … without gaps.
+/// Never edit an entry once released: add a new one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "core_health_tables",
+        up: Step::Sql("
+            -- Supplement intake log
+            CREATE TABLE IF NOT EXISTS supplements (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                dosage TEXT NOT NULL,
+                taken_at TEXT NOT NULL,
+                category TEXT NOT NULL,
+                notes TEXT,
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Vital signs / biometrics
+            CREATE TABLE IF NOT EXISTS vitals (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                vital_type TEXT NOT NULL,
+                value REAL NOT NULL,
+                unit TEXT NOT NULL,
+                recorded_at TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT 'manual',
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Clinical lab results (from Gemini OCR)
+            CREATE TABLE IF NOT EXISTS lab_results (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                marker TEXT NOT NULL,
+                value REAL NOT NULL,
+                unit TEXT NOT NULL,
+                reference_range TEXT,
+                status TEXT NOT NULL,
+                lab_name TEXT,
+                test_date TEXT,
+                pdf_source TEXT,
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Health scheduler (predictive exams)
+            CREATE TABLE IF NOT EXISTS health_schedule (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                exam_type TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                scheduled_date TEXT NOT NULL,
+                triggered_by TEXT,
+                completed INTEGER DEFAULT 0,
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Agent memory (voice inputs, context, RAG embeddings)
+            CREATE TABLE IF NOT EXISTS agent_memory (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                timestamp TEXT DEFAULT (datetime('now')),
+                category TEXT,
+                embedding BLOB
+            );
+
+            -- Indexes
+            CREATE INDEX IF NOT EXISTS idx_supplements_taken_at ON supplements(taken_at);
+            CREATE INDEX IF NOT EXISTS idx_vitals_recorded_at ON vitals(recorded_at);
+            CREATE INDEX IF NOT EXISTS idx_lab_results_marker ON lab_results(marker);
+            CREATE INDEX IF NOT EXISTS idx_health_schedule_date ON health_schedule(scheduled_date);
+            CREATE INDEX IF NOT EXISTS idx_agent_memory_key ON agent_memory(key, timestamp);
+        "),
+        down: "
+            DROP TABLE agent_memory;
+            DROP TABLE health_schedule;
+            DROP TABLE lab_results;
+            DROP TABLE vitals;
+            DROP TABLE supplements;
+        ",
+    },
+    Migration {
+        version: 2,
+        name: "uv_forecast_cache",
+        up: Step::Sql("
+            -- Hourly UV forecast cache (Open-Meteo), keyed by rounded location + UTC hour
+            CREATE TABLE IF NOT EXISTS uv_forecast (
+                latitude REAL NOT NULL,
+                longitude REAL NOT NULL,
+                hour TEXT NOT NULL,
+                uv_index REAL NOT NULL,
+                cloud_cover REAL NOT NULL DEFAULT 0,
+                fetched_at TEXT DEFAULT (datetime('now')),
+                PRIMARY KEY (latitude, longitude, hour)
+            );
+        "),
+        down: "
+            DROP TABLE uv_forecast;
+        ",
+    },
+    Migration {
+        version: 3,
+        name: "sun_exposure",
+        up: Step::Sql("
+            -- Sun exposure sessions with estimated vitamin D synthesis
+            CREATE TABLE IF NOT EXISTS sun_exposure (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                duration_minutes INTEGER NOT NULL,
+                body_area TEXT NOT NULL,
+                spf REAL,
+                uv_index REAL NOT NULL,
+                estimated_iu INTEGER NOT NULL,
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_sun_exposure_started_at ON sun_exposure(started_at);
+        "),
+        down: "
+            DROP TABLE sun_exposure;
+        ",
+    },
+    Migration {
+        version: 4,
+        name: "canonical_utc_timestamps",
+        up: Step::WithZone("
+            -- Forecast cache is keyed by hour string; refetching is cheaper than rewriting keys
+            DELETE FROM uv_forecast;
+        ", normalize_timestamps),
+        down: "
+            -- Canonical timestamps are still readable by v3; the cache just refills
+        ",
+    },
+    Migration {
+        version: 5,
+        name: "presence_breaks_posture",
+        up: Step::Sql("
+            -- Presence sessions from the webcam detector (ended_at NULL while open)
+            CREATE TABLE IF NOT EXISTS presence_sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT,
+                source TEXT NOT NULL DEFAULT 'webcam',
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Breaks away from the screen
+            CREATE TABLE IF NOT EXISTS breaks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                reason TEXT NOT NULL DEFAULT 'away',
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+
+            -- Posture score samples (0-100)
+            CREATE TABLE IF NOT EXISTS posture_samples (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                head_x REAL,
+                head_y REAL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_presence_sessions_started_at ON presence_sessions(started_at);
+            CREATE INDEX IF NOT EXISTS idx_breaks_ended_at ON breaks(ended_at);
+            CREATE INDEX IF NOT EXISTS idx_posture_samples_recorded_at ON posture_samples(recorded_at);
+        "),
+        down: "
+            DROP TABLE posture_samples;
+            DROP TABLE breaks;
+            DROP TABLE presence_sessions;
+        ",
+    },
+    Migration {
+        version: 6,
+        name: "typing_baselines",
+        up: Step::Sql("
+            -- Typing speed baseline per local weekday (0 = Monday) and hour (Welford mean/M2)
+            CREATE TABLE IF NOT EXISTS wpm_baselines (
+                weekday INTEGER NOT NULL,
+                hour INTEGER NOT NULL,
+                samples INTEGER NOT NULL DEFAULT 0,
+                mean REAL NOT NULL DEFAULT 0,
+                m2 REAL NOT NULL DEFAULT 0,
+                updated_at TEXT DEFAULT (datetime('now')),
+                PRIMARY KEY (weekday, hour)
+            );
+
+            -- Per-minute WPM snapshots live in vitals (vital_type = 'wpm')
+            CREATE INDEX IF NOT EXISTS idx_vitals_type_recorded_at ON vitals(vital_type, recorded_at);
+        "),
+        down: "
+            DROP INDEX idx_vitals_type_recorded_at;
+            DROP TABLE wpm_baselines;
+        ",
+    },
+    Migration {
+        version: 7,
+        name: "eye_care",
+        up: Step::Sql("
+            -- Per-minute blink rates live in vitals (vital_type = 'blink_rate')
+
+            -- Short look-aways (>= 20 s) that satisfy the 20-20-20 rule
+            CREATE TABLE IF NOT EXISTS eye_rests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT 'presence'
+            );
+
+            -- 20-20-20 reminders; outcome stays NULL until complied/missed
+            CREATE TABLE IF NOT EXISTS eye_reminders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                emitted_at TEXT NOT NULL,
+                resolved_at TEXT,
+                outcome TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_eye_rests_ended_at ON eye_rests(ended_at);
+            CREATE INDEX IF NOT EXISTS idx_eye_reminders_emitted_at ON eye_reminders(emitted_at);
+        "),
+        down: "
+            DROP TABLE eye_reminders;
+            DROP TABLE eye_rests;
+        ",
+    },
+    Migration {
+        version: 8,
+        name: "deferred_messages",
+        up: Step::Sql("
+            -- Non-urgent agent messages held back while the user is away
+            CREATE TABLE IF NOT EXISTS deferred_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL,
+                category TEXT NOT NULL,
+                priority TEXT NOT NULL,
+                text TEXT NOT NULL,
+                delivered_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_deferred_messages_pending ON deferred_messages(delivered_at);
+        "),
+        down: "
+            DROP TABLE deferred_messages;
+        ",
+    },
+    Migration {
+        version: 9,
+        name: "unique_wearable_vitals",
+        up: Step::Sql("
+            -- Wearable imports are idempotent: one reading per (type, timestamp, source)
+            DELETE FROM vitals WHERE source = 'wearable' AND id NOT IN (
+                SELECT MIN(id) FROM vitals WHERE source = 'wearable' GROUP BY vital_type, recorded_at
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_vitals_wearable_unique
+                ON vitals(vital_type, recorded_at, source) WHERE source = 'wearable';
+        "),
+        down: "
+            -- Duplicates removed on the way up stay removed
+            DROP INDEX idx_vitals_wearable_unique;
+        ",
+    },
+    Migration {
+        version: 10,
+        name: "ingest_api",
+        up: Step::Sql("
+            -- Clients of the local ingestion API; only the SHA-256 of each token is kept
+            CREATE TABLE IF NOT EXISTS ingest_clients (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                last_used_at TEXT,
+                revoked_at TEXT
+            );
+
+            -- Every request that reached the API: who, what, outcome
+            CREATE TABLE IF NOT EXISTS ingest_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                received_at TEXT NOT NULL,
+                client_id INTEGER REFERENCES ingest_clients(id),
+                client_name TEXT,
+                method TEXT NOT NULL,
+                path TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                record_kind TEXT,
+                record_ids TEXT NOT NULL DEFAULT '[]',
+                detail TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_ingest_audit_received_at ON ingest_audit(received_at);
+        "),
+        down: "
+            DROP TABLE ingest_audit;
+            DROP TABLE ingest_clients;
+        ",
+    },
+    Migration {
+        version: 11,
+        name: "soft_delete_audit_log",
+        up: Step::Sql("
+            -- Soft delete: rows stay for the audit trail and undo, reads skip them
+            ALTER TABLE supplements ADD COLUMN deleted_at TEXT;
+            ALTER TABLE vitals ADD COLUMN deleted_at TEXT;
+            ALTER TABLE lab_results ADD COLUMN deleted_at TEXT;
+            ALTER TABLE health_schedule ADD COLUMN deleted_at TEXT;
+
+            -- Every change to a health record, with the row before and after as JSON
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                changed_at TEXT NOT NULL,
+                record_type TEXT NOT NULL,   -- supplements | vitals | lab_results | health_schedule
+                record_id INTEGER,           -- NULL for bulk imports
+                action TEXT NOT NULL,        -- insert | update | delete | restore | import | undo
+                source TEXT NOT NULL,        -- ui | voice | agent | import
+                before_json TEXT,
+                after_json TEXT,
+                undone_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_audit_log_record ON audit_log(record_type, record_id);
+        "),
+        down: "
+            DROP TABLE audit_log;
+            ALTER TABLE health_schedule DROP COLUMN deleted_at;
+            ALTER TABLE lab_results DROP COLUMN deleted_at;
+            ALTER TABLE vitals DROP COLUMN deleted_at;
+            ALTER TABLE supplements DROP COLUMN deleted_at;
+        ",
+    },
+    Migration {
+        version: 12,
+        name: "wpm_judged_baselines",
+        up: Step::WithZone("
+            -- Baseline each WPM minute was judged against when stored (no row while calibrating),
+            -- so fatigue episodes don't change when the baseline moves on
+            CREATE TABLE IF NOT EXISTS wpm_judgements (
+                vital_id INTEGER PRIMARY KEY REFERENCES vitals(id),
+                baseline_wpm REAL NOT NULL
+            );
+
+            INSERT INTO wpm_judgements (vital_id, baseline_wpm) SELECT vital_id, baseline_wpm FROM temp.wpm_judged;
+            DROP TABLE temp.wpm_judged;
+        ", judge_stored_wpm),
+        down: "
+            DROP TABLE wpm_judgements;
+        ",
+    },
+];
+
+/// Newest schema this build knows
+pub const LATEST_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Normalize every stored instant to canonical UTC (`time::format_utc`).
+/// Older rows mixed `Local` offsets, `Utc` offsets and SQLite's `datetime('now')`.
+fn normalize_timestamps(conn: &Connection, local_tz: Tz) -> rusqlite::Result<()> {
+    for (table, column) in [
+        ("supplements", "taken_at"),
+        ("vitals", "recorded_at"),
+        ("agent_memory", "timestamp"),
+        ("sun_exposure", "started_at"),
+    ] {
+        let rows = {
+            let mut stmt = conn.prepare(&format!("SELECT id, {column} FROM {table} WHERE {column} IS NOT NULL"))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        let mut update = conn.prepare(&format!("UPDATE {table} SET {column} = ?1 WHERE id = ?2"))?;
+        for (id, raw) in rows {
+            match time::parse_instant(&raw, local_tz).map(time::format_utc) {
+                Some(normalized) if normalized != raw => {
+                    update.execute(rusqlite::params![normalized, id])?;
+                }
+                Some(_) => {}
+                None => log::warn!("Leaving unparseable {}.{} = {:?} (id {})", table, column, raw, id),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Minutes stored before v12 were never judged; the baselines as they stand at upgrade
+/// are the closest record of what they were compared with. Fills `temp.wpm_judged`.
+fn judge_stored_wpm(conn: &Connection, local_tz: Tz) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE TEMP TABLE wpm_judged (vital_id INTEGER PRIMARY KEY, baseline_wpm REAL NOT NULL)")?;
+    let table = {
+        let mut stmt = conn.prepare("SELECT weekday, hour, samples, mean, m2 FROM wpm_baselines")?;
+        let cells = stmt.query_map([], |row| {
+            Ok((
+                (row.get::<_, u32>(0)?, row.get::<_, u32>(1)?),
+                Baseline { samples: row.get(2)?, mean: row.get(3)?, m2: row.get(4)? },
+            ))
+        })?.collect::<Result<_, _>>()?;
+        BaselineTable { cells }
+    };
+
+    let minutes = {
+        let mut stmt = conn.prepare("SELECT id, recorded_at FROM vitals WHERE vital_type = 'wpm'")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+    for (id, recorded_at) in minutes {
+        let Ok(at) = chrono::DateTime::parse_from_rfc3339(&recorded_at) else { continue };
+        if let Some(baseline) = table.lookup_at(&at.with_timezone(&local_tz)) {
+            conn.execute("INSERT INTO temp.wpm_judged (vital_id, baseline_wpm) VALUES (?1, ?2)", rusqlite::params![id, baseline.mean])?;
+        }
+    }
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+/// Create or upgrade the bookkeeping table, refuse newer or edited schemas and
+/// return the current version
+fn prepare(conn: &Connection) -> Result<i64, String> {
+    let prepared = (|| {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS _migrations (
+                version INTEGER PRIMARY KEY,
+                applied_at TEXT DEFAULT (datetime('now'))
+            );"
+        )?;
+        let current: i64 = conn.query_row("SELECT COALESCE(MAX(version), 0) FROM _migrations", [], |row| row.get(0))?;
+        if current > LATEST_VERSION {
+            return Ok(Err(format!(
+                "A base de dados é de uma versão mais recente do HoloSelf (esquema v{}, esta versão suporta até v{}). Atualize a aplicação.",
+                current, LATEST_VERSION
+            )));
+        }
+        // Databases from before checksums: the applied steps are trusted as shipped
+        for column in ["name", "checksum"] {
+            if !has_column(conn, "_migrations", column)? {
+                conn.execute_batch(&format!("ALTER TABLE _migrations ADD COLUMN {column} TEXT;"))?;
+            }
+        }
+        let applied = {
+            let mut stmt = conn.prepare("SELECT version, checksum FROM _migrations ORDER BY version")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for (version, stored) in applied {
+            let Some(migration) = MIGRATIONS.iter().find(|m| m.version == version) else {
+                return Ok(Err(format!("Migração v{} desconhecida nesta versão do HoloSelf.", version)));
+            };
+            let expected = migration.checksum();
+            match stored {
+                None => {
+                    conn.execute(
+                        "UPDATE _migrations SET name = ?1, checksum = ?2 WHERE version = ?3",
+                        rusqlite::params![migration.name, expected, version],
+                    )?;
+                }
+                Some(stored) if stored != expected => {
+                    return Ok(Err(format!(
+                        "A migração v{} ({}) aplicada a esta base de dados difere da incluída nesta versão (checksum {} em vez de {}).",
+                        version, migration.name, stored, expected
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        Ok::<_, rusqlite::Error>(Ok(current))
+    })();
+    prepared.map_err(|e| format!("Falha ao ler as migrações: {}", e))?
+}
+
+fn apply_up(conn: &Connection, migration: &Migration, local_tz: Tz, now: &str) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    if let Step::WithZone(_, pass) = migration.up {
+        pass(&tx, local_tz)?;
+    }
+    tx.execute_batch(migration.up.sql())?;
+    tx.execute(
+        "INSERT INTO _migrations (version, applied_at, name, checksum) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![migration.version, now, migration.name, migration.checksum()],
+    )?;
+    tx.commit()
+}
+
+fn apply_down(conn: &Connection, migration: &Migration) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(migration.down)?;
+    tx.execute("DELETE FROM _migrations WHERE version = ?1", [migration.version])?;
+    tx.commit()
+}
+
+/// Bring the database to schema `target`, stepping up or down one migration at a time.
+/// `local_tz` is the user's zone, needed to read legacy timestamps without an offset;
+/// `now` stamps each applied migration.
+pub fn migrate_to(conn: &Connection, target: i64, local_tz: Tz, now: DateTime<Utc>) -> Result<(), String> {
+    if !(0..=LATEST_VERSION).contains(&target) {
+        return Err(format!("Versão de esquema inválida: v{} (de v0 a v{}).", target, LATEST_VERSION));
+    }
+    let current = prepare(conn)?;
+    let failed = |migration: &Migration, e: rusqlite::Error| {
+        format!("Falha na migração v{} ({}): {}", migration.version, migration.name, e)
+    };
+    let now = time::format_utc(now);
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target) {
+        apply_up(conn, migration, local_tz, &now).map_err(|e| failed(migration, e))?;
+        log::info!("Applied migration v{} ({})", migration.version, migration.name);
+    }
+    for migration in MIGRATIONS.iter().rev().filter(|m| m.version <= current && m.version > target) {
+        apply_down(conn, migration).map_err(|e| failed(migration, e))?;
+        log::info!("Reverted migration v{} ({})", migration.version, migration.name);
+    }
+    Ok(())
+}
+
+/// `PRAGMA integrity_check` and `PRAGMA foreign_key_check`; Err describes the damage
+pub fn check_integrity(conn: &Connection) -> Result<(), String> {
+    let checked = (|| {
+        let integrity = {
+            let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        let orphans = {
+            let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(format!("{}#{} → {}", row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?.unwrap_or(0), row.get::<_, String>(2)?))
+            })?.collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        Ok::<_, rusqlite::Error>((integrity, orphans))
+    })();
+    let (integrity, orphans) = checked.map_err(|e| format!("Falha na verificação de integridade: {}", e))?;
+    if integrity != ["ok"] {
+        return Err(format!("A base de dados está danificada: {}", integrity.join("; ")));
+    }
+    if !orphans.is_empty() {
+        return Err(format!(
+            "{} registo(s) com referências inválidas: {}",
+            orphans.len(),
+            orphans.iter().take(10).cloned().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZONE: Tz = chrono_tz::America::Sao_Paulo;
+
+    fn applied() -> DateTime<Utc> {
+        "2026-07-01T09:30:00Z".parse().unwrap()
+    }
+
+    /// Rows each historical version could have written, in that version's shapes
+    /// (legacy timestamps before v4, duplicate wearable imports before v9)
+    const FIXTURES: [(i64, &str); 11] = [
+        (1, "
+            INSERT INTO supplements (name, dosage, taken_at, category) VALUES ('Vitamina D3', '2000 UI', '2025-03-01T08:00:00-03:00', 'vitamin');
+            INSERT INTO vitals (vital_type, value, unit, recorded_at) VALUES ('heart_rate', 62, 'bpm', '2025-03-01 11:00:00');
+            INSERT INTO vitals (vital_type, value, unit, recorded_at, source) VALUES ('steps', 800, 'count', '2025-03-01T09:00:00Z', 'wearable');
+            INSERT INTO vitals (vital_type, value, unit, recorded_at, source) VALUES ('steps', 800, 'count', '2025-03-01T09:00:00Z', 'wearable');
+            INSERT INTO lab_results (marker, value, unit, status, test_date) VALUES ('Vitamina D', 28, 'ng/mL', 'low', '2025-02-20');
+            INSERT INTO health_schedule (exam_type, reason, scheduled_date) VALUES ('Vitamina D', 'Reavaliar', '2025-05-20');
+            INSERT INTO agent_memory (key, value, timestamp) VALUES ('voice_input', 'tomei a vitamina', '2025-03-01T08:05:00');
+        "),
+        (2, "INSERT INTO uv_forecast (latitude, longitude, hour, uv_index) VALUES (-23.55, -46.63, '2025-03-01T12:00:00+00:00', 9.5);"),
+        (3, "
+            INSERT INTO sun_exposure (started_at, duration_minutes, body_area, uv_index, estimated_iu)
+            VALUES ('2025-03-01T12:00:00-03:00', 15, 'arms_legs', 9.5, 1200);
+        "),
+        (5, "
+            INSERT INTO presence_sessions (started_at, ended_at) VALUES ('2025-03-02T12:00:00Z', '2025-03-02T13:00:00Z');
+            INSERT INTO breaks (started_at, ended_at) VALUES ('2025-03-02T13:00:00Z', '2025-03-02T13:10:00Z');
+            INSERT INTO posture_samples (recorded_at, score) VALUES ('2025-03-02T12:30:00Z', 80);
+        "),
+        (6, "
+            INSERT INTO wpm_baselines (weekday, hour, samples, mean, m2) VALUES (0, 9, 12, 48.5, 30.2);
+            INSERT INTO vitals (vital_type, value, unit, recorded_at) VALUES ('wpm', 51, 'wpm', '2025-03-03T12:01:00Z');
+        "),
+        (7, "
+            INSERT INTO eye_rests (started_at, ended_at) VALUES ('2025-03-03T12:20:00Z', '2025-03-03T12:20:25Z');
+            INSERT INTO eye_reminders (emitted_at, resolved_at, outcome) VALUES ('2025-03-03T12:19:00Z', '2025-03-03T12:20:25Z', 'complied');
+        "),
+        (8, "INSERT INTO deferred_messages (created_at, category, priority, text) VALUES ('2025-03-03T15:00:00Z', 'hydration', 'low', 'Beba água.');"),
+        (9, "INSERT INTO vitals (vital_type, value, unit, recorded_at, source) VALUES ('sleep', 7.5, 'h', '2025-03-04T06:00:00Z', 'wearable');"),
+        (10, "
+            INSERT INTO ingest_clients (name, token_hash, created_at) VALUES ('balança', 'ab12', '2025-03-04T10:00:00Z');
+            INSERT INTO ingest_audit (received_at, client_id, client_name, method, path, status)
+            VALUES ('2025-03-04T10:01:00Z', 1, 'balança', 'POST', '/v1/vitals', 201);
+        "),
+        (11, "
+            UPDATE vitals SET deleted_at = '2025-03-05T10:00:00Z' WHERE vital_type = 'wpm';
+            INSERT INTO audit_log (changed_at, record_type, record_id, action, source)
+            VALUES ('2025-03-05T10:00:00Z', 'vitals', 5, 'delete', 'ui');
+        "),
+        (12, "INSERT INTO wpm_judgements (vital_id, baseline_wpm) SELECT id, 48.5 FROM vitals WHERE vital_type = 'wpm';"),
+    ];
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys=ON;").unwrap();
+        conn
+    }
+
+    /// Database as a user who last ran version `version` left it
+    fn history(version: i64) -> Connection {
+        let conn = open();
+        for step in 1..=version {
+            migrate_to(&conn, step, ZONE, applied()).unwrap();
+            for (_, sql) in FIXTURES.iter().filter(|(v, _)| *v == step) {
+                conn.execute_batch(sql).unwrap();
+            }
+        }
+        conn
+    }
+
+    fn version(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COALESCE(MAX(version), 0) FROM _migrations", [], |row| row.get(0)).unwrap()
+    }
+
+    fn query(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    /// Tables with their columns, and indexes, independent of how the DDL text was edited
+    fn schema(conn: &Connection) -> Vec<String> {
+        query(conn, "
+            SELECT m.type || ' ' || m.name || ' (' || COALESCE((
+                SELECT group_concat(c.name || ' ' || c.type || ' ' || c.\"notnull\" || ' ' || COALESCE(c.dflt_value, '') || ' ' || c.pk, ', ')
+                FROM pragma_table_info(m.name) c
+            ), m.sql) || ')'
+            FROM sqlite_master m
+            WHERE m.name NOT IN ('_migrations', 'sqlite_sequence') AND m.name NOT LIKE 'sqlite_autoindex%'
+            ORDER BY m.type, m.name
+        ")
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn registry_is_ordered_without_gaps() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1, "{}", migration.name);
+        }
+        assert_eq!(LATEST_VERSION, super::super::CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn every_historical_version_migrates_to_current() {
+        let fresh = open();
+        migrate_to(&fresh, LATEST_VERSION, ZONE, applied()).unwrap();
+        let expected = schema(&fresh);
+        // Every step is stamped with the injected instant, not the wall clock
+        assert_eq!(count(&fresh, "SELECT COUNT(*) FROM _migrations WHERE applied_at = '2026-07-01T09:30:00Z'"), LATEST_VERSION);
+
+        for from in 0..=LATEST_VERSION {
+            let conn = history(from);
+            migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap_or_else(|e| panic!("from v{}: {}", from, e));
+
+            assert_eq!(version(&conn), LATEST_VERSION, "from v{}", from);
+            assert_eq!(schema(&conn), expected, "from v{}", from);
+            check_integrity(&conn).unwrap_or_else(|e| panic!("from v{}: {}", from, e));
+            assert_eq!(count(&conn, "SELECT COUNT(*) FROM _migrations WHERE checksum IS NOT NULL"), LATEST_VERSION);
+
+            if from >= 1 {
+                // Legacy instants end up canonical UTC; naive ones are read in the user's zone
+                assert_eq!(query(&conn, "SELECT taken_at FROM supplements"), ["2025-03-01T11:00:00Z"]);
+                assert_eq!(query(&conn, "SELECT recorded_at FROM vitals WHERE vital_type = 'heart_rate'"), ["2025-03-01T11:00:00Z"]);
+                assert_eq!(query(&conn, "SELECT timestamp FROM agent_memory"), ["2025-03-01T11:05:00Z"]);
+                assert_eq!(count(&conn, "SELECT COUNT(*) FROM vitals WHERE vital_type = 'steps'"), 1);
+                assert_eq!(count(&conn, "SELECT COUNT(*) FROM lab_results WHERE deleted_at IS NULL"), 1);
+            }
+            if from >= 2 {
+                assert_eq!(count(&conn, "SELECT COUNT(*) FROM uv_forecast"), 0);
+            }
+            if from >= 3 {
+                assert_eq!(query(&conn, "SELECT started_at FROM sun_exposure"), ["2025-03-01T15:00:00Z"]);
+            }
+            if from >= 8 {
+                assert_eq!(query(&conn, "SELECT text FROM deferred_messages WHERE delivered_at IS NULL"), ["Beba água."]);
+            }
+            if from >= 10 {
+                assert_eq!(count(&conn, "SELECT COUNT(*) FROM ingest_audit WHERE client_id = 1"), 1);
+            }
+            // The v6 minute predates a trusted baseline, so only the v12 fixture judges it
+            let judged = count(&conn, "SELECT COUNT(*) FROM wpm_judgements");
+            assert_eq!(judged, if from >= 12 { 1 } else { 0 }, "from v{}", from);
+            let deleted = count(&conn, "SELECT COUNT(*) FROM vitals WHERE deleted_at IS NOT NULL");
+            assert_eq!(deleted, if from >= 11 { 1 } else { 0 }, "from v{}", from);
+        }
+    }
+
+    #[test]
+    fn stored_wpm_minutes_are_judged_against_trusted_baselines_on_upgrade() {
+        let conn = history(11);
+        conn.execute_batch("
+            UPDATE wpm_baselines SET samples = 30;
+            INSERT INTO vitals (vital_type, value, unit, recorded_at) VALUES ('wpm', 30, 'wpm', '2025-03-04T12:01:00Z');
+        ").unwrap();
+        migrate_to(&conn, 12, ZONE, applied()).unwrap();
+
+        // Monday 09:01 in São Paulo has its own cell; Tuesday falls back to every cell at 9:00
+        let judged = query(&conn, "SELECT v.recorded_at || ' ' || j.baseline_wpm FROM wpm_judgements j JOIN vitals v ON v.id = j.vital_id ORDER BY v.recorded_at");
+        assert_eq!(judged, ["2025-03-03T12:01:00Z 48.5", "2025-03-04T12:01:00Z 48.5"]);
+    }
+
+    #[test]
+    fn down_steps_restore_each_previous_schema() {
+        for target in (0..LATEST_VERSION).rev() {
+            let reference = open();
+            migrate_to(&reference, target, ZONE, applied()).unwrap();
+
+            let conn = history(LATEST_VERSION);
+            migrate_to(&conn, target, ZONE, applied()).unwrap_or_else(|e| panic!("down to v{}: {}", target, e));
+            assert_eq!(version(&conn), target);
+            assert_eq!(schema(&conn), schema(&reference), "down to v{}", target);
+            check_integrity(&conn).unwrap();
+
+            // And back up again on top of whatever the older version kept
+            migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap_or_else(|e| panic!("up from v{}: {}", target, e));
+            assert_eq!(version(&conn), LATEST_VERSION);
+        }
+    }
+
+    #[test]
+    fn newer_database_is_refused_untouched() {
+        let conn = history(LATEST_VERSION);
+        conn.execute("INSERT INTO _migrations (version, name, checksum) VALUES (?1, 'future', 'x')", [LATEST_VERSION + 1]).unwrap();
+        let before = schema(&conn);
+
+        let err = migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap_err();
+        assert!(err.contains("mais recente"), "{}", err);
+        assert_eq!(schema(&conn), before);
+        assert_eq!(version(&conn), LATEST_VERSION + 1);
+    }
+
+    #[test]
+    fn edited_migration_is_refused() {
+        let conn = history(5);
+        conn.execute("UPDATE _migrations SET checksum = 'edited' WHERE version = 3", []).unwrap();
+        let err = migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap_err();
+        assert!(err.contains("v3"), "{}", err);
+        assert_eq!(version(&conn), 5);
+    }
+
+    #[test]
+    fn databases_from_before_checksums_are_adopted() {
+        let conn = history(6);
+        // The bookkeeping table as versions up to 11 created it
+        conn.execute_batch("
+            CREATE TABLE legacy AS SELECT version, applied_at FROM _migrations;
+            DROP TABLE _migrations;
+            CREATE TABLE _migrations (version INTEGER PRIMARY KEY, applied_at TEXT DEFAULT (datetime('now')));
+            INSERT INTO _migrations SELECT * FROM legacy;
+            DROP TABLE legacy;
+        ").unwrap();
+
+        migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap();
+        assert_eq!(version(&conn), LATEST_VERSION);
+        let recorded = query(&conn, "SELECT checksum FROM _migrations ORDER BY version");
+        let shipped: Vec<_> = MIGRATIONS.iter().map(Migration::checksum).collect();
+        assert_eq!(recorded, shipped);
+    }
+
+    #[test]
+    fn failed_step_rolls_back_to_the_previous_version() {
+        let conn = history(10);
+        // A stray column makes v11's ALTER on vitals fail after supplements was altered
+        conn.execute_batch("ALTER TABLE vitals ADD COLUMN deleted_at TEXT;").unwrap();
+
+        let err = migrate_to(&conn, LATEST_VERSION, ZONE, applied()).unwrap_err();
+        assert!(err.contains("v11"), "{}", err);
+        assert_eq!(version(&conn), 10);
+        assert!(!has_column(&conn, "supplements", "deleted_at").unwrap());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sqlite_master WHERE name = 'audit_log'"), 0);
+    }
+
+    #[test]
+    fn integrity_check_reports_dangling_references() {
+        let conn = history(LATEST_VERSION);
+        check_integrity(&conn).unwrap();
+        conn.execute_batch("
+            PRAGMA foreign_keys=OFF;
+            INSERT INTO ingest_audit (received_at, client_id, method, path, status) VALUES ('2025-03-06T10:00:00Z', 99, 'POST', '/v1/vitals', 401);
+        ").unwrap();
+        let err = check_integrity(&conn).unwrap_err();
+        assert!(err.contains("ingest_audit"), "{}", err);
+    }
+
+    #[test]
+    fn out_of_range_targets_are_rejected() {
+        let conn = open();
+        assert!(migrate_to(&conn, LATEST_VERSION + 1, ZONE, applied()).is_err());
+        assert!(migrate_to(&conn, -1, ZONE, applied()).is_err());
+    }
+}
@@ -1,48 +1,251 @@
 use rusqlite::{Connection, Result as SqlResult};
 use std::path::Path;
-use std::sync::Mutex;
-use crate::commands::health::{SupplementEntry, VitalEntry, HealthTimelineEntry};
+use std::time::Duration;
+use crate::commands::activity::{BreakEntry, PostureSample, PresenceSession};
+use crate::commands::gemini::ClinicalResult;
+use crate::commands::presence::DeferredMessage;
+use crate::commands::records::AuditEntry;
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::ingest::{IngestAuditEntry, IngestClient};
+use crate::commands::vitamin_d::SunExposureEntry;
+use crate::services::eye_care::BlinkPoint;
+use crate::services::fhir::{LabRecord, ScheduleRecord};
+use crate::services::health_report::VitalSummary;
+use crate::services::tabular::MemoryEntry;
+use crate::services::time;
+use crate::services::timeline::{DayTally, TimelineEvent, TimelineQuery};
+use crate::services::typing::{Baseline, BaselineTable, WpmPoint};
+use crate::services::vitamin_d::UvHour;
 
-pub struct DbState(pub Mutex<Database>);
+mod migrations;
+mod pool;
+pub use pool::{DbState, READ_CONNECTIONS};
 
 pub struct Database {
     conn: Connection,
 }
 
-const _CURRENT_SCHEMA_VERSION: i64 = 1;
+/// Forecast cache key precision (~1 km) so small GPS jitter reuses the same rows
+fn round_coord(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// SQL for one stored timeline kind: (key, timestamp, event_type, payload) rows.
+/// ?3 is a JSON object of local date → UTC midnight for date-only columns; ?4 is a JSON
+/// array of vital types or NULL for the default set.
+fn timeline_branch(event_type: &str) -> Option<&'static str> {
+    Some(match event_type {
+        "supplement" => "SELECT 'supplements:' || printf('%012d', id) AS key, taken_at AS timestamp,
+                'supplement' AS event_type,
+                json_object('id', id, 'name', name, 'dosage', dosage, 'category', category, 'notes', notes) AS payload
+            FROM supplements WHERE deleted_at IS NULL",
+        "vital" => "SELECT 'vitals:' || printf('%012d', id) AS key, recorded_at AS timestamp, 'vital' AS event_type,
+                json_object('id', id, 'vital_type', vital_type, 'value', value, 'unit', unit, 'source', source) AS payload
+            FROM vitals WHERE deleted_at IS NULL
+              AND (vital_type IN (SELECT value FROM json_each(?4))
+                   -- by default per-minute series are summarized as episodes, and imported
+                   -- per-second heart rate and per-minute steps would drown everything else
+                   OR (?4 IS NULL AND vital_type NOT IN ('wpm', 'blink_rate')
+                       AND NOT (source = 'wearable' AND vital_type IN ('heart_rate', 'steps'))))",
+        "lab_result" => "SELECT 'lab_results:' || printf('%012d', id) AS key,
+                (SELECT value FROM json_each(?3) WHERE key = test_date) AS timestamp, 'lab_result' AS event_type,
+                json_object('id', id, 'marker', marker, 'value', value, 'unit', unit,
+                            'reference_range', reference_range, 'status', status,
+                            'lab_name', lab_name, 'test_date', test_date) AS payload
+            FROM lab_results WHERE deleted_at IS NULL AND test_date IS NOT NULL",
+        "exam" => "SELECT 'health_schedule:' || printf('%012d', id) AS key,
+                (SELECT value FROM json_each(?3) WHERE key = scheduled_date) AS timestamp, 'exam' AS event_type,
+                json_object('id', id, 'exam_type', exam_type, 'reason', reason,
+                            'scheduled_date', scheduled_date, 'triggered_by', triggered_by,
+                            'completed', json(CASE WHEN completed THEN 'true' ELSE 'false' END)) AS payload
+            FROM health_schedule WHERE deleted_at IS NULL",
+        "voice" => "SELECT 'agent_memory:' || printf('%012d', id) AS key, timestamp, 'voice' AS event_type,
+                json_object('transcript', value) AS payload
+            FROM agent_memory WHERE key = 'voice_input'",
+        "agent_message" => "SELECT 'agent_memory:' || printf('%012d', id) AS key, timestamp, 'agent_message' AS event_type,
+                json_object('text', value, 'category', category, 'deferred', json('false'), 'delivered_at', timestamp) AS payload
+            FROM agent_memory WHERE key = 'agent_message'
+            UNION ALL
+            SELECT 'deferred_messages:' || printf('%012d', id), created_at, 'agent_message',
+                json_object('text', text, 'category', category, 'priority', priority,
+                            'deferred', json('true'), 'delivered_at', delivered_at)
+            FROM deferred_messages",
+        _ => return None, // derived kinds are not stored
+    })
+}
+
+/// SQL for one stored kind in `get_timeline_tallies`: (at, day, event_type, vital_type,
+/// unit, value) rows with either the instant or, for date-only columns, the local date.
+/// ?4 is a JSON array of vital types or NULL for all of them; ?5 and ?6 are the local
+/// dates of the range.
+fn tally_branch(event_type: &str) -> Option<&'static str> {
+    Some(match event_type {
+        "supplement" => "SELECT taken_at AS at, NULL AS day, 'supplement' AS event_type,
+                NULL AS vital_type, NULL AS unit, NULL AS value
+            FROM supplements WHERE deleted_at IS NULL AND taken_at BETWEEN ?1 AND ?2",
+        "vital" => "SELECT recorded_at AS at, NULL AS day, 'vital' AS event_type, vital_type, unit, value
+            FROM vitals WHERE deleted_at IS NULL AND recorded_at BETWEEN ?1 AND ?2
+              AND (?4 IS NULL OR vital_type IN (SELECT value FROM json_each(?4)))",
+        "lab_result" => "SELECT NULL AS at, test_date AS day, 'lab_result' AS event_type, NULL AS vital_type, NULL AS unit, NULL AS value
+            FROM lab_results WHERE deleted_at IS NULL AND test_date BETWEEN ?5 AND ?6",
+        "exam" => "SELECT NULL AS at, scheduled_date AS day, 'exam' AS event_type, NULL AS vital_type, NULL AS unit, NULL AS value
+            FROM health_schedule WHERE deleted_at IS NULL AND scheduled_date BETWEEN ?5 AND ?6",
+        "voice" => "SELECT timestamp AS at, NULL AS day, 'voice' AS event_type, NULL AS vital_type, NULL AS unit, NULL AS value
+            FROM agent_memory WHERE key = 'voice_input' AND timestamp BETWEEN ?1 AND ?2",
+        "agent_message" => "SELECT timestamp AS at, NULL AS day, 'agent_message' AS event_type, NULL AS vital_type, NULL AS unit, NULL AS value
+            FROM agent_memory WHERE key = 'agent_message' AND timestamp BETWEEN ?1 AND ?2
+            UNION ALL
+            SELECT created_at, NULL, 'agent_message', NULL, NULL, NULL
+            FROM deferred_messages WHERE created_at BETWEEN ?1 AND ?2",
+        _ => return None,
+    })
+}
+
+/// `periods`: the offsets in effect over a range (JSON from `offset_periods_json` bound to ?3)
+const PERIODS_CTE: &str = "periods AS (
+    SELECT json_extract(value, '$[0]') AS start, json_extract(value, '$[1]') AS until,
+           json_extract(value, '$[2]') || ' minutes' AS shift
+    FROM json_each(?3))";
+
+/// `time::offset_periods` over canonical [from, to] as JSON for `PERIODS_CTE`
+fn offset_periods_json(from: &str, to: &str, tz: chrono_tz::Tz) -> String {
+    let periods = time::parse_instant(from, tz)
+        .zip(time::parse_instant(to, tz))
+        .map(|(from, to)| time::offset_periods(from, to, tz))
+        .unwrap_or_default();
+    serde_json::json!(periods).to_string()
+}
+
+/// Local date of a canonical instant, "" when unparseable
+fn local_date(instant: &str, tz: chrono_tz::Tz) -> String {
+    time::parse_instant(instant, tz)
+        .map(|at| at.with_timezone(&tz).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// How long a connection waits on a lock held elsewhere (checkpoints, backups)
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema version `run_migrations` brings a database to
+pub const CURRENT_SCHEMA_VERSION: i64 = migrations::LATEST_VERSION;
+
+/// Database file inside the app data directory
+pub const DB_FILE_NAME: &str = "holoself.db";
+
+/// Health record tables that support edits, soft delete and the audit trail
+pub const RECORD_TABLES: [&str; 4] = ["supplements", "vitals", "lab_results", "health_schedule"];
+
+/// (id, exam_type, reason, scheduled_date, completed) of a pending exam
+pub type UpcomingExam = (i64, String, String, String, bool);
+
+/// Table names are interpolated into SQL, so only the known ones get through
+fn record_table(table: &str) -> SqlResult<&str> {
+    RECORD_TABLES.iter().copied()
+        .find(|t| *t == table)
+        .ok_or_else(|| rusqlite::Error::InvalidParameterName(table.to_string()))
+}
+
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value as Sql;
+    match value {
+        serde_json::Value::Null => Sql::Null,
+        serde_json::Value::Bool(b) => Sql::Integer(*b as i64),
+        serde_json::Value::Number(n) => n.as_i64().map(Sql::Integer)
+            .unwrap_or_else(|| Sql::Real(n.as_f64().unwrap_or_default())),
+        serde_json::Value::String(s) => Sql::Text(s.clone()),
+        other => Sql::Text(other.to_string()),
+    }
+}
+
+/// `agent_memory` rows older than this are deleted at startup
+pub const AGENT_MEMORY_RETENTION_DAYS: i64 = 30;
+
+/// Copy all of `src` into `dst` with the SQLite online backup API in a single step,
+/// so the copy is one consistent read. Busy/locked steps are retried for a few seconds.
+fn copy_database(src: &Connection, dst: &mut Connection) -> SqlResult<()> {
+    let backup = rusqlite::backup::Backup::new(src, dst)?;
+    for _ in 0..50 {
+        match backup.step(-1)? {
+            rusqlite::backup::StepResult::Done => return Ok(()),
+            _ => std::thread::sleep(std::time::Duration::from_millis(100)),
+        }
+    }
+    Err(rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+        Some("database stayed busy during backup".to_string()),
+    ))
+}
 
 impl Database {
     pub fn new(path: &Path) -> SqlResult<Self> {
         let conn = Connection::open(path)?;
         conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         Ok(Self { conn })
     }
 
-    pub fn run_migrations(&self) -> SqlResult<()> {
-        // Migration versioning table
-        self.conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS _migrations (
-                version INTEGER PRIMARY KEY,
-                applied_at TEXT DEFAULT (datetime('now'))
-            );"
+    /// Private database in memory, for tests and simulations; gone when dropped
+    pub fn open_in_memory() -> SqlResult<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+        Ok(Self { conn })
+    }
+
+    /// Read-only connection for the pool; through WAL it sees every committed write
+    pub fn open_reader(path: &Path) -> SqlResult<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(Self { conn })
+    }
+
+    /// Consistent copy of the database file at `source` into a new file at `dest`.
+    /// Reads through its own connection, so with WAL the app keeps writing meanwhile.
+    pub fn snapshot(source: &Path, dest: &Path) -> SqlResult<()> {
+        let src = Connection::open_with_flags(source, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut dst = Connection::open(dest)?;
+        copy_database(&src, &mut dst)?;
+        // Self-contained file: no -wal/-shm companions next to the copy
+        dst.query_row("PRAGMA journal_mode=DELETE", [], |_| Ok(()))
+    }
 
-        let current_version: i64 = self.conn.query_row(
-            "SELECT COALESCE(MAX(version), 0) FROM _migrations",
+    /// (schema version, `PRAGMA integrity_check` result) of a database file
+    pub fn inspect(path: &Path) -> SqlResult<(i64, String)> {
+        let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        let has_migrations: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_migrations')",
             [],
             |row| row.get(0),
         )?;
+        let version = if has_migrations {
+            conn.query_row("SELECT COALESCE(MAX(version), 0) FROM _migrations", [], |row| row.get(0))?
+        } else {
+            0
+        };
+        Ok((version, integrity))
+    }
 
-        if current_version < 1 {
-            self.apply_v1()?;
-        }
+    /// Replace the whole content of this database with the file at `source`.
+    /// Run `run_migrations` afterwards to bring an older copy up to date.
+    pub fn restore_from(&mut self, source: &Path) -> SqlResult<()> {
+        let src = Connection::open_with_flags(source, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        copy_database(&src, &mut self.conn)
+    }
 
-        // Future: if current_version < 2 { self.apply_v2()?; }
+    /// Bring the schema to `CURRENT_SCHEMA_VERSION` and prune agent memory older than the retention from `now`.
+    /// `local_tz` is the user's zone, needed to read legacy timestamps without an offset.
+    pub fn run_migrations(&self, local_tz: chrono_tz::Tz, now: chrono::DateTime<chrono::Utc>) -> Result<(), String> {
+        migrations::migrate_to(&self.conn, CURRENT_SCHEMA_VERSION, local_tz, now)?;
 
-        // Cleanup old agent_memory entries (>30 days)
+        // Cleanup old agent_memory entries
+        let cutoff = time::format_utc(now - chrono::Duration::days(AGENT_MEMORY_RETENTION_DAYS));
         if let Err(e) = self.conn.execute(
-            "DELETE FROM agent_memory WHERE timestamp < datetime('now', '-30 days')",
-            [],
+            "DELETE FROM agent_memory WHERE timestamp < ?1",
+            [&cutoff],
         ) {
             log::warn!("Failed to cleanup old agent_memory: {}", e);
         }
@@ -50,78 +253,9 @@ impl Database {
         Ok(())
     }
 
-    fn apply_v1(&self) -> SqlResult<()> {
-        self.conn.execute_batch(
-            "
-            -- Supplement intake log
-            CREATE TABLE IF NOT EXISTS supplements (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                dosage TEXT NOT NULL,
-                taken_at TEXT NOT NULL,
-                category TEXT NOT NULL,
-                notes TEXT,
-                created_at TEXT DEFAULT (datetime('now'))
-            );
-
-            -- Vital signs / biometrics
-            CREATE TABLE IF NOT EXISTS vitals (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vital_type TEXT NOT NULL,
-                value REAL NOT NULL,
-                unit TEXT NOT NULL,
-                recorded_at TEXT NOT NULL,
-                source TEXT NOT NULL DEFAULT 'manual',
-                created_at TEXT DEFAULT (datetime('now'))
-            );
-
-            -- Clinical lab results (from Gemini OCR)
-            CREATE TABLE IF NOT EXISTS lab_results (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                marker TEXT NOT NULL,
-                value REAL NOT NULL,
-                unit TEXT NOT NULL,
-                reference_range TEXT,
-                status TEXT NOT NULL,
-                lab_name TEXT,
-                test_date TEXT,
-                pdf_source TEXT,
-                created_at TEXT DEFAULT (datetime('now'))
-            );
-
-            -- Health scheduler (predictive exams)
-            CREATE TABLE IF NOT EXISTS health_schedule (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                exam_type TEXT NOT NULL,
-                reason TEXT NOT NULL,
-                scheduled_date TEXT NOT NULL,
-                triggered_by TEXT,
-                completed INTEGER DEFAULT 0,
-                created_at TEXT DEFAULT (datetime('now'))
-            );
-
-            -- Agent memory (voice inputs, context, RAG embeddings)
-            CREATE TABLE IF NOT EXISTS agent_memory (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                key TEXT NOT NULL,
-                value TEXT NOT NULL,
-                timestamp TEXT DEFAULT (datetime('now')),
-                category TEXT,
-                embedding BLOB
-            );
-
-            -- Indexes
-            CREATE INDEX IF NOT EXISTS idx_supplements_taken_at ON supplements(taken_at);
-            CREATE INDEX IF NOT EXISTS idx_vitals_recorded_at ON vitals(recorded_at);
-            CREATE INDEX IF NOT EXISTS idx_lab_results_marker ON lab_results(marker);
-            CREATE INDEX IF NOT EXISTS idx_health_schedule_date ON health_schedule(scheduled_date);
-            CREATE INDEX IF NOT EXISTS idx_agent_memory_key ON agent_memory(key, timestamp);
-
-            -- Record migration
-            INSERT INTO _migrations (version) VALUES (1);
-            "
-        )?;
-        Ok(())
+    /// `PRAGMA integrity_check` plus `foreign_key_check`; Err describes what is wrong
+    pub fn check_integrity(&self) -> Result<(), String> {
+        migrations::check_integrity(&self.conn)
     }
 
     pub fn insert_supplement(&self, entry: &SupplementEntry) -> SqlResult<i64> {
@@ -134,7 +268,7 @@ impl Database {
 
     pub fn get_supplements(&self, from: &str, to: &str) -> SqlResult<Vec<SupplementEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, dosage, taken_at, category, notes FROM supplements WHERE taken_at BETWEEN ?1 AND ?2 ORDER BY taken_at DESC"
+            "SELECT id, name, dosage, taken_at, category, notes FROM supplements WHERE taken_at BETWEEN ?1 AND ?2 AND deleted_at IS NULL ORDER BY taken_at DESC"
         )?;
         let entries = stmt.query_map(rusqlite::params![from, to], |row| {
             Ok(SupplementEntry {
@@ -157,45 +291,163 @@ impl Database {
         Ok(self.conn.last_insert_rowid())
     }
 
-    pub fn check_supplement_taken(&self, name: &str, date: &str) -> SqlResult<bool> {
+    /// Insert a batch of imported wearable readings in one transaction.
+    /// Rows already stored for the same (type, timestamp, source) are skipped; returns how many were new.
+    pub fn insert_wearable_vitals(&mut self, entries: &[VitalEntry]) -> SqlResult<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO vitals (vital_type, value, unit, recorded_at, source) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for entry in entries {
+                inserted += stmt.execute(rusqlite::params![entry.vital_type, entry.value, entry.unit, entry.recorded_at, entry.source])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Whether `name` was logged within [day_start, day_end) — the user's local day
+    /// as canonical UTC bounds (`time::local_day_bounds`)
+    pub fn check_supplement_taken(&self, name: &str, day_start: &str, day_end: &str) -> SqlResult<bool> {
         let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM supplements WHERE name = ?1 AND DATE(taken_at) = DATE(?2)",
-            rusqlite::params![name, date],
+            "SELECT COUNT(*) FROM supplements WHERE name = ?1 AND taken_at >= ?2 AND taken_at < ?3 AND deleted_at IS NULL",
+            rusqlite::params![name, day_start, day_end],
             |row| row.get(0),
         )?;
         Ok(count > 0)
     }
 
-    /// Unified health timeline using UNION for single-query performance
-    pub fn get_health_timeline(&self, from: &str, to: &str) -> SqlResult<Vec<HealthTimelineEntry>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT timestamp, event_type, label, value FROM (
-                SELECT taken_at AS timestamp, 'supplement' AS event_type,
-                       name || ' (' || dosage || ')' AS label, NULL AS value
-                FROM supplements WHERE taken_at BETWEEN ?1 AND ?2
-                UNION ALL
-                SELECT recorded_at AS timestamp, 'vital' AS event_type,
-                       vital_type || ': ' || CAST(value AS TEXT) || ' ' || unit AS label, value
-                FROM vitals WHERE recorded_at BETWEEN ?1 AND ?2
-            ) ORDER BY timestamp DESC"
+    /// Local date → UTC midnight (as JSON) for every date-only lab and exam date, so
+    /// they sort at the start of their day in the offset `tz` had then
+    fn local_midnights(&self, types: &[&str], tz: chrono_tz::Tz) -> SqlResult<String> {
+        let mut midnights = serde_json::Map::new();
+        if types.iter().any(|t| matches!(*t, "lab_result" | "exam")) {
+            let mut stmt = self.conn.prepare(
+                "SELECT test_date FROM lab_results WHERE deleted_at IS NULL AND test_date IS NOT NULL
+                 UNION SELECT scheduled_date FROM health_schedule WHERE deleted_at IS NULL"
+            )?;
+            let dates = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            for date in dates {
+                let date = date?;
+                if let Ok(day) = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                    midnights.insert(date, time::local_day_bounds(day, tz).0.into());
+                }
+            }
+        }
+        Ok(serde_json::Value::Object(midnights).to_string())
+    }
+
+    /// One page of stored timeline events, newest first, from a single UNION over the
+    /// requested kinds. Returns up to `limit + 1` rows so the caller can tell whether
+    /// another page follows.
+    pub fn get_health_timeline(&self, query: &TimelineQuery) -> SqlResult<Vec<TimelineEvent>> {
+        let branches: Vec<&str> = query.types.iter().filter_map(|t| timeline_branch(t)).collect();
+        if branches.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT key, timestamp, event_type, payload FROM ({})
+             WHERE timestamp BETWEEN ?1 AND ?2
+               AND (?5 IS NULL OR timestamp < ?5 OR (timestamp = ?5 AND key < ?6))
+             ORDER BY timestamp DESC, key DESC LIMIT ?7",
+            branches.join(" UNION ALL ")
+        );
+        let midnights = self.local_midnights(&query.types, query.tz)?;
+        let vital_types = query.vital_types.as_ref().map(|types| serde_json::json!(types).to_string());
+        let (cursor_ts, cursor_key) = match &query.cursor {
+            Some(c) => (Some(c.timestamp.as_str()), Some(c.key.as_str())),
+            None => (None, None),
+        };
+        let mut stmt = self.conn.prepare(&sql)?;
+        let events = stmt.query_map(
+            rusqlite::params![query.from, query.to, midnights, vital_types, cursor_ts, cursor_key, query.limit + 1],
+            |row| {
+                let payload: String = row.get(3)?;
+                Ok(TimelineEvent {
+                    key: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    event_type: row.get(2)?,
+                    payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                })
+            },
         )?;
-        let entries = stmt.query_map(rusqlite::params![from, to], |row| {
-            Ok(HealthTimelineEntry {
-                timestamp: row.get(0)?,
+        events.collect()
+    }
+
+    /// Stored events per local day and kind for bucketing; vitals carry count, sum,
+    /// min and max per type and unit. Each instant is dated with the offset `tz` had then.
+    pub fn get_timeline_tallies(
+        &self,
+        from: &str,
+        to: &str,
+        types: &[&str],
+        vital_types: Option<&[String]>,
+        tz: chrono_tz::Tz,
+    ) -> SqlResult<Vec<DayTally>> {
+        let branches: Vec<&str> = types.iter().filter_map(|t| tally_branch(t)).collect();
+        if branches.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "WITH {PERIODS_CTE}
+             SELECT day, event_type, vital_type, unit, COUNT(*), SUM(value), MIN(value), MAX(value)
+             FROM (
+                 SELECT COALESCE(b.day, (SELECT date(b.at, p.shift) FROM periods p WHERE b.at >= p.start AND b.at < p.until)) AS day,
+                        b.event_type, b.vital_type, b.unit, b.value
+                 FROM ({}) b
+             ) WHERE day BETWEEN ?5 AND ?6
+             GROUP BY day, event_type, vital_type, unit ORDER BY day",
+            branches.join(" UNION ALL ")
+        );
+        let periods = offset_periods_json(from, to, tz);
+        let (first_day, last_day) = (local_date(from, tz), local_date(to, tz));
+        let vital_types = vital_types.map(|types| serde_json::json!(types).to_string());
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params![from, to, periods, vital_types, first_day, last_day], |row| {
+            let day: String = row.get(0)?;
+            let vital_type: Option<String> = row.get(2)?;
+            let unit: Option<String> = row.get(3)?;
+            // Date-only columns may hold anything an import let through
+            let Ok(day) = chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d") else { return Ok(None) };
+            Ok(Some(DayTally {
+                day,
                 event_type: row.get(1)?,
-                label: row.get(2)?,
-                value: row.get(3)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(entries)
+                vital: vital_type.zip(unit),
+                count: row.get(4)?,
+                sum: row.get::<_, Option<f64>>(5)?.unwrap_or(0.0),
+                min: row.get::<_, Option<f64>>(6)?.unwrap_or(0.0),
+                max: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+            }))
+        })?;
+        rows.filter_map(Result::transpose).collect()
+    }
+
+    /// Keep a delivered agent message for the timeline (in `agent_memory`, so it shares
+    /// its retention). The same text again within the hour is skipped.
+    pub fn record_agent_message(&self, text: &str, category: &str, at: &str, repeat_after: &str) -> SqlResult<bool> {
+        let repeated: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM agent_memory WHERE key = 'agent_message' AND value = ?1 AND timestamp >= ?2)",
+            rusqlite::params![text, repeat_after],
+            |row| row.get(0),
+        )?;
+        if repeated {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO agent_memory (key, value, timestamp, category) VALUES ('agent_message', ?1, ?2, ?3)",
+            rusqlite::params![text, at, category],
+        )?;
+        Ok(true)
     }
 
-    /// Get distinct supplement names taken in last 90 days
-    pub fn get_active_supplements(&self) -> SqlResult<Vec<String>> {
+    /// Get distinct supplement names taken since `since` (canonical UTC)
+    pub fn get_active_supplements(&self, since: &str) -> SqlResult<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT DISTINCT name FROM supplements WHERE taken_at >= DATE('now', '-90 days') ORDER BY name"
+            "SELECT DISTINCT name FROM supplements WHERE taken_at >= ?1 AND deleted_at IS NULL ORDER BY name"
         )?;
-        let names = stmt.query_map([], |row| row.get(0))?
+        let names = stmt.query_map([since], |row| row.get(0))?
             .collect::<Result<Vec<String>, _>>()?;
         Ok(names)
     }
@@ -203,7 +455,7 @@ impl Database {
     /// Get latest lab result date for each marker
     pub fn get_latest_labs(&self) -> SqlResult<Vec<(String, String)>> {
         let mut stmt = self.conn.prepare(
-            "SELECT marker, MAX(test_date) FROM lab_results GROUP BY marker"
+            "SELECT marker, MAX(test_date) FROM lab_results WHERE deleted_at IS NULL GROUP BY marker"
         )?;
         let labs = stmt.query_map([], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
@@ -211,6 +463,51 @@ impl Database {
         Ok(labs)
     }
 
+    /// Insert one extracted lab marker
+    pub fn insert_lab_result(
+        &self,
+        result: &ClinicalResult,
+        lab_name: Option<&str>,
+        test_date: &str,
+        pdf_source: Option<&str>,
+    ) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO lab_results (marker, value, unit, reference_range, status, lab_name, test_date, pdf_source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                result.marker, result.value, result.unit, result.reference_range,
+                result.status, lab_name, test_date, pdf_source
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All results for markers matching any of the given (lowercase) name fragments,
+    /// oldest first: (value, unit, test_date)
+    pub fn get_lab_history(&self, marker_patterns: &[&str]) -> SqlResult<Vec<(f64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT marker, value, unit, test_date FROM lab_results
+             WHERE test_date IS NOT NULL AND deleted_at IS NULL ORDER BY test_date ASC"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|(marker, ..)| {
+            let lower = marker.to_lowercase();
+            marker_patterns.iter().any(|p| lower.contains(p))
+        })
+        .map(|(_, value, unit, date)| (value, unit, date))
+        .collect();
+        Ok(rows)
+    }
+
     /// Insert a scheduled exam
     pub fn insert_scheduled_exam(&self, exam: &crate::services::scheduler::ScheduledExam) -> SqlResult<i64> {
         self.conn.execute(
@@ -220,6 +517,959 @@ impl Database {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Run `f` inside one transaction: every write it makes lands, or none does
+    pub fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+        F: FnOnce(&Self) -> Result<T, E>,
+    {
+        self.conn.execute_batch("BEGIN")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = self.conn.execute_batch("ROLLBACK") {
+                    log::warn!("Rollback failed: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Like `transaction`, but always rolled back: a dry run sees exactly what a real
+    /// run would do (duplicates within the batch included) without keeping any of it
+    pub fn rehearse<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+        F: FnOnce(&Self) -> Result<T, E>,
+    {
+        self.conn.execute_batch("BEGIN")?;
+        let result = f(self);
+        self.conn.execute_batch("ROLLBACK")?;
+        result
+    }
+
+    /// Vitals recorded within [from, to], oldest first
+    pub fn get_vitals(&self, from: &str, to: &str) -> SqlResult<Vec<VitalEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, vital_type, value, unit, recorded_at, source FROM vitals
+             WHERE recorded_at BETWEEN ?1 AND ?2 AND deleted_at IS NULL ORDER BY recorded_at ASC, id ASC"
+        )?;
+        let entries = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok(VitalEntry {
+                id: Some(row.get(0)?),
+                vital_type: row.get(1)?,
+                value: row.get(2)?,
+                unit: row.get(3)?,
+                recorded_at: row.get(4)?,
+                source: row.get(5)?,
+            })
+        })?;
+        entries.collect()
+    }
+
+    // Import existence checks include soft-deleted rows, so re-importing an old file
+    // doesn't bring back what the user deleted
+
+    /// Same key as `idx_vitals_wearable_unique`: a reading of one type, time and source
+    /// is the same reading whatever its value
+    pub fn vital_exists(&self, entry: &VitalEntry) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM vitals WHERE vital_type = ?1 AND recorded_at = ?2 AND source = ?3)",
+            rusqlite::params![entry.vital_type, entry.recorded_at, entry.source],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn supplement_exists(&self, entry: &SupplementEntry) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM supplements WHERE name = ?1 AND taken_at = ?2)",
+            rusqlite::params![entry.name, entry.taken_at],
+            |row| row.get(0),
+        )
+    }
+
+    /// Every stored lab marker, oldest test first
+    pub fn get_lab_results(&self) -> SqlResult<Vec<LabRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, marker, value, unit, reference_range, status, lab_name, test_date, pdf_source
+             FROM lab_results WHERE deleted_at IS NULL ORDER BY test_date ASC, id ASC"
+        )?;
+        let labs = stmt.query_map([], |row| {
+            Ok(LabRecord {
+                id: Some(row.get(0)?),
+                marker: row.get(1)?,
+                value: row.get(2)?,
+                unit: row.get(3)?,
+                reference_range: row.get(4)?,
+                status: row.get(5)?,
+                lab_name: row.get(6)?,
+                test_date: row.get(7)?,
+                pdf_source: row.get(8)?,
+            })
+        })?;
+        labs.collect()
+    }
+
+    pub fn insert_lab_record(&self, lab: &LabRecord) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO lab_results (marker, value, unit, reference_range, status, lab_name, test_date, pdf_source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                lab.marker, lab.value, lab.unit, lab.reference_range,
+                lab.status, lab.lab_name, lab.test_date, lab.pdf_source
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn lab_record_exists(&self, lab: &LabRecord) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM lab_results WHERE marker = ?1 AND value = ?2 AND test_date IS ?3)",
+            rusqlite::params![lab.marker, lab.value, lab.test_date],
+            |row| row.get(0),
+        )
+    }
+
+    /// Every scheduled exam, completed or not
+    pub fn get_health_schedule(&self) -> SqlResult<Vec<ScheduleRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, exam_type, reason, scheduled_date, triggered_by, completed
+             FROM health_schedule WHERE deleted_at IS NULL ORDER BY scheduled_date ASC, id ASC"
+        )?;
+        let exams = stmt.query_map([], |row| {
+            Ok(ScheduleRecord {
+                id: Some(row.get(0)?),
+                exam_type: row.get(1)?,
+                reason: row.get(2)?,
+                scheduled_date: row.get(3)?,
+                triggered_by: row.get(4)?,
+                completed: row.get(5)?,
+            })
+        })?;
+        exams.collect()
+    }
+
+    pub fn insert_schedule_record(&self, exam: &ScheduleRecord) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO health_schedule (exam_type, reason, scheduled_date, triggered_by, completed) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![exam.exam_type, exam.reason, exam.scheduled_date, exam.triggered_by, exam.completed],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn schedule_record_exists(&self, exam: &ScheduleRecord) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM health_schedule WHERE exam_type = ?1 AND scheduled_date = ?2)",
+            rusqlite::params![exam.exam_type, exam.scheduled_date],
+            |row| row.get(0),
+        )
+    }
+
+    /// One record as column → value JSON (soft-deleted rows included), for the audit trail
+    pub fn record_snapshot(&self, table: &str, id: i64) -> SqlResult<Option<serde_json::Value>> {
+        let table = record_table(table)?;
+        let mut stmt = self.conn.prepare(&format!("SELECT * FROM {table} WHERE id = ?1"))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([id])?;
+        let Some(row) = rows.next()? else { return Ok(None) };
+        let mut object = serde_json::Map::new();
+        for (index, column) in columns.into_iter().enumerate() {
+            let value = match row.get_ref(index)? {
+                rusqlite::types::ValueRef::Null => serde_json::Value::Null,
+                rusqlite::types::ValueRef::Integer(i) => i.into(),
+                rusqlite::types::ValueRef::Real(f) => f.into(),
+                rusqlite::types::ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+                rusqlite::types::ValueRef::Blob(_) => continue,
+            };
+            object.insert(column, value);
+        }
+        Ok(Some(serde_json::Value::Object(object)))
+    }
+
+    /// Write a snapshot from `record_snapshot` back over the row (undo of an edit).
+    /// Only columns the table still has are written; `id` and `created_at` are kept.
+    pub fn restore_snapshot(&self, table: &str, id: i64, snapshot: &serde_json::Value) -> SqlResult<bool> {
+        let table = record_table(table)?;
+        let columns: Vec<String> = {
+            let mut stmt = self.conn.prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?;
+            let names = stmt.query_map([], |row| row.get(0))?.collect::<SqlResult<Vec<String>>>()?;
+            names
+        };
+        let fields: Vec<(&String, rusqlite::types::Value)> = snapshot.as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(column, _)| columns.contains(column) && *column != "id" && *column != "created_at")
+            .map(|(column, value)| (column, json_to_sql(value)))
+            .collect();
+        if fields.is_empty() {
+            return Ok(false);
+        }
+        let assignments: Vec<String> = fields.iter().enumerate()
+            .map(|(index, (column, _))| format!("{} = ?{}", column, index + 2))
+            .collect();
+        let mut params: Vec<&dyn rusqlite::types::ToSql> = vec![&id];
+        params.extend(fields.iter().map(|(_, value)| value as &dyn rusqlite::types::ToSql));
+        let changed = self.conn.execute(
+            &format!("UPDATE {table} SET {} WHERE id = ?1", assignments.join(", ")),
+            params.as_slice(),
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Edits apply to live rows only; false when the row is missing or deleted
+    pub fn update_supplement(&self, id: i64, entry: &SupplementEntry) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE supplements SET name = ?2, dosage = ?3, taken_at = ?4, category = ?5, notes = ?6
+             WHERE id = ?1 AND deleted_at IS NULL",
+            rusqlite::params![id, entry.name, entry.dosage, entry.taken_at, entry.category, entry.notes],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn update_vital(&self, id: i64, entry: &VitalEntry) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE vitals SET vital_type = ?2, value = ?3, unit = ?4, recorded_at = ?5, source = ?6
+             WHERE id = ?1 AND deleted_at IS NULL",
+            rusqlite::params![id, entry.vital_type, entry.value, entry.unit, entry.recorded_at, entry.source],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn update_lab_record(&self, id: i64, lab: &LabRecord) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE lab_results SET marker = ?2, value = ?3, unit = ?4, reference_range = ?5, status = ?6,
+                    lab_name = ?7, test_date = ?8, pdf_source = ?9
+             WHERE id = ?1 AND deleted_at IS NULL",
+            rusqlite::params![
+                id, lab.marker, lab.value, lab.unit, lab.reference_range,
+                lab.status, lab.lab_name, lab.test_date, lab.pdf_source
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn update_schedule_record(&self, id: i64, exam: &ScheduleRecord) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE health_schedule SET exam_type = ?2, reason = ?3, scheduled_date = ?4, triggered_by = ?5, completed = ?6
+             WHERE id = ?1 AND deleted_at IS NULL",
+            rusqlite::params![id, exam.exam_type, exam.reason, exam.scheduled_date, exam.triggered_by, exam.completed],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Soft-delete (`Some(at)`) a live row or restore (`None`) a deleted one;
+    /// false when the row is missing or already in that state
+    pub fn set_deleted(&self, table: &str, id: i64, deleted_at: Option<&str>) -> SqlResult<bool> {
+        let table = record_table(table)?;
+        let sql = match deleted_at {
+            Some(_) => format!("UPDATE {table} SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NULL"),
+            None => format!("UPDATE {table} SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NOT NULL"),
+        };
+        Ok(self.conn.execute(&sql, rusqlite::params![id, deleted_at])? > 0)
+    }
+
+    pub fn insert_audit(&self, entry: &AuditEntry) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO audit_log (changed_at, record_type, record_id, action, source, before_json, after_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                entry.changed_at, entry.record_type, entry.record_id, entry.action, entry.source,
+                entry.before.as_ref().map(|v| v.to_string()),
+                entry.after.as_ref().map(|v| v.to_string()),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn audit_from_row(row: &rusqlite::Row) -> SqlResult<AuditEntry> {
+        let json = |index: usize| -> SqlResult<Option<serde_json::Value>> {
+            Ok(row.get::<_, Option<String>>(index)?.and_then(|text| serde_json::from_str(&text).ok()))
+        };
+        Ok(AuditEntry {
+            id: Some(row.get(0)?),
+            changed_at: row.get(1)?,
+            record_type: row.get(2)?,
+            record_id: row.get(3)?,
+            action: row.get(4)?,
+            source: row.get(5)?,
+            before: json(6)?,
+            after: json(7)?,
+            undone_at: row.get(8)?,
+        })
+    }
+
+    /// Newest first; optionally only one table or one record
+    pub fn get_audit_log(&self, record_type: Option<&str>, record_id: Option<i64>, limit: u32) -> SqlResult<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, changed_at, record_type, record_id, action, source, before_json, after_json, undone_at
+             FROM audit_log
+             WHERE (?1 IS NULL OR record_type = ?1) AND (?2 IS NULL OR record_id = ?2)
+             ORDER BY id DESC LIMIT ?3"
+        )?;
+        let entries = stmt.query_map(rusqlite::params![record_type, record_id, limit], Self::audit_from_row)?;
+        entries.collect()
+    }
+
+    /// The most recent single-record change made by the agent (or by voice through it)
+    /// that hasn't been undone yet
+    pub fn last_agent_change(&self) -> SqlResult<Option<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, changed_at, record_type, record_id, action, source, before_json, after_json, undone_at
+             FROM audit_log
+             WHERE source IN ('agent', 'voice') AND undone_at IS NULL AND record_id IS NOT NULL
+               AND action IN ('insert', 'update', 'delete', 'restore')
+             ORDER BY id DESC LIMIT 1"
+        )?;
+        let mut entries = stmt.query_map([], Self::audit_from_row)?;
+        entries.next().transpose()
+    }
+
+    /// Whether the record changed again after audit entry `audit_id`
+    pub fn changed_after(&self, record_type: &str, record_id: i64, audit_id: i64) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM audit_log WHERE record_type = ?1 AND record_id = ?2 AND id > ?3)",
+            rusqlite::params![record_type, record_id, audit_id],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn mark_audit_undone(&self, audit_id: i64, undone_at: &str) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE audit_log SET undone_at = ?2 WHERE id = ?1 AND undone_at IS NULL",
+            rusqlite::params![audit_id, undone_at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Agent memory entries within [from, to], oldest first (embeddings left out)
+    pub fn get_agent_memory(&self, from: &str, to: &str) -> SqlResult<Vec<MemoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, key, value, timestamp, category FROM agent_memory
+             WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp ASC, id ASC"
+        )?;
+        let entries = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok(MemoryEntry {
+                id: Some(row.get(0)?),
+                key: row.get(1)?,
+                value: row.get(2)?,
+                timestamp: row.get(3)?,
+                category: row.get(4)?,
+            })
+        })?;
+        entries.collect()
+    }
+
+    pub fn insert_agent_memory(&self, entry: &MemoryEntry) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO agent_memory (key, value, timestamp, category) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![entry.key, entry.value, entry.timestamp, entry.category],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn agent_memory_exists(&self, entry: &MemoryEntry) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM agent_memory WHERE key = ?1 AND timestamp = ?2 AND value = ?3)",
+            rusqlite::params![entry.key, entry.timestamp, entry.value],
+            |row| row.get(0),
+        )
+    }
+
+    /// Per-type aggregates over [from, to] for the health report. Steps and sleep are
+    /// summed per local day first (a night counts towards the morning it ends on), each
+    /// reading dated with the offset `tz` had then.
+    pub fn get_vital_summaries(&self, from: &str, to: &str, tz: chrono_tz::Tz) -> SqlResult<Vec<VitalSummary>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "WITH {PERIODS_CTE},
+            local AS (
+                SELECT vital_type, unit, value, recorded_at,
+                       (SELECT p.shift FROM periods p WHERE recorded_at >= p.start AND recorded_at < p.until) AS shift
+                FROM vitals
+                WHERE recorded_at BETWEEN ?1 AND ?2 AND deleted_at IS NULL AND vital_type IN ('steps', 'sleep')
+            ),
+            readings AS (
+                SELECT vital_type, unit, value, recorded_at FROM vitals
+                WHERE recorded_at BETWEEN ?1 AND ?2 AND deleted_at IS NULL AND vital_type NOT IN ('wpm', 'blink_rate', 'steps', 'sleep')
+                UNION ALL
+                SELECT vital_type, unit, SUM(value), MAX(recorded_at) FROM local
+                WHERE vital_type = 'steps'
+                GROUP BY date(recorded_at, shift), unit
+                UNION ALL
+                SELECT vital_type, unit, SUM(value), MAX(recorded_at) FROM local
+                WHERE vital_type = 'sleep'
+                GROUP BY date(recorded_at, shift, '+12 hours'), unit
+            )
+            SELECT vital_type, unit, COUNT(*), AVG(value), MIN(value), MAX(value), MAX(recorded_at),
+                   (SELECT latest.value FROM readings latest
+                    WHERE latest.vital_type = r.vital_type AND latest.unit = r.unit
+                    ORDER BY latest.recorded_at DESC LIMIT 1)
+            FROM readings r GROUP BY vital_type, unit ORDER BY vital_type, unit"
+        ))?;
+        let summaries = stmt.query_map(rusqlite::params![from, to, offset_periods_json(from, to, tz)], |row| {
+            let vital_type: String = row.get(0)?;
+            Ok(VitalSummary {
+                per_day: matches!(vital_type.as_str(), "steps" | "sleep"),
+                vital_type,
+                unit: row.get(1)?,
+                count: row.get(2)?,
+                mean: row.get(3)?,
+                min: row.get(4)?,
+                max: row.get(5)?,
+                latest_at: row.get(6)?,
+                latest: row.get(7)?,
+            })
+        })?;
+        summaries.collect()
+    }
+
+    /// Cache hourly UV forecast rows fetched at `now` (replaces any previous fetch for the same hour)
+    pub fn upsert_uv_forecast(&mut self, latitude: f64, longitude: f64, hours: &[UvHour], now: chrono::DateTime<chrono::Utc>) -> SqlResult<()> {
+        let (lat, lon) = (round_coord(latitude), round_coord(longitude));
+        let fetched_at = time::format_utc(now);
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO uv_forecast (latitude, longitude, hour, uv_index, cloud_cover, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            )?;
+            for h in hours {
+                stmt.execute(rusqlite::params![lat, lon, time::format_utc(h.time), h.uv_index, h.cloud_cover, fetched_at])?;
+            }
+        }
+
+        // Keep the cache small: drop hours older than a week
+        tx.execute(
+            "DELETE FROM uv_forecast WHERE hour < ?1",
+            [time::format_utc(now - chrono::Duration::days(7))],
+        )?;
+        tx.commit()
+    }
+
+    /// Cached forecast hours for a location from `from` (RFC 3339) onwards
+    pub fn get_uv_forecast(&self, latitude: f64, longitude: f64, from: &str) -> SqlResult<Vec<UvHour>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT hour, uv_index, cloud_cover FROM uv_forecast
+             WHERE latitude = ?1 AND longitude = ?2 AND hour >= ?3 ORDER BY hour ASC"
+        )?;
+        let hours = stmt.query_map(
+            rusqlite::params![round_coord(latitude), round_coord(longitude), from],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?)),
+        )?
+        .filter_map(|r| r.ok())
+        .filter_map(|(hour, uv_index, cloud_cover)| {
+            let time = chrono::DateTime::parse_from_rfc3339(&hour).ok()?.with_timezone(&chrono::Utc);
+            Some(UvHour { time, uv_index, cloud_cover })
+        })
+        .collect();
+        Ok(hours)
+    }
+
+    /// Cached UV index for the forecast hour containing `at`
+    pub fn get_uv_at(&self, latitude: f64, longitude: f64, at: chrono::DateTime<chrono::Utc>) -> SqlResult<Option<f64>> {
+        use chrono::Timelike;
+        let hour = at
+            .with_minute(0).and_then(|t| t.with_second(0)).and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(at);
+        let hour = time::format_utc(hour);
+        match self.conn.query_row(
+            "SELECT uv_index FROM uv_forecast WHERE latitude = ?1 AND longitude = ?2 AND hour = ?3",
+            rusqlite::params![round_coord(latitude), round_coord(longitude), hour],
+            |row| row.get(0),
+        ) {
+            Ok(uv) => Ok(Some(uv)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn insert_sun_exposure(&self, entry: &SunExposureEntry) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO sun_exposure (started_at, duration_minutes, body_area, spf, uv_index, estimated_iu)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                entry.started_at, entry.duration_minutes, entry.body_area,
+                entry.spf, entry.uv_index.unwrap_or(0.0), entry.estimated_iu.unwrap_or(0)
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Insert a new presence session, or close/update an existing one when `id` is set
+    pub fn upsert_presence_session(&self, session: &PresenceSession) -> SqlResult<i64> {
+        let source = session.source.as_deref().unwrap_or("webcam");
+        match session.id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE presence_sessions SET started_at = ?1, ended_at = ?2, source = ?3 WHERE id = ?4",
+                    rusqlite::params![session.started_at, session.ended_at, source, id],
+                )?;
+                Ok(id)
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO presence_sessions (started_at, ended_at, source) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![session.started_at, session.ended_at, source],
+                )?;
+                Ok(self.conn.last_insert_rowid())
+            }
+        }
+    }
+
+    /// Seconds of presence within [from, to). Sessions are clipped to the window; an open
+    /// session counts up to `now` only if it is the latest one (older open rows are
+    /// leftovers from a crash and are ignored).
+    pub fn presence_seconds(&self, from: &str, to: &str, now: &str) -> SqlResult<i64> {
+        let mut stmt = self.conn.prepare(
+            "SELECT started_at, ended_at FROM presence_sessions
+             WHERE started_at < ?2 AND (ended_at IS NULL OR ended_at > ?1)
+             ORDER BY started_at ASC"
+        )?;
+        let sessions = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let latest_start: Option<String> = self.conn.query_row(
+            "SELECT started_at FROM presence_sessions ORDER BY started_at DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        ).ok();
+
+        let parse = |s: &str| chrono::DateTime::parse_from_rfc3339(s).ok();
+        let (Some(window_start), Some(window_end)) = (parse(from), parse(to)) else {
+            return Ok(0);
+        };
+        let total = sessions.iter()
+            .filter_map(|(started_at, ended_at)| {
+                let end = match ended_at {
+                    Some(end) => end.as_str(),
+                    None if latest_start.as_deref() == Some(started_at.as_str()) => now,
+                    None => return None,
+                };
+                let start = parse(started_at)?.max(window_start);
+                let end = parse(end)?.min(window_end);
+                Some((end - start).num_seconds().max(0))
+            })
+            .sum();
+        Ok(total)
+    }
+
+    pub fn insert_break(&self, entry: &BreakEntry) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO breaks (started_at, ended_at, reason) VALUES (?1, ?2, ?3)",
+            rusqlite::params![entry.started_at, entry.ended_at, entry.reason.as_deref().unwrap_or("away")],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Breaks that ended within [from, to)
+    pub fn count_breaks(&self, from: &str, to: &str) -> SqlResult<u32> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM breaks WHERE ended_at >= ?1 AND ended_at < ?2",
+            rusqlite::params![from, to],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn insert_posture_samples(&mut self, samples: &[PostureSample]) -> SqlResult<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO posture_samples (recorded_at, score, head_x, head_y) VALUES (?1, ?2, ?3, ?4)"
+            )?;
+            for s in samples {
+                stmt.execute(rusqlite::params![s.recorded_at, s.score, s.head_x, s.head_y])?;
+            }
+        }
+        tx.commit()?;
+        Ok(samples.len())
+    }
+
+    /// Mean posture score within [from, to), None without samples
+    pub fn average_posture(&self, from: &str, to: &str) -> SqlResult<Option<f64>> {
+        self.conn.query_row(
+            "SELECT AVG(score) FROM posture_samples WHERE recorded_at >= ?1 AND recorded_at < ?2",
+            rusqlite::params![from, to],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_sun_exposures(&self, from: &str, to: &str) -> SqlResult<Vec<SunExposureEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, started_at, duration_minutes, body_area, spf, uv_index, estimated_iu
+             FROM sun_exposure WHERE started_at BETWEEN ?1 AND ?2 ORDER BY started_at DESC"
+        )?;
+        let entries = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok(SunExposureEntry {
+                id: Some(row.get(0)?),
+                started_at: row.get(1)?,
+                duration_minutes: row.get(2)?,
+                body_area: row.get(3)?,
+                spf: row.get(4)?,
+                uv_index: Some(row.get(5)?),
+                estimated_iu: Some(row.get(6)?),
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Store a per-minute WPM snapshot as a `wpm` vital with the baseline it was judged
+    /// against (None while calibrating), and fold it into the `learn` weekday/hour cell.
+    /// `learn` is None for fatigued minutes so they don't drag the baseline down.
+    /// Returns false if that minute was already stored. `now` stamps the baseline update.
+    pub fn insert_wpm_snapshot(
+        &mut self,
+        recorded_at: &str,
+        wpm: f64,
+        source: &str,
+        baseline_wpm: Option<f64>,
+        learn: Option<(u32, u32)>,
+        now: &str,
+    ) -> SqlResult<bool> {
+        let tx = self.conn.transaction()?;
+        let exists: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM vitals WHERE vital_type = 'wpm' AND recorded_at = ?1 AND source = ?2",
+            rusqlite::params![recorded_at, source],
+            |row| row.get(0),
+        )?;
+        if exists > 0 {
+            return Ok(false);
+        }
+
+        tx.execute(
+            "INSERT INTO vitals (vital_type, value, unit, recorded_at, source) VALUES ('wpm', ?1, 'wpm', ?2, ?3)",
+            rusqlite::params![wpm, recorded_at, source],
+        )?;
+        if let Some(baseline) = baseline_wpm {
+            tx.execute(
+                "INSERT INTO wpm_judgements (vital_id, baseline_wpm) VALUES (?1, ?2)",
+                rusqlite::params![tx.last_insert_rowid(), baseline],
+            )?;
+        }
+
+        if let Some((weekday, hour)) = learn {
+            let mut cell = match tx.query_row(
+                "SELECT samples, mean, m2 FROM wpm_baselines WHERE weekday = ?1 AND hour = ?2",
+                rusqlite::params![weekday, hour],
+                |row| Ok(Baseline { samples: row.get(0)?, mean: row.get(1)?, m2: row.get(2)? }),
+            ) {
+                Ok(cell) => cell,
+                Err(rusqlite::Error::QueryReturnedNoRows) => Baseline::default(),
+                Err(e) => return Err(e),
+            };
+            cell.add(wpm);
+            tx.execute(
+                "INSERT OR REPLACE INTO wpm_baselines (weekday, hour, samples, mean, m2, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![weekday, hour, cell.samples, cell.mean, cell.m2, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn get_wpm_baselines(&self) -> SqlResult<BaselineTable> {
+        let mut stmt = self.conn.prepare("SELECT weekday, hour, samples, mean, m2 FROM wpm_baselines")?;
+        let cells = stmt.query_map([], |row| {
+            Ok((
+                (row.get::<_, u32>(0)?, row.get::<_, u32>(1)?),
+                Baseline { samples: row.get(2)?, mean: row.get(3)?, m2: row.get(4)? },
+            ))
+        })?.collect::<Result<_, _>>()?;
+        Ok(BaselineTable { cells })
+    }
+
+    /// WPM snapshots within [from, to) with their judged baselines, oldest first
+    pub fn get_wpm_points(&self, from: &str, to: &str) -> SqlResult<Vec<WpmPoint>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT v.recorded_at, v.value, j.baseline_wpm FROM vitals v
+             LEFT JOIN wpm_judgements j ON j.vital_id = v.id
+             WHERE v.vital_type = 'wpm' AND v.recorded_at >= ?1 AND v.recorded_at < ?2 AND v.deleted_at IS NULL
+             ORDER BY v.recorded_at ASC"
+        )?;
+        let points = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, Option<f64>>(2)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter_map(|(at, wpm, baseline_wpm)| {
+            let time = chrono::DateTime::parse_from_rfc3339(&at).ok()?.with_timezone(&chrono::Utc);
+            Some(WpmPoint { time, wpm, baseline_wpm })
+        })
+        .collect();
+        Ok(points)
+    }
+
+    /// Store one per-minute blink rate; false if that minute is already stored for the source
+    pub fn insert_blink_rate(&self, recorded_at: &str, rate: f64, source: &str) -> SqlResult<bool> {
+        let exists: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM vitals WHERE vital_type = 'blink_rate' AND recorded_at = ?1 AND source = ?2",
+            rusqlite::params![recorded_at, source],
+            |row| row.get(0),
+        )?;
+        if exists > 0 {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO vitals (vital_type, value, unit, recorded_at, source) VALUES ('blink_rate', ?1, 'blinks/min', ?2, ?3)",
+            rusqlite::params![rate, recorded_at, source],
+        )?;
+        Ok(true)
+    }
+
+    pub fn get_blink_points(&self, from: &str, to: &str) -> SqlResult<Vec<BlinkPoint>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT recorded_at, value FROM vitals
+             WHERE vital_type = 'blink_rate' AND recorded_at >= ?1 AND recorded_at < ?2 AND deleted_at IS NULL
+             ORDER BY recorded_at ASC"
+        )?;
+        let points = stmt.query_map(rusqlite::params![from, to], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter_map(|(at, rate)| {
+            let time = chrono::DateTime::parse_from_rfc3339(&at).ok()?.with_timezone(&chrono::Utc);
+            Some(BlinkPoint { time, rate })
+        })
+        .collect();
+        Ok(points)
+    }
+
+    pub fn insert_eye_rest(&self, started_at: &str, ended_at: &str, source: &str) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO eye_rests (started_at, ended_at, source) VALUES (?1, ?2, ?3)",
+            rusqlite::params![started_at, ended_at, source],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// End of the latest look-away: an eye rest or a break (both are at least 20 s)
+    pub fn last_eye_rest_end(&self) -> SqlResult<Option<String>> {
+        self.conn.query_row(
+            "SELECT MAX(ended_at) FROM (SELECT ended_at FROM eye_rests UNION ALL SELECT ended_at FROM breaks)",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Whether the user is at the screen. The webcam presence session decides when it has
+    /// been active since `since`; otherwise recent typing or blink samples count as presence.
+    pub fn screen_active(&self, since: &str) -> SqlResult<bool> {
+        let latest_session: Option<(String, Option<String>)> = self.conn.query_row(
+            "SELECT started_at, ended_at FROM presence_sessions ORDER BY started_at DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).ok();
+        match latest_session {
+            Some((_, None)) => return Ok(true),
+            Some((_, Some(ended_at))) if ended_at.as_str() >= since => return Ok(false),
+            _ => {}
+        }
+
+        let recent: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM vitals WHERE vital_type IN ('wpm', 'blink_rate') AND recorded_at >= ?1",
+            [since],
+            |row| row.get(0),
+        )?;
+        Ok(recent > 0)
+    }
+
+    pub fn insert_eye_reminder(&self, emitted_at: &str) -> SqlResult<i64> {
+        self.conn.execute("INSERT INTO eye_reminders (emitted_at) VALUES (?1)", [emitted_at])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Close every open reminder with `outcome` (complied | missed)
+    pub fn resolve_eye_reminders(&self, outcome: &str, resolved_at: &str) -> SqlResult<usize> {
+        self.conn.execute(
+            "UPDATE eye_reminders SET outcome = ?1, resolved_at = ?2 WHERE outcome IS NULL",
+            rusqlite::params![outcome, resolved_at],
+        )
+    }
+
+    /// (reminders, complied, missed) for reminders emitted within [from, to)
+    pub fn eye_reminder_counts(&self, from: &str, to: &str) -> SqlResult<(u32, u32, u32)> {
+        self.conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(outcome = 'complied'), 0),
+                    COALESCE(SUM(outcome = 'missed'), 0)
+             FROM eye_reminders WHERE emitted_at >= ?1 AND emitted_at < ?2",
+            rusqlite::params![from, to],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+    }
+
+    /// Close sessions left open by a previous run at the last sample recorded during them
+    pub fn close_stale_presence_sessions(&self) -> SqlResult<usize> {
+        self.conn.execute(
+            "UPDATE presence_sessions SET ended_at = MAX(
+                started_at,
+                COALESCE((SELECT MAX(recorded_at) FROM posture_samples
+                          WHERE recorded_at >= presence_sessions.started_at), started_at),
+                COALESCE((SELECT MAX(recorded_at) FROM vitals
+                          WHERE vital_type IN ('wpm', 'blink_rate') AND recorded_at >= presence_sessions.started_at), started_at)
+             )
+             WHERE ended_at IS NULL",
+            [],
+        )
+    }
+
+    /// Queue a message for the welcome-back digest; an identical pending one is kept instead
+    pub fn insert_deferred_message(&self, message: &DeferredMessage) -> SqlResult<bool> {
+        let pending: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM deferred_messages WHERE delivered_at IS NULL AND category = ?1 AND text = ?2",
+            rusqlite::params![message.category, message.text],
+            |row| row.get(0),
+        )?;
+        if pending > 0 {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO deferred_messages (created_at, category, priority, text) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![message.created_at, message.category, message.priority, message.text],
+        )?;
+        Ok(true)
+    }
+
+    /// The newest message deferred at exactly `created_at`, delivered or not
+    pub fn deferred_message_at(&self, created_at: &str) -> SqlResult<Option<DeferredMessage>> {
+        match self.conn.query_row(
+            "SELECT created_at, category, priority, text FROM deferred_messages
+             WHERE created_at = ?1 ORDER BY id DESC LIMIT 1",
+            [created_at],
+            |row| Ok(DeferredMessage {
+                created_at: row.get(0)?,
+                category: row.get(1)?,
+                priority: row.get(2)?,
+                text: row.get(3)?,
+            }),
+        ) {
+            Ok(message) => Ok(Some(message)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Pending deferred messages, oldest first, marked as delivered
+    pub fn take_deferred_messages(&mut self, delivered_at: &str) -> SqlResult<Vec<DeferredMessage>> {
+        let tx = self.conn.transaction()?;
+        let messages = {
+            let mut stmt = tx.prepare(
+                "SELECT created_at, category, priority, text FROM deferred_messages
+                 WHERE delivered_at IS NULL ORDER BY created_at ASC, id ASC"
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(DeferredMessage {
+                    created_at: row.get(0)?,
+                    category: row.get(1)?,
+                    priority: row.get(2)?,
+                    text: row.get(3)?,
+                })
+            })?;
+            rows.collect::<SqlResult<Vec<_>>>()?
+        };
+        tx.execute(
+            "UPDATE deferred_messages SET delivered_at = ?1 WHERE delivered_at IS NULL",
+            [delivered_at],
+        )?;
+        tx.commit()?;
+        Ok(messages)
+    }
+
+    pub fn count_deferred_messages(&self) -> SqlResult<u32> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM deferred_messages WHERE delivered_at IS NULL",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn insert_ingest_client(&self, name: &str, token_hash: &str, created_at: &str) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO ingest_clients (name, token_hash, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![name, token_hash, created_at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn get_ingest_clients(&self) -> SqlResult<Vec<IngestClient>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, created_at, last_used_at, revoked_at FROM ingest_clients ORDER BY created_at DESC"
+        )?;
+        let clients = stmt.query_map([], |row| {
+            Ok(IngestClient {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+                last_used_at: row.get(3)?,
+                revoked_at: row.get(4)?,
+            })
+        })?;
+        clients.collect()
+    }
+
+    /// Active (not revoked) client owning this token hash, marked as used
+    pub fn authenticate_ingest_client(&self, token_hash: &str, used_at: &str) -> SqlResult<Option<(i64, String)>> {
+        let client = self.conn.query_row(
+            "SELECT id, name FROM ingest_clients WHERE token_hash = ?1 AND revoked_at IS NULL",
+            [token_hash],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        match client {
+            Ok((id, name)) => {
+                self.conn.execute("UPDATE ingest_clients SET last_used_at = ?1 WHERE id = ?2", rusqlite::params![used_at, id])?;
+                Ok(Some((id, name)))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn revoke_ingest_client(&self, id: i64, revoked_at: &str) -> SqlResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE ingest_clients SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL",
+            rusqlite::params![revoked_at, id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn insert_ingest_audit(&self, entry: &IngestAuditEntry) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO ingest_audit (received_at, client_id, client_name, method, path, status, record_kind, record_ids, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                entry.received_at, entry.client_id, entry.client_name, entry.method, entry.path,
+                entry.status, entry.record_kind, serde_json::to_string(&entry.record_ids).unwrap_or_default(), entry.detail,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Most recent API requests first
+    pub fn get_ingest_audit(&self, limit: u32) -> SqlResult<Vec<IngestAuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, received_at, client_id, client_name, method, path, status, record_kind, record_ids, detail
+             FROM ingest_audit ORDER BY received_at DESC, id DESC LIMIT ?1"
+        )?;
+        let entries = stmt.query_map([limit], |row| {
+            let record_ids: String = row.get(8)?;
+            Ok(IngestAuditEntry {
+                id: Some(row.get(0)?),
+                received_at: row.get(1)?,
+                client_id: row.get(2)?,
+                client_name: row.get(3)?,
+                method: row.get(4)?,
+                path: row.get(5)?,
+                status: row.get(6)?,
+                record_kind: row.get(7)?,
+                record_ids: serde_json::from_str(&record_ids).unwrap_or_default(),
+                detail: row.get(9)?,
+            })
+        })?;
+        entries.collect()
+    }
+
     /// Get upcoming scheduled exams (not completed)
     /// Execute a raw SQL statement
     pub fn execute(&self, sql: &str, params: &[&dyn rusqlite::types::ToSql]) -> SqlResult<usize> {
@@ -234,9 +1484,9 @@ impl Database {
         self.conn.query_row(sql, params, f)
     }
 
-    pub fn get_upcoming_exams(&self) -> SqlResult<Vec<(i64, String, String, String, bool)>> {
+    pub fn get_upcoming_exams(&self) -> SqlResult<Vec<UpcomingExam>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, exam_type, reason, scheduled_date, completed FROM health_schedule WHERE completed = 0 ORDER BY scheduled_date ASC"
+            "SELECT id, exam_type, reason, scheduled_date, completed FROM health_schedule WHERE completed = 0 AND deleted_at IS NULL ORDER BY scheduled_date ASC"
         )?;
         let exams = stmt.query_map([], |row| {
             Ok((
@@ -250,3 +1500,175 @@ impl Database {
         Ok(exams)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db.run_migrations(chrono_tz::Europe::Lisbon, "2026-07-01T09:00:00Z".parse().unwrap()).unwrap();
+        db
+    }
+
+    fn lab(value: f64, status: &str) -> LabRecord {
+        LabRecord {
+            id: None,
+            marker: "Ferritina".to_string(),
+            value,
+            unit: "ng/mL".to_string(),
+            reference_range: Some("30-400".to_string()),
+            status: status.to_string(),
+            lab_name: Some("Synlab".to_string()),
+            test_date: Some("2026-06-20".to_string()),
+            pdf_source: None,
+        }
+    }
+
+    fn deleted_at(db: &Database, id: i64) -> Option<String> {
+        db.query_row("SELECT deleted_at FROM lab_results WHERE id = ?1", &[&id], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn set_deleted_only_changes_rows_in_the_other_state() {
+        let db = open();
+        let id = db.insert_lab_record(&lab(12.0, "low")).unwrap();
+
+        assert!(!db.set_deleted("lab_results", id, None).unwrap());
+        assert!(db.set_deleted("lab_results", id, Some("2026-07-01T09:00:00Z")).unwrap());
+        assert!(!db.set_deleted("lab_results", id, Some("2026-07-01T10:00:00Z")).unwrap());
+        assert_eq!(deleted_at(&db, id).as_deref(), Some("2026-07-01T09:00:00Z"));
+        assert!(!db.update_lab_record(id, &lab(13.0, "low")).unwrap());
+
+        assert!(db.set_deleted("lab_results", id, None).unwrap());
+        assert_eq!(deleted_at(&db, id), None);
+        assert!(!db.set_deleted("lab_results", id + 1, Some("2026-07-01T09:00:00Z")).unwrap());
+        assert!(db.set_deleted("agent_memory", id, None).is_err());
+    }
+
+    #[test]
+    fn a_vital_exists_by_type_time_and_source_whatever_its_value() {
+        let db = open();
+        let reading = |value: f64, source: &str| VitalEntry {
+            id: None,
+            vital_type: "heart_rate".to_string(),
+            value,
+            unit: "bpm".to_string(),
+            recorded_at: "2026-07-01T07:30:00Z".to_string(),
+            source: source.to_string(),
+        };
+        db.insert_vital(&reading(62.0, "wearable")).unwrap();
+
+        // A corrected value would still hit the wearable unique index
+        assert!(db.vital_exists(&reading(64.0, "wearable")).unwrap());
+        assert!(db.insert_vital(&reading(64.0, "wearable")).is_err());
+        assert!(!db.vital_exists(&reading(62.0, "manual")).unwrap());
+        assert!(!db.vital_exists(&VitalEntry { recorded_at: "2026-07-01T07:31:00Z".to_string(), ..reading(62.0, "wearable") }).unwrap());
+    }
+
+    fn lisbon_day(day: &str) -> (String, String) {
+        time::local_day_bounds(day.parse().unwrap(), chrono_tz::Europe::Lisbon)
+    }
+
+    fn session(started_at: &str, ended_at: Option<&str>) -> PresenceSession {
+        PresenceSession { id: None, started_at: started_at.to_string(), ended_at: ended_at.map(str::to_string), source: None }
+    }
+
+    #[test]
+    fn presence_is_clipped_to_the_local_day() {
+        let db = open();
+        for (started_at, ended_at) in [
+            ("2026-06-30T22:30:00Z", Some("2026-06-30T23:30:00Z")), // 23:30-00:30 in Lisbon, half on the 1st
+            ("2026-07-01T08:00:00Z", None),                         // left open by a crash
+            ("2026-07-01T12:00:00Z", Some("2026-07-01T13:00:00Z")),
+            ("2026-07-01T22:40:00Z", None),                         // still sitting past local midnight
+        ] {
+            db.upsert_presence_session(&session(started_at, ended_at)).unwrap();
+        }
+        let now = "2026-07-01T23:20:00Z";
+
+        let (from, to) = lisbon_day("2026-07-01");
+        assert_eq!((from.as_str(), to.as_str()), ("2026-06-30T23:00:00Z", "2026-07-01T23:00:00Z"));
+        assert_eq!(db.presence_seconds(&from, &to, now).unwrap(), (30 + 60 + 20) * 60);
+
+        let (from, to) = lisbon_day("2026-06-30");
+        assert_eq!(db.presence_seconds(&from, &to, now).unwrap(), 30 * 60);
+        let (from, to) = lisbon_day("2026-07-02");
+        assert_eq!(db.presence_seconds(&from, &to, now).unwrap(), 20 * 60);
+        let (from, to) = lisbon_day("2026-07-05");
+        assert_eq!(db.presence_seconds(&from, &to, now).unwrap(), 0);
+    }
+
+    #[test]
+    fn breaks_and_posture_count_on_the_local_day() {
+        let mut db = open();
+        for (started_at, ended_at) in [
+            ("2026-06-30T22:40:00Z", "2026-06-30T22:50:00Z"), // 23:50 local, the day before
+            ("2026-06-30T23:00:00Z", "2026-06-30T23:10:00Z"), // 00:10 local
+            ("2026-07-01T22:50:00Z", "2026-07-01T22:59:59Z"),
+            ("2026-07-01T22:55:00Z", "2026-07-01T23:00:00Z"), // ends at local midnight: the next day
+        ] {
+            db.insert_break(&BreakEntry { id: None, started_at: started_at.to_string(), ended_at: ended_at.to_string(), reason: None }).unwrap();
+        }
+        let samples = [("2026-06-30T22:59:59Z", 20), ("2026-06-30T23:00:00Z", 80), ("2026-07-01T12:00:00Z", 90), ("2026-07-01T23:00:00Z", 10)]
+            .map(|(recorded_at, score)| PostureSample { recorded_at: recorded_at.to_string(), score, head_x: None, head_y: None });
+        db.insert_posture_samples(&samples).unwrap();
+
+        let (from, to) = lisbon_day("2026-07-01");
+        assert_eq!(db.count_breaks(&from, &to).unwrap(), 2);
+        assert_eq!(db.average_posture(&from, &to).unwrap(), Some(85.0));
+
+        let (from, to) = lisbon_day("2026-07-05");
+        assert_eq!(db.count_breaks(&from, &to).unwrap(), 0);
+        assert_eq!(db.average_posture(&from, &to).unwrap(), None);
+    }
+
+    #[test]
+    fn agent_memory_retention_counts_from_the_given_instant() {
+        let db = open();
+        for (key, at) in [("old", "2026-05-31T23:59:59Z"), ("kept", "2026-06-01T00:00:00Z"), ("today", "2026-07-01T08:00:00Z")] {
+            db.execute("INSERT INTO agent_memory (key, value, timestamp) VALUES (?1, 'x', ?2)", &[&key, &at]).unwrap();
+        }
+        // 30 days before the next start, not the wall clock's
+        db.run_migrations(chrono_tz::Europe::Lisbon, "2026-07-01T00:00:00Z".parse().unwrap()).unwrap();
+        let kept: i64 = db.query_row("SELECT COUNT(*) FROM agent_memory WHERE key != 'old'", &[], |row| row.get(0)).unwrap();
+        let total: i64 = db.query_row("SELECT COUNT(*) FROM agent_memory", &[], |row| row.get(0)).unwrap();
+        assert_eq!((kept, total), (2, 2));
+    }
+
+    #[test]
+    fn restore_snapshot_writes_back_known_columns() {
+        let db = open();
+        let id = db.insert_lab_record(&lab(12.0, "low")).unwrap();
+        let before = db.record_snapshot("lab_results", id).unwrap().unwrap();
+        db.update_lab_record(id, &LabRecord { lab_name: None, ..lab(45.0, "normal") }).unwrap();
+
+        // Columns the table no longer has are ignored; id and created_at stay put
+        let mut snapshot = before.clone();
+        snapshot["legacy_column"] = "x".into();
+        snapshot["id"] = (id + 100).into();
+        assert!(db.restore_snapshot("lab_results", id, &snapshot).unwrap());
+        assert_eq!(db.record_snapshot("lab_results", id).unwrap().unwrap(), before);
+
+        assert!(!db.restore_snapshot("lab_results", id, &serde_json::json!({ "legacy_column": 1 })).unwrap());
+        assert!(!db.restore_snapshot("lab_results", id + 1, &before).unwrap());
+    }
+
+    #[test]
+    fn daily_vital_totals_use_the_offset_of_each_reading() {
+        let db = open();
+        // Around the spring change in Lisbon: 23:30 WET on the 28th, then 00:30 WEST on the 30th
+        for (value, at) in [(4000.0, "2026-03-28T23:30:00Z"), (6000.0, "2026-03-29T12:00:00Z"), (5000.0, "2026-03-29T23:30:00Z")] {
+            db.insert_vital(&VitalEntry {
+                id: None,
+                vital_type: "steps".to_string(),
+                value,
+                unit: "steps".to_string(),
+                recorded_at: at.to_string(),
+                source: "manual".to_string(),
+            }).unwrap();
+        }
+        let summaries = db.get_vital_summaries("2026-03-01T00:00:00Z", "2026-03-31T22:59:59Z", chrono_tz::Europe::Lisbon).unwrap();
+        assert_eq!((summaries[0].count, summaries[0].mean, summaries[0].max), (3, 5000.0, 6000.0));
+    }
+}
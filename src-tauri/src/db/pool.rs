@@ -0,0 +1,326 @@
+use rusqlite::Result as SqlResult;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use super::Database;
+
+// Connection pool: one writer plus a few read-only connections on the same WAL file.
+//
+// SQLite allows a single writer, so writes queue on one connection. Each reader has its
+// own connection and, with WAL, sees the last committed state without waiting for the
+// writer. The async API runs every call on tokio's blocking pool, so a slow OCR import
+// or timeline query never stalls the runtime or a reminder write. Background threads
+// use the `_blocking` variants directly.
+
+/// Read-only connections kept open next to the writer
+pub const READ_CONNECTIONS: usize = 4;
+
+struct Pool {
+    writer: Mutex<Database>,
+    idle: Mutex<Vec<Database>>,
+    returned: Condvar,
+    readers: usize,
+}
+
+/// Shared database handle managed by Tauri; cheap to clone into blocking tasks
+#[derive(Clone)]
+pub struct DbState(Arc<Pool>);
+
+/// A read connection borrowed from the pool, handed back on drop (also on panic)
+struct Reader<'a> {
+    pool: &'a Pool,
+    db: Option<Database>,
+}
+
+impl Drop for Reader<'_> {
+    fn drop(&mut self) {
+        if let (Some(db), Ok(mut idle)) = (self.db.take(), self.pool.idle.lock()) {
+            idle.push(db);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+impl DbState {
+    /// Wrap a migrated writer and open `readers` read-only connections to `path`.
+    /// With no readers, reads share the writer.
+    pub fn open(writer: Database, path: &Path, readers: usize) -> SqlResult<Self> {
+        let idle = (0..readers).map(|_| Database::open_reader(path)).collect::<SqlResult<Vec<_>>>()?;
+        Ok(DbState(Arc::new(Pool {
+            writer: Mutex::new(writer),
+            idle: Mutex::new(idle),
+            returned: Condvar::new(),
+            readers,
+        })))
+    }
+
+    /// Only the writer, which also serves reads (e.g. an in-memory database)
+    pub fn single(writer: Database) -> Self {
+        DbState(Arc::new(Pool {
+            writer: Mutex::new(writer),
+            idle: Mutex::new(Vec::new()),
+            returned: Condvar::new(),
+            readers: 0,
+        }))
+    }
+
+    /// Run `f` on a read-only connection, waiting for one to be free.
+    /// Any write inside `f` fails with SQLITE_READONLY.
+    pub fn read_blocking<T, E, F>(&self, f: F) -> Result<T, String>
+    where
+        E: ToString,
+        F: FnOnce(&Database) -> Result<T, E>,
+    {
+        let pool = &self.0;
+        if pool.readers == 0 {
+            let db = pool.writer.lock().map_err(|e| e.to_string())?;
+            return f(&db).map_err(|e| e.to_string());
+        }
+        let db = {
+            let mut idle = pool.idle.lock().map_err(|e| e.to_string())?;
+            loop {
+                if let Some(db) = idle.pop() {
+                    break db;
+                }
+                idle = pool.returned.wait(idle).map_err(|e| e.to_string())?;
+            }
+        };
+        let reader = Reader { pool, db: Some(db) };
+        let db = reader.db.as_ref().expect("reader holds a connection until dropped");
+        f(db).map_err(|e| e.to_string())
+    }
+
+    /// Run `f` on the writer; writes from all callers are serialized here
+    pub fn write_blocking<T, E, F>(&self, f: F) -> Result<T, String>
+    where
+        E: ToString,
+        F: FnOnce(&mut Database) -> Result<T, E>,
+    {
+        let mut db = self.0.writer.lock().map_err(|e| e.to_string())?;
+        f(&mut db).map_err(|e| e.to_string())
+    }
+
+    /// `read_blocking` on the blocking pool
+    pub async fn read<T, E, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        E: ToString,
+        F: FnOnce(&Database) -> Result<T, E> + Send + 'static,
+    {
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || state.read_blocking(f))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+    }
+
+    /// `write_blocking` on the blocking pool
+    pub async fn write<T, E, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        E: ToString,
+        F: FnOnce(&mut Database) -> Result<T, E> + Send + 'static,
+    {
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || state.write_blocking(f))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::health::VitalEntry;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Only there so a regression fails instead of hanging; ordering comes from the channels
+    const DEADLOCK: Duration = Duration::from_secs(10);
+
+    /// Migrated database in a fresh temp file, removed with its WAL files on drop
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("holoself-pool-{}-{}.db", name, std::process::id()));
+            let temp = TempDb(path);
+            temp.cleanup();
+            temp
+        }
+
+        fn open(&self, readers: usize) -> DbState {
+            let writer = Database::new(&self.0).unwrap();
+            writer.run_migrations(chrono_tz::UTC, "2026-07-01T09:00:00Z".parse().unwrap()).unwrap();
+            DbState::open(writer, &self.0, readers).unwrap()
+        }
+
+        fn cleanup(&self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+            }
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            self.cleanup();
+        }
+    }
+
+    fn vital(value: f64) -> VitalEntry {
+        VitalEntry {
+            id: None,
+            vital_type: "heart_rate".to_string(),
+            value,
+            unit: "bpm".to_string(),
+            recorded_at: "2026-01-01T08:00:00Z".to_string(),
+            source: "manual".to_string(),
+        }
+    }
+
+    fn count_vitals(db: &Database) -> rusqlite::Result<i64> {
+        db.query_row("SELECT COUNT(*) FROM vitals", &[], |row| row.get(0))
+    }
+
+    #[test]
+    fn concurrent_writes_are_serialized() {
+        let temp = TempDb::new("writes");
+        let state = temp.open(READ_CONNECTIONS);
+        let handles: Vec<_> = (0..8).map(|t| {
+            let state = state.clone();
+            thread::spawn(move || {
+                for i in 0..50 {
+                    state.write_blocking(|db| db.insert_vital(&vital((t * 100 + i) as f64))).unwrap();
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(state.read_blocking(count_vitals).unwrap(), 400);
+    }
+
+    #[test]
+    fn reads_do_not_wait_for_an_open_write() {
+        let temp = TempDb::new("read-during-write");
+        let state = temp.open(READ_CONNECTIONS);
+        state.write_blocking(|db| db.insert_vital(&vital(60.0))).unwrap();
+
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let writer = {
+            let state = state.clone();
+            thread::spawn(move || {
+                state.write_blocking(|db| db.transaction(|db| {
+                    db.insert_vital(&vital(61.0))?;
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Ok::<_, rusqlite::Error>(())
+                })).unwrap();
+            })
+        };
+        started.recv().unwrap();
+
+        // The read finishes while the write transaction is still open, seeing only the committed row
+        let (read_tx, read) = mpsc::channel();
+        let reading = state.clone();
+        thread::spawn(move || read_tx.send(reading.read_blocking(count_vitals)).unwrap());
+        assert_eq!(read.recv_timeout(DEADLOCK).expect("read waited for the writer"), Ok(1));
+
+        release.send(()).unwrap();
+        writer.join().unwrap();
+        assert_eq!(state.read_blocking(count_vitals).unwrap(), 2);
+    }
+
+    #[test]
+    fn slow_read_does_not_block_writes() {
+        let temp = TempDb::new("write-during-read");
+        let state = temp.open(READ_CONNECTIONS);
+        state.write_blocking(|db| db.insert_vital(&vital(60.0))).unwrap();
+
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let reader = {
+            let state = state.clone();
+            thread::spawn(move || {
+                // A long read transaction keeps its snapshot while the writer commits
+                state.read_blocking(|db| db.rehearse(|db| {
+                    let before = count_vitals(db)?;
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Ok::<_, rusqlite::Error>((before, count_vitals(db)?))
+                })).unwrap()
+            })
+        };
+        started.recv().unwrap();
+
+        // The write commits while the read transaction is still open
+        let (write_tx, write) = mpsc::channel();
+        let writing = state.clone();
+        thread::spawn(move || write_tx.send(writing.write_blocking(|db| db.insert_vital(&vital(61.0)))).unwrap());
+        assert!(write.recv_timeout(DEADLOCK).expect("write waited for the reader").is_ok());
+
+        release.send(()).unwrap();
+        assert_eq!(reader.join().unwrap(), (1, 1));
+        assert_eq!(state.read_blocking(count_vitals).unwrap(), 2);
+    }
+
+    #[test]
+    fn readers_queue_for_a_free_connection() {
+        let temp = TempDb::new("queue");
+        let state = temp.open(1);
+        let handles: Vec<_> = (0..4).map(|_| {
+            let state = state.clone();
+            thread::spawn(move || state.read_blocking(|db| {
+                thread::sleep(Duration::from_millis(20));
+                count_vitals(db)
+            }))
+        }).collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(0));
+        }
+    }
+
+    #[test]
+    fn read_connections_refuse_writes() {
+        let temp = TempDb::new("readonly");
+        let state = temp.open(READ_CONNECTIONS);
+        let err = state.read_blocking(|db| db.insert_vital(&vital(60.0))).unwrap_err();
+        assert!(err.contains("readonly"), "{}", err);
+        // The connection went back to the pool
+        assert_eq!(state.read_blocking(count_vitals).unwrap(), 0);
+    }
+
+    #[test]
+    fn without_readers_reads_use_the_writer() {
+        let temp = TempDb::new("no-readers");
+        let state = temp.open(0);
+        state.write_blocking(|db| db.insert_vital(&vital(60.0))).unwrap();
+        assert_eq!(state.read_blocking(count_vitals).unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn async_calls_run_on_the_blocking_pool() {
+        let temp = TempDb::new("async");
+        let state = temp.open(READ_CONNECTIONS);
+
+        // The read holds its connection (and a blocking thread) until the write is done
+        let (release, release_rx) = mpsc::channel::<()>();
+        let slow_read = state.read(move |db| {
+            release_rx.recv().unwrap();
+            count_vitals(db)
+        });
+        let write = async {
+            let written = tokio::time::timeout(DEADLOCK, state.write(|db| db.insert_vital(&vital(60.0))))
+                .await
+                .expect("write waited for the read");
+            release.send(()).unwrap();
+            written
+        };
+        let (read, written) = tokio::join!(slow_read, write);
+        assert!(written.is_ok());
+        assert!(read.is_ok());
+        assert_eq!(state.read(count_vitals).await, Ok(1));
+    }
+}
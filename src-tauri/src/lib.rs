@@ -1,13 +1,55 @@
 // HoloSelf OS — Core Library
 // Privacy-first AI health agent with holographic HUD
 
-mod commands;
-mod db;
-mod services;
+mod cli;
+pub mod commands;
+pub mod db;
+pub mod services;
 
+use std::path::Path;
+#[cfg(feature = "gui")]
 use tauri::Manager;
 
+/// Create `config_dir`, open the secret store and load the remaining .env entries.
+/// Leaves the user's files as they are; the plaintext key migration is the app's job.
+#[cfg(feature = "gui")]
+fn prepare_config(config_dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(config_dir).map_err(|e| format!("Failed to create app config dir: {}", e))?;
+
+    // Secret store: OS keyring, or encrypted file on headless Linux
+    match services::secrets::init(config_dir) {
+        Ok(backend) => log::info!("Secret store backend: {}", backend),
+        Err(e) => log::error!("Secret store unavailable: {}", e),
+    }
+
+    // Non-secret .env entries; API keys are only read from the store
+    let env_path = config_dir.join(".env");
+    if let Ok(content) = std::fs::read_to_string(&env_path) {
+        for (key, value) in services::secrets::env_entries(&content) {
+            std::env::set_var(key, value);
+        }
+        log::info!("Loaded env from {:?}", env_path);
+    }
+    Ok(())
+}
+
+/// Open and migrate the database in `data_dir` at `now`.
+/// `local_tz` reads legacy rows without an offset; `readers` sizes the read pool.
+fn open_database(data_dir: &Path, local_tz: chrono_tz::Tz, readers: usize, now: chrono::DateTime<chrono::Utc>) -> Result<db::DbState, String> {
+    std::fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let db_path = data_dir.join(db::DB_FILE_NAME);
+    let db = db::Database::new(&db_path).map_err(|e| e.to_string())?;
+    db.run_migrations(local_tz, now)?;
+    db::DbState::open(db, &db_path, readers).map_err(|e| e.to_string())
+}
+
+/// Headless entry point of `holoself-cli`: same data and settings as the app, no webview
+pub fn run_cli() -> std::process::ExitCode {
+    cli::run(std::env::args().skip(1).collect())
+}
+
 /// Initialize the HoloSelf OS application
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     env_logger::init();
@@ -17,56 +59,64 @@ pub fn run() {
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
-            // Load .env from app config dir (saved by onboarding)
+            // One clock for every command and background loop
+            app.manage(services::clock::ClockState::default());
+
             if let Ok(config_dir) = app.path().app_config_dir() {
-                let env_path = config_dir.join(".env");
-                if env_path.exists() {
-                    // Secure .env permissions on Unix (0600 = owner-only)
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::PermissionsExt;
-                        if let Ok(meta) = std::fs::metadata(&env_path) {
-                            let mode = meta.permissions().mode();
-                            if mode & 0o077 != 0 {
-                                // Fix permissions automatically
-                                let _ = std::fs::set_permissions(
-                                    &env_path,
-                                    std::fs::Permissions::from_mode(0o600),
-                                );
-                                log::warn!("Fixed insecure .env permissions (was {:o})", mode);
-                            }
-                        }
-                    }
-                    if let Ok(content) = std::fs::read_to_string(&env_path) {
-                        for line in content.lines() {
-                            let line = line.trim();
-                            if line.is_empty() || line.starts_with('#') { continue; }
-                            if let Some((key, value)) = line.split_once('=') {
-                                std::env::set_var(key.trim(), value.trim());
-                            }
-                        }
-                        log::info!("Loaded env from {:?}", env_path);
-                    }
+                if let Err(e) = prepare_config(&config_dir) {
+                    log::error!("{}", e);
                 }
+                // Move API keys out of legacy plaintext .env / settings.json
+                match services::secrets::migrate_plaintext(&config_dir) {
+                    Ok(0) => {}
+                    Ok(n) => log::info!("Migrated {} plaintext API key(s) into the secret store", n),
+                    Err(e) => log::error!("Failed to migrate plaintext API keys: {}", e),
+                }
+                // Live settings (upgraded + validated); replaced on save
+                app.manage(commands::settings::SettingsState::load(&config_dir));
             }
 
             // Initialize SQLite database
             let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
-            std::fs::create_dir_all(&app_data).expect("Failed to create app data dir");
+            // Legacy rows without an offset are read in the user's zone
+            let local_tz = commands::settings::load_settings(app.handle())
+                .map(|s| commands::settings::user_timezone(&s))
+                .unwrap_or(chrono_tz::UTC);
+            // Store database handle in app state: the migrated writer plus read connections
+            let now = commands::system::clock(app.handle()).now();
+            let db = open_database(&app_data, local_tz, db::READ_CONNECTIONS, now).expect("Failed to initialize database");
+            // Damage is logged rather than fatal so a backup can still be restored from the UI
+            if let Err(e) = db.write_blocking(|db| db.check_integrity()) {
+                log::error!("Database integrity check failed: {}", e);
+            }
+            app.manage(db);
+
+            // Presence state machine: sessions, breaks, idle detection
+            app.manage(commands::presence::PresenceState::default());
+            commands::presence::spawn_idle_monitor(app.handle().clone());
+
+            // Opt-in system-wide typing cadence (needs the DB for flushing)
+            app.manage(services::input_cadence::InputCadenceState::default());
+            commands::typing::apply_input_cadence(app.handle());
+
+            // 20-20-20 eye-care engine
+            app.manage(commands::eye_care::EyeCareState::default());
+            commands::eye_care::spawn_engine(app.handle().clone());
 
-            let db_path = app_data.join("holoself.db");
-            let db = db::Database::new(&db_path).expect("Failed to initialize database");
-            db.run_migrations().expect("Failed to run migrations");
+            // Opt-in localhost API for sensor scripts
+            app.manage(services::ingest_api::IngestApiState::default());
+            commands::ingest::apply_ingest_api(app.handle());
 
-            // Store database handle in app state
-            app.manage(db::DbState(std::sync::Mutex::new(db)));
+            // Manual and scheduled backups of the data directory
+            app.manage(commands::backup::BackupState::default());
+            commands::backup::spawn_backup_scheduler(app.handle().clone());
 
             // Configure transparent window for holographic HUD
             if let Some(_window) = app.get_webview_window("main") {
                 log::info!("HoloSelf OS HUD window initialized — transparent frameless mode");
             }
 
-            log::info!("HoloSelf OS started — Database at {:?}", db_path);
+            log::info!("HoloSelf OS started — Database at {:?}", app_data.join(db::DB_FILE_NAME));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -74,11 +124,60 @@ pub fn run() {
             commands::health::log_supplement,
             commands::health::get_supplement_log,
             commands::health::get_health_timeline,
+            commands::health::get_timeline_buckets,
             commands::health::log_vital,
+            // Record edits, soft delete and audit trail
+            commands::records::update_supplement,
+            commands::records::update_vital,
+            commands::records::update_lab_result,
+            commands::records::update_scheduled_exam,
+            commands::records::delete_record,
+            commands::records::restore_record,
+            commands::records::get_audit_log,
+            commands::records::undo_last_agent_action,
+            // Activity (presence, breaks, posture)
+            commands::activity::log_presence_session,
+            commands::activity::log_break,
+            commands::activity::log_posture_samples,
+            // Typing cadence / fatigue
+            commands::typing::record_wpm_snapshots,
+            commands::typing::get_typing_fatigue,
+            commands::typing::get_fatigue_episodes,
+            commands::typing::get_input_cadence_status,
+            // Eye care commands
+            commands::eye_care::log_blink_rates,
+            commands::eye_care::log_blink_events,
+            commands::eye_care::log_eye_rest,
+            commands::eye_care::get_eye_care_stats,
+            // Presence commands
+            commands::presence::report_presence,
+            commands::presence::get_presence_status,
+            // Wearable imports
+            commands::wearables::import_wearable_file,
+            // FHIR R4 export / import
+            commands::fhir::export_fhir_bundle,
+            commands::fhir::import_fhir_bundle,
+            // Spreadsheet export / import (CSV, NDJSON)
+            commands::tabular::export_records,
+            commands::tabular::import_records,
+            // Doctor-ready PDF report
+            commands::report::generate_health_report,
+            // Backup / restore
+            commands::backup::create_backup,
+            commands::backup::restore_backup,
+            commands::backup::get_backup_status,
+            commands::backup::set_backup_passphrase,
+            // Local ingestion API
+            commands::ingest::create_ingest_client,
+            commands::ingest::list_ingest_clients,
+            commands::ingest::revoke_ingest_client,
+            commands::ingest::get_ingest_audit,
+            commands::ingest::get_ingest_api_status,
             // Agent commands
             commands::agent::get_agent_message,
             commands::agent::execute_agent_action,
             commands::agent::get_daily_stats,
+            commands::simulation::run_agent_simulation,
             // Gemini Bridge
             commands::gemini::ocr_clinical_pdf,
             // Voice (Cartesia TTS + Whisper STT)
@@ -95,6 +194,10 @@ pub fn run() {
             // Vitamin D Calculator
             commands::vitamin_d::get_vitamin_d_recommendation,
             commands::vitamin_d::get_current_uv_index,
+            commands::vitamin_d::get_vitamin_d_plan,
+            commands::vitamin_d::log_sun_exposure,
+            commands::vitamin_d::get_sun_exposure_log,
+            commands::vitamin_d::get_vitamin_d_budget,
             // Settings
             commands::settings::get_settings,
             commands::settings::save_settings,
@@ -0,0 +1,491 @@
+use chrono::{DateTime, Timelike, Utc};
+use crate::commands::agent::{AgentAction, AgentMessage, PROTOCOLS};
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::{eye_care, presence, records, typing, vitamin_d};
+use crate::services::context::ServiceContext;
+use crate::services::{gemini, time};
+
+/// Voice input newer than this is answered before anything else
+const VOICE_INPUT_WINDOW_SECONDS: i64 = 30;
+
+/// The HoloSelf agent: contextual messages, confirmed actions and voice input
+pub struct AgentService<'a> {
+    ctx: &'a ServiceContext,
+}
+
+/// What the database says about right now, for the message rules and the Gemini prompt
+struct Snapshot {
+    adherence_pct: u32,
+    taken_today: usize,
+    total: usize,
+    supplement_names: Vec<&'static str>,
+    taken_names: Vec<&'static str>,
+    pending_names: Vec<&'static str>,
+    exam_context: String,
+    /// Reminder or nudge that wins over the general message
+    early: Option<AgentMessage>,
+}
+
+fn log_supplement_action(protocol: &crate::commands::agent::Protocol) -> AgentAction {
+    AgentAction {
+        action_type: "log_supplement".into(),
+        payload: serde_json::json!({
+            "name": protocol.name,
+            "dosage": protocol.dosage,
+            "category": protocol.category
+        }),
+    }
+}
+
+impl<'a> AgentService<'a> {
+    pub fn new(ctx: &'a ServiceContext) -> Self {
+        AgentService { ctx }
+    }
+
+    /// Compose the next message and deliver it, or defer it while `away`.
+    /// Delivered messages are recorded for the timeline.
+    pub async fn next_message(&self, away: bool) -> Result<Option<AgentMessage>, String> {
+        let message = self.compose_message().await?;
+        let now = self.ctx.clock.now();
+        self.ctx.db.write(move |db| {
+            let delivered = presence::deliver_or_defer(db, message, away, now)?;
+            if let Some(message) = &delivered {
+                // Kept for the timeline; the 15-minute poll would otherwise repeat it
+                let repeat_after = time::format_utc(now - chrono::Duration::hours(1));
+                if let Err(e) = db.record_agent_message(&message.text, &message.category, &time::format_utc(now), &repeat_after) {
+                    log::warn!("Failed to record agent message: {}", e);
+                }
+            }
+            Ok::<_, String>(delivered)
+        }).await
+    }
+
+    /// Calm Technology: never alarming, always solution-oriented
+    async fn compose_message(&self) -> Result<AgentMessage, String> {
+        let tz = self.ctx.tz();
+        let now = self.ctx.clock.now();
+        let hour = now.with_timezone(&tz).hour();
+        let today = self.ctx.clock.today(tz);
+        let settings = self.ctx.config.settings.clone();
+
+        // All DB access happens on a read connection; voice replies come back as Err
+        let context = self.ctx.db.read(move |db| {
+            let (day_start, day_end) = time::local_day_bounds(today, tz);
+            let taken = |name: &str| db.check_supplement_taken(name, &day_start, &day_end).unwrap_or(false);
+
+            // Pre-fetch upcoming exams
+            let upcoming_exams = db.get_upcoming_exams().unwrap_or_default();
+            let exam_context = match upcoming_exams.first() {
+                Some(exam) => format!("Próximo exame: {} em {}.", exam.1, exam.3),
+                None => "Sem exames próximos.".to_string(),
+            };
+
+            // 0. Check for recent voice input
+            let recent = time::format_utc(now - chrono::Duration::seconds(VOICE_INPUT_WINDOW_SECONDS));
+            let voice_input: Option<String> = db.query_row(
+                "SELECT value FROM agent_memory WHERE key = 'voice_input' AND timestamp > ?1 ORDER BY rowid DESC LIMIT 1",
+                &[&recent],
+                |row| row.get(0),
+            ).ok();
+
+            if let Some(ref input) = voice_input {
+                let lower = input.to_lowercase();
+                if lower.contains("status") || lower.contains("como estou") || lower.contains("relatório") {
+                    let taken_count = PROTOCOLS.iter().filter(|p| taken(p.name)).count();
+                    return Ok(Err(AgentMessage {
+                        text: format!(
+                            "Relatório rápido: {} de {} suplementos hoje ({}%). {}",
+                            taken_count,
+                            PROTOCOLS.len(),
+                            (taken_count as f64 / PROTOCOLS.len() as f64 * 100.0) as u32,
+                            exam_context,
+                        ),
+                        category: "voice_response".into(),
+                        priority: "high".into(),
+                        action: None,
+                    }));
+                } else if lower.contains("tomei") || lower.contains("registar") || lower.contains("suplemento") {
+                    if let Some(protocol) = PROTOCOLS.iter().find(|p| lower.contains(&p.name.to_lowercase())) {
+                        return Ok(Err(AgentMessage {
+                            text: format!("Registando {} — {}.", protocol.name, protocol.dosage),
+                            category: "voice_response".into(),
+                            priority: "high".into(),
+                            action: Some(log_supplement_action(protocol)),
+                        }));
+                    }
+                }
+
+                // Generic voice acknowledgment
+                return Ok(Err(AgentMessage {
+                    text: format!("Entendido: \"{}\". A processar.", input),
+                    category: "voice_response".into(),
+                    priority: "medium".into(),
+                    action: None,
+                }));
+            }
+
+            // 1. Check for pending supplements in current time window
+            let mut early = PROTOCOLS.iter()
+                .find(|p| p.hours.contains(&hour) && !taken(p.name))
+                .map(|protocol| AgentMessage {
+                    text: format!("{} — {}. {}", protocol.name, protocol.dosage, protocol.benefit),
+                    category: "supplement_reminder".into(),
+                    priority: "medium".into(),
+                    action: Some(log_supplement_action(protocol)),
+                });
+
+            // 1b. Sun exposure nudge during today's best UV slot (cached forecast only)
+            if early.is_none() {
+                if let Some(plan) = vitamin_d::cached_plan_for_today(db, &settings, today) {
+                    let in_best_slot = plan.slots.first()
+                        .and_then(|slot| slot.start.get(..2)?.parse::<u32>().ok())
+                        .is_some_and(|slot_hour| slot_hour == hour);
+                    if in_best_slot {
+                        early = Some(AgentMessage {
+                            text: plan.nudge,
+                            category: "sun_exposure".into(),
+                            priority: "low".into(),
+                            action: None,
+                        });
+                    }
+                }
+            }
+
+            // 1c. Typing fatigue episode still going on (per-minute WPM vs. personal baseline)
+            if early.is_none() {
+                let recent = time::format_utc(now - chrono::Duration::hours(2));
+                let episodes = typing::episodes_between(db, &recent, &time::format_utc(now))
+                    .unwrap_or_default();
+                let ongoing = episodes.last().filter(|e| ended_within(&e.ended_at, now, 5, tz));
+                if let Some(episode) = ongoing {
+                    early = Some(AgentMessage {
+                        text: format!(
+                            "Ritmo de escrita {}% abaixo do teu habitual há {} min. Uma pausa de 10 min ajuda a recuperar.",
+                            episode.mean_drop_percent.round(), episode.duration_minutes
+                        ),
+                        category: "fatigue".into(),
+                        priority: "medium".into(),
+                        action: None,
+                    });
+                }
+            }
+
+            // 1d. Sustained low blink rate right now (eye strain)
+            if early.is_none() {
+                let recent = time::format_utc(now - chrono::Duration::hours(1));
+                let periods = eye_care::low_blink_between(db, &recent, &time::format_utc(now))
+                    .unwrap_or_default();
+                let ongoing = periods.last().filter(|p| ended_within(&p.ended_at, now, 3, tz));
+                if let Some(period) = ongoing {
+                    early = Some(AgentMessage {
+                        text: format!(
+                            "Só {} piscadas/min há {} min. Pisca devagar algumas vezes e olha para longe.",
+                            period.mean_rate.round(), period.duration_minutes
+                        ),
+                        category: "eye_care".into(),
+                        priority: "medium".into(),
+                        action: None,
+                    });
+                }
+            }
+
+            // 2. Count today's adherence and build context for Gemini
+            let (taken_names, pending_names): (Vec<&'static str>, Vec<&'static str>) = PROTOCOLS.iter()
+                .map(|p| p.name)
+                .partition(|name| taken(name));
+            let total = PROTOCOLS.len();
+            let taken_today = taken_names.len();
+
+            Ok::<_, String>(Ok(Snapshot {
+                adherence_pct: (taken_today as f64 / total as f64 * 100.0) as u32,
+                taken_today,
+                total,
+                supplement_names: PROTOCOLS.iter().map(|p| p.name).collect(),
+                taken_names,
+                pending_names,
+                exam_context,
+                early,
+            }))
+        }).await?;
+        let snapshot = match context {
+            Ok(snapshot) => snapshot,
+            Err(reply) => return Ok(reply),
+        };
+
+        // Return early supplement reminder if found
+        if let Some(msg) = snapshot.early {
+            return Ok(msg);
+        }
+
+        // Try Gemini for intelligent response, fallback to templates
+        if let Some(api_key) = &self.ctx.config.gemini_api_key {
+            match self.call_gemini(api_key, hour, &snapshot).await {
+                Ok(text) => {
+                    return Ok(AgentMessage {
+                        text,
+                        category: if snapshot.adherence_pct == 100 { "health_insight" } else { "calm_nudge" }.into(),
+                        priority: "low".into(),
+                        action: None,
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Gemini agent call failed, using fallback: {}", e);
+                    // Fall through to hardcoded
+                }
+            }
+        }
+
+        Ok(fallback_message(hour, &snapshot))
+    }
+
+    /// Call Gemini for contextual agent intelligence
+    async fn call_gemini(&self, api_key: &str, hour: u32, snapshot: &Snapshot) -> Result<String, String> {
+        let time_period = match hour {
+            6..=9 => "manhã (despertar)",
+            10..=13 => "meio do dia (foco)",
+            14..=17 => "tarde (manutenção)",
+            18..=21 => "noite (desaceleração)",
+            22..=23 => "noite tardia (preparar sono)",
+            _ => "madrugada",
+        };
+
+        let prompt = format!(
+            "Tu és o HoloSelf, um agente de saúde pessoal calmo e direto (estilo Jarvis). \
+             Responde em Português (PT-BR). Máximo 2 frases curtas. Sem emojis. Tom: calmo, preciso, encorajador.\n\n\
+             Contexto actual:\n\
+             - Hora: {}h ({})\n\
+             - Aderência hoje: {}%\n\
+             - Suplementos tomados: {}\n\
+             - Pendentes: {}\n\
+             - Protocolo completo: {}\n\
+             - {}\n\n\
+             Dá uma mensagem contextual breve baseada neste estado. \
+             Se tudo está em dia, encoraja. Se há pendentes, lembra com calma. \
+             Se é noite, sugere desacelerar. Nunca alarmar.",
+            hour, time_period, snapshot.adherence_pct,
+            if snapshot.taken_names.is_empty() { "nenhum".to_string() } else { snapshot.taken_names.join(", ") },
+            if snapshot.pending_names.is_empty() { "nenhum".to_string() } else { snapshot.pending_names.join(", ") },
+            snapshot.supplement_names.join(", "),
+            snapshot.exam_context,
+        );
+
+        let body = serde_json::json!({
+            "contents": [{"parts": [{"text": prompt}]}],
+            "generationConfig": {
+                "maxOutputTokens": 100,
+                "temperature": 0.7,
+            }
+        });
+        let text = gemini::generate(self.ctx.http.as_ref(), api_key, body, std::time::Duration::from_secs(30)).await?;
+        Ok(text.trim().to_string())
+    }
+
+    /// Execute an agent-suggested action the user confirmed; audited with `source`
+    pub async fn execute_action(&self, action_type: String, payload: serde_json::Value, source: &'static str) -> Result<String, String> {
+        let now = time::format_utc(self.ctx.clock.now());
+        self.ctx.db.write(move |db| {
+            match action_type.as_str() {
+                "log_supplement" => {
+                    let name = payload["name"].as_str().unwrap_or("Unknown");
+                    let entry = SupplementEntry {
+                        id: None,
+                        name: name.to_string(),
+                        dosage: payload["dosage"].as_str().unwrap_or("").to_string(),
+                        taken_at: now.clone(),
+                        category: payload["category"].as_str().unwrap_or("as_needed").to_string(),
+                        notes: None,
+                    };
+                    db.transaction(|db| {
+                        let id = db.insert_supplement(&entry)?;
+                        records::audit_insert(db, &now, "supplements", id, source)
+                    }).map_err(|e| e.to_string())?;
+                    Ok(format!("{} registado com sucesso.", name))
+                }
+                "log_vital" => {
+                    let vital_type = payload["type"].as_str().unwrap_or("unknown");
+                    let value = payload["value"].as_f64().unwrap_or(0.0);
+                    let entry = VitalEntry {
+                        id: None,
+                        vital_type: vital_type.to_string(),
+                        value,
+                        unit: payload["unit"].as_str().unwrap_or("").to_string(),
+                        recorded_at: now.clone(),
+                        source: "agent".to_string(),
+                    };
+                    db.transaction(|db| {
+                        let id = db.insert_vital(&entry)?;
+                        records::audit_insert(db, &now, "vitals", id, source)
+                    }).map_err(|e| e.to_string())?;
+                    Ok(format!("{} registado: {}", vital_type, value))
+                }
+                _ => Err(format!("Ação desconhecida: {}", action_type)),
+            }
+        }).await
+    }
+
+    /// Store a transcript as voice input; the next message answers it
+    pub async fn remember_voice_input(&self, transcript: String) -> Result<(), String> {
+        let now = time::format_utc(self.ctx.clock.now());
+        self.ctx.db.write(move |db| db.execute(
+            "INSERT INTO agent_memory (key, value, timestamp) VALUES ('voice_input', ?1, ?2)",
+            rusqlite::params![&transcript, &now],
+        )).await.map(|_| ())
+    }
+}
+
+/// Whether `ended_at` is at most `minutes` before `now`
+fn ended_within(ended_at: &str, now: DateTime<Utc>, minutes: i64, tz: chrono_tz::Tz) -> bool {
+    time::parse_instant(ended_at, tz).is_some_and(|end| now - end <= chrono::Duration::minutes(minutes))
+}
+
+/// Hardcoded contextual messages when Gemini is unavailable
+fn fallback_message(hour: u32, snapshot: &Snapshot) -> AgentMessage {
+    let (text, category) = if snapshot.taken_today == snapshot.total {
+        (
+            format!(
+                "Protocolo 100% hoje. {} de {} suplementos registados. Sistema em carga total.",
+                snapshot.taken_today, snapshot.total
+            ),
+            "health_insight",
+        )
+    } else if (6..9).contains(&hour) {
+        (
+            format!("Bom dia. Aderência: {}%. Pendentes: {}.", snapshot.adherence_pct, snapshot.pending_names.join(", ")),
+            "calm_nudge",
+        )
+    } else if (20..23).contains(&hour) {
+        (
+            format!("Fase noturna. Aderência: {}%. {}", snapshot.adherence_pct, snapshot.exam_context),
+            "calm_nudge",
+        )
+    } else {
+        (
+            format!(
+                "Sistema estável. Aderência: {}%. {}",
+                snapshot.adherence_pct,
+                if !snapshot.pending_names.is_empty() {
+                    format!("Pendentes: {}.", snapshot.pending_names.join(", "))
+                } else {
+                    "Tudo em dia.".to_string()
+                }
+            ),
+            "health_insight",
+        )
+    };
+    AgentMessage {
+        text,
+        category: category.into(),
+        priority: "low".into(),
+        action: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::context::testing::{context_at, gemini_reply};
+    use crate::services::gemini::GENERATE_URL;
+
+    #[tokio::test]
+    async fn reminds_the_pending_supplement_of_the_current_window() {
+        // 09:00 in Lisbon (summer time)
+        let (ctx, _, _) = context_at("2026-07-01T08:00:00Z", None);
+        let agent = AgentService::new(&ctx);
+
+        let message = agent.next_message(false).await.unwrap().unwrap();
+        assert_eq!(message.category, "supplement_reminder");
+        assert!(message.text.starts_with("Winfit"));
+
+        let action = message.action.unwrap();
+        agent.execute_action(action.action_type, action.payload, "agent").await.unwrap();
+        let message = agent.next_message(false).await.unwrap().unwrap();
+        assert!(message.text.starts_with("Vitamina D3"));
+    }
+
+    #[tokio::test]
+    async fn defers_while_away_at_the_frozen_time() {
+        let (ctx, _, _) = context_at("2026-07-01T08:00:00Z", None);
+        assert!(AgentService::new(&ctx).next_message(true).await.unwrap().is_none());
+
+        let deferred: (i64, String, String) = ctx.db.read(|db| db.query_row(
+            "SELECT COUNT(*), MAX(created_at), MAX(category) FROM deferred_messages",
+            &[],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )).await.unwrap();
+        assert_eq!(deferred, (1, "2026-07-01T08:00:00Z".to_string(), "supplement_reminder".to_string()));
+    }
+
+    #[tokio::test]
+    async fn answers_recent_voice_input_first() {
+        let (ctx, clock, _) = context_at("2026-07-01T08:00:00Z", None);
+        let agent = AgentService::new(&ctx);
+        agent.remember_voice_input("Como estou hoje?".to_string()).await.unwrap();
+
+        clock.advance(chrono::Duration::seconds(10));
+        let message = agent.next_message(true).await.unwrap().unwrap();
+        assert_eq!((message.category.as_str(), message.priority.as_str()), ("voice_response", "high"));
+        assert!(message.text.starts_with("Relatório rápido: 0 de 5"));
+
+        clock.advance(chrono::Duration::seconds(VOICE_INPUT_WINDOW_SECONDS));
+        let message = agent.next_message(false).await.unwrap().unwrap();
+        assert_eq!(message.category, "supplement_reminder");
+    }
+
+    #[tokio::test]
+    async fn at_22h_with_three_of_five_taken_reminds_the_magnesium() {
+        let (ctx, clock, _) = context_at("2026-07-01T08:00:00Z", None);
+        let agent = AgentService::new(&ctx);
+        for name in ["Winfit", "Vitamina D3", "Ómega 3"] {
+            let payload = serde_json::json!({ "name": name, "dosage": "1", "category": "morning" });
+            agent.execute_action("log_supplement".to_string(), payload, "agent").await.unwrap();
+        }
+
+        // 22:00 in Lisbon
+        clock.set(chrono::DateTime::parse_from_rfc3339("2026-07-01T21:00:00Z").unwrap().with_timezone(&Utc));
+        let message = agent.next_message(false).await.unwrap().unwrap();
+        assert_eq!(message.category, "supplement_reminder");
+        assert!(message.text.starts_with("Magnésio Bisglicinato"));
+
+        let action = message.action.unwrap();
+        agent.execute_action(action.action_type, action.payload, "agent").await.unwrap();
+        let message = agent.next_message(false).await.unwrap().unwrap();
+        assert!(message.text.starts_with("Fase noturna. Aderência: 80%."));
+    }
+
+    #[tokio::test]
+    async fn asks_gemini_outside_the_supplement_windows() {
+        // 17:00 in Lisbon: no protocol window is open
+        let (ctx, _, http) = context_at("2026-07-01T16:00:00Z", Some("test-key"));
+        http.reply(Ok(gemini_reply("  Tarde calma. Lembra o Ómega 3.  ")));
+
+        let message = AgentService::new(&ctx).next_message(false).await.unwrap().unwrap();
+        assert_eq!(message.text, "Tarde calma. Lembra o Ómega 3.");
+        assert_eq!(message.category, "calm_nudge");
+
+        let sent = http.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, GENERATE_URL);
+        let prompt = sent[0].1["contents"][0]["parts"][0]["text"].as_str().unwrap();
+        assert!(prompt.contains("Hora: 17h (tarde (manutenção))"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_templates_when_gemini_fails() {
+        let (ctx, _, http) = context_at("2026-07-01T16:00:00Z", Some("test-key"));
+        http.reply(Err("HTTP 503".to_string()));
+
+        let message = AgentService::new(&ctx).next_message(false).await.unwrap().unwrap();
+        assert!(message.text.starts_with("Sistema estável. Aderência: 0%."));
+        assert_eq!(message.category, "health_insight");
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_actions() {
+        let (ctx, _, _) = context_at("2026-07-01T16:00:00Z", None);
+        let err = AgentService::new(&ctx)
+            .execute_action("delete_everything".to_string(), serde_json::json!({}), "agent")
+            .await
+            .unwrap_err();
+        assert_eq!(err, "Ação desconhecida: delete_everything");
+    }
+}
@@ -0,0 +1,486 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Backup archive: a tar holding manifest.json (always first), holoself.db, settings.json
+// and .env. Without a passphrase it is a plain tar any archiver can open. With one, the
+// tar is wrapped in an encrypted stream:
+//   "HSBKENC1" | salt (16) | nonce prefix (19) | sealed chunks
+// The key is Argon2id(passphrase, salt); each 64 KiB chunk is sealed with
+// XChaCha20-Poly1305 in the STREAM construction, so truncated or reordered files fail.
+
+pub const ARCHIVE_FORMAT: u32 = 1;
+pub const EXTENSION: &str = "hsbak";
+pub const MANIFEST_NAME: &str = "manifest.json";
+pub const DB_FILE: &str = "holoself.db";
+pub const SETTINGS_FILE: &str = "settings.json";
+pub const ENV_FILE: &str = ".env";
+/// Everything besides the manifest a backup may contain; other entries are rejected
+const ALLOWED_FILES: &[&str] = &[DB_FILE, SETTINGS_FILE, ENV_FILE];
+
+const MAGIC: &[u8; 8] = b"HSBKENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 19; // XChaCha20 nonce minus the 5-byte STREAM counter
+const CHUNK: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub schema_version: i64,
+    pub reason: String, // manual | scheduled | pre-restore
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    pub fn has(&self, name: &str) -> bool {
+        self.files.iter().any(|f| f.name == name)
+    }
+}
+
+/// Size and SHA-256 (hex) of a file
+pub fn checksum(path: &Path) -> io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Falha ao derivar a chave: {}", e))?;
+    Ok(key)
+}
+
+/// Whether a backup file needs a passphrase
+pub fn is_encrypted(path: &Path) -> io::Result<bool> {
+    let mut head = [0u8; 8];
+    match File::open(path)?.read_exact(&mut head) {
+        Ok(()) => Ok(&head == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Seals everything written into 64 KiB STREAM chunks; `finish` seals the last one
+struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<XChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    fn new(mut inner: W, passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+        inner.write_all(MAGIC)
+            .and_then(|_| inner.write_all(&salt))
+            .and_then(|_| inner.write_all(&nonce))
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            inner,
+            encryptor: Some(EncryptorBE32::from_aead(cipher, nonce.as_ref().into())),
+            buffer: Vec::with_capacity(CHUNK),
+        })
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        let encryptor = self.encryptor.take().ok_or_else(|| io::Error::other("stream already finished"))?;
+        let sealed = encryptor.encrypt_last(self.buffer.as_slice()).map_err(|_| io::Error::other("encryption failed"))?;
+        self.inner.write_all(&sealed)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // A full chunk is sealed only once more data arrives: the last one is sealed differently
+        if self.buffer.len() == CHUNK {
+            let encryptor = self.encryptor.as_mut().ok_or_else(|| io::Error::other("stream already finished"))?;
+            let sealed = encryptor.encrypt_next(self.buffer.as_slice()).map_err(|_| io::Error::other("encryption failed"))?;
+            self.inner.write_all(&sealed)?;
+            self.buffer.clear();
+        }
+        let take = data.len().min(CHUNK - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..take]);
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads up to one sealed chunk; shorter only at end of file
+fn read_block<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut block = Vec::with_capacity(CHUNK + TAG_LEN);
+    reader.take((CHUNK + TAG_LEN) as u64).read_to_end(&mut block)?;
+    Ok(block)
+}
+
+/// Opens the STREAM written by `EncryptWriter`, one chunk ahead to spot the last one
+struct DecryptReader<R: Read> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    pending: Vec<u8>, // next sealed chunk, empty at end of stream
+    plain: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    /// Expects the magic to be consumed already. Decrypts the first chunk right away so a
+    /// wrong passphrase is reported as such instead of as a corrupt archive.
+    fn new(mut inner: R, passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        inner.read_exact(&mut salt)
+            .and_then(|_| inner.read_exact(&mut nonce))
+            .map_err(|_| "Cópia de segurança truncada.".to_string())?;
+        let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+        let pending = read_block(&mut inner).map_err(|e| e.to_string())?;
+        if pending.is_empty() {
+            return Err("Cópia de segurança truncada.".to_string());
+        }
+        let mut reader = Self {
+            inner,
+            decryptor: Some(DecryptorBE32::from_aead(cipher, nonce.as_ref().into())),
+            pending,
+            plain: Vec::new(),
+            position: 0,
+        };
+        reader.refill().map_err(|_| "Palavra-passe errada ou cópia corrompida.".to_string())?;
+        Ok(reader)
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(()); // end of stream
+        }
+        let sealed = std::mem::take(&mut self.pending);
+        let next = read_block(&mut self.inner)?;
+        let corrupt = || io::Error::new(io::ErrorKind::InvalidData, "backup chunk failed authentication");
+        self.plain = if next.is_empty() {
+            let decryptor = self.decryptor.take().ok_or_else(corrupt)?;
+            decryptor.decrypt_last(sealed.as_slice()).map_err(|_| corrupt())?
+        } else {
+            let decryptor = self.decryptor.as_mut().ok_or_else(corrupt)?;
+            decryptor.decrypt_next(sealed.as_slice()).map_err(|_| corrupt())?
+        };
+        self.pending = next;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plain.len() {
+            if self.pending.is_empty() {
+                return Ok(0);
+            }
+            self.refill()?;
+        }
+        let n = out.len().min(self.plain.len() - self.position);
+        out[..n].copy_from_slice(&self.plain[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+enum Sink {
+    Plain(BufWriter<File>),
+    Encrypted(EncryptWriter<BufWriter<File>>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(w) => w.write(data),
+            Self::Encrypted(w) => w.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.flush(),
+            Self::Encrypted(w) => w.flush(),
+        }
+    }
+}
+
+fn tar_header(size: u64, mtime: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o600);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    header
+}
+
+/// Write the archive for `files` (name → staged path) described by `manifest`.
+/// Goes through a temp file so an interrupted backup never looks complete.
+pub fn write_archive(dest: &Path, manifest: &Manifest, files: &[(&str, PathBuf)], passphrase: Option<&str>) -> Result<u64, String> {
+    let tmp = dest.with_extension(format!("{}.tmp", EXTENSION));
+    let result = (|| {
+        let file = BufWriter::new(File::create(&tmp).map_err(|e| format!("Falha ao criar {:?}: {}", tmp, e))?);
+        let sink = match passphrase {
+            Some(passphrase) => Sink::Encrypted(EncryptWriter::new(file, passphrase)?),
+            None => Sink::Plain(file),
+        };
+        let mtime = chrono::DateTime::parse_from_rfc3339(&manifest.created_at)
+            .map(|t| t.timestamp().max(0) as u64)
+            .unwrap_or(0);
+
+        let mut builder = tar::Builder::new(sink);
+        let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+        builder.append_data(&mut tar_header(manifest_json.len() as u64, mtime), MANIFEST_NAME, manifest_json.as_slice())
+            .map_err(|e| e.to_string())?;
+        for (name, path) in files {
+            let file = File::open(path).map_err(|e| format!("Falha ao abrir {:?}: {}", path, e))?;
+            let size = file.metadata().map_err(|e| e.to_string())?.len();
+            builder.append_data(&mut tar_header(size, mtime), name, BufReader::new(file))
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut file = match builder.into_inner().map_err(|e| e.to_string())? {
+            Sink::Plain(file) => file,
+            Sink::Encrypted(writer) => writer.finish().map_err(|e| e.to_string())?,
+        };
+        file.flush().map_err(|e| e.to_string())?;
+        file.get_ref().sync_all().map_err(|e| e.to_string())
+    })();
+
+    match result {
+        Ok(()) => {
+            std::fs::rename(&tmp, dest).map_err(|e| format!("Falha ao gravar {:?}: {}", dest, e))?;
+            std::fs::metadata(dest).map(|m| m.len()).map_err(|e| e.to_string())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// Unpack a backup into `dir`, verifying every file against the manifest.
+/// `check` sees the manifest before anything is extracted and can refuse the archive.
+pub fn extract_archive<F>(src: &Path, passphrase: Option<&str>, dir: &Path, check: F) -> Result<Manifest, String>
+where
+    F: FnOnce(&Manifest) -> Result<(), String>,
+{
+    let mut file = BufReader::new(File::open(src).map_err(|e| format!("Falha ao abrir {}: {}", src.display(), e))?);
+    let encrypted = file.fill_buf().map_err(|e| e.to_string())?.starts_with(MAGIC);
+    let reader: Box<dyn Read> = if encrypted {
+        let passphrase = passphrase.ok_or("Esta cópia está cifrada: indique a palavra-passe.")?;
+        file.consume(MAGIC.len());
+        Box::new(DecryptReader::new(file, passphrase)?)
+    } else {
+        Box::new(file)
+    };
+
+    let invalid = |e: io::Error| format!("Cópia de segurança inválida ou corrompida: {}", e);
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = archive.entries().map_err(invalid)?;
+
+    let manifest: Manifest = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry.map_err(invalid)?;
+            if entry.path().map_err(invalid)?.to_str() != Some(MANIFEST_NAME) {
+                return Err("Cópia de segurança sem manifesto.".to_string());
+            }
+            serde_json::from_reader(&mut entry).map_err(|e| format!("Manifesto inválido: {}", e))?
+        }
+        None => return Err("Cópia de segurança vazia.".to_string()),
+    };
+    if manifest.format > ARCHIVE_FORMAT {
+        return Err(format!("Formato de cópia v{} não suportado por esta versão.", manifest.format));
+    }
+    check(&manifest)?;
+
+    let mut seen = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(invalid)?;
+        let name = entry.path().map_err(invalid)?.to_string_lossy().into_owned();
+        let expected = manifest.files.iter()
+            .find(|f| f.name == name && ALLOWED_FILES.contains(&f.name.as_str()))
+            .ok_or_else(|| format!("Ficheiro inesperado na cópia: {}", name))?;
+
+        let mut hasher = Sha256::new();
+        let mut out = BufWriter::new(File::create(dir.join(&expected.name)).map_err(|e| e.to_string())?);
+        let mut buffer = vec![0u8; CHUNK];
+        let mut size = 0u64;
+        loop {
+            let n = entry.read(&mut buffer).map_err(invalid)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            out.write_all(&buffer[..n]).map_err(|e| e.to_string())?;
+            size += n as u64;
+        }
+        out.flush().map_err(|e| e.to_string())?;
+        if size != expected.size || format!("{:x}", hasher.finalize()) != expected.sha256 {
+            return Err(format!("Checksum inválido para {}: a cópia está corrompida.", name));
+        }
+        seen.push(name);
+    }
+
+    if let Some(missing) = manifest.files.iter().find(|f| !seen.contains(&f.name)) {
+        return Err(format!("Falta {} na cópia de segurança.", missing.name));
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("holoself-backup-{}-{}", name, uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Bytes that differ per position, so misplaced chunks don't decrypt to the same data
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    fn seal(plain: &[u8], passphrase: &str) -> Vec<u8> {
+        let mut writer = EncryptWriter::new(Vec::new(), passphrase).unwrap();
+        writer.write_all(plain).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+        let body = sealed.strip_prefix(MAGIC.as_slice()).ok_or("no magic")?;
+        let mut reader = DecryptReader::new(body, passphrase)?;
+        let mut plain = Vec::new();
+        reader.read_to_end(&mut plain).map_err(|e| e.to_string())?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn stream_round_trips_across_chunk_boundaries() {
+        for len in [0, CHUNK, CHUNK + 1, 2 * CHUNK + 17] {
+            let plain = pattern(len);
+            let sealed = seal(&plain, "correct horse");
+            let chunks = len.div_ceil(CHUNK).max(1);
+            assert_eq!(sealed.len(), MAGIC.len() + SALT_LEN + NONCE_LEN + len + chunks * TAG_LEN, "len {}", len);
+            assert_eq!(open(&sealed, "correct horse").unwrap(), plain, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase_and_tampering_are_refused() {
+        let sealed = seal(&pattern(2 * CHUNK + 5), "correct horse");
+        assert_eq!(open(&sealed, "battery staple").unwrap_err(), "Palavra-passe errada ou cópia corrompida.");
+
+        // Dropping the final chunk leaves a stream whose last chunk was not sealed as last
+        let truncated = &sealed[..sealed.len() - (5 + TAG_LEN)];
+        assert!(open(truncated, "correct horse").is_err());
+
+        let mut flipped = sealed.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        assert!(open(&flipped, "correct horse").is_err());
+    }
+
+    fn manifest(files: &[(&str, PathBuf)]) -> Manifest {
+        Manifest {
+            format: ARCHIVE_FORMAT,
+            app_version: "0.2.0".to_string(),
+            created_at: "2026-10-18T03:15:00Z".to_string(),
+            schema_version: 12,
+            reason: "manual".to_string(),
+            files: files.iter()
+                .map(|(name, path)| {
+                    let (size, sha256) = checksum(path).unwrap();
+                    ManifestFile { name: name.to_string(), size, sha256 }
+                })
+                .collect(),
+        }
+    }
+
+    fn staged(dir: &Path) -> Vec<(&'static str, PathBuf)> {
+        let db = dir.join("staged.db");
+        let settings = dir.join("staged-settings.json");
+        std::fs::write(&db, pattern(CHUNK + 300)).unwrap();
+        std::fs::write(&settings, br#"{"backup_encrypt":true}"#).unwrap();
+        vec![(DB_FILE, db), (SETTINGS_FILE, settings)]
+    }
+
+    #[test]
+    fn archives_round_trip_plain_and_encrypted() {
+        let dir = temp_dir("archive");
+        let files = staged(&dir);
+        let manifest = manifest(&files);
+
+        for passphrase in [None, Some("correct horse")] {
+            let archive = dir.join(format!("backup-{}.{}", passphrase.is_some(), EXTENSION));
+            write_archive(&archive, &manifest, &files, passphrase).unwrap();
+            assert_eq!(is_encrypted(&archive).unwrap(), passphrase.is_some());
+
+            let out = temp_dir("extract");
+            let restored = extract_archive(&archive, passphrase, &out, |m| {
+                assert_eq!(m.files.len(), 2);
+                Ok(())
+            }).unwrap();
+            assert_eq!(restored.created_at, manifest.created_at);
+            for (name, path) in &files {
+                assert_eq!(std::fs::read(out.join(name)).unwrap(), std::fs::read(path).unwrap(), "{}", name);
+            }
+            std::fs::remove_dir_all(out).unwrap();
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn extraction_checks_passphrase_manifest_and_contents() {
+        let dir = temp_dir("checks");
+        let files = staged(&dir);
+        let out = temp_dir("checks-out");
+
+        let encrypted = dir.join("encrypted.hsbak");
+        write_archive(&encrypted, &manifest(&files), &files, Some("correct horse")).unwrap();
+        let err = extract_archive(&encrypted, None, &out, |_| Ok(())).unwrap_err();
+        assert_eq!(err, "Esta cópia está cifrada: indique a palavra-passe.");
+
+        // The check refuses before anything is written
+        let plain = dir.join("plain.hsbak");
+        write_archive(&plain, &manifest(&files), &files, None).unwrap();
+        let err = extract_archive(&plain, None, &out, |_| Err("too new".to_string())).unwrap_err();
+        assert_eq!(err, "too new");
+        assert_eq!(std::fs::read_dir(&out).unwrap().count(), 0);
+
+        // A manifest that disagrees with the contents is a corrupt archive
+        let mut lying = manifest(&files);
+        lying.files[0].sha256 = "0".repeat(64);
+        let corrupt = dir.join("corrupt.hsbak");
+        write_archive(&corrupt, &lying, &files, None).unwrap();
+        let err = extract_archive(&corrupt, None, &out, |_| Ok(())).unwrap_err();
+        assert_eq!(err, format!("Checksum inválido para {}: a cópia está corrompida.", DB_FILE));
+
+        std::fs::remove_dir_all(dir).unwrap();
+        std::fs::remove_dir_all(out).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-/// Cartesia.ai Sonic API — Real-time Text-to-Speech
-/// Sub-100ms latency voice synthesis for the HoloSelf agent
+// Cartesia.ai Sonic API — Real-time Text-to-Speech
+// Sub-100ms latency voice synthesis for the HoloSelf agent
 
 const CARTESIA_API_URL: &str = "https://api.cartesia.ai/tts/bytes";
 
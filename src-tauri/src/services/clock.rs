@@ -0,0 +1,87 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::sync::{Arc, Mutex};
+
+// Time source for the whole backend. The app runs on the system clock; tests and the agent
+// simulation freeze time and move it by hand, so "what happens at 22h" doesn't depend on
+// when they run. Only raw keyboard event stamps and scratch file names bypass it.
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The user's calendar date in `tz`
+    fn today(&self, tz: Tz) -> NaiveDate {
+        self.now().with_timezone(&tz).date_naive()
+    }
+}
+
+/// Shared clock handle held by services
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The app's clock, managed by Tauri; every command and background loop reads time from it
+pub struct ClockState(pub SharedClock);
+
+impl Default for ClockState {
+    fn default() -> Self {
+        ClockState(Arc::new(SystemClock))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Stands still until `set` or `advance`
+#[derive(Debug)]
+pub struct FrozenClock(Mutex<DateTime<Utc>>);
+
+impl FrozenClock {
+    pub fn new(at: DateTime<Utc>) -> Self {
+        FrozenClock(Mutex::new(at))
+    }
+
+    pub fn set(&self, at: DateTime<Utc>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = at;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for FrozenClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frozen_clock_only_moves_when_told() {
+        let start = DateTime::parse_from_rfc3339("2026-03-01T23:30:00Z").unwrap().with_timezone(&Utc);
+        let clock = FrozenClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::minutes(45));
+        assert_eq!(clock.now(), start + Duration::minutes(45));
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn today_is_the_users_local_date() {
+        let clock = FrozenClock::new(DateTime::parse_from_rfc3339("2026-03-01T23:30:00Z").unwrap().with_timezone(&Utc));
+        assert_eq!(clock.today(chrono_tz::UTC).to_string(), "2026-03-01");
+        assert_eq!(clock.today(chrono_tz::Europe::Berlin).to_string(), "2026-03-02");
+        assert_eq!(clock.today(chrono_tz::America::Sao_Paulo).to_string(), "2026-03-01");
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::commands::settings::{self, AppSettings};
+use crate::db::{Database, DbState};
+use crate::services::clock::SharedClock;
+use crate::services::http::{ReqwestClient, SharedHttp};
+use crate::services::secrets;
+
+// What the core services run against: the database, a clock, the user's configuration and
+// an HTTP client. Tauri commands and the CLI build one per call from the live app state;
+// tests build one over an in-memory database with a frozen clock and canned HTTP replies.
+
+/// User configuration as the services see it
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub settings: AppSettings,
+    /// None when no key is configured; features that need Gemini fall back or fail
+    pub gemini_api_key: Option<String>,
+    pub tools: ToolPaths,
+}
+
+impl Config {
+    /// Settings plus the API keys from the secret store
+    pub fn load(settings: AppSettings) -> Self {
+        Config {
+            gemini_api_key: secrets::get(secrets::GEMINI_API_KEY),
+            tools: ToolPaths::from_settings(&settings),
+            settings,
+        }
+    }
+
+    /// The user's zone for "today" calculations
+    pub fn tz(&self) -> chrono_tz::Tz {
+        settings::user_timezone(&self.settings)
+    }
+}
+
+/// Speech tools recorded by the installer; None means search the usual places
+#[derive(Debug, Clone, Default)]
+pub struct ToolPaths {
+    pub whisper_binary: Option<PathBuf>,
+    pub whisper_model: Option<PathBuf>,
+    pub ffmpeg: Option<PathBuf>,
+}
+
+impl ToolPaths {
+    fn from_settings(settings: &AppSettings) -> Self {
+        let path = |raw: &str| (!raw.is_empty()).then(|| PathBuf::from(raw));
+        ToolPaths {
+            whisper_binary: path(&settings.whisper_binary_path),
+            whisper_model: path(&settings.whisper_model_path),
+            ffmpeg: path(&settings.ffmpeg_path),
+        }
+    }
+}
+
+/// Cheap to clone: every part is shared
+#[derive(Clone)]
+pub struct ServiceContext {
+    pub db: DbState,
+    pub clock: SharedClock,
+    pub config: Arc<Config>,
+    pub http: SharedHttp,
+}
+
+impl ServiceContext {
+    pub fn new(db: DbState, clock: SharedClock, config: Config, http: SharedHttp) -> Self {
+        ServiceContext { db, clock, config: Arc::new(config), http }
+    }
+
+    /// Production wiring: the app's clock, reqwest, keys from the secret store
+    pub fn live(db: DbState, clock: SharedClock, settings: AppSettings) -> Self {
+        Self::new(db, clock, Config::load(settings), Arc::new(ReqwestClient::default()))
+    }
+
+    /// Fresh migrated database in memory, for tests and simulations
+    pub fn in_memory(clock: SharedClock, config: Config, http: SharedHttp) -> Result<Self, String> {
+        let db = Database::open_in_memory().map_err(|e| e.to_string())?;
+        db.run_migrations(config.tz(), clock.now())?;
+        Ok(Self::new(DbState::single(db), clock, config, http))
+    }
+
+    pub fn tz(&self) -> chrono_tz::Tz {
+        self.config.tz()
+    }
+}
+
+/// Fixtures shared by the unit tests and tests/services.rs (feature `test-util`)
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing {
+    use super::*;
+    use crate::commands::health::SupplementEntry;
+    use crate::services::clock::FrozenClock;
+    use crate::services::http::CannedHttp;
+
+    pub fn instant(raw: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(raw).unwrap().with_timezone(&chrono::Utc)
+    }
+
+    /// User in `timezone` on an in-memory database, frozen at `at` (RFC 3339), HTTP answered by the returned `CannedHttp`
+    pub fn context_in(at: &str, timezone: &str, gemini_api_key: Option<&str>) -> (ServiceContext, Arc<FrozenClock>, Arc<CannedHttp>) {
+        let clock = Arc::new(FrozenClock::new(instant(at)));
+        let http = Arc::new(CannedHttp::offline());
+        let config = Config {
+            settings: AppSettings { timezone: timezone.to_string(), ..AppSettings::default() },
+            gemini_api_key: gemini_api_key.map(str::to_string),
+            tools: ToolPaths::default(),
+        };
+        let ctx = ServiceContext::in_memory(clock.clone(), config, http.clone()).unwrap();
+        (ctx, clock, http)
+    }
+
+    /// `context_in` for a Lisbon user
+    pub fn context_at(at: &str, gemini_api_key: Option<&str>) -> (ServiceContext, Arc<FrozenClock>, Arc<CannedHttp>) {
+        context_in(at, "Europe/Lisbon", gemini_api_key)
+    }
+
+    /// A Gemini generateContent response carrying `text`
+    pub fn gemini_reply(text: &str) -> serde_json::Value {
+        serde_json::json!({ "candidates": [{ "content": { "parts": [{ "text": text }] } }] })
+    }
+
+    pub fn supplement(name: &str, taken_at: &str) -> SupplementEntry {
+        SupplementEntry {
+            id: None,
+            name: name.to_string(),
+            dosage: "1".to_string(),
+            taken_at: taken_at.to_string(),
+            category: "morning".to_string(),
+            notes: None,
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Eye strain: blink rate drops from ~15-20/min at rest to well under 10/min during screen
+// work (computer vision syndrome). The 20-20-20 rule — every 20 minutes, look at something
+// 20 feet away for 20 seconds — is the standard counter-measure.
+
+pub const LOW_BLINK_RATE: f64 = 10.0;          // blinks/min
+pub const BLINK_SMOOTHING_MINUTES: i64 = 3;    // rolling mean before comparing to the threshold
+pub const LOW_BLINK_MIN_MINUTES: i64 = 10;     // shorter dips are normal focus
+pub const LOW_BLINK_MAX_GAP_MINUTES: i64 = 3;  // missing minutes that don't split a period
+
+pub const RULE_INTERVAL_MINUTES: i64 = 20;     // screen time before a reminder
+pub const RULE_REST_SECONDS: i64 = 20;         // shortest look-away that counts
+pub const REMINDER_GRACE_MINUTES: i64 = 5;     // time to comply before it counts as missed
+
+/// One stored per-minute blink rate
+#[derive(Debug, Clone, Copy)]
+pub struct BlinkPoint {
+    pub time: DateTime<Utc>,
+    pub rate: f64,
+}
+
+/// Sustained stretch with a smoothed blink rate below `LOW_BLINK_RATE`
+#[derive(Debug, Serialize, Clone)]
+pub struct LowBlinkPeriod {
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration_minutes: i64,
+    pub mean_rate: f64,
+    pub min_rate: f64,
+}
+
+/// Group low-blink minutes into periods. `points` must be sorted by time.
+pub fn detect_low_blink_periods(points: &[BlinkPoint]) -> Vec<LowBlinkPeriod> {
+    struct Open {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        rates: Vec<f64>,
+    }
+
+    let close = |open: Open, periods: &mut Vec<LowBlinkPeriod>| {
+        let duration = (open.end - open.start).num_minutes() + 1;
+        if duration < LOW_BLINK_MIN_MINUTES {
+            return;
+        }
+        let mean = open.rates.iter().sum::<f64>() / open.rates.len() as f64;
+        let min = open.rates.iter().copied().fold(f64::INFINITY, f64::min);
+        periods.push(LowBlinkPeriod {
+            started_at: crate::services::time::format_utc(open.start),
+            ended_at: crate::services::time::format_utc(open.end),
+            duration_minutes: duration,
+            mean_rate: (mean * 10.0).round() / 10.0,
+            min_rate: min,
+        });
+    };
+
+    let mut periods = Vec::new();
+    let mut open: Option<Open> = None;
+    let (mut window_start, mut window_sum) = (0usize, 0.0);
+
+    for (i, point) in points.iter().enumerate() {
+        window_sum += point.rate;
+        while points[window_start].time <= point.time - chrono::Duration::minutes(BLINK_SMOOTHING_MINUTES) {
+            window_sum -= points[window_start].rate;
+            window_start += 1;
+        }
+        let smoothed = window_sum / (i + 1 - window_start) as f64;
+        let low = smoothed < LOW_BLINK_RATE;
+
+        if let Some(o) = open.take() {
+            if (point.time - o.end).num_minutes() > LOW_BLINK_MAX_GAP_MINUTES || !low {
+                close(o, &mut periods);
+            } else {
+                open = Some(o);
+            }
+        }
+
+        if low {
+            let o = open.get_or_insert_with(|| Open { start: point.time, end: point.time, rates: Vec::new() });
+            o.end = point.time;
+            o.rates.push(point.rate);
+        }
+    }
+    if let Some(o) = open {
+        close(o, &mut periods);
+    }
+    periods
+}
+
+/// What a 20-20-20 tick decided
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleEvent {
+    Remind,
+    Complied,
+    Missed,
+}
+
+/// 20-20-20 rule state machine, ticked every ~30 s by the backend.
+/// Any look-away (a logged eye rest, a break, or leaving the screen) restarts the clock.
+#[derive(Debug, Default)]
+pub struct TwentyTwentyEngine {
+    work_started: Option<DateTime<Utc>>,
+    last_rest_seen: Option<DateTime<Utc>>,
+    pending_since: Option<DateTime<Utc>>,
+}
+
+impl TwentyTwentyEngine {
+    /// `at_screen`: the user is currently in front of the screen.
+    /// `last_rest_end`: end of the latest look-away of at least `RULE_REST_SECONDS`.
+    pub fn tick(&mut self, now: DateTime<Utc>, at_screen: bool, last_rest_end: Option<DateTime<Utc>>) -> Option<RuleEvent> {
+        let new_rest = last_rest_end.filter(|end| self.last_rest_seen.is_none_or(|seen| *end > seen));
+        if let Some(rest_end) = new_rest {
+            self.last_rest_seen = Some(rest_end);
+            // A rest from before we started tracking (e.g. yesterday) must not backdate the clock
+            self.work_started = at_screen.then(|| self.work_started.map_or(now, |s| s.max(rest_end.min(now))));
+            if self.pending_since.take().is_some() {
+                return Some(RuleEvent::Complied);
+            }
+        }
+
+        if !at_screen {
+            self.work_started = None;
+            return self.pending_since.take().map(|_| RuleEvent::Complied);
+        }
+
+        let started = *self.work_started.get_or_insert(now);
+        match self.pending_since {
+            Some(since) if now - since >= chrono::Duration::minutes(REMINDER_GRACE_MINUTES) => {
+                self.pending_since = None;
+                self.work_started = Some(now);
+                Some(RuleEvent::Missed)
+            }
+            None if now - started >= chrono::Duration::minutes(RULE_INTERVAL_MINUTES) => {
+                self.pending_since = Some(now);
+                Some(RuleEvent::Remind)
+            }
+            _ => None,
+        }
+    }
+
+    /// Minutes of uninterrupted screen time so far
+    pub fn screen_minutes(&self, now: DateTime<Utc>) -> i64 {
+        self.work_started.map(|s| (now - s).num_minutes()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::clock::{Clock, FrozenClock};
+    use chrono::Duration;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-03-02T09:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    /// One reading per minute from the clock's current time, leaving it after the last one
+    fn minutes(clock: &FrozenClock, rates: &[f64]) -> Vec<BlinkPoint> {
+        rates
+            .iter()
+            .map(|&rate| {
+                let point = BlinkPoint { time: clock.now(), rate };
+                clock.advance(Duration::minutes(1));
+                point
+            })
+            .collect()
+    }
+
+    /// Tick every 30 s for `span`, returning each event with the minutes elapsed since `from`
+    fn run(
+        engine: &mut TwentyTwentyEngine,
+        clock: &FrozenClock,
+        from: DateTime<Utc>,
+        span: Duration,
+        at_screen: bool,
+        last_rest_end: Option<DateTime<Utc>>,
+    ) -> Vec<(f64, RuleEvent)> {
+        let until = clock.now() + span;
+        let mut events = Vec::new();
+        while clock.now() < until {
+            clock.advance(Duration::seconds(30));
+            if let Some(event) = engine.tick(clock.now(), at_screen, last_rest_end) {
+                events.push(((clock.now() - from).num_seconds() as f64 / 60.0, event));
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn sustained_low_blinking_becomes_a_period_once_the_mean_drops() {
+        let clock = FrozenClock::new(start());
+        let mut rates = vec![16.0; 5];
+        rates.extend([6.0; 15]);
+        let points = minutes(&clock, &rates);
+
+        let periods = detect_low_blink_periods(&points);
+        assert_eq!(periods.len(), 1);
+        // The 3-minute mean only falls below 10/min on the second low minute
+        assert_eq!(periods[0].started_at, "2026-03-02T09:06:00Z");
+        assert_eq!(periods[0].ended_at, "2026-03-02T09:19:00Z");
+        assert_eq!(periods[0].duration_minutes, 14);
+        assert_eq!(periods[0].mean_rate, 6.0);
+        assert_eq!(periods[0].min_rate, 6.0);
+    }
+
+    #[test]
+    fn short_dips_are_normal_focus() {
+        let clock = FrozenClock::new(start());
+        let mut rates = vec![16.0; 5];
+        rates.extend([4.0; 8]);
+        rates.extend([16.0; 5]);
+        assert!(detect_low_blink_periods(&minutes(&clock, &rates)).is_empty());
+
+        let clock = FrozenClock::new(start());
+        assert!(detect_low_blink_periods(&minutes(&clock, &[16.0, 16.0, 2.0, 16.0, 16.0])).is_empty());
+    }
+
+    #[test]
+    fn missing_minutes_only_split_a_period_past_the_gap_limit() {
+        let clock = FrozenClock::new(start());
+        let mut points = minutes(&clock, &[5.0; 12]);
+        clock.advance(Duration::minutes(LOW_BLINK_MAX_GAP_MINUTES - 1));
+        points.extend(minutes(&clock, &[5.0; 12]));
+        let periods = detect_low_blink_periods(&points);
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].duration_minutes, 26);
+
+        let clock = FrozenClock::new(start());
+        let mut points = minutes(&clock, &[5.0; 12]);
+        clock.advance(Duration::minutes(LOW_BLINK_MAX_GAP_MINUTES + 1));
+        points.extend(minutes(&clock, &[5.0; 12]));
+        let periods = detect_low_blink_periods(&points);
+        assert_eq!(periods.iter().map(|p| p.duration_minutes).collect::<Vec<_>>(), vec![12, 12]);
+        assert_eq!(periods[1].started_at, "2026-03-02T09:16:00Z");
+    }
+
+    #[test]
+    fn reminds_after_twenty_minutes_at_the_screen() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        assert_eq!(engine.tick(clock.now(), true, None), None);
+
+        let events = run(&mut engine, &clock, start(), Duration::minutes(22), true, None);
+        assert_eq!(events, vec![(20.0, RuleEvent::Remind)]);
+        assert_eq!(engine.screen_minutes(clock.now()), 22);
+    }
+
+    #[test]
+    fn a_rest_before_the_reminder_restarts_the_interval() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        engine.tick(clock.now(), true, None);
+
+        assert!(run(&mut engine, &clock, start(), Duration::minutes(10), true, None).is_empty());
+        let rest_end = clock.now();
+        let events = run(&mut engine, &clock, start(), Duration::minutes(21), true, Some(rest_end));
+        assert_eq!(events, vec![(30.0, RuleEvent::Remind)]);
+    }
+
+    #[test]
+    fn a_rest_after_the_reminder_counts_as_complied() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        engine.tick(clock.now(), true, None);
+        run(&mut engine, &clock, start(), Duration::minutes(21), true, None);
+
+        let rest_end = clock.now() + Duration::seconds(RULE_REST_SECONDS);
+        clock.set(rest_end);
+        assert_eq!(engine.tick(clock.now(), true, Some(rest_end)), Some(RuleEvent::Complied));
+        assert_eq!(engine.screen_minutes(clock.now()), 0);
+
+        // Nothing left pending: the next reminder is a full interval later
+        let from = clock.now();
+        let events = run(&mut engine, &clock, from, Duration::minutes(21), true, Some(rest_end));
+        assert_eq!(events, vec![(20.0, RuleEvent::Remind)]);
+    }
+
+    #[test]
+    fn leaving_the_screen_counts_as_complied() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        engine.tick(clock.now(), true, None);
+        run(&mut engine, &clock, start(), Duration::minutes(20), true, None);
+
+        clock.advance(Duration::seconds(30));
+        assert_eq!(engine.tick(clock.now(), false, None), Some(RuleEvent::Complied));
+        assert_eq!(engine.screen_minutes(clock.now()), 0);
+        assert!(run(&mut engine, &clock, start(), Duration::minutes(30), false, None).is_empty());
+    }
+
+    #[test]
+    fn an_ignored_reminder_is_missed_after_the_grace_period() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        engine.tick(clock.now(), true, None);
+
+        let events = run(&mut engine, &clock, start(), Duration::minutes(46), true, None);
+        assert_eq!(
+            events,
+            vec![
+                (20.0, RuleEvent::Remind),
+                (25.0, RuleEvent::Missed),
+                // The miss restarts the interval rather than nagging every tick
+                (45.0, RuleEvent::Remind),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_rest_from_before_tracking_does_not_backdate_the_interval() {
+        let clock = FrozenClock::new(start());
+        let mut engine = TwentyTwentyEngine::default();
+        let yesterday = Some(start() - Duration::hours(16));
+        assert_eq!(engine.tick(clock.now(), true, yesterday), None);
+
+        let events = run(&mut engine, &clock, start(), Duration::minutes(21), true, yesterday);
+        assert_eq!(events, vec![(20.0, RuleEvent::Remind)]);
+    }
+}
@@ -0,0 +1,455 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::commands::health::{SupplementEntry, VitalEntry};
+
+// FHIR R4 mapping of the personal health record:
+//   lab_results     → Observation (category laboratory, LOINC when the marker is known)
+//   vitals          → Observation (category vital-signs / activity, LOINC when one exists)
+//   supplements     → MedicationStatement
+//   health_schedule → ServiceRequest
+// Every resource also carries HoloSelf's own coding/extensions, so an exported Bundle
+// imports back into the same rows. Foreign bundles are read through the LOINC codes.
+
+pub const LOINC: &str = "http://loinc.org";
+pub const UCUM: &str = "http://unitsofmeasure.org";
+const OBSERVATION_CATEGORY: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+const INTERPRETATION: &str = "http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation";
+const VITAL_TYPE_SYSTEM: &str = "https://holoself.app/fhir/CodeSystem/vital-type";
+const LAB_MARKER_SYSTEM: &str = "https://holoself.app/fhir/CodeSystem/lab-marker";
+const SUPPLEMENT_CATEGORY_SYSTEM: &str = "https://holoself.app/fhir/CodeSystem/supplement-category";
+const EXT_SOURCE: &str = "https://holoself.app/fhir/StructureDefinition/source";
+const EXT_LAB_STATUS: &str = "https://holoself.app/fhir/StructureDefinition/lab-status";
+const EXT_PDF_SOURCE: &str = "https://holoself.app/fhir/StructureDefinition/pdf-source";
+const EXT_TRIGGERED_BY: &str = "https://holoself.app/fhir/StructureDefinition/triggered-by";
+pub const PATIENT_ID: &str = "holoself-user";
+
+/// A stored lab marker (`lab_results` row)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabRecord {
+    pub id: Option<i64>,
+    pub marker: String,
+    pub value: f64,
+    pub unit: String,
+    pub reference_range: Option<String>,
+    pub status: String, // normal | high | low | ...
+    pub lab_name: Option<String>,
+    pub test_date: Option<String>,
+    pub pdf_source: Option<String>,
+}
+
+/// A stored exam (`health_schedule` row)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRecord {
+    pub id: Option<i64>,
+    pub exam_type: String,
+    pub reason: String,
+    pub scheduled_date: String,
+    pub triggered_by: Option<String>,
+    pub completed: bool,
+}
+
+/// One row recovered from a Bundle
+#[derive(Debug)]
+pub enum Imported {
+    Vital(VitalEntry),
+    Supplement(SupplementEntry),
+    Lab(LabRecord),
+    Schedule(ScheduleRecord),
+}
+
+// (vital_type, LOINC code, display, UCUM unit, category)
+const VITAL_CODES: &[(&str, &str, &str, &str, &str)] = &[
+    ("heart_rate", "8867-4", "Heart rate", "/min", "vital-signs"),
+    ("resting_heart_rate", "40443-4", "Heart rate --resting", "/min", "vital-signs"),
+    ("hrv", "80404-7", "R-R interval.standard deviation (Heart rate variability)", "ms", "vital-signs"),
+    ("blood_pressure_systolic", "8480-6", "Systolic blood pressure", "mm[Hg]", "vital-signs"),
+    ("blood_pressure_diastolic", "8462-4", "Diastolic blood pressure", "mm[Hg]", "vital-signs"),
+    ("spo2", "59408-5", "Oxygen saturation in Arterial blood by Pulse oximetry", "%", "vital-signs"),
+    ("body_temperature", "8310-5", "Body temperature", "Cel", "vital-signs"),
+    ("weight", "29463-7", "Body weight", "kg", "vital-signs"),
+    ("body_fat", "41982-0", "Percentage of body fat Measured", "%", "vital-signs"),
+    ("glucose", "2339-0", "Glucose [Mass/volume] in Blood", "mg/dL", "laboratory"),
+    ("steps", "55423-8", "Number of steps in unspecified time Pedometer", "{steps}", "activity"),
+    ("sleep", "93832-4", "Sleep duration", "min", "activity"),
+];
+
+// (lowercase name fragments incl. Portuguese, LOINC code, display)
+const LAB_CODES: &[(&[&str], &str, &str)] = &[
+    (&["vitamin d", "vitamina d", "25-oh", "25(oh)"], "1989-3", "25-Hydroxyvitamin D3 [Mass/volume] in Serum or Plasma"),
+    (&["ferritin"], "2276-4", "Ferritin [Mass/volume] in Serum or Plasma"),
+    (&["tsh"], "3016-3", "Thyrotropin [Units/volume] in Serum or Plasma"),
+    (&["t4 livre", "free t4", "t4l"], "3024-7", "Thyroxine (T4) free [Mass/volume] in Serum or Plasma"),
+    (&["b12", "cobalamin"], "2132-9", "Cobalamin (Vitamin B12) [Mass/volume] in Serum or Plasma"),
+    (&["zinc", "zinco"], "5763-6", "Zinc [Mass/volume] in Serum or Plasma"),
+    (&["magnesium", "magnésio", "magnesio"], "19123-9", "Magnesium [Mass/volume] in Serum or Plasma"),
+    (&["hba1c", "a1c", "hemoglobina glicada", "glycated"], "4548-4", "Hemoglobin A1c/Hemoglobin.total in Blood"),
+    (&["hemoglobin", "hemoglobina", "haemoglobin"], "718-7", "Hemoglobin [Mass/volume] in Blood"),
+    (&["glucose", "glicose", "glicemia"], "2345-7", "Glucose [Mass/volume] in Serum or Plasma"),
+    (&["hdl"], "2085-9", "Cholesterol in HDL [Mass/volume] in Serum or Plasma"),
+    (&["ldl"], "13457-7", "Cholesterol in LDL [Mass/volume] in Serum or Plasma by calculation"),
+    (&["cholesterol", "colesterol"], "2093-3", "Cholesterol [Mass/volume] in Serum or Plasma"),
+    (&["triglycerides", "triglicer"], "2571-8", "Triglyceride [Mass/volume] in Serum or Plasma"),
+    (&["iron", "ferro"], "2498-4", "Iron [Mass/volume] in Serum or Plasma"),
+    (&["creatinine", "creatinina"], "2160-0", "Creatinine [Mass/volume] in Serum or Plasma"),
+    (&["alt", "tgp"], "1742-6", "Alanine aminotransferase [Enzymatic activity/volume] in Serum or Plasma"),
+    (&["ast", "tgo"], "1920-8", "Aspartate aminotransferase [Enzymatic activity/volume] in Serum or Plasma"),
+    (&["crp", "pcr", "c-reactive", "proteína c"], "1988-5", "C reactive protein [Mass/volume] in Serum or Plasma"),
+    (&["ana", "antinuclear", "fan"], "8061-4", "Nuclear Ab [Presence] in Serum"),
+];
+
+/// LOINC (code, display) for a lab marker name, matched on whole words
+pub fn lab_loinc(marker: &str) -> Option<(&'static str, &'static str)> {
+    let lower = marker.to_lowercase();
+    let words: Vec<&str> = lower.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '(' && c != ')').collect();
+    LAB_CODES.iter()
+        .find(|(names, ..)| names.iter().any(|n| {
+            // Short codes ("ana", "alt") must be whole words; longer names may be fragments
+            if n.len() <= 4 { words.contains(n) } else { lower.contains(n) }
+        }))
+        .map(|(_, code, display)| (*code, *display))
+}
+
+fn vital_code(vital_type: &str) -> Option<&'static (&'static str, &'static str, &'static str, &'static str, &'static str)> {
+    VITAL_CODES.iter().find(|c| c.0 == vital_type)
+}
+
+fn category(code: &str) -> Value {
+    json!([{ "coding": [{ "system": OBSERVATION_CATEGORY, "code": code }] }])
+}
+
+fn subject() -> Value {
+    json!({ "reference": format!("Patient/{}", PATIENT_ID) })
+}
+
+fn extension_string(url: &str, value: &str) -> Value {
+    json!({ "url": url, "valueString": value })
+}
+
+/// (low, high) of a lab reference range: "3.5 - 5.0", "3,5–5,0", "< 200", "> 30"
+pub fn reference_bounds(text: &str) -> (Option<f64>, Option<f64>) {
+    let normalized = text.replace(',', ".");
+    let numbers: Vec<f64> = normalized
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    match numbers.as_slice() {
+        [low, high] => (Some(*low), Some(*high)),
+        [bound] if normalized.contains('<') => (None, Some(*bound)),
+        [bound] if normalized.contains('>') => (Some(*bound), None),
+        _ => (None, None),
+    }
+}
+
+fn parse_reference_range(text: &str, unit: &str) -> Value {
+    let quantity = |v: f64| json!({ "value": v, "unit": unit });
+    let mut range = json!({ "text": text });
+    let (low, high) = reference_bounds(text);
+    if let Some(low) = low {
+        range["low"] = quantity(low);
+    }
+    if let Some(high) = high {
+        range["high"] = quantity(high);
+    }
+    range
+}
+
+fn interpretation(status: &str) -> Option<Value> {
+    let code = match status.to_lowercase().as_str() {
+        "normal" => "N",
+        "high" | "alto" | "elevado" => "H",
+        "low" | "baixo" => "L",
+        "critical" | "crítico" => "AA",
+        _ => return None,
+    };
+    Some(json!([{ "coding": [{ "system": INTERPRETATION, "code": code }], "text": status }]))
+}
+
+pub fn lab_observation(lab: &LabRecord) -> Value {
+    let mut coding = Vec::new();
+    if let Some((code, display)) = lab_loinc(&lab.marker) {
+        coding.push(json!({ "system": LOINC, "code": code, "display": display }));
+    }
+    coding.push(json!({ "system": LAB_MARKER_SYSTEM, "code": lab.marker }));
+
+    let mut extension = vec![extension_string(EXT_LAB_STATUS, &lab.status)];
+    if let Some(pdf) = &lab.pdf_source {
+        extension.push(extension_string(EXT_PDF_SOURCE, pdf));
+    }
+    let mut resource = json!({
+        "resourceType": "Observation",
+        "id": format!("lab-{}", lab.id.unwrap_or_default()),
+        "status": "final",
+        "category": category("laboratory"),
+        "code": { "coding": coding, "text": lab.marker },
+        "subject": subject(),
+        "valueQuantity": { "value": lab.value, "unit": lab.unit },
+        "extension": extension,
+    });
+    if let Some(date) = &lab.test_date {
+        resource["effectiveDateTime"] = json!(date);
+    }
+    if let Some(range) = lab.reference_range.as_deref().filter(|r| !r.trim().is_empty()) {
+        resource["referenceRange"] = json!([parse_reference_range(range, &lab.unit)]);
+    }
+    if let Some(interp) = interpretation(&lab.status) {
+        resource["interpretation"] = interp;
+    }
+    if let Some(lab_name) = &lab.lab_name {
+        resource["performer"] = json!([{ "display": lab_name }]);
+    }
+    resource
+}
+
+pub fn vital_observation(vital: &VitalEntry) -> Value {
+    let known = vital_code(&vital.vital_type);
+    let mut coding = Vec::new();
+    if let Some((_, code, display, ..)) = known {
+        coding.push(json!({ "system": LOINC, "code": code, "display": display }));
+    }
+    coding.push(json!({ "system": VITAL_TYPE_SYSTEM, "code": vital.vital_type }));
+
+    let mut quantity = json!({ "value": vital.value, "unit": vital.unit });
+    if let Some((.., ucum, _)) = known {
+        quantity["system"] = json!(UCUM);
+        quantity["code"] = json!(ucum);
+    }
+    json!({
+        "resourceType": "Observation",
+        "id": format!("vital-{}", vital.id.unwrap_or_default()),
+        "status": "final",
+        "category": category(known.map_or("survey", |k| k.4)),
+        "code": { "coding": coding, "text": vital.vital_type },
+        "subject": subject(),
+        "effectiveDateTime": vital.recorded_at,
+        "valueQuantity": quantity,
+        "extension": [extension_string(EXT_SOURCE, &vital.source)],
+    })
+}
+
+pub fn medication_statement(entry: &SupplementEntry) -> Value {
+    let mut resource = json!({
+        "resourceType": "MedicationStatement",
+        "id": format!("supplement-{}", entry.id.unwrap_or_default()),
+        "status": "completed",
+        "category": { "coding": [{ "system": SUPPLEMENT_CATEGORY_SYSTEM, "code": entry.category }] },
+        "medicationCodeableConcept": { "text": entry.name },
+        "subject": subject(),
+        "effectiveDateTime": entry.taken_at,
+        "dosage": [{ "text": entry.dosage }],
+    });
+    if let Some(notes) = entry.notes.as_deref().filter(|n| !n.is_empty()) {
+        resource["note"] = json!([{ "text": notes }]);
+    }
+    resource
+}
+
+pub fn service_request(exam: &ScheduleRecord) -> Value {
+    let mut resource = json!({
+        "resourceType": "ServiceRequest",
+        "id": format!("exam-{}", exam.id.unwrap_or_default()),
+        "status": if exam.completed { "completed" } else { "active" },
+        "intent": "plan",
+        "code": { "text": exam.exam_type },
+        "subject": subject(),
+        "occurrenceDateTime": exam.scheduled_date,
+        "reasonCode": [{ "text": exam.reason }],
+    });
+    if let Some(trigger) = &exam.triggered_by {
+        resource["extension"] = json!([extension_string(EXT_TRIGGERED_BY, trigger)]);
+    }
+    resource
+}
+
+/// Collection Bundle with a minimal Patient that every resource points at
+pub fn bundle(timestamp: &str, resources: Vec<Value>) -> Value {
+    let patient = json!({ "resourceType": "Patient", "id": PATIENT_ID });
+    let entries: Vec<Value> = std::iter::once(patient)
+        .chain(resources)
+        .map(|resource| json!({ "resource": resource }))
+        .collect();
+    json!({
+        "resourceType": "Bundle",
+        "type": "collection",
+        "timestamp": timestamp,
+        "entry": entries,
+    })
+}
+
+fn coding_code<'a>(concept: &'a Value, system: &str) -> Option<&'a str> {
+    concept["coding"].as_array()?
+        .iter()
+        .find(|c| c["system"] == system)
+        .and_then(|c| c["code"].as_str())
+}
+
+fn extension<'a>(resource: &'a Value, url: &str) -> Option<&'a str> {
+    resource["extension"].as_array()?
+        .iter()
+        .find(|e| e["url"] == url)
+        .and_then(|e| e["valueString"].as_str())
+}
+
+fn is_laboratory(resource: &Value) -> bool {
+    resource["category"].as_array().into_iter().flatten()
+        .any(|c| coding_code(c, OBSERVATION_CATEGORY) == Some("laboratory"))
+}
+
+/// Map one resource back to a row. Err(reason) when it can't be read;
+/// Ok(None) for resource types that aren't part of the record (Patient, ...).
+pub fn parse_resource(resource: &Value) -> Result<Option<Imported>, String> {
+    let text = |v: &Value| v.as_str().map(str::to_string);
+    match resource["resourceType"].as_str() {
+        Some("Observation") => {
+            let value = resource["valueQuantity"]["value"].as_f64()
+                .ok_or("Observation sem valueQuantity")?;
+            let unit = text(&resource["valueQuantity"]["unit"])
+                .or_else(|| text(&resource["valueQuantity"]["code"]))
+                .unwrap_or_default();
+            let effective = text(&resource["effectiveDateTime"])
+                .or_else(|| text(&resource["effectivePeriod"]["start"]));
+            let code = &resource["code"];
+
+            // Our own coding decides (glucose is a vital here yet exported as laboratory);
+            // other systems' observations go by LOINC code and category
+            let vital_type = coding_code(code, VITAL_TYPE_SYSTEM).map(str::to_string).or_else(|| {
+                let loinc = coding_code(code, LOINC)?;
+                let known = VITAL_CODES.iter().find(|c| c.1 == loinc && c.4 != "laboratory")?;
+                (!is_laboratory(resource)).then(|| known.0.to_string())
+            });
+            if let Some(vital_type) = vital_type {
+                return Ok(Some(Imported::Vital(VitalEntry {
+                    id: None,
+                    vital_type,
+                    value,
+                    unit,
+                    recorded_at: effective.ok_or("Observation sem effectiveDateTime")?,
+                    source: extension(resource, EXT_SOURCE).unwrap_or("manual").to_string(),
+                })));
+            }
+
+            let marker = coding_code(code, LAB_MARKER_SYSTEM).map(str::to_string)
+                .or_else(|| text(&code["text"]))
+                .or_else(|| code["coding"][0]["display"].as_str().map(str::to_string))
+                .ok_or("Observation sem código legível")?;
+            let status = extension(resource, EXT_LAB_STATUS).map(str::to_string)
+                .or_else(|| text(&resource["interpretation"][0]["text"]))
+                .or_else(|| resource["interpretation"][0]["coding"][0]["code"].as_str().map(|c| match c {
+                    "H" | "HH" => "high".to_string(),
+                    "L" | "LL" => "low".to_string(),
+                    "N" => "normal".to_string(),
+                    other => other.to_lowercase(),
+                }))
+                .unwrap_or_else(|| "unknown".to_string());
+            Ok(Some(Imported::Lab(LabRecord {
+                id: None,
+                marker,
+                value,
+                unit,
+                reference_range: text(&resource["referenceRange"][0]["text"]),
+                status,
+                lab_name: text(&resource["performer"][0]["display"]),
+                test_date: effective,
+                pdf_source: extension(resource, EXT_PDF_SOURCE).map(str::to_string),
+            })))
+        }
+        Some("MedicationStatement") => Ok(Some(Imported::Supplement(SupplementEntry {
+            id: None,
+            name: text(&resource["medicationCodeableConcept"]["text"])
+                .or_else(|| text(&resource["medicationCodeableConcept"]["coding"][0]["display"]))
+                .ok_or("MedicationStatement sem medicamento")?,
+            dosage: text(&resource["dosage"][0]["text"]).unwrap_or_default(),
+            taken_at: text(&resource["effectiveDateTime"])
+                .or_else(|| text(&resource["effectivePeriod"]["start"]))
+                .ok_or("MedicationStatement sem data")?,
+            category: coding_code(&resource["category"], SUPPLEMENT_CATEGORY_SYSTEM)
+                .unwrap_or("as_needed")
+                .to_string(),
+            notes: text(&resource["note"][0]["text"]),
+        }))),
+        Some("ServiceRequest") => Ok(Some(Imported::Schedule(ScheduleRecord {
+            id: None,
+            exam_type: text(&resource["code"]["text"])
+                .or_else(|| text(&resource["code"]["coding"][0]["display"]))
+                .ok_or("ServiceRequest sem exame")?,
+            reason: text(&resource["reasonCode"][0]["text"]).unwrap_or_default(),
+            scheduled_date: text(&resource["occurrenceDateTime"])
+                .or_else(|| text(&resource["occurrencePeriod"]["start"]))
+                .ok_or("ServiceRequest sem data")?,
+            triggered_by: extension(resource, EXT_TRIGGERED_BY).map(str::to_string),
+            completed: resource["status"] == "completed",
+        }))),
+        Some(_) => Ok(None),
+        None => Err("Recurso sem resourceType".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vital(vital_type: &str, unit: &str) -> VitalEntry {
+        VitalEntry {
+            id: Some(7),
+            vital_type: vital_type.to_string(),
+            value: 42.0,
+            unit: unit.to_string(),
+            recorded_at: "2026-07-01T08:00:00Z".to_string(),
+            source: "wearable".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_vital_type_survives_export_and_import() {
+        let unknown = ("mood", "", "", "score", "survey");
+        for &(vital_type, _, _, _, category) in VITAL_CODES.iter().chain([&unknown]) {
+            let resource = vital_observation(&vital(vital_type, "x"));
+            assert_eq!(resource["category"][0]["coding"][0]["code"], category, "{}", vital_type);
+
+            let Ok(Some(Imported::Vital(back))) = parse_resource(&resource) else {
+                panic!("{} did not import as a vital", vital_type);
+            };
+            assert_eq!(
+                (back.vital_type.as_str(), back.value, back.unit.as_str(), back.recorded_at.as_str(), back.source.as_str()),
+                (vital_type, 42.0, "x", "2026-07-01T08:00:00Z", "wearable"),
+            );
+        }
+    }
+
+    #[test]
+    fn foreign_observations_go_by_loinc_and_category() {
+        let observation = |loinc: &str, category: &str| json!({
+            "resourceType": "Observation",
+            "category": [{ "coding": [{ "system": OBSERVATION_CATEGORY, "code": category }] }],
+            "code": { "coding": [{ "system": LOINC, "code": loinc, "display": "Glucose" }] },
+            "effectiveDateTime": "2026-07-01T08:00:00Z",
+            "valueQuantity": { "value": 95.0, "unit": "mg/dL" },
+        });
+
+        assert!(matches!(parse_resource(&observation("8867-4", "vital-signs")), Ok(Some(Imported::Vital(v))) if v.vital_type == "heart_rate" && v.source == "manual"));
+        // A laboratory glucose from another system is a lab result
+        assert!(matches!(parse_resource(&observation("2339-0", "laboratory")), Ok(Some(Imported::Lab(l))) if l.marker == "Glucose"));
+        assert!(matches!(parse_resource(&observation("2345-7", "laboratory")), Ok(Some(Imported::Lab(_)))));
+    }
+
+    #[test]
+    fn lab_results_keep_their_marker_and_status() {
+        let lab = LabRecord {
+            id: Some(3),
+            marker: "Glicose".to_string(),
+            value: 101.0,
+            unit: "mg/dL".to_string(),
+            reference_range: Some("70 - 99".to_string()),
+            status: "high".to_string(),
+            lab_name: Some("Synlab".to_string()),
+            test_date: Some("2026-06-28".to_string()),
+            pdf_source: Some("analises.pdf".to_string()),
+        };
+        let resource = lab_observation(&lab);
+        assert_eq!(resource["code"]["coding"][0]["code"], "2345-7");
+        let Ok(Some(Imported::Lab(back))) = parse_resource(&resource) else { panic!("not a lab result") };
+        assert_eq!(
+            (back.marker, back.status, back.reference_range, back.test_date, back.pdf_source),
+            (lab.marker, lab.status, lab.reference_range, lab.test_date, lab.pdf_source),
+        );
+    }
+}
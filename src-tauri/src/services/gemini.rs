@@ -0,0 +1,57 @@
+use serde_json::Value;
+use std::time::Duration;
+use crate::services::http::HttpClient;
+
+// Gemini generateContent over the injected HTTP client, shared by the agent and the lab import.
+
+pub const GENERATE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent";
+
+/// Send one generateContent request and return the first candidate's text
+pub async fn generate(http: &dyn HttpClient, api_key: &str, body: Value, timeout: Duration) -> Result<String, String> {
+    let response = http.post_json(GENERATE_URL, &[("x-goog-api-key", api_key)], body, timeout)
+        .await
+        .map_err(|e| format!("Gemini API error: {}", e))?;
+    candidate_text(&response)
+        .map(str::to_string)
+        .ok_or_else(|| "Invalid Gemini response: missing candidates or text".to_string())
+}
+
+fn candidate_text(response: &Value) -> Option<&str> {
+    response.get("candidates")?
+        .get(0)?
+        .get("content")?
+        .get("parts")?
+        .get(0)?
+        .get("text")?
+        .as_str()
+}
+
+/// Drop the markdown code fences Gemini sometimes wraps JSON in
+pub fn strip_code_fences(text: &str) -> &str {
+    let text = text.trim();
+    let text = text.strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .unwrap_or(text);
+    text.strip_suffix("```").unwrap_or(text).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_first_candidate() {
+        let response = serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": "Olá" }] } }]
+        });
+        assert_eq!(candidate_text(&response), Some("Olá"));
+        assert_eq!(candidate_text(&serde_json::json!({ "candidates": [] })), None);
+    }
+
+    #[test]
+    fn strips_code_fences() {
+        assert_eq!(strip_code_fences("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(strip_code_fences("```\n[]\n```"), "[]");
+        assert_eq!(strip_code_fences("  {} "), "{}");
+    }
+}
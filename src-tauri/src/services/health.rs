@@ -0,0 +1,339 @@
+use chrono::Timelike;
+use crate::commands::agent::{DailyStats, PROTOCOLS};
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::commands::{eye_care, records, typing};
+use crate::db::Database;
+use crate::services::context::ServiceContext;
+use crate::services::scheduler::{self, LabInfo, ScheduledExam, SupplementInfo};
+use crate::services::time;
+use crate::services::timeline::{self, Bucket, Cursor, DayTally, TimelineBucket, TimelineEvent, TimelinePage, TimelineQuery};
+
+/// Days of supplement history that count as the active protocol for exam predictions
+const ACTIVE_SUPPLEMENT_DAYS: i64 = 90;
+
+/// Health records: supplements, vitals, the timeline, exam predictions and daily stats
+pub struct HealthService<'a> {
+    ctx: &'a ServiceContext,
+}
+
+impl<'a> HealthService<'a> {
+    pub fn new(ctx: &'a ServiceContext) -> Self {
+        HealthService { ctx }
+    }
+
+    fn now(&self) -> String {
+        time::format_utc(self.ctx.clock.now())
+    }
+
+    /// Normalize, insert and audit a supplement intake
+    pub async fn log_supplement(&self, mut entry: SupplementEntry, source: &'static str) -> Result<i64, String> {
+        entry.taken_at = time::normalize(&entry.taken_at, self.ctx.tz())?;
+        let now = self.now();
+        self.ctx.db.write(move |db| db.transaction(|db| {
+            let id = db.insert_supplement(&entry)?;
+            records::audit_insert(db, &now, "supplements", id, source)?;
+            Ok::<_, rusqlite::Error>(id)
+        })).await
+    }
+
+    /// Normalize, insert and audit a vital reading
+    pub async fn log_vital(&self, mut entry: VitalEntry, source: &'static str) -> Result<i64, String> {
+        entry.recorded_at = time::normalize(&entry.recorded_at, self.ctx.tz())?;
+        let now = self.now();
+        self.ctx.db.write(move |db| db.transaction(|db| {
+            let id = db.insert_vital(&entry)?;
+            records::audit_insert(db, &now, "vitals", id, source)?;
+            Ok::<_, rusqlite::Error>(id)
+        })).await
+    }
+
+    /// Supplement intakes in [from, to], read in the user's zone
+    pub async fn supplement_log(&self, from: &str, to: &str) -> Result<Vec<SupplementEntry>, String> {
+        let (from, to) = time::normalize_range(from, to, self.ctx.tz())?;
+        self.ctx.db.read(move |db| db.get_supplements(&from, &to)).await
+    }
+
+    /// Validate timeline parameters into a query; `from`/`to` are read in the user's zone
+    pub fn timeline_query(
+        &self,
+        from: &str,
+        to: &str,
+        types: Option<&[String]>,
+        vital_types: Option<Vec<String>>,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<TimelineQuery, String> {
+        let (from, to) = time::normalize_range(from, to, self.ctx.tz())?;
+        Ok(TimelineQuery {
+            types: timeline::parse_types(types)?,
+            vital_types,
+            cursor: cursor.map(Cursor::parse).transpose()?,
+            limit: limit.unwrap_or(timeline::DEFAULT_PAGE_SIZE).clamp(1, timeline::MAX_PAGE_SIZE),
+            tz: self.ctx.tz(),
+            from,
+            to,
+        })
+    }
+
+    /// One timeline page: stored events merged with derived episodes and periods
+    pub async fn timeline(&self, query: TimelineQuery) -> Result<TimelinePage, String> {
+        self.ctx.db.read(move |db| {
+            let stored = db.get_health_timeline(&query).map_err(|e| e.to_string())?;
+            let derived = derived_events(db, &query.from, &query.to, &query.types)?;
+            Ok::<_, String>(timeline::merge_page(stored, derived, query.cursor.as_ref(), query.limit))
+        }).await
+    }
+
+    /// Timeline grouped by local day or ISO week, oldest first
+    pub async fn timeline_buckets(
+        &self,
+        from: &str,
+        to: &str,
+        bucket: &str,
+        types: Option<&[String]>,
+        vital_types: Option<Vec<String>>,
+    ) -> Result<Vec<TimelineBucket>, String> {
+        let bucket = Bucket::parse(bucket)?;
+        let types = timeline::parse_types(types)?;
+        let tz = self.ctx.tz();
+        let (from, to) = time::normalize_range(from, to, tz)?;
+        let (mut tallies, derived) = self.ctx.db.read(move |db| {
+            let tallies = db.get_timeline_tallies(&from, &to, &types, vital_types.as_deref(), tz)
+                .map_err(|e| e.to_string())?;
+            Ok::<_, String>((tallies, derived_events(db, &from, &to, &types)?))
+        }).await?;
+        for event in derived {
+            if let Some(at) = time::parse_instant(&event.timestamp, tz) {
+                tallies.push(DayTally {
+                    day: at.with_timezone(&tz).date_naive(),
+                    event_type: event.event_type,
+                    vital: None,
+                    count: 1,
+                    sum: 0.0,
+                    min: 0.0,
+                    max: 0.0,
+                });
+            }
+        }
+        Ok(timeline::fold_buckets(&tallies, bucket))
+    }
+
+    /// Exams predicted from the supplements of the last 90 days and the latest labs
+    pub async fn predict_exams(&self) -> Result<Vec<ScheduledExam>, String> {
+        let since = time::format_utc(self.ctx.clock.now() - chrono::Duration::days(ACTIVE_SUPPLEMENT_DAYS));
+        let (supplements, labs) = self.ctx.db.read(move |db| {
+            let supplements = db.get_active_supplements(&since)?;
+            let labs = db.get_latest_labs()?;
+            Ok::<_, rusqlite::Error>((supplements, labs))
+        }).await?;
+
+        let supp_info: Vec<SupplementInfo> = supplements.into_iter().map(|name| SupplementInfo {
+            name,
+            started_date: String::new(),
+        }).collect();
+        let lab_info: Vec<LabInfo> = labs.into_iter().map(|(marker, date)| LabInfo { marker, date }).collect();
+
+        Ok(scheduler::generate_exam_schedule(&supp_info, &lab_info, self.ctx.clock.today(self.ctx.tz())))
+    }
+
+    /// Insert and audit one scheduled exam
+    pub async fn store_exam(&self, exam: ScheduledExam, source: &'static str) -> Result<i64, String> {
+        let now = self.now();
+        self.ctx.db.write(move |db| db.transaction(|db| {
+            let id = db.insert_scheduled_exam(&exam)?;
+            records::audit_insert(db, &now, "health_schedule", id, source)?;
+            Ok::<_, rusqlite::Error>(id)
+        })).await
+    }
+
+    /// Today's adherence, focus, breaks, posture and eye care in the user's local day
+    pub async fn daily_stats(&self) -> Result<DailyStats, String> {
+        let tz = self.ctx.tz();
+        let now = self.ctx.clock.now();
+        let (day_start, day_end) = time::local_day_bounds(self.ctx.clock.today(tz), tz);
+        let hour = now.with_timezone(&tz).hour();
+        let now = time::format_utc(now);
+
+        self.ctx.db.read(move |db| {
+            // Adherence against protocols whose window has opened today (or already taken early)
+            let (taken, due) = PROTOCOLS.iter().fold((0u32, 0u32), |(taken, due), p| {
+                let took = db.check_supplement_taken(p.name, &day_start, &day_end).unwrap_or(false);
+                let is_due = took || *p.hours.start() <= hour;
+                (taken + took as u32, due + is_due as u32)
+            });
+            let adherence = if due > 0 {
+                (taken as f64 / due as f64 * 100.0).round() as u32
+            } else {
+                100
+            };
+
+            let focus_minutes = (db.presence_seconds(&day_start, &day_end, &now).map_err(|e| e.to_string())? / 60) as u32;
+            let breaks_taken = db.count_breaks(&day_start, &day_end).map_err(|e| e.to_string())?;
+            let avg_posture_score = db.average_posture(&day_start, &day_end)
+                .map_err(|e| e.to_string())?
+                .map(|avg| avg.round() as u32);
+
+            let (_, complied, missed) = db.eye_reminder_counts(&day_start, &day_end).map_err(|e| e.to_string())?;
+            let eye_care_compliance = (complied + missed > 0)
+                .then(|| (complied as f64 / (complied + missed) as f64 * 100.0).round() as u32);
+
+            // Voice commands today
+            let voice_count: u32 = db.query_row(
+                "SELECT COUNT(*) FROM agent_memory WHERE key = 'voice_input' AND timestamp >= ?1 AND timestamp < ?2",
+                &[&day_start, &day_end],
+                |row| row.get(0),
+            ).unwrap_or(0);
+
+            Ok::<_, String>(DailyStats {
+                adherence_percent: adherence,
+                protocols_taken: taken,
+                protocols_due: due,
+                breaks_taken,
+                avg_posture_score,
+                focus_minutes,
+                voice_commands: voice_count,
+                eye_care_compliance,
+            })
+        }).await
+    }
+}
+
+/// Fatigue episodes and low-blink periods in [from, to], when requested
+fn derived_events(db: &Database, from: &str, to: &str, types: &[&str]) -> Result<Vec<TimelineEvent>, String> {
+    let mut events = Vec::new();
+    // Per-minute WPM snapshots are summarized as fatigue episodes
+    if types.contains(&"fatigue_episode") {
+        let episodes = typing::episodes_between(db, from, to)?;
+        events.extend(episodes.iter().map(|e| timeline::derived_event("fatigue_episode", &e.started_at, e)));
+    }
+    // Per-minute blink rates are summarized as low-blink periods
+    if types.contains(&"low_blink_period") {
+        let periods = eye_care::low_blink_between(db, from, to)?;
+        events.extend(periods.iter().map(|p| timeline::derived_event("low_blink_period", &p.started_at, p)));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::activity::BreakEntry;
+    use crate::services::context::testing::{context_at, supplement};
+
+    #[tokio::test]
+    async fn daily_stats_count_protocols_due_by_the_frozen_hour() {
+        // 11:30 in Lisbon: Winfit and Vitamina D3 windows are open
+        let (ctx, _, _) = context_at("2026-07-01T10:30:00Z", None);
+        let health = HealthService::new(&ctx);
+        // Local wall-clock time, read in the user's zone
+        health.log_supplement(supplement("Winfit", "2026-07-01T09:00"), "ui").await.unwrap();
+        // Late evening the day before is another local day
+        health.log_supplement(supplement("Vitamina D3", "2026-06-30T23:30"), "ui").await.unwrap();
+
+        let stats = health.daily_stats().await.unwrap();
+        assert_eq!((stats.protocols_taken, stats.protocols_due, stats.adherence_percent), (1, 2, 50));
+    }
+
+    #[tokio::test]
+    async fn an_empty_day_has_no_focus_breaks_or_posture() {
+        let (ctx, _, _) = context_at("2026-07-01T23:30:00Z", None);
+        // 00:30 on the 2nd in Lisbon; the only break ended at 23:50 the night before
+        ctx.db.write(|db| {
+            db.insert_break(&BreakEntry { id: None, started_at: "2026-07-01T22:00:00Z".to_string(), ended_at: "2026-07-01T22:50:00Z".to_string(), reason: None })
+        }).await.unwrap();
+
+        let stats = HealthService::new(&ctx).daily_stats().await.unwrap();
+        assert_eq!((stats.focus_minutes, stats.breaks_taken, stats.avg_posture_score), (0, 0, None));
+        assert_eq!(stats.eye_care_compliance, None);
+    }
+
+    #[tokio::test]
+    async fn exam_predictions_follow_the_clock() {
+        let (ctx, clock, _) = context_at("2026-07-01T10:00:00Z", None);
+        let health = HealthService::new(&ctx);
+        health.log_supplement(supplement("Winfit", "2026-06-20T09:00:00Z"), "ui").await.unwrap();
+
+        let exams = health.predict_exams().await.unwrap();
+        let zinc = exams.iter().find(|e| e.exam_type == "zinc_copper_panel").unwrap();
+        assert_eq!(zinc.scheduled_date, "2026-07-08");
+
+        // 100 days later the supplement no longer counts as active
+        clock.advance(chrono::Duration::days(100));
+        let exams = health.predict_exams().await.unwrap();
+        assert!(exams.iter().all(|e| e.exam_type != "zinc_copper_panel"));
+        assert!(exams.iter().any(|e| e.exam_type == "vitamin_d_panel" && e.scheduled_date == "2026-10-16"));
+    }
+
+    fn reading(value: f64, recorded_at: &str) -> VitalEntry {
+        VitalEntry {
+            id: None,
+            vital_type: "steps".to_string(),
+            value,
+            unit: "steps".to_string(),
+            recorded_at: recorded_at.to_string(),
+            source: "manual".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn timeline_days_follow_the_offset_of_each_instant() {
+        // Asked in summer about a range that spans both DST changes in Lisbon
+        let (ctx, _, _) = context_at("2026-07-15T12:00:00Z", None);
+        let health = HealthService::new(&ctx);
+        for (value, at) in [
+            (1.0, "2026-03-28T23:30:00Z"), // 23:30 WET, still the 28th
+            (2.0, "2026-03-29T23:30:00Z"), // 00:30 WEST on the 30th
+            (3.0, "2026-10-25T00:30:00Z"), // 01:30 WEST, before the clocks go back
+            (4.0, "2026-10-25T23:30:00Z"), // 23:30 WET, still the 25th
+        ] {
+            health.log_vital(reading(value, at), "ui").await.unwrap();
+        }
+        let lab = crate::services::fhir::LabRecord {
+            id: None,
+            marker: "Ferritina".to_string(),
+            value: 45.0,
+            unit: "ng/mL".to_string(),
+            reference_range: None,
+            status: "normal".to_string(),
+            lab_name: None,
+            test_date: Some("2026-01-10".to_string()),
+            pdf_source: None,
+        };
+        ctx.db.write(move |db| db.insert_lab_record(&lab)).await.unwrap();
+
+        let buckets = health.timeline_buckets("2026-01-01", "2026-12-31", "day", Some(&["vital".to_string()]), None)
+            .await
+            .unwrap();
+        let days: Vec<(&str, f64)> = buckets.iter().map(|b| (b.start.as_str(), b.vitals[0].sum)).collect();
+        assert_eq!(days, [("2026-03-28", 1.0), ("2026-03-30", 2.0), ("2026-10-25", 7.0)]);
+
+        // A winter lab sits at winter midnight, not an hour before it
+        let query = health.timeline_query("2026-01-01", "2026-01-31", Some(&["lab_result".to_string()]), None, None, None)
+            .unwrap();
+        let page = health.timeline(query).await.unwrap();
+        assert_eq!(page.events[0].timestamp, "2026-01-10T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn logged_records_are_audited_and_on_the_timeline() {
+        let (ctx, _, _) = context_at("2026-07-01T10:00:00Z", None);
+        let health = HealthService::new(&ctx);
+        let id = health.log_vital(VitalEntry {
+            id: None,
+            vital_type: "heart_rate".to_string(),
+            value: 62.0,
+            unit: "bpm".to_string(),
+            recorded_at: "2026-07-01T08:00:00+01:00".to_string(),
+            source: "manual".to_string(),
+        }, "cli").await.unwrap();
+
+        let query = health.timeline_query("2026-07-01", "2026-07-01", None, None, None, None).unwrap();
+        let page = health.timeline(query).await.unwrap();
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].timestamp, "2026-07-01T07:00:00Z");
+
+        let audit = ctx.db.read(move |db| db.get_audit_log(Some("vitals"), Some(id), 10)).await.unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].source, "cli");
+    }
+}
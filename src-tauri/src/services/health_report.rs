@@ -0,0 +1,992 @@
+use chrono::{Datelike, NaiveDate};
+use pdf_writer::{Content, Date, Name, Pdf, Rect, Ref, Str, TextStr};
+use serde::Serialize;
+use crate::services::fhir::{self, LabRecord, ScheduleRecord};
+
+// Printable summary for medical appointments, drawn with the standard PDF fonts
+// (Helvetica, WinAnsi encoding) so nothing is embedded. The output depends only on
+// `ReportData`: no clock, no random IDs and no compression, so the same data always
+// renders to the same bytes.
+
+const PAGE_W: f32 = 595.0; // A4 in points
+const PAGE_H: f32 = 842.0;
+const MARGIN: f32 = 42.0;
+const BOTTOM: f32 = 56.0; // room for the footer
+const ROW_H: f32 = 16.0;
+const SPARK_POINTS: usize = 12;
+const SPARK_W: f32 = 64.0;
+const SPARK_H: f32 = 10.0;
+
+type Rgb = (f32, f32, f32);
+const INK: Rgb = (0.12, 0.12, 0.14);
+const MUTED: Rgb = (0.45, 0.45, 0.5);
+const RULE: Rgb = (0.82, 0.82, 0.85);
+const BAND: Rgb = (0.86, 0.94, 0.88);
+const GOOD: Rgb = (0.16, 0.55, 0.3);
+const WARN: Rgb = (0.85, 0.55, 0.1);
+const BAD: Rgb = (0.78, 0.16, 0.16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Pt,
+    En,
+}
+
+impl Language {
+    /// "pt", "pt-PT", "en", "en-GB", ...
+    pub fn parse(code: &str) -> Result<Self, String> {
+        match code.trim().to_lowercase().split(['-', '_']).next() {
+            Some("pt") => Ok(Self::Pt),
+            Some("en") => Ok(Self::En),
+            _ => Err(format!("Idioma não suportado: '{}' (use pt ou en)", code)),
+        }
+    }
+
+    fn labels(self) -> &'static Labels {
+        match self {
+            Self::Pt => &PT,
+            Self::En => &EN,
+        }
+    }
+}
+
+/// Intake of one supplement protocol over the report window
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolAdherence {
+    pub name: String,
+    pub dosage: String,
+    pub days_taken: u32,
+    pub days_expected: u32,       // from the first intake in the window (or the whole window) to today
+    pub since: Option<NaiveDate>, // first intake in the window
+}
+
+/// Aggregate of one vital type over the report window.
+/// Cumulative types (steps, sleep) are summed per day first, so the figures are daily totals.
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalSummary {
+    pub vital_type: String,
+    pub unit: String,
+    pub per_day: bool,
+    pub count: u32, // readings, or days for per-day types
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub latest: f64,
+    pub latest_at: String,
+}
+
+/// Everything the report shows, gathered from the database beforehand
+#[derive(Debug, Clone)]
+pub struct ReportData {
+    pub generated_on: NaiveDate,
+    pub window_days: u32,            // adherence / vitals window
+    pub labs: Vec<LabRecord>,        // oldest first
+    pub adherence: Vec<ProtocolAdherence>,
+    pub vitals: Vec<VitalSummary>,
+    pub exams: Vec<ScheduleRecord>,  // pending, soonest first
+}
+
+pub struct RenderedReport {
+    pub bytes: Vec<u8>,
+    pub pages: usize,
+    pub markers: usize,
+}
+
+struct Labels {
+    title: &'static str,
+    generated: &'static str,
+    disclaimer: &'static str,
+    page: &'static str,
+    of: &'static str,
+    labs: &'static str,
+    labs_empty: &'static str,
+    marker: &'static str,
+    latest: &'static str,
+    unit: &'static str,
+    range: &'static str,
+    status: &'static str,
+    trend: &'static str,
+    date: &'static str,
+    adherence: &'static str,
+    adherence_empty: &'static str,
+    protocol: &'static str,
+    dosage: &'static str,
+    days: &'static str,
+    since: &'static str,
+    no_intake: &'static str,
+    vitals: &'static str,
+    vitals_empty: &'static str,
+    measure: &'static str,
+    mean: &'static str,
+    min: &'static str,
+    max: &'static str,
+    readings: &'static str,
+    per_day: &'static str,
+    exams: &'static str,
+    exams_empty: &'static str,
+    exam: &'static str,
+    reason: &'static str,
+    overdue: &'static str,
+    last_days: &'static str,
+}
+
+const PT: Labels = Labels {
+    title: "Relatório de saúde",
+    generated: "Gerado em",
+    disclaimer: "Gerado pelo HoloSelf a partir de registos pessoais. Não substitui avaliação clínica.",
+    page: "Página",
+    of: "de",
+    labs: "Análises clínicas",
+    labs_empty: "Sem análises registadas nos últimos dois anos.",
+    marker: "Marcador",
+    latest: "Último",
+    unit: "Unidade",
+    range: "Referência",
+    status: "Estado",
+    trend: "Tendência",
+    date: "Data",
+    adherence: "Adesão aos suplementos",
+    adherence_empty: "Sem protocolos de suplementação.",
+    protocol: "Protocolo",
+    dosage: "Dose",
+    days: "Dias",
+    since: "desde",
+    no_intake: "sem registos",
+    vitals: "Sinais vitais",
+    vitals_empty: "Sem sinais vitais registados no período.",
+    measure: "Medida",
+    mean: "Média",
+    min: "Mín.",
+    max: "Máx.",
+    readings: "Registos",
+    per_day: "por dia",
+    exams: "Exames agendados",
+    exams_empty: "Sem exames pendentes.",
+    exam: "Exame",
+    reason: "Motivo",
+    overdue: "em atraso",
+    last_days: "últimos {} dias",
+};
+
+const EN: Labels = Labels {
+    title: "Health report",
+    generated: "Generated on",
+    disclaimer: "Generated by HoloSelf from self-tracked records. Not a substitute for clinical assessment.",
+    page: "Page",
+    of: "of",
+    labs: "Lab results",
+    labs_empty: "No lab results in the last two years.",
+    marker: "Marker",
+    latest: "Latest",
+    unit: "Unit",
+    range: "Reference",
+    status: "Status",
+    trend: "Trend",
+    date: "Date",
+    adherence: "Supplement adherence",
+    adherence_empty: "No supplement protocols.",
+    protocol: "Protocol",
+    dosage: "Dose",
+    days: "Days",
+    since: "since",
+    no_intake: "no intake logged",
+    vitals: "Vital signs",
+    vitals_empty: "No vital signs recorded in this period.",
+    measure: "Measure",
+    mean: "Mean",
+    min: "Min",
+    max: "Max",
+    readings: "Readings",
+    per_day: "per day",
+    exams: "Scheduled exams",
+    exams_empty: "No pending exams.",
+    exam: "Exam",
+    reason: "Reason",
+    overdue: "overdue",
+    last_days: "last {} days",
+};
+
+// ─── Formatting ────────────────────────────────────────────────
+
+/// Fixed decimals with trailing zeros dropped; decimal comma in Portuguese
+fn number(value: f64, decimals: usize, lang: Language) -> String {
+    let mut text = format!("{:.*}", decimals, value);
+    if text.contains('.') {
+        text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    match lang {
+        Language::Pt => text.replace('.', ","),
+        Language::En => text,
+    }
+}
+
+/// Fewer decimals for larger magnitudes (8432 steps, 61.5 bpm, 0.82 h)
+fn measure(value: f64, lang: Language) -> String {
+    let decimals = match value.abs() {
+        v if v >= 100.0 => 0,
+        v if v >= 10.0 => 1,
+        _ => 2,
+    };
+    number(value, decimals, lang)
+}
+
+/// Calendar date from a date or timestamp string; unparseable input is shown as is
+fn date(raw: &str, lang: Language) -> String {
+    match raw.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+        Some(day) => format_day(day, lang),
+        None => raw.to_string(),
+    }
+}
+
+fn format_day(day: NaiveDate, lang: Language) -> String {
+    match lang {
+        Language::Pt => day.format("%d/%m/%Y").to_string(),
+        Language::En => day.format("%Y-%m-%d").to_string(),
+    }
+}
+
+fn status_label(status: &str, lang: Language) -> (String, Rgb) {
+    let (pt, en, color) = match status.to_lowercase().as_str() {
+        "normal" => ("Normal", "Normal", GOOD),
+        "high" | "alto" | "elevado" => ("Alto", "High", WARN),
+        "low" | "baixo" => ("Baixo", "Low", WARN),
+        "critical" | "crítico" => ("Crítico", "Critical", BAD),
+        _ => return (status.to_string(), INK),
+    };
+    (if lang == Language::Pt { pt } else { en }.to_string(), color)
+}
+
+// (vital_type, Portuguese, English)
+const VITAL_LABELS: &[(&str, &str, &str)] = &[
+    ("heart_rate", "Frequência cardíaca", "Heart rate"),
+    ("resting_heart_rate", "FC em repouso", "Resting heart rate"),
+    ("hrv", "Variabilidade da FC", "Heart rate variability"),
+    ("sleep", "Sono", "Sleep"),
+    ("sleep_score", "Pontuação do sono", "Sleep score"),
+    ("steps", "Passos", "Steps"),
+    ("stress_level", "Nível de stress", "Stress level"),
+    ("blood_pressure_systolic", "Tensão sistólica", "Systolic blood pressure"),
+    ("blood_pressure_diastolic", "Tensão diastólica", "Diastolic blood pressure"),
+    ("spo2", "Saturação de O2", "Oxygen saturation"),
+    ("body_temperature", "Temperatura", "Body temperature"),
+    ("weight", "Peso", "Weight"),
+    ("body_fat", "Massa gorda", "Body fat"),
+    ("glucose", "Glicemia", "Glucose"),
+];
+
+fn vital_label(vital_type: &str, lang: Language) -> String {
+    VITAL_LABELS.iter()
+        .find(|(t, ..)| *t == vital_type)
+        .map(|(_, pt, en)| if lang == Language::Pt { *pt } else { *en }.to_string())
+        .unwrap_or_else(|| vital_type.replace('_', " "))
+}
+
+// ─── Text metrics and encoding ─────────────────────────────────
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> Name<'static> {
+        match self {
+            Self::Regular => Name(b"F1"),
+            Self::Bold => Name(b"F2"),
+        }
+    }
+}
+
+// Advance widths (1/1000 em) of ' '..='~' from the Helvetica / Helvetica-Bold AFM files
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    let table = match font {
+        Font::Regular => &HELVETICA,
+        Font::Bold => &HELVETICA_BOLD,
+    };
+    let units: u32 = text.chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => table[(code - 32) as usize] as u32,
+            _ => 556, // accented letters are close to their base glyph
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// Cut `text` with an ellipsis so it fits `width`
+fn fit(text: &str, width: f32, font: Font, size: f32) -> String {
+    if text_width(text, font, size) <= width {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().collect();
+    while !out.is_empty() && text_width(&format!("{}…", out), font, size) > width {
+        out.pop();
+    }
+    format!("{}…", out.trim_end())
+}
+
+/// WinAnsiEncoding bytes; characters outside it become '?'
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.replace('≥', ">=").replace('≤', "<=").chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
+
+// ─── Page layout ───────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+}
+
+struct Column {
+    title: &'static str,
+    width: f32,
+    align: Align,
+}
+
+const fn col(title: &'static str, width: f32, align: Align) -> Column {
+    Column { title, width, align }
+}
+
+/// Pages under construction; `y` is the baseline cursor on the current page
+struct Layout {
+    pages: Vec<Content>,
+    current: usize,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        let mut layout = Self { pages: Vec::new(), current: 0, y: 0.0 };
+        layout.new_page();
+        layout
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.current = self.pages.len() - 1;
+        self.y = PAGE_H - MARGIN;
+    }
+
+    fn content(&mut self) -> &mut Content {
+        &mut self.pages[self.current]
+    }
+
+    /// Start a new page unless `height` still fits; true when a page was added
+    fn ensure(&mut self, height: f32) -> bool {
+        if self.y - height < BOTTOM {
+            self.new_page();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, font: Font, color: Rgb, text: &str) {
+        let bytes = win_ansi(text);
+        let content = self.content();
+        content.set_fill_rgb(color.0, color.1, color.2);
+        content.begin_text();
+        content.set_font(font.resource(), size);
+        content.next_line(x, y);
+        content.show(Str(&bytes));
+        content.end_text();
+    }
+
+    fn text_right(&mut self, right: f32, y: f32, size: f32, font: Font, color: Rgb, text: &str) {
+        let x = right - text_width(text, font, size);
+        self.text(x, y, size, font, color, text);
+    }
+
+    fn rule(&mut self, y: f32) {
+        let content = self.content();
+        content.set_stroke_rgb(RULE.0, RULE.1, RULE.2);
+        content.set_line_width(0.5);
+        content.move_to(MARGIN, y);
+        content.line_to(PAGE_W - MARGIN, y);
+        content.stroke();
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Rgb) {
+        let content = self.content();
+        content.set_fill_rgb(color.0, color.1, color.2);
+        content.rect(x, y, width, height);
+        content.fill_nonzero();
+    }
+
+    fn section(&mut self, title: &str, subtitle: Option<&str>) {
+        self.ensure(24.0 + 2.0 * ROW_H);
+        self.y -= 22.0;
+        self.text(MARGIN, self.y, 12.0, Font::Bold, INK, title);
+        if let Some(subtitle) = subtitle {
+            let x = MARGIN + text_width(title, Font::Bold, 12.0) + 8.0;
+            self.text(x, self.y, 8.5, Font::Regular, MUTED, subtitle);
+        }
+        self.y -= 6.0;
+    }
+
+    fn note(&mut self, text: &str) {
+        self.y -= ROW_H;
+        self.text(MARGIN, self.y, 9.0, Font::Regular, MUTED, text);
+    }
+
+    fn header_row(&mut self, columns: &[Column]) {
+        self.y -= ROW_H;
+        let mut x = MARGIN;
+        for column in columns {
+            match column.align {
+                Align::Left => self.text(x, self.y, 8.0, Font::Bold, MUTED, column.title),
+                Align::Right => self.text_right(x + column.width - 4.0, self.y, 8.0, Font::Bold, MUTED, column.title),
+            }
+            x += column.width;
+        }
+        self.rule(self.y - 4.0);
+    }
+
+    /// Move to the next row, repeating the column titles after a page break
+    fn next_row(&mut self, columns: &[Column]) {
+        if self.ensure(ROW_H) {
+            self.header_row(columns);
+        }
+        self.y -= ROW_H;
+    }
+
+    /// Draw text cells; `None` leaves a cell for custom drawing
+    fn cells(&mut self, columns: &[Column], cells: &[Option<(String, Rgb)>]) {
+        let mut x = MARGIN;
+        for (column, cell) in columns.iter().zip(cells) {
+            if let Some((text, color)) = cell {
+                let text = fit(text, column.width - 6.0, Font::Regular, 9.0);
+                match column.align {
+                    Align::Left => self.text(x, self.y, 9.0, Font::Regular, *color, &text),
+                    Align::Right => self.text_right(x + column.width - 4.0, self.y, 9.0, Font::Regular, *color, &text),
+                }
+            }
+            x += column.width;
+        }
+    }
+
+    fn column_x(columns: &[Column], index: usize) -> f32 {
+        MARGIN + columns[..index].iter().map(|c| c.width).sum::<f32>()
+    }
+
+    /// Values as a polyline in a small box whose bottom-left is (x, y), with the
+    /// reference range shaded and the latest point in its status colour
+    fn sparkline(&mut self, x: f32, y: f32, values: &[f64], bounds: (Option<f64>, Option<f64>), last: Rgb) {
+        let (width, height) = (SPARK_W, SPARK_H);
+        if values.is_empty() {
+            return;
+        }
+        let mut lo = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let mut hi = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        for bound in [bounds.0, bounds.1].into_iter().flatten() {
+            lo = lo.min(bound);
+            hi = hi.max(bound);
+        }
+        if hi <= lo {
+            lo -= 1.0;
+            hi += 1.0;
+        }
+        let scale_y = |v: f64| y + ((v - lo) / (hi - lo)) as f32 * height;
+        let step = if values.len() > 1 { width / (values.len() - 1) as f32 } else { 0.0 };
+        let point = |i: usize| {
+            let px = if values.len() > 1 { x + i as f32 * step } else { x + width / 2.0 };
+            (px, scale_y(values[i]))
+        };
+
+        if bounds.0.is_some() || bounds.1.is_some() {
+            let band_lo = bounds.0.map_or(y, scale_y);
+            let band_hi = bounds.1.map_or(y + height, scale_y);
+            self.fill_rect(x, band_lo, width, (band_hi - band_lo).max(0.5), BAND);
+        }
+
+        let content = self.content();
+        content.set_stroke_rgb(MUTED.0, MUTED.1, MUTED.2);
+        content.set_line_width(0.8);
+        let (x0, y0) = point(0);
+        content.move_to(x0, y0);
+        for i in 1..values.len() {
+            let (px, py) = point(i);
+            content.line_to(px, py);
+        }
+        content.stroke();
+        let (lx, ly) = point(values.len() - 1);
+        self.fill_rect(lx - 1.5, ly - 1.5, 3.0, 3.0, last);
+    }
+}
+
+// ─── Sections ──────────────────────────────────────────────────
+
+/// History of one marker, grouped case-insensitively
+struct MarkerTrend<'a> {
+    latest: &'a LabRecord,
+    values: Vec<f64>,
+}
+
+fn marker_trends(labs: &[LabRecord]) -> Vec<MarkerTrend<'_>> {
+    let mut trends: Vec<(String, MarkerTrend)> = Vec::new();
+    for lab in labs {
+        let key = lab.marker.trim().to_lowercase();
+        match trends.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, trend)) => {
+                trend.latest = lab;
+                trend.values.push(lab.value);
+            }
+            None => trends.push((key, MarkerTrend { latest: lab, values: vec![lab.value] })),
+        }
+    }
+    trends.sort_by(|a, b| a.0.cmp(&b.0));
+    trends.into_iter().map(|(_, trend)| trend).collect()
+}
+
+fn labs_section(layout: &mut Layout, labs: &[LabRecord], lang: Language) -> usize {
+    let l = lang.labels();
+    let columns = [
+        col(l.marker, 142.0, Align::Left),
+        col(l.latest, 56.0, Align::Right),
+        col(l.unit, 52.0, Align::Left),
+        col(l.range, 74.0, Align::Left),
+        col(l.status, 50.0, Align::Left),
+        col(l.trend, 76.0, Align::Left),
+        col(l.date, 61.0, Align::Right),
+    ];
+    layout.section(l.labs, None);
+    let trends = marker_trends(labs);
+    if trends.is_empty() {
+        layout.note(l.labs_empty);
+        return 0;
+    }
+    layout.header_row(&columns);
+    for trend in &trends {
+        let lab = trend.latest;
+        let (status, color) = status_label(&lab.status, lang);
+        let range = lab.reference_range.clone().unwrap_or_default();
+        layout.next_row(&columns);
+        layout.cells(&columns, &[
+            Some((lab.marker.clone(), INK)),
+            Some((number(lab.value, 2, lang), color)),
+            Some((lab.unit.clone(), MUTED)),
+            Some((range.clone(), MUTED)),
+            Some((status, color)),
+            None,
+            Some((lab.test_date.as_deref().map(|d| date(d, lang)).unwrap_or_default(), MUTED)),
+        ]);
+        let recent = &trend.values[trend.values.len().saturating_sub(SPARK_POINTS)..];
+        let x = Layout::column_x(&columns, 5);
+        layout.sparkline(x, layout.y - 2.0, recent, fhir::reference_bounds(&range), color);
+    }
+    trends.len()
+}
+
+fn adherence_section(layout: &mut Layout, adherence: &[ProtocolAdherence], window_days: u32, lang: Language) {
+    let l = lang.labels();
+    let columns = [
+        col(l.protocol, 170.0, Align::Left),
+        col(l.dosage, 90.0, Align::Left),
+        col(l.days, 60.0, Align::Right),
+        col("%", 45.0, Align::Right),
+        col("", 146.0, Align::Left),
+    ];
+    layout.section(l.adherence, Some(&l.last_days.replace("{}", &window_days.to_string())));
+    if adherence.is_empty() {
+        layout.note(l.adherence_empty);
+        return;
+    }
+    layout.header_row(&columns);
+    for protocol in adherence {
+        let ratio = if protocol.days_expected > 0 {
+            protocol.days_taken as f64 / protocol.days_expected as f64
+        } else {
+            0.0
+        };
+        let color = match ratio {
+            r if r >= 0.8 => GOOD,
+            r if r >= 0.5 => WARN,
+            _ => BAD,
+        };
+        let note = match protocol.since {
+            Some(since) => format!("{} {}", l.since, format_day(since, lang)),
+            None => l.no_intake.to_string(),
+        };
+        layout.next_row(&columns);
+        layout.cells(&columns, &[
+            Some((protocol.name.clone(), INK)),
+            Some((protocol.dosage.clone(), MUTED)),
+            Some((format!("{}/{}", protocol.days_taken, protocol.days_expected), INK)),
+            Some((number(ratio * 100.0, 0, lang), color)),
+            None,
+        ]);
+        let x = Layout::column_x(&columns, 4) + 6.0;
+        let y = layout.y;
+        layout.fill_rect(x, y - 1.0, 60.0, 7.0, RULE);
+        layout.fill_rect(x, y - 1.0, 60.0 * ratio.clamp(0.0, 1.0) as f32, 7.0, color);
+        layout.text(x + 66.0, y, 8.0, Font::Regular, MUTED, &note);
+    }
+}
+
+fn vitals_section(layout: &mut Layout, vitals: &[VitalSummary], window_days: u32, lang: Language) {
+    let l = lang.labels();
+    let columns = [
+        col(l.measure, 150.0, Align::Left),
+        col(l.mean, 62.0, Align::Right),
+        col(l.min, 58.0, Align::Right),
+        col(l.max, 58.0, Align::Right),
+        col(l.latest, 70.0, Align::Right),
+        col(l.readings, 55.0, Align::Right),
+        col(l.date, 58.0, Align::Right),
+    ];
+    layout.section(l.vitals, Some(&l.last_days.replace("{}", &window_days.to_string())));
+    if vitals.is_empty() {
+        layout.note(l.vitals_empty);
+        return;
+    }
+    layout.header_row(&columns);
+    for vital in vitals {
+        let unit = if vital.per_day { format!("{} {}", vital.unit, l.per_day) } else { vital.unit.clone() };
+        layout.next_row(&columns);
+        layout.cells(&columns, &[
+            Some((format!("{} ({})", vital_label(&vital.vital_type, lang), unit), INK)),
+            Some((measure(vital.mean, lang), INK)),
+            Some((measure(vital.min, lang), MUTED)),
+            Some((measure(vital.max, lang), MUTED)),
+            Some((measure(vital.latest, lang), INK)),
+            Some((vital.count.to_string(), MUTED)),
+            Some((date(&vital.latest_at, lang), MUTED)),
+        ]);
+    }
+}
+
+fn exams_section(layout: &mut Layout, exams: &[ScheduleRecord], today: NaiveDate, lang: Language) {
+    let l = lang.labels();
+    let columns = [
+        col(l.date, 112.0, Align::Left),
+        col(l.exam, 150.0, Align::Left),
+        col(l.reason, 249.0, Align::Left),
+    ];
+    layout.section(l.exams, None);
+    if exams.is_empty() {
+        layout.note(l.exams_empty);
+        return;
+    }
+    layout.header_row(&columns);
+    let today = today.format("%Y-%m-%d").to_string();
+    for exam in exams {
+        let overdue = exam.scheduled_date.get(..10).is_some_and(|d| d < today.as_str());
+        let (when, color) = if overdue {
+            (format!("{} ({})", date(&exam.scheduled_date, lang), l.overdue), WARN)
+        } else {
+            (date(&exam.scheduled_date, lang), INK)
+        };
+        layout.next_row(&columns);
+        layout.cells(&columns, &[
+            Some((when, color)),
+            Some((exam.exam_type.clone(), INK)),
+            Some((exam.reason.clone(), MUTED)),
+        ]);
+    }
+}
+
+fn footers(layout: &mut Layout, lang: Language) {
+    let l = lang.labels();
+    let total = layout.pages.len();
+    for index in 0..total {
+        layout.current = index;
+        layout.rule(BOTTOM - 20.0);
+        layout.text(MARGIN, BOTTOM - 32.0, 7.0, Font::Regular, MUTED, l.disclaimer);
+        let label = format!("{} {} {} {}", l.page, index + 1, l.of, total);
+        layout.text_right(PAGE_W - MARGIN, BOTTOM - 32.0, 7.0, Font::Regular, MUTED, &label);
+    }
+}
+
+/// Lay out and serialize the report
+pub fn render(data: &ReportData, lang: Language) -> RenderedReport {
+    let l = lang.labels();
+    let mut layout = Layout::new();
+
+    layout.y -= 16.0;
+    layout.text(MARGIN, layout.y, 18.0, Font::Bold, INK, l.title);
+    let generated = format!("{} {}", l.generated, format_day(data.generated_on, lang));
+    layout.text_right(PAGE_W - MARGIN, layout.y, 9.0, Font::Regular, MUTED, &generated);
+    layout.y -= 10.0;
+    layout.rule(layout.y);
+
+    let markers = labs_section(&mut layout, &data.labs, lang);
+    adherence_section(&mut layout, &data.adherence, data.window_days, lang);
+    vitals_section(&mut layout, &data.vitals, data.window_days, lang);
+    exams_section(&mut layout, &data.exams, data.generated_on, lang);
+    footers(&mut layout, lang);
+
+    let pages = layout.pages.len();
+    RenderedReport { bytes: write_pdf(layout.pages, l.title, data.generated_on), pages, markers }
+}
+
+fn write_pdf(pages: Vec<Content>, title: &str, generated_on: NaiveDate) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    // Page n is object 6 + 2n, its content stream the next one
+    let ids: Vec<(Ref, Ref)> = (0..pages.len() as i32)
+        .map(|n| (Ref::new(6 + 2 * n), Ref::new(7 + 2 * n)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id).kids(ids.iter().map(|(page, _)| *page)).count(ids.len() as i32);
+    pdf.type1_font(regular_id).base_font(Name(b"Helvetica")).encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id).base_font(Name(b"Helvetica-Bold")).encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    for ((page_id, content_id), content) in ids.iter().zip(pages) {
+        {
+            let mut page = pdf.page(*page_id);
+            page.parent(tree_id)
+                .media_box(Rect::new(0.0, 0.0, PAGE_W, PAGE_H))
+                .contents(*content_id);
+            page.resources().fonts()
+                .pair(Name(b"F1"), regular_id)
+                .pair(Name(b"F2"), bold_id);
+        }
+        pdf.stream(*content_id, &content.finish());
+    }
+
+    pdf.document_info(info_id)
+        .title(TextStr(title))
+        .producer(TextStr("HoloSelf OS"))
+        .creation_date(
+            Date::new(generated_on.year() as u16)
+                .month(generated_on.month() as u8)
+                .day(generated_on.day() as u8),
+        );
+    pdf.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn lab(marker: &str, value: f64, unit: &str, range: &str, status: &str, test_date: &str) -> LabRecord {
+        LabRecord {
+            id: None,
+            marker: marker.to_string(),
+            value,
+            unit: unit.to_string(),
+            reference_range: Some(range.to_string()),
+            status: status.to_string(),
+            lab_name: None,
+            test_date: Some(test_date.to_string()),
+            pdf_source: None,
+        }
+    }
+
+    fn vital(vital_type: &str, unit: &str, per_day: bool, count: u32, stats: [f64; 4], latest_at: &str) -> VitalSummary {
+        VitalSummary {
+            vital_type: vital_type.to_string(),
+            unit: unit.to_string(),
+            per_day,
+            count,
+            mean: stats[0],
+            min: stats[1],
+            max: stats[2],
+            latest: stats[3],
+            latest_at: latest_at.to_string(),
+        }
+    }
+
+    fn exam(exam_type: &str, reason: &str, scheduled_date: &str) -> ScheduleRecord {
+        ScheduleRecord {
+            id: None,
+            exam_type: exam_type.to_string(),
+            reason: reason.to_string(),
+            scheduled_date: scheduled_date.to_string(),
+            triggered_by: None,
+            completed: false,
+        }
+    }
+
+    fn sample() -> ReportData {
+        ReportData {
+            generated_on: day("2026-03-15"),
+            window_days: 30,
+            labs: vec![
+                lab("Vitamina D", 18.4, "ng/mL", "30-100", "low", "2025-09-02"),
+                lab("Ferritina", 45.0, "ng/mL", "30-400", "normal", "2025-09-02"),
+                lab("vitamina d", 27.25, "ng/mL", "30-100", "low", "2026-02-20"),
+                lab("Colesterol LDL (calculado pela fórmula de Friedewald)", 131.0, "mg/dL", "< 116", "high", "2026-02-20"),
+            ],
+            adherence: vec![
+                ProtocolAdherence {
+                    name: "Vitamina D3".to_string(),
+                    dosage: "2000 UI".to_string(),
+                    days_taken: 27,
+                    days_expected: 30,
+                    since: Some(day("2026-02-14")),
+                },
+                ProtocolAdherence {
+                    name: "Magnésio".to_string(),
+                    dosage: "300 mg".to_string(),
+                    days_taken: 0,
+                    days_expected: 30,
+                    since: None,
+                },
+            ],
+            vitals: vec![
+                vital("resting_heart_rate", "bpm", false, 28, [58.43, 54.0, 63.0, 57.0], "2026-03-14T07:10:00Z"),
+                vital("steps", "steps", true, 30, [8432.6, 2104.0, 15321.0, 9120.0], "2026-03-14T21:00:00Z"),
+                vital("sleep", "h", true, 29, [7.125, 5.5, 8.75, 0.82], "2026-03-15T06:30:00Z"),
+            ],
+            exams: vec![
+                exam("Análises de rotina", "Controlo da vitamina D", "2026-03-01"),
+                exam("Perfil lipídico", "LDL acima da referência", "2026-05-20"),
+            ],
+        }
+    }
+
+    fn empty() -> ReportData {
+        ReportData {
+            generated_on: day("2026-03-15"),
+            window_days: 90,
+            labs: Vec::new(),
+            adherence: Vec::new(),
+            vitals: Vec::new(),
+            exams: Vec::new(),
+        }
+    }
+
+    /// WinAnsi bytes back to text (the inverse of `win_ansi`)
+    fn decode(bytes: &[u8]) -> String {
+        bytes.iter()
+            .map(|&b| match b {
+                0x80 => '€',
+                0x85 => '…',
+                0x91 => '‘',
+                0x92 => '’',
+                0x93 => '“',
+                0x94 => '”',
+                0x95 => '•',
+                0x96 => '–',
+                0x97 => '—',
+                _ => b as char,
+            })
+            .collect()
+    }
+
+    /// Operand of a `Tj`: a literal `(...)` or hex `<...>` string
+    fn shown(operand: &str) -> Vec<u8> {
+        if let Some(hex) = operand.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            return (0..hex.len()).step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect();
+        }
+        let literal = operand.strip_prefix('(').and_then(|s| s.strip_suffix(')')).unwrap();
+        let mut out = Vec::new();
+        let mut chars = literal.bytes();
+        while let Some(b) = chars.next() {
+            if b != b'\\' {
+                out.push(b);
+                continue;
+            }
+            match chars.next().unwrap() {
+                b'n' => out.push(b'\n'),
+                d @ b'0'..=b'7' => {
+                    let mut code = (d - b'0') as u32;
+                    for _ in 0..2 {
+                        code = code * 8 + (chars.next().unwrap() - b'0') as u32;
+                    }
+                    out.push(code as u8);
+                }
+                other => out.push(other),
+            }
+        }
+        out
+    }
+
+    /// Text drawn on each page, one line per `Tj`, in drawing order
+    fn page_text(pdf: &[u8]) -> String {
+        let raw = String::from_utf8_lossy(pdf);
+        let mut out = String::new();
+        let chunks: Vec<&str> = raw.split("\nendstream").collect();
+        for (index, chunk) in chunks[..chunks.len() - 1].iter().enumerate() {
+            let stream = chunk.rsplit("stream\n").next().unwrap();
+            out.push_str(&format!("--- page {} ---\n", index + 1));
+            for line in stream.lines() {
+                if let Some(operand) = line.strip_suffix(" Tj") {
+                    out.push_str(&decode(&shown(operand)));
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+
+    fn assert_golden(name: &str, report: &RenderedReport) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/health_report").join(name);
+        let expected = std::fs::read_to_string(&path).unwrap();
+        let actual = page_text(&report.bytes);
+        assert!(actual == expected, "{} no longer matches; rendered text:\n{}", name, actual);
+    }
+
+    #[test]
+    fn portuguese_report_matches_the_golden_text() {
+        let report = render(&sample(), Language::Pt);
+        assert_eq!((report.pages, report.markers), (1, 3));
+        assert_golden("pt.txt", &report);
+    }
+
+    #[test]
+    fn english_report_matches_the_golden_text() {
+        let report = render(&sample(), Language::En);
+        assert_eq!((report.pages, report.markers), (1, 3));
+        assert_golden("en.txt", &report);
+    }
+
+    #[test]
+    fn empty_report_shows_every_section_note() {
+        let report = render(&empty(), Language::Pt);
+        assert_eq!((report.pages, report.markers), (1, 0));
+        assert_golden("empty.txt", &report);
+    }
+
+    #[test]
+    fn same_data_renders_the_same_bytes() {
+        assert_eq!(render(&sample(), Language::En).bytes, render(&sample(), Language::En).bytes);
+    }
+}
@@ -0,0 +1,96 @@
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Outbound HTTP for the service layer. The app talks through reqwest; tests and offline
+// runs hand the services a `CannedHttp` that answers from fixtures instead of the network.
+
+pub type HttpFuture<'a> = Pin<Box<dyn Future<Output = Result<Value, String>> + Send + 'a>>;
+
+pub trait HttpClient: Send + Sync {
+    /// POST `body` as JSON and parse the JSON reply; non-2xx statuses are errors
+    fn post_json<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, &'a str)], body: Value, timeout: Duration) -> HttpFuture<'a>;
+
+    /// GET `url` and parse the JSON reply; non-2xx statuses are errors
+    fn get_json<'a>(&'a self, url: &'a str, timeout: Duration) -> HttpFuture<'a>;
+}
+
+/// Shared HTTP handle held by services
+pub type SharedHttp = Arc<dyn HttpClient>;
+
+#[derive(Default)]
+pub struct ReqwestClient {
+    http: reqwest::Client,
+}
+
+impl HttpClient for ReqwestClient {
+    fn post_json<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, &'a str)], body: Value, timeout: Duration) -> HttpFuture<'a> {
+        Box::pin(async move {
+            let mut request = self.http.post(url).json(&body).timeout(timeout);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            read_json(request.send().await).await
+        })
+    }
+
+    fn get_json<'a>(&'a self, url: &'a str, timeout: Duration) -> HttpFuture<'a> {
+        Box::pin(async move { read_json(self.http.get(url).timeout(timeout).send().await).await })
+    }
+}
+
+/// JSON body of a 2xx response; anything else becomes an error message
+async fn read_json(sent: reqwest::Result<reqwest::Response>) -> Result<Value, String> {
+    let response = sent.map_err(|e| format!("Request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response.text().await
+            .unwrap_or_else(|_| "<unable to read error body>".to_string());
+        return Err(format!("HTTP {}: {}", status, error_body));
+    }
+    response.json().await.map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+/// Replies queued up front, in order; once they run out every request fails as offline.
+/// Keeps what was sent so tests can check the request.
+#[derive(Default)]
+pub struct CannedHttp {
+    replies: Mutex<VecDeque<Result<Value, String>>>,
+    sent: Mutex<Vec<(String, Value)>>,
+}
+
+impl CannedHttp {
+    /// No replies: every request fails
+    pub fn offline() -> Self {
+        Self::default()
+    }
+
+    pub fn reply(&self, reply: Result<Value, String>) {
+        self.replies.lock().unwrap_or_else(|e| e.into_inner()).push_back(reply);
+    }
+
+    /// (url, body) of every request so far; GETs have a null body
+    pub fn sent(&self) -> Vec<(String, Value)> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn answer<'a>(&'a self, url: &str, body: Value) -> HttpFuture<'a> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).push((url.to_string(), body));
+        let reply = self.replies.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+        Box::pin(async move { reply.unwrap_or_else(|| Err("offline".to_string())) })
+    }
+}
+
+impl HttpClient for CannedHttp {
+    fn post_json<'a>(&'a self, url: &'a str, _headers: &'a [(&'a str, &'a str)], body: Value, _timeout: Duration) -> HttpFuture<'a> {
+        self.answer(url, body)
+    }
+
+    fn get_json<'a>(&'a self, url: &'a str, _timeout: Duration) -> HttpFuture<'a> {
+        self.answer(url, Value::Null)
+    }
+}
@@ -0,0 +1,406 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Opt-in local HTTP API for third-party sensors (scales, BP cuffs, scripts).
+//
+// Bound to 127.0.0.1 only. Every request needs `Authorization: Bearer <token>`; tokens are
+// issued per client from the settings panel and only their SHA-256 is stored. Requests
+// from a browser (with an Origin header) are refused so a web page can't post readings.
+
+pub const DEFAULT_PORT: u16 = 47_830;
+pub const MAX_BODY_BYTES: u64 = 64 * 1024;
+pub const MAX_RECORDS_PER_REQUEST: usize = 500;
+pub const RATE_LIMIT_PER_MINUTE: f64 = 60.0; // requests per client
+pub const RATE_LIMIT_BURST: f64 = 20.0;
+const ACCEPT_POLL_MS: u64 = 500;
+
+/// One vital reading as posted by a client
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VitalInput {
+    pub vital_type: String,
+    pub value: f64,
+    pub unit: Option<String>,        // defaults to the canonical unit of the type
+    pub recorded_at: Option<String>, // ISO 8601, defaults to now
+}
+
+/// One supplement intake as posted by a client
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SupplementInput {
+    pub name: String,
+    pub dosage: String,
+    pub taken_at: Option<String>,
+    pub category: Option<String>, // morning | night | as_needed (default)
+    pub notes: Option<String>,
+}
+
+/// Accepted vital types: canonical unit and plausible range
+const VITAL_RULES: &[(&str, &str, f64, f64)] = &[
+    ("heart_rate", "bpm", 20.0, 250.0),
+    ("resting_heart_rate", "bpm", 20.0, 200.0),
+    ("hrv", "ms", 1.0, 500.0),
+    ("blood_pressure_systolic", "mmHg", 50.0, 260.0),
+    ("blood_pressure_diastolic", "mmHg", 30.0, 160.0),
+    ("spo2", "%", 50.0, 100.0),
+    ("body_temperature", "°C", 30.0, 45.0),
+    ("weight", "kg", 20.0, 400.0),
+    ("body_fat", "%", 1.0, 75.0),
+    ("glucose", "mg/dL", 20.0, 600.0),
+    ("steps", "steps", 1.0, 100_000.0),
+    ("sleep", "min", 1.0, 1440.0),
+];
+
+const SUPPLEMENT_CATEGORIES: [&str; 3] = ["morning", "night", "as_needed"];
+
+/// Check one vital against the schema; returns (type, value, unit, recorded_at)
+pub fn validate_vital(
+    input: &VitalInput,
+    tz: chrono_tz::Tz,
+    now: DateTime<Utc>,
+) -> Result<(String, f64, String, DateTime<Utc>), String> {
+    let Some(&(vital_type, unit, min, max)) = VITAL_RULES.iter().find(|r| r.0 == input.vital_type) else {
+        let known: Vec<&str> = VITAL_RULES.iter().map(|r| r.0).collect();
+        return Err(format!("vital_type desconhecido '{}' (aceites: {})", input.vital_type, known.join(", ")));
+    };
+    if let Some(given) = input.unit.as_deref().filter(|u| *u != unit) {
+        return Err(format!("unidade '{}' inválida para {} (use '{}')", given, vital_type, unit));
+    }
+    if !input.value.is_finite() || !(min..=max).contains(&input.value) {
+        return Err(format!("valor {} fora do intervalo de {} ({}–{} {})", input.value, vital_type, min, max, unit));
+    }
+    let recorded_at = parse_time(input.recorded_at.as_deref(), tz, now)?;
+    Ok((vital_type.to_string(), input.value, unit.to_string(), recorded_at))
+}
+
+/// Check one supplement intake; returns (name, dosage, taken_at, category)
+pub fn validate_supplement(
+    input: &SupplementInput,
+    tz: chrono_tz::Tz,
+    now: DateTime<Utc>,
+) -> Result<(String, String, DateTime<Utc>, String), String> {
+    let name = input.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err("name deve ter entre 1 e 100 caracteres".to_string());
+    }
+    if input.dosage.trim().is_empty() || input.dosage.len() > 50 {
+        return Err("dosage deve ter entre 1 e 50 caracteres".to_string());
+    }
+    if input.notes.as_ref().is_some_and(|n| n.len() > 500) {
+        return Err("notes não pode exceder 500 caracteres".to_string());
+    }
+    let category = input.category.as_deref().unwrap_or("as_needed");
+    if !SUPPLEMENT_CATEGORIES.contains(&category) {
+        return Err(format!("category desconhecida '{}' (aceites: {})", category, SUPPLEMENT_CATEGORIES.join(", ")));
+    }
+    let taken_at = parse_time(input.taken_at.as_deref(), tz, now)?;
+    Ok((name.to_string(), input.dosage.trim().to_string(), taken_at, category.to_string()))
+}
+
+fn parse_time(raw: Option<&str>, tz: chrono_tz::Tz, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let Some(raw) = raw else { return Ok(now) };
+    let at = crate::services::time::parse_instant(raw, tz)
+        .ok_or_else(|| format!("data/hora inválida '{}'", raw))?;
+    // Small allowance for clock skew between the sensor script and this machine
+    if at > now + chrono::Duration::minutes(5) {
+        return Err(format!("data/hora no futuro '{}'", raw));
+    }
+    Ok(at)
+}
+
+/// New client token; shown once, only its hash is stored
+pub fn generate_token() -> String {
+    format!("hs_{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Token bucket per key (client id, or the remote IP for failed auth)
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: HashMap<String, (f64, DateTime<Utc>)>,
+    swept_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimiter {
+    /// Take one request from `key`'s bucket; Err(seconds until the next one) when empty
+    pub fn check(&mut self, key: &str, now: DateTime<Utc>) -> Result<(), u64> {
+        self.evict_idle(now);
+        let (tokens, last) = self.buckets.entry(key.to_string()).or_insert((RATE_LIMIT_BURST, now));
+        *tokens = refilled(*tokens, *last, now);
+        *last = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - *tokens) / refill_per_sec()).ceil() as u64)
+        }
+    }
+
+    /// Forget buckets that have refilled completely: a new bucket starts full anyway.
+    /// Swept at most once per refill period, so a check stays cheap.
+    fn evict_idle(&mut self, now: DateTime<Utc>) {
+        let period = chrono::Duration::milliseconds((RATE_LIMIT_BURST / refill_per_sec() * 1000.0) as i64);
+        if self.swept_at.is_some_and(|at| now - at < period) {
+            return;
+        }
+        self.swept_at = Some(now);
+        self.buckets.retain(|_, (tokens, last)| refilled(*tokens, *last, now) < RATE_LIMIT_BURST);
+    }
+}
+
+fn refill_per_sec() -> f64 {
+    RATE_LIMIT_PER_MINUTE / 60.0
+}
+
+fn refilled(tokens: f64, last: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let elapsed = (now - last).num_milliseconds().max(0) as f64 / 1000.0;
+    (tokens + elapsed * refill_per_sec()).min(RATE_LIMIT_BURST)
+}
+
+/// Caps audit rows for rejected requests (bad token, rate limited) at one per key per
+/// minute, so a misconfigured script can't flood the audit log
+#[derive(Debug, Default)]
+pub struct RejectionAudit {
+    last: HashMap<String, (DateTime<Utc>, u32)>, // last written row, rejections left out since
+}
+
+impl RejectionAudit {
+    /// Some(left out since the previous row) when this rejection gets a row, None to skip it
+    pub fn admit(&mut self, key: &str, now: DateTime<Utc>) -> Option<u32> {
+        let minute = chrono::Duration::minutes(1);
+        // Keys quiet for an hour have nothing worth reporting any more
+        self.last.retain(|_, (at, _)| now - *at < chrono::Duration::hours(1));
+        match self.last.get_mut(key) {
+            Some((at, skipped)) if now - *at < minute => {
+                *skipped += 1;
+                None
+            }
+            Some((at, skipped)) => {
+                *at = now;
+                Some(std::mem::take(skipped))
+            }
+            None => {
+                self.last.insert(key.to_string(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// What the handler sees of an HTTP request
+#[derive(Debug)]
+pub struct ApiRequest {
+    pub method: String,
+    pub path: String,
+    pub bearer: Option<String>,
+    pub remote: Option<std::net::IpAddr>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+    pub retry_after: Option<u64>,
+}
+
+impl ApiResponse {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self { status, body, retry_after: None }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, serde_json::json!({ "error": message.into() }))
+    }
+}
+
+/// Service status for the settings panel
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IngestApiStatus {
+    pub running: bool,
+    pub address: Option<String>,
+    pub error: Option<String>,
+}
+
+struct Running {
+    stop: Arc<AtomicBool>,
+    port: u16,
+}
+
+/// Managed state: at most one listening server
+#[derive(Default)]
+pub struct IngestApiState {
+    running: Mutex<Option<Running>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl IngestApiState {
+    pub fn status(&self) -> IngestApiStatus {
+        let port = self.running.lock().ok().and_then(|r| r.as_ref().map(|r| r.port));
+        IngestApiStatus {
+            running: port.is_some(),
+            address: port.map(|p| format!("http://127.0.0.1:{}", p)),
+            error: self.last_error.lock().ok().and_then(|e| e.clone()),
+        }
+    }
+
+    /// Listen on 127.0.0.1:`port`, passing every acceptable request to `handler`.
+    /// A running server on another port is restarted.
+    pub fn start<F>(&self, port: u16, handler: F) -> Result<(), String>
+    where
+        F: FnMut(ApiRequest) -> ApiResponse + Send + 'static,
+    {
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        match running.as_ref() {
+            Some(r) if r.port == port => return Ok(()),
+            Some(r) => r.stop.store(true, Ordering::Relaxed),
+            None => {}
+        }
+        *running = None;
+
+        let result = tiny_http::Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Não foi possível abrir a porta {}: {}", port, e));
+        if let Ok(last_error) = self.last_error.lock().as_deref_mut() {
+            *last_error = result.as_ref().err().cloned();
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        spawn_server(result?, stop.clone(), handler);
+        *running = Some(Running { stop, port });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut running) = self.running.lock() {
+            if let Some(r) = running.take() {
+                r.stop.store(true, Ordering::Relaxed);
+                log::info!("Ingestion API stopped");
+            }
+        }
+    }
+}
+
+fn spawn_server<F>(server: tiny_http::Server, stop: Arc<AtomicBool>, mut handler: F)
+where
+    F: FnMut(ApiRequest) -> ApiResponse + Send + 'static,
+{
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(Duration::from_millis(ACCEPT_POLL_MS)) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("Ingestion API accept failed: {}", e);
+                    continue;
+                }
+            };
+            let (request, response) = screen(request, &mut handler);
+            if let Err(e) = respond(request, response) {
+                log::warn!("Ingestion API response failed: {}", e);
+            }
+        }
+        // Dropping the server closes the socket
+    });
+}
+
+/// Transport-level checks, then the handler
+fn screen<F>(mut request: tiny_http::Request, handler: &mut F) -> (tiny_http::Request, ApiResponse)
+where
+    F: FnMut(ApiRequest) -> ApiResponse,
+{
+    let remote = request.remote_addr().copied();
+    if !remote.is_some_and(|addr| addr.ip().is_loopback()) {
+        return (request, ApiResponse::error(403, "Apenas pedidos locais são aceites."));
+    }
+    let header = |name: &str| {
+        request.headers().iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str().to_string())
+    };
+    if header("Origin").is_some() {
+        return (request, ApiResponse::error(403, "Pedidos de navegadores não são aceites."));
+    }
+    let bearer = header("Authorization")
+        .and_then(|v| v.strip_prefix("Bearer ").map(|t| t.trim().to_string()));
+    if request.body_length().is_some_and(|len| len as u64 > MAX_BODY_BYTES) {
+        return (request, ApiResponse::error(413, format!("Corpo excede {} KB.", MAX_BODY_BYTES / 1024)));
+    }
+
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().take(MAX_BODY_BYTES + 1).read_to_end(&mut body) {
+        return (request, ApiResponse::error(400, format!("Falha ao ler o corpo: {}", e)));
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return (request, ApiResponse::error(413, format!("Corpo excede {} KB.", MAX_BODY_BYTES / 1024)));
+    }
+
+    let api_request = ApiRequest {
+        method: request.method().as_str().to_uppercase(),
+        path: request.url().split('?').next().unwrap_or("/").to_string(),
+        bearer,
+        remote: remote.map(|a| a.ip()),
+        body,
+    };
+    let response = handler(api_request);
+    (request, response)
+}
+
+fn respond(request: tiny_http::Request, response: ApiResponse) -> std::io::Result<()> {
+    let mut reply = tiny_http::Response::from_string(response.body.to_string())
+        .with_status_code(response.status)
+        .with_header(tiny_http::Header::from_bytes("Content-Type", "application/json").expect("static header"));
+    if let Some(seconds) = response.retry_after {
+        if let Ok(h) = tiny_http::Header::from_bytes("Retry-After", seconds.to_string()) {
+            reply.add_header(h);
+        }
+    }
+    request.respond(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_782_900_000 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn buckets_refill_and_idle_ones_are_forgotten() {
+        let mut limiter = RateLimiter::default();
+        for _ in 0..RATE_LIMIT_BURST as usize {
+            limiter.check("client:1", at(0)).unwrap();
+        }
+        assert_eq!(limiter.check("client:1", at(0)), Err(1));
+        assert!(limiter.check("client:1", at(1)).is_ok());
+        for _ in 0..RATE_LIMIT_BURST as usize {
+            limiter.check("anonymous:127.0.0.1", at(25)).unwrap();
+        }
+
+        // client:1 is full again after 20 s of quiet: dropping its bucket changes nothing
+        limiter.check("client:2", at(30)).unwrap();
+        assert_eq!(limiter.buckets.len(), 2);
+        assert!(limiter.buckets.contains_key("client:2") && limiter.buckets.contains_key("anonymous:127.0.0.1"));
+        limiter.check("client:2", at(60)).unwrap();
+        assert_eq!(limiter.buckets.keys().collect::<Vec<_>>(), ["client:2"]);
+    }
+
+    #[test]
+    fn one_rejection_row_per_key_and_minute() {
+        let mut audit = RejectionAudit::default();
+        assert_eq!(audit.admit("anonymous:127.0.0.1", at(0)), Some(0));
+        assert_eq!(audit.admit("anonymous:127.0.0.1", at(10)), None);
+        assert_eq!(audit.admit("anonymous:127.0.0.1", at(59)), None);
+        assert_eq!(audit.admit("client:1", at(59)), Some(0));
+        assert_eq!(audit.admit("anonymous:127.0.0.1", at(60)), Some(2));
+        assert_eq!(audit.admit("anonymous:127.0.0.1", at(121)), Some(0));
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{DateTime, Timelike, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Opt-in system-wide typing cadence from Linux input devices (evdev).
+//
+// Privacy guarantee: key codes are only used to decide "is this a typing key?" inside
+// the reader loop and are dropped immediately. The only state ever kept is a keypress
+// COUNT per wall-clock minute, in memory; completed minutes are handed to the WPM
+// pipeline as a single number. No key identities, characters or per-key timestamps
+// are stored, logged or sent anywhere.
+
+const CHARS_PER_WORD: f64 = 5.0;
+const FLUSH_INTERVAL_SECS: u64 = 60;
+const POLL_INTERVAL_MS: u64 = 50;
+
+/// Keypress counts per minute — nothing else
+#[derive(Debug, Default)]
+pub struct CadenceAggregator {
+    minutes: BTreeMap<DateTime<Utc>, u32>,
+}
+
+impl CadenceAggregator {
+    pub fn record_keypress(&mut self, at: DateTime<Utc>) {
+        let minute = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(at);
+        *self.minutes.entry(minute).or_default() += 1;
+    }
+
+    /// Remove finished minutes (before the one containing `now`) as (minute, WPM)
+    pub fn drain_completed(&mut self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, f64)> {
+        let current = now.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(now);
+        let pending = self.minutes.split_off(&current);
+        std::mem::replace(&mut self.minutes, pending)
+            .into_iter()
+            .map(|(minute, presses)| (minute, presses as f64 / CHARS_PER_WORD))
+            .collect()
+    }
+}
+
+/// Service status for the settings panel
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct CadenceStatus {
+    pub supported: bool,
+    pub running: bool,
+    pub devices: Vec<String>, // device names, e.g. "AT Translated Set 2 keyboard"
+    pub error: Option<String>,
+}
+
+struct Running {
+    stop: Arc<AtomicBool>,
+    live: Arc<AtomicUsize>,
+    devices: Vec<String>,
+}
+
+/// Held by each reader thread while it polls its device. When the last one exits
+/// (unplugged keyboards, lost permissions) it stops the flusher, which drains and ends.
+pub(crate) struct LiveReader {
+    live: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+}
+
+impl LiveReader {
+    fn enter(live: &Arc<AtomicUsize>, stop: &Arc<AtomicBool>) -> Self {
+        live.fetch_add(1, Ordering::SeqCst);
+        Self { live: live.clone(), stop: stop.clone() }
+    }
+}
+
+impl Drop for LiveReader {
+    fn drop(&mut self) {
+        if self.live.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Managed state: at most one running reader set
+#[derive(Default)]
+pub struct InputCadenceState {
+    running: Mutex<Option<Running>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl InputCadenceState {
+    /// The running reader set, cleared once every reader has exited
+    fn running(&self) -> Option<std::sync::MutexGuard<'_, Option<Running>>> {
+        let mut running = self.running.lock().ok()?;
+        if running.as_ref().is_some_and(|r| r.live.load(Ordering::SeqCst) == 0) {
+            *running = None;
+            if let Ok(last_error) = self.last_error.lock().as_deref_mut() {
+                *last_error = Some("Nenhum teclado está a ser lido (dispositivos desligados ou sem permissão).".to_string());
+            }
+            log::warn!("Input cadence service stopped: every reader exited");
+        }
+        Some(running)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running().is_some_and(|r| r.is_some())
+    }
+
+    pub fn status(&self) -> CadenceStatus {
+        let devices = self.running()
+            .and_then(|r| r.as_ref().map(|r| r.devices.clone()))
+            .unwrap_or_default();
+        CadenceStatus {
+            supported: cfg!(target_os = "linux"),
+            running: !devices.is_empty(),
+            devices,
+            error: self.last_error.lock().ok().and_then(|e| e.clone()),
+        }
+    }
+
+    /// Start reading every keyboard, flushing finished minutes to `sink` once a minute
+    pub fn start<F>(&self, sink: F) -> Result<(), String>
+    where
+        F: Fn(Vec<(DateTime<Utc>, f64)>) + Send + 'static,
+    {
+        let mut running = self.running().ok_or("Input cadence state is poisoned.")?;
+        if running.is_some() {
+            return Ok(());
+        }
+
+        let result = spawn_readers(sink);
+        if let Ok(last_error) = self.last_error.lock().as_deref_mut() {
+            *last_error = result.as_ref().err().cloned();
+        }
+        *running = Some(result?);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(mut running) = self.running() {
+            if let Some(r) = running.take() {
+                r.stop.store(true, Ordering::Relaxed);
+                log::info!("Input cadence service stopped");
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn spawn_readers<F>(sink: F) -> Result<Running, String>
+where
+    F: Fn(Vec<(DateTime<Utc>, f64)>) + Send + 'static,
+{
+    let keyboards: Vec<(std::path::PathBuf, evdev::Device)> = evdev::enumerate()
+        .filter(|(_, device)| linux::is_keyboard(device))
+        .collect();
+    if keyboards.is_empty() {
+        return Err(
+            "Nenhum teclado acessível em /dev/input (adicione o utilizador ao grupo 'input').".to_string(),
+        );
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let live = Arc::new(AtomicUsize::new(0));
+    let aggregator = Arc::new(Mutex::new(CadenceAggregator::default()));
+    let mut names = Vec::new();
+    for (path, device) in keyboards {
+        let name = device.name().unwrap_or("keyboard").to_string();
+        log::info!("Input cadence: reading {} ({:?})", name, path);
+        names.push(name);
+        linux::spawn_reader(device, aggregator.clone(), LiveReader::enter(&live, &stop));
+    }
+    spawn_flusher(aggregator, stop.clone(), sink);
+    Ok(Running { stop, live, devices: names })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn spawn_readers<F>(_sink: F) -> Result<Running, String>
+where
+    F: Fn(Vec<(DateTime<Utc>, f64)>) + Send + 'static,
+{
+    Err("Ritmo de escrita do sistema só está disponível em Linux.".to_string())
+}
+
+/// Hand finished minutes to the WPM pipeline once a minute (and on stop)
+fn spawn_flusher<F>(aggregator: Arc<Mutex<CadenceAggregator>>, stop: Arc<AtomicBool>, sink: F)
+where
+    F: Fn(Vec<(DateTime<Utc>, f64)>) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut elapsed_ms = 0;
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            elapsed_ms += 500;
+            let stopping = stop.load(Ordering::Relaxed);
+            if !stopping && elapsed_ms < FLUSH_INTERVAL_SECS * 1000 {
+                continue;
+            }
+            elapsed_ms = 0;
+
+            let minutes = aggregator.lock()
+                .map(|mut a| a.drain_completed(Utc::now()))
+                .unwrap_or_default();
+            if !minutes.is_empty() {
+                sink(minutes);
+            }
+            if stopping {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod linux {
+    use super::*;
+    use evdev::{Device, EventSummary, KeyCode};
+
+    /// A device that can type text (letters + space), not a power button or mouse
+    pub fn is_keyboard(device: &Device) -> bool {
+        device.supported_keys().is_some_and(|keys| {
+            keys.contains(KeyCode::KEY_A) && keys.contains(KeyCode::KEY_Z) && keys.contains(KeyCode::KEY_SPACE)
+        })
+    }
+
+    /// Keys that produce text or edit it — same set the HUD keydown hook counts
+    fn is_typing_key(key: KeyCode) -> bool {
+        matches!(key.code(), 2..=13 | 14 | 16..=27 | 28 | 30..=41 | 43..=53 | 57)
+    }
+
+    /// Poll one device until stopped; only key-down events of typing keys are counted.
+    /// `reader` is released when the thread exits, for whatever reason.
+    pub fn spawn_reader(mut device: Device, aggregator: Arc<Mutex<CadenceAggregator>>, reader: LiveReader) {
+        std::thread::spawn(move || {
+            let reader = reader; // released on every exit path below
+            if let Err(e) = device.set_nonblocking(true) {
+                log::warn!("Input cadence: cannot poll device: {}", e);
+                return;
+            }
+            while !reader.stop.load(Ordering::Relaxed) {
+                match device.fetch_events() {
+                    Ok(events) => {
+                        // Count first, then drop the events (and their key codes) before locking
+                        let presses = events
+                            .filter(|ev| matches!(ev.destructure(), EventSummary::Key(_, key, 1) if is_typing_key(key)))
+                            .count();
+                        if presses > 0 {
+                            if let Ok(mut agg) = aggregator.lock() {
+                                let now = Utc::now();
+                                for _ in 0..presses {
+                                    agg.record_keypress(now);
+                                }
+                            }
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(e) => {
+                        log::warn!("Input cadence: device read failed, stopping reader: {}", e);
+                        return;
+                    }
+                }
+                std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn aggregator_keeps_only_counts_and_drains_finished_minutes() {
+        let mut agg = CadenceAggregator::default();
+        let t0 = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 10).unwrap();
+        for i in 0..200 {
+            agg.record_keypress(t0 + chrono::Duration::milliseconds(i * 200));
+        }
+        agg.record_keypress(t0 + chrono::Duration::minutes(1));
+
+        let drained = agg.drain_completed(t0 + chrono::Duration::seconds(65));
+        assert_eq!(drained, vec![(Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap(), 40.0)]);
+        // The still-open minute stays in memory until it completes
+        assert_eq!(agg.drain_completed(t0 + chrono::Duration::minutes(2)).len(), 1);
+    }
+
+    #[test]
+    fn service_stops_when_the_last_reader_exits() {
+        let state = InputCadenceState::default();
+        let (stop, live) = (Arc::new(AtomicBool::new(false)), Arc::new(AtomicUsize::new(0)));
+        let readers = [LiveReader::enter(&live, &stop), LiveReader::enter(&live, &stop)];
+        *state.running.lock().unwrap() = Some(Running {
+            stop: stop.clone(),
+            live,
+            devices: vec!["usb".to_string(), "laptop".to_string()],
+        });
+
+        let [usb, laptop] = readers;
+        drop(usb);
+        assert!(state.is_running());
+        assert!(!stop.load(Ordering::Relaxed));
+
+        // With no keyboard left the HUD's own snapshots must count again
+        drop(laptop);
+        assert!(stop.load(Ordering::Relaxed), "the flusher drains and exits");
+        assert!(!state.is_running());
+        let status = state.status();
+        assert!(!status.running && status.devices.is_empty());
+        assert!(status.error.is_some());
+    }
+
+    /// Needs write access to /dev/uinput and read access to /dev/input:
+    /// `cargo test --lib input_cadence -- --ignored`
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore]
+    fn counts_typing_keys_from_virtual_uinput_keyboard() {
+        use evdev::{uinput::VirtualDevice, AttributeSet, KeyCode, KeyEvent};
+
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for key in [KeyCode::KEY_A, KeyCode::KEY_Z, KeyCode::KEY_SPACE, KeyCode::KEY_LEFTSHIFT] {
+            keys.insert(key);
+        }
+        let mut virtual_kb = VirtualDevice::builder().unwrap()
+            .name("holoself-test-keyboard")
+            .with_keys(&keys).unwrap()
+            .build().unwrap();
+        let node = virtual_kb.enumerate_dev_nodes_blocking().unwrap()
+            .next().unwrap().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300)); // let udev settle
+
+        let device = evdev::Device::open(&node).unwrap();
+        assert!(linux::is_keyboard(&device));
+        let aggregator = Arc::new(Mutex::new(CadenceAggregator::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let live = Arc::new(AtomicUsize::new(0));
+        linux::spawn_reader(device, aggregator.clone(), LiveReader::enter(&live, &stop));
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        // 10 letters (down + up), 1 space, and shift presses that must not count
+        for key in std::iter::repeat_n(KeyCode::KEY_A, 10).chain([KeyCode::KEY_SPACE, KeyCode::KEY_LEFTSHIFT]) {
+            virtual_kb.emit(&[*KeyEvent::new(key, 1)]).unwrap();
+            virtual_kb.emit(&[*KeyEvent::new(key, 0)]).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        stop.store(true, Ordering::Relaxed);
+
+        let total: f64 = aggregator.lock().unwrap()
+            .drain_completed(Utc::now() + chrono::Duration::minutes(2))
+            .iter()
+            .map(|(_, wpm)| wpm * CHARS_PER_WORD)
+            .sum();
+        assert_eq!(total, 11.0);
+    }
+}
@@ -0,0 +1,199 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::path::Path;
+use crate::commands::gemini::OcrResult;
+use crate::commands::records;
+use crate::services::context::ServiceContext;
+use crate::services::{gemini, time};
+
+/// Largest PDF sent to Gemini
+const MAX_PDF_BYTES: usize = 50_000_000;
+
+const OCR_PROMPT: &str = r#"You are a clinical lab results parser. Extract ALL health markers from this clinical analysis PDF.
+
+Return a JSON object with this exact structure:
+{
+  "patient_name": "string or null",
+  "date": "YYYY-MM-DD or null",
+  "lab": "laboratory name or null",
+  "markers": [
+    {
+      "marker": "Vitamin D",
+      "value": 25.3,
+      "unit": "ng/mL",
+      "reference_range": "30-100",
+      "status": "low"
+    }
+  ]
+}
+
+Focus especially on: Vitamin D, Zinc, Copper, Cortisol, TSH, T3, T4, ANA, Ferritin, B12, Iron, Hemoglobin.
+Only return valid JSON, no markdown."#;
+
+/// Clinical lab PDFs: OCR through Gemini, then stored markers
+pub struct LabService<'a> {
+    ctx: &'a ServiceContext,
+}
+
+impl<'a> LabService<'a> {
+    pub fn new(ctx: &'a ServiceContext) -> Self {
+        LabService { ctx }
+    }
+
+    /// Extract the markers of one lab PDF and store them; undated reports are filed
+    /// under today in the user's zone
+    pub async fn import_pdf(&self, file_path: &str) -> Result<OcrResult, String> {
+        // Security: validate file path
+        let path = Path::new(file_path);
+        if !path.exists() {
+            return Err(format!("File not found: {}", file_path));
+        }
+        let canonical = path.canonicalize()
+            .map_err(|e| format!("Invalid file path: {}", e))?;
+
+        // Block path traversal — only allow files with .pdf extension
+        if canonical.extension().and_then(|e| e.to_str()) != Some("pdf") {
+            return Err("Only PDF files are accepted.".to_string());
+        }
+
+        let pdf_bytes = std::fs::read(&canonical)
+            .map_err(|e| format!("Failed to read PDF: {}", e))?;
+        if pdf_bytes.len() > MAX_PDF_BYTES {
+            return Err("PDF too large (max 50MB).".to_string());
+        }
+
+        let api_key = self.ctx.config.gemini_api_key.as_deref()
+            .ok_or_else(|| "GEMINI_API_KEY not set. Configure in settings.".to_string())?;
+
+        let body = serde_json::json!({
+            "contents": [{
+                "parts": [
+                    { "text": OCR_PROMPT },
+                    {
+                        "inline_data": {
+                            "mime_type": "application/pdf",
+                            "data": BASE64.encode(&pdf_bytes)
+                        }
+                    }
+                ]
+            }],
+            "generationConfig": {
+                "temperature": 0.1,
+                "maxOutputTokens": 4096
+            }
+        });
+        let text = gemini::generate(self.ctx.http.as_ref(), api_key, body, std::time::Duration::from_secs(120))
+            .await
+            .inspect_err(|e| log::error!("{}", e))?;
+        let result = parse_ocr(&text)?;
+
+        let pdf_source = canonical.file_name().map(|n| n.to_string_lossy().to_string());
+        self.store(result, pdf_source).await
+    }
+
+    /// Persist the markers of one report — undated reports are filed under today
+    async fn store(&self, result: OcrResult, pdf_source: Option<String>) -> Result<OcrResult, String> {
+        let test_date = result.date.clone()
+            .unwrap_or_else(|| self.ctx.clock.today(self.ctx.tz()).format("%Y-%m-%d").to_string());
+        let now = time::format_utc(self.ctx.clock.now());
+        // The markers and their audit entry land together or not at all
+        self.ctx.db.write(move |db| db.transaction(|db| {
+            let mut saved = 0;
+            for marker in &result.markers {
+                match db.insert_lab_result(marker, result.lab.as_deref(), &test_date, pdf_source.as_deref()) {
+                    Ok(_) => saved += 1,
+                    Err(e) => log::warn!("Failed to save lab marker {}: {}", marker.marker, e),
+                }
+            }
+            let source_name = pdf_source.as_deref().unwrap_or("pdf");
+            records::audit_import(db, &now, "lab_results", source_name, saved)?;
+            Ok::<_, rusqlite::Error>(result)
+        })).await
+    }
+}
+
+/// The report Gemini read out of a PDF, with or without code fences around the JSON
+pub fn parse_ocr(text: &str) -> Result<OcrResult, String> {
+    serde_json::from_str(gemini::strip_code_fences(text))
+        .map_err(|e| format!("Failed to parse clinical data: {}. Raw: {}", e, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::context::testing::{context_at, gemini_reply};
+
+    const REPORT: &str = r#"```json
+{"patient_name": null, "date": null, "lab": "Synlab",
+ "markers": [{"marker": "Vitamin D", "value": 24.1, "unit": "ng/mL", "reference_range": "30-100", "status": "low"}]}
+```"#;
+
+    /// Minimal file with a .pdf extension; its content only travels to the canned client
+    fn temp_pdf(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("holoself-labs-{}-{}.pdf", name, std::process::id()));
+        std::fs::write(&path, b"%PDF-1.4\n%%EOF\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_fenced_reports() {
+        let report = parse_ocr(REPORT).unwrap();
+        assert_eq!(report.lab.as_deref(), Some("Synlab"));
+        assert_eq!(report.markers[0].marker, "Vitamin D");
+        assert!(parse_ocr("not json").unwrap_err().starts_with("Failed to parse clinical data"));
+    }
+
+    #[tokio::test]
+    async fn files_undated_reports_under_the_local_today() {
+        // 00:30 on 1 July in Lisbon, still 30 June in UTC
+        let (ctx, _, http) = context_at("2026-06-30T23:30:00Z", Some("test-key"));
+        http.reply(Ok(gemini_reply(REPORT)));
+        let pdf = temp_pdf("undated");
+
+        let report = LabService::new(&ctx).import_pdf(pdf.to_str().unwrap()).await.unwrap();
+        std::fs::remove_file(&pdf).unwrap();
+        assert_eq!(report.markers.len(), 1);
+
+        let labs = ctx.db.read(|db| db.get_latest_labs()).await.unwrap();
+        assert_eq!(labs, vec![("Vitamin D".to_string(), "2026-07-01".to_string())]);
+        let sent = http.sent();
+        assert_eq!(sent[0].1["contents"][0]["parts"][1]["inline_data"]["mime_type"], "application/pdf");
+    }
+
+    #[tokio::test]
+    async fn markers_and_their_audit_entry_land_together() {
+        let (ctx, _, http) = context_at("2026-06-30T23:30:00Z", Some("test-key"));
+        let reply = gemini_reply(REPORT);
+        http.reply(Ok(reply.clone()));
+        let pdf = temp_pdf("audited");
+        LabService::new(&ctx).import_pdf(pdf.to_str().unwrap()).await.unwrap();
+
+        let log = ctx.db.read(|db| db.get_audit_log(Some("lab_results"), None, 10)).await.unwrap();
+        assert_eq!((log[0].action.as_str(), log[0].changed_at.as_str()), ("import", "2026-06-30T23:30:00Z"));
+        assert_eq!(log[0].after.as_ref().unwrap()["inserted"], 1);
+
+        // Without its audit entry the import doesn't happen at all
+        ctx.db.write(|db| db.execute(
+            "CREATE TEMP TRIGGER audit_full BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'disco cheio'); END",
+            &[],
+        )).await.unwrap();
+        http.reply(Ok(reply));
+        assert!(LabService::new(&ctx).import_pdf(pdf.to_str().unwrap()).await.is_err());
+        std::fs::remove_file(&pdf).unwrap();
+        let count: i64 = ctx.db.read(|db| db.query_row("SELECT COUNT(*) FROM lab_results", &[], |row| row.get(0))).await.unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_without_an_api_key_or_a_pdf() {
+        let (ctx, _, http) = context_at("2026-06-30T23:30:00Z", None);
+        let pdf = temp_pdf("no-key");
+        let err = LabService::new(&ctx).import_pdf(pdf.to_str().unwrap()).await.unwrap_err();
+        std::fs::remove_file(&pdf).unwrap();
+        assert!(err.starts_with("GEMINI_API_KEY not set"));
+
+        let err = LabService::new(&ctx).import_pdf("/nonexistent/report.pdf").await.unwrap_err();
+        assert!(err.starts_with("File not found"));
+        assert!(http.sent().is_empty());
+    }
+}
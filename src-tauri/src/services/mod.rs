@@ -1,5 +1,27 @@
+pub mod agent;
+pub mod backup;
 pub mod cartesia;
+pub mod clock;
+pub mod context;
+pub mod eye_care;
+pub mod fhir;
+pub mod gemini;
+pub mod health;
+pub mod health_report;
+pub mod http;
+pub mod ingest_api;
+pub mod input_cadence;
+pub mod labs;
 pub mod native_tts;
+pub mod open_meteo;
+pub mod presence;
 pub mod scheduler;
+pub mod secrets;
+pub mod simulation;
+pub mod tabular;
+pub mod time;
+pub mod timeline;
+pub mod typing;
 pub mod vitamin_d;
+pub mod wearables;
 pub mod whisper;
@@ -1,8 +1,8 @@
 use std::process::Command;
 
-/// macOS native TTS via `say` command
-/// Uses "Luciana" voice (PT-BR) — built-in, zero API keys needed
-/// Returns WAV audio bytes that can be played by the frontend AudioContext
+// macOS native TTS via `say` command
+// Uses "Luciana" voice (PT-BR) — built-in, zero API keys needed
+// Returns WAV audio bytes that can be played by the frontend AudioContext
 
 #[allow(dead_code)]
 pub fn is_available() -> bool {
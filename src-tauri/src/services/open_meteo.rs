@@ -0,0 +1,234 @@
+use chrono::{DateTime, NaiveDateTime, Timelike, Utc};
+use std::time::Duration;
+use crate::db::DbState;
+use crate::services::http::SharedHttp;
+use crate::services::vitamin_d::UvHour;
+
+const OPEN_METEO_API_URL: &str = "https://api.open-meteo.com";
+
+/// Open-Meteo Forecast API — UV index and cloud cover (free, no key needed).
+/// Requests go through the service layer's `HttpClient`, so tests answer them from fixtures.
+pub struct OpenMeteoClient {
+    base_url: String,
+    http: SharedHttp,
+}
+
+impl OpenMeteoClient {
+    pub fn new(http: SharedHttp) -> Self {
+        Self::with_base_url(http, OPEN_METEO_API_URL)
+    }
+
+    pub fn with_base_url(http: SharedHttp, base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+        }
+    }
+
+    async fn get_json(&self, query: &str) -> Result<serde_json::Value, String> {
+        let url = format!("{}/v1/forecast?{}", self.base_url, query);
+        self.http
+            .get_json(&url, Duration::from_secs(15))
+            .await
+            .map_err(|e| format!("Weather API error: {}", e))
+    }
+
+    /// Current UV index at a location
+    pub async fn current_uv_index(&self, latitude: f64, longitude: f64) -> Result<f64, String> {
+        let data = self.get_json(&format!(
+            "latitude={}&longitude={}&current=uv_index",
+            latitude, longitude
+        )).await?;
+
+        data["current"]["uv_index"]
+            .as_f64()
+            .ok_or_else(|| "UV index not available.".to_string())
+    }
+
+    /// Hourly UV index + cloud cover for today and the next `days - 1` days,
+    /// plus `past_days` of history (Open-Meteo keeps up to 92).
+    /// Times are requested in GMT so every hour maps to an unambiguous UTC instant.
+    pub async fn hourly_uv_forecast(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        days: u32,
+        past_days: u32,
+    ) -> Result<Vec<UvHour>, String> {
+        let data = self.get_json(&format!(
+            "latitude={}&longitude={}&hourly=uv_index,cloud_cover&forecast_days={}&past_days={}&timezone=GMT",
+            latitude, longitude, days, past_days.min(92)
+        )).await?;
+
+        parse_hourly(&data)
+    }
+
+    /// Forecast for `days` days, network first. Fresh hours are cached in `db` (fetched at
+    /// `now`); when the request fails the cached hours from `from` (RFC 3339) are used instead.
+    pub async fn cached_hourly_forecast(
+        &self,
+        db: &DbState,
+        latitude: f64,
+        longitude: f64,
+        days: u32,
+        from: String,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UvHour>, String> {
+        match self.hourly_uv_forecast(latitude, longitude, days, 0).await {
+            Ok(hours) => db.write(move |db| {
+                if let Err(e) = db.upsert_uv_forecast(latitude, longitude, &hours, now) {
+                    log::warn!("Failed to cache UV forecast: {}", e);
+                }
+                Ok::<_, String>(hours)
+            }).await,
+            Err(e) => {
+                log::warn!("UV forecast fetch failed, using cache: {}", e);
+                db.read(move |db| db.get_uv_forecast(latitude, longitude, &from)).await
+            }
+        }
+    }
+
+    /// UV index for the hour containing `at`: from the cache, or on a miss from the
+    /// history Open-Meteo keeps up to `now` (which is cached too). None when neither has it.
+    pub async fn uv_index_at(
+        &self,
+        db: &DbState,
+        latitude: f64,
+        longitude: f64,
+        at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<f64>, String> {
+        if let Some(uv) = db.read(move |db| db.get_uv_at(latitude, longitude, at)).await? {
+            return Ok(Some(uv));
+        }
+
+        let past_days = (now.date_naive() - at.date_naive()).num_days().max(0) as u32 + 1;
+        let hours = self.hourly_uv_forecast(latitude, longitude, 1, past_days).await?;
+        // Read from the reply: the cache drops hours older than a week on write
+        let hour = at.with_minute(0).and_then(|t| t.with_second(0)).and_then(|t| t.with_nanosecond(0));
+        let uv = hours.iter().find(|h| Some(h.time) == hour).map(|h| h.uv_index);
+        db.write(move |db| db.upsert_uv_forecast(latitude, longitude, &hours, now)).await?;
+        Ok(uv)
+    }
+}
+
+/// Zip Open-Meteo's parallel `hourly` arrays into rows, skipping hours with null values
+fn parse_hourly(data: &serde_json::Value) -> Result<Vec<UvHour>, String> {
+    let hourly = &data["hourly"];
+    let times = hourly["time"].as_array()
+        .ok_or_else(|| "Hourly forecast not available.".to_string())?;
+    let uv = hourly["uv_index"].as_array()
+        .ok_or_else(|| "Hourly UV index not available.".to_string())?;
+    let clouds = hourly["cloud_cover"].as_array();
+
+    let hours = times.iter().enumerate()
+        .filter_map(|(i, t)| {
+            let time = NaiveDateTime::parse_from_str(t.as_str()?, "%Y-%m-%dT%H:%M").ok()?;
+            Some(UvHour {
+                time: time.and_utc(),
+                uv_index: uv.get(i)?.as_f64()?,
+                cloud_cover: clouds
+                    .and_then(|c| c.get(i))
+                    .and_then(|c| c.as_f64())
+                    .unwrap_or(0.0),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if hours.is_empty() {
+        return Err("Hourly forecast was empty.".to_string());
+    }
+    log::debug!("Parsed {} hourly UV rows", hours.len());
+    Ok(hours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::clock::Clock;
+    use crate::services::context::testing::context_at;
+    use serde_json::json;
+
+    const LISBON: (f64, f64) = (38.72, -9.14);
+
+    fn hourly(times: &[&str], uv: &[Option<f64>]) -> serde_json::Value {
+        json!({ "hourly": { "time": times, "uv_index": uv, "cloud_cover": vec![20.0; times.len()] } })
+    }
+
+    #[tokio::test]
+    async fn hourly_forecast_is_requested_in_gmt_and_skips_null_hours() {
+        let (ctx, _, http) = context_at("2026-07-01T09:00:00Z", None);
+        http.reply(Ok(hourly(&["2026-07-01T11:00", "2026-07-01T12:00", "2026-07-01T13:00"], &[Some(7.5), None, Some(8.1)])));
+        let client = OpenMeteoClient::with_base_url(ctx.http.clone(), "http://fixtures.test/");
+
+        let hours = client.hourly_uv_forecast(LISBON.0, LISBON.1, 2, 0).await.unwrap();
+        assert_eq!(hours.len(), 2);
+        assert_eq!(time_of(&hours[1]), "2026-07-01T13:00:00Z");
+        assert_eq!((hours[1].uv_index, hours[1].cloud_cover), (8.1, 20.0));
+
+        let (url, body) = &http.sent()[0];
+        assert_eq!(url, "http://fixtures.test/v1/forecast?latitude=38.72&longitude=-9.14&hourly=uv_index,cloud_cover&forecast_days=2&past_days=0&timezone=GMT");
+        assert!(body.is_null());
+    }
+
+    #[tokio::test]
+    async fn malformed_replies_are_errors() {
+        let (ctx, _, http) = context_at("2026-07-01T09:00:00Z", None);
+        let client = OpenMeteoClient::new(ctx.http.clone());
+        http.reply(Ok(json!({ "current": {} })));
+        assert_eq!(client.current_uv_index(LISBON.0, LISBON.1).await.unwrap_err(), "UV index not available.");
+        http.reply(Ok(hourly(&["2026-07-01T11:00"], &[None])));
+        assert_eq!(client.hourly_uv_forecast(LISBON.0, LISBON.1, 1, 0).await.unwrap_err(), "Hourly forecast was empty.");
+        assert_eq!(client.current_uv_index(LISBON.0, LISBON.1).await.unwrap_err(), "Weather API error: offline");
+    }
+
+    #[tokio::test]
+    async fn fetched_forecast_is_cached_for_offline_use() {
+        let (ctx, clock, http) = context_at("2026-07-01T09:00:00Z", None);
+        let client = OpenMeteoClient::new(ctx.http.clone());
+        http.reply(Ok(hourly(&["2026-07-01T11:00", "2026-07-01T12:00"], &[Some(7.0), Some(8.0)])));
+        let from = "2026-06-30T23:00:00Z".to_string();
+
+        let online = client.cached_hourly_forecast(&ctx.db, LISBON.0, LISBON.1, 1, from.clone(), clock.now()).await.unwrap();
+        // No reply queued: the second call is offline and served from the cache
+        let offline = client.cached_hourly_forecast(&ctx.db, LISBON.0, LISBON.1, 1, from, clock.now()).await.unwrap();
+        assert_eq!(offline.iter().map(time_of).collect::<Vec<_>>(), online.iter().map(time_of).collect::<Vec<_>>());
+
+        let fetched_at: String = ctx.db.read(|db| db.query_row("SELECT DISTINCT fetched_at FROM uv_forecast", &[], |row| row.get(0))).await.unwrap();
+        assert_eq!(fetched_at, "2026-07-01T09:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn cache_keeps_a_week_of_hours_by_forecast_time() {
+        let (ctx, clock, http) = context_at("2026-07-10T09:00:00Z", None);
+        let client = OpenMeteoClient::new(ctx.http.clone());
+        http.reply(Ok(hourly(&["2026-07-02T12:00", "2026-07-04T12:00", "2026-07-10T12:00"], &[Some(6.0), Some(7.0), Some(8.0)])));
+        client.cached_hourly_forecast(&ctx.db, LISBON.0, LISBON.1, 1, String::new(), clock.now()).await.unwrap();
+
+        let cached = ctx.db.read(|db| db.get_uv_forecast(LISBON.0, LISBON.1, "")).await.unwrap();
+        assert_eq!(cached.iter().map(time_of).collect::<Vec<_>>(), ["2026-07-04T12:00:00Z", "2026-07-10T12:00:00Z"]);
+    }
+
+    #[tokio::test]
+    async fn uv_at_an_old_session_comes_from_the_history_reply() {
+        let (ctx, clock, http) = context_at("2026-07-20T09:00:00Z", None);
+        let client = OpenMeteoClient::new(ctx.http.clone());
+        let session = DateTime::parse_from_rfc3339("2026-07-01T12:40:00Z").unwrap().with_timezone(&Utc);
+        http.reply(Ok(hourly(&["2026-07-01T12:00", "2026-07-01T13:00"], &[Some(8.5), Some(9.0)])));
+
+        // Older than the cache keeps, yet the value is still found
+        assert_eq!(client.uv_index_at(&ctx.db, LISBON.0, LISBON.1, session, clock.now()).await.unwrap(), Some(8.5));
+        assert!(http.sent()[0].0.contains("past_days=20"));
+
+        // A cached hour needs no request
+        let recent = DateTime::parse_from_rfc3339("2026-07-20T08:10:00Z").unwrap().with_timezone(&Utc);
+        http.reply(Ok(hourly(&["2026-07-20T08:00"], &[Some(3.0)])));
+        client.uv_index_at(&ctx.db, LISBON.0, LISBON.1, recent, clock.now()).await.unwrap();
+        assert_eq!(client.uv_index_at(&ctx.db, LISBON.0, LISBON.1, recent, clock.now()).await.unwrap(), Some(3.0));
+        assert_eq!(http.sent().len(), 2);
+    }
+
+    fn time_of(hour: &UvHour) -> String {
+        crate::services::time::format_utc(hour.time)
+    }
+}
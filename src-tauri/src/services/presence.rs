@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Presence state machine: at the desk or away. Fed by the webcam detector ("present" /
+// "away"), by typing activity, and by idle detection when every signal goes quiet.
+
+pub const IDLE_TIMEOUT_MINUTES: i64 = 5;  // no signal at all for this long → away
+pub const MIN_BREAK_SECONDS: i64 = 60;    // shorter absences are eye rests, not breaks
+
+/// Input to the state machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceSignal {
+    Seen,     // webcam saw a face (also sent as a heartbeat while present)
+    Left,     // webcam lost the face; `at` is when it was last seen
+    Activity, // keyboard or other input
+}
+
+impl PresenceSignal {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "present" => Some(Self::Seen),
+            "away" => Some(Self::Left),
+            "activity" => Some(Self::Activity),
+            _ => None,
+        }
+    }
+}
+
+/// What changed, for the caller to persist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// A work session starts at `at`; `away_since` is set when returning from an absence
+    Arrived { at: DateTime<Utc>, away_since: Option<DateTime<Utc>> },
+    /// The current session ends at `at`
+    Left { at: DateTime<Utc> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Status {
+    #[default]
+    Unknown,
+    Present,
+    Away,
+}
+
+/// Current state for the HUD and the agent
+#[derive(Debug, Serialize, Clone)]
+pub struct PresenceSnapshot {
+    pub state: String,         // unknown | present | away
+    pub since: Option<String>,
+    pub last_signal_at: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PresenceMachine {
+    status: Status,
+    since: Option<DateTime<Utc>>,
+    last_signal: Option<DateTime<Utc>>,
+}
+
+impl PresenceMachine {
+    pub fn signal(&mut self, signal: PresenceSignal, at: DateTime<Utc>) -> Option<Transition> {
+        // Signals can arrive late (typing is flushed once a minute): anything older than
+        // the current state can't change it
+        if self.since.is_some_and(|since| at <= since) && self.status != Status::Present {
+            return None;
+        }
+        let at = self.since.map_or(at, |since| at.max(since));
+
+        match (signal, self.status) {
+            (PresenceSignal::Seen | PresenceSignal::Activity, Status::Present) => {
+                self.last_signal = Some(self.last_signal.map_or(at, |last| last.max(at)));
+                None
+            }
+            (PresenceSignal::Seen | PresenceSignal::Activity, status) => {
+                let away_since = (status == Status::Away).then_some(self.since).flatten();
+                self.status = Status::Present;
+                self.since = Some(at);
+                self.last_signal = Some(at);
+                Some(Transition::Arrived { at, away_since })
+            }
+            (PresenceSignal::Left, Status::Present) => {
+                // Typing after the webcam last saw a face means they left later
+                let at = self.last_signal.map_or(at, |last| at.max(last));
+                self.status = Status::Away;
+                self.since = Some(at);
+                Some(Transition::Left { at })
+            }
+            (PresenceSignal::Left, Status::Unknown) => {
+                self.status = Status::Away;
+                self.since = Some(at);
+                None
+            }
+            (PresenceSignal::Left, Status::Away) => None,
+        }
+    }
+
+    /// Idle detection: a present user with no signal for `IDLE_TIMEOUT_MINUTES` left
+    /// when the last signal arrived
+    pub fn tick(&mut self, now: DateTime<Utc>) -> Option<Transition> {
+        let last = self.last_signal?;
+        if self.status == Status::Present && now - last > chrono::Duration::minutes(IDLE_TIMEOUT_MINUTES) {
+            return self.signal(PresenceSignal::Left, last);
+        }
+        None
+    }
+
+    /// None until the first signal of this run
+    pub fn is_present(&self) -> Option<bool> {
+        match self.status {
+            Status::Unknown => None,
+            status => Some(status == Status::Present),
+        }
+    }
+
+    pub fn snapshot(&self) -> PresenceSnapshot {
+        PresenceSnapshot {
+            state: match self.status {
+                Status::Unknown => "unknown",
+                Status::Present => "present",
+                Status::Away => "away",
+            }.to_string(),
+            since: self.since.map(crate::services::time::format_utc),
+            last_signal_at: self.last_signal.map(crate::services::time::format_utc),
+        }
+    }
+}
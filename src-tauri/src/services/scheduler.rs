@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, Utc, Duration};
+use chrono::{NaiveDate, Duration};
 use serde::{Deserialize, Serialize};
 
 /// Predictive Health Scheduler
@@ -12,12 +12,13 @@ pub struct ScheduledExam {
     pub triggered_by: String,
 }
 
-/// Rules engine: given a supplement protocol, generate upcoming exam schedule
+/// Rules engine: given a supplement protocol, generate upcoming exam schedule.
+/// `today` is the user's local date; exam dates count from it.
 pub fn generate_exam_schedule(
     supplements: &[SupplementInfo],
     last_labs: &[LabInfo],
+    today: NaiveDate,
 ) -> Vec<ScheduledExam> {
-    let today = Utc::now().date_naive();
     let mut exams = Vec::new();
 
     for supp in supplements {
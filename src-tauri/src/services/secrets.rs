@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+pub const GEMINI_API_KEY: &str = "GEMINI_API_KEY";
+pub const CARTESIA_API_KEY: &str = "CARTESIA_API_KEY";
+/// Passphrase for encrypted backups
+pub const BACKUP_PASSPHRASE: &str = "BACKUP_PASSPHRASE";
+
+/// Every secret HoloSelf manages — used for migration out of plaintext files
+pub const SECRET_NAMES: &[&str] = &[GEMINI_API_KEY, CARTESIA_API_KEY];
+
+const KEYRING_SERVICE: &str = "com.holoself.os";
+const SECRETS_FILE: &str = "secrets.enc";
+const KEY_FILE: &str = "secrets.key";
+
+/// Backend-agnostic secret storage for API keys
+pub trait SecretStore: Send + Sync {
+    fn get(&self, name: &str) -> Result<Option<String>, String>;
+    fn set(&self, name: &str, value: &str) -> Result<(), String>;
+    fn delete(&self, name: &str) -> Result<(), String>;
+    fn backend(&self) -> &'static str;
+}
+
+// === OS keyring (macOS Keychain, Windows Credential Manager, Secret Service) ===
+
+pub struct KeyringStore;
+
+impl KeyringStore {
+    fn entry(name: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYRING_SERVICE, name).map_err(|e| format!("Keyring error: {}", e))
+    }
+
+    /// The keyring is usable if a lookup either succeeds or reports a missing entry.
+    /// Headless Linux without a Secret Service daemon fails with a platform error instead.
+    pub fn probe() -> bool {
+        match Self::entry(GEMINI_API_KEY).map(|e| e.get_password()) {
+            Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry)) => true,
+            Ok(Err(e)) => {
+                log::info!("OS keyring unavailable: {}", e);
+                false
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        match Self::entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Keyring read failed: {}", e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        Self::entry(name)?
+            .set_password(value)
+            .map_err(|e| format!("Keyring write failed: {}", e))
+    }
+
+    fn delete(&self, name: &str) -> Result<(), String> {
+        match Self::entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Keyring delete failed: {}", e)),
+        }
+    }
+
+    fn backend(&self) -> &'static str {
+        "keyring"
+    }
+}
+
+// === Encrypted file fallback (ChaCha20-Poly1305) ===
+
+const FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Where the file key comes from
+enum KeySource {
+    /// HOLOSELF_SECRETS_PASSPHRASE, stretched with Argon2id over the file's salt
+    Passphrase(String),
+    /// 32 random bytes in a 0600 key file next to the secrets
+    KeyFile(Vec<u8>),
+}
+
+/// Secrets encrypted at rest in the config dir. The key comes from
+/// HOLOSELF_SECRETS_PASSPHRASE (Argon2id) when set, else from a random key file created
+/// 0600 beside the secrets, so only the owner (or root) can decrypt them.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    source: KeySource,
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileStore {
+    pub fn new(config_dir: &Path) -> Result<Self, String> {
+        let source = match std::env::var("HOLOSELF_SECRETS_PASSPHRASE") {
+            Ok(passphrase) if !passphrase.is_empty() => KeySource::Passphrase(passphrase),
+            _ => KeySource::KeyFile(load_or_create_key(&config_dir.join(KEY_FILE))?),
+        };
+        Ok(Self::with_source(config_dir, source))
+    }
+
+    fn with_source(config_dir: &Path, source: KeySource) -> Self {
+        Self {
+            path: config_dir.join(SECRETS_FILE),
+            source,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305, String> {
+        let mut key = [0u8; 32];
+        match &self.source {
+            KeySource::Passphrase(passphrase) => argon2::Argon2::default()
+                .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                .map_err(|e| format!("Key derivation failed: {}", e))?,
+            // Key files are already uniform random
+            KeySource::KeyFile(bytes) => hkdf::Hkdf::<sha2::Sha256>::new(Some(salt), bytes)
+                .expand(b"holoself-secrets-v1", &mut key)
+                .map_err(|e| format!("Key derivation failed: {}", e))?,
+        }
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    fn read_all(&self) -> Result<HashMap<String, String>, String> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read secrets: {}", e))?;
+        let file: EncryptedFile = serde_json::from_str(&data)
+            .map_err(|e| format!("Corrupt secrets file: {}", e))?;
+        if file.version != FILE_VERSION {
+            return Err(format!("Unsupported secrets file version {}", file.version));
+        }
+
+        let decode = |s: &str| BASE64.decode(s).map_err(|e| format!("Corrupt secrets file: {}", e));
+        let (salt, nonce, ciphertext) = (decode(&file.salt)?, decode(&file.nonce)?, decode(&file.ciphertext)?);
+        if nonce.len() != 12 {
+            return Err("Corrupt secrets file: bad nonce".to_string());
+        }
+
+        let plaintext = self.cipher(&salt)?
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| "Failed to decrypt secrets (wrong passphrase or key file?)".to_string())?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Corrupt secrets payload: {}", e))
+    }
+
+    fn write_all(&self, secrets: &HashMap<String, String>) -> Result<(), String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+        let ciphertext = self.cipher(&salt)?
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+        let file = EncryptedFile {
+            version: FILE_VERSION,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let data = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        write_private(&self.path, data.as_bytes())
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        Ok(self.read_all()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let mut secrets = self.read_all()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write_all(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let mut secrets = self.read_all()?;
+        if secrets.remove(name).is_some() {
+            self.write_all(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn backend(&self) -> &'static str {
+        "encrypted_file"
+    }
+}
+
+/// Random file key, created on first use
+fn load_or_create_key(key_path: &Path) -> Result<Vec<u8>, String> {
+    match std::fs::read(key_path) {
+        Ok(key) if key.len() == 32 => return Ok(key),
+        Ok(_) => return Err(format!("Corrupt secrets key file {:?}", key_path)),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("Failed to read {:?}: {}", key_path, e));
+        }
+        Err(_) => {}
+    }
+    let mut key = vec![0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    write_private(key_path, &key)?;
+    log::info!("Created secrets key file {:?}", key_path);
+    Ok(key)
+}
+
+/// Write a file readable only by the owner (created 0600 on Unix)
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    let tmp = path.with_extension("tmp");
+    let _ = std::fs::remove_file(&tmp);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(&tmp)
+        .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+// === Process-wide access ===
+
+struct Secrets {
+    store: Box<dyn SecretStore>,
+    cache: Mutex<HashMap<String, Option<String>>>,
+}
+
+static SECRETS: OnceLock<Secrets> = OnceLock::new();
+
+/// Pick the backend (OS keyring if reachable, else encrypted file) and make it global.
+/// Call once at startup before any command runs.
+pub fn init(config_dir: &Path) -> Result<&'static str, String> {
+    let store: Box<dyn SecretStore> = if KeyringStore::probe() {
+        Box::new(KeyringStore)
+    } else {
+        Box::new(EncryptedFileStore::new(config_dir)?)
+    };
+    let backend = store.backend();
+    let _ = SECRETS.set(Secrets { store, cache: Mutex::new(HashMap::new()) });
+    Ok(backend)
+}
+
+/// Like `init`, for processes that must not change the config dir (holoself-cli):
+/// no key file is created, so without one the file backend has no secrets to offer.
+pub fn init_read_only(config_dir: &Path) -> Result<&'static str, String> {
+    let store: Box<dyn SecretStore> = if KeyringStore::probe() {
+        Box::new(KeyringStore)
+    } else {
+        let source = match std::env::var("HOLOSELF_SECRETS_PASSPHRASE") {
+            Ok(passphrase) if !passphrase.is_empty() => KeySource::Passphrase(passphrase),
+            _ => match std::fs::read(config_dir.join(KEY_FILE)) {
+                Ok(key) if key.len() == 32 => KeySource::KeyFile(key),
+                Ok(_) => return Err(format!("Corrupt secrets key file {:?}", config_dir.join(KEY_FILE))),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok("none"),
+                Err(e) => return Err(format!("Failed to read {:?}: {}", config_dir.join(KEY_FILE), e)),
+            },
+        };
+        Box::new(EncryptedFileStore::with_source(config_dir, source))
+    };
+    let backend = store.backend();
+    let _ = SECRETS.set(Secrets { store, cache: Mutex::new(HashMap::new()) });
+    Ok(backend)
+}
+
+fn is_placeholder(value: &str) -> bool {
+    value.is_empty() || (value.starts_with("your_") && value.ends_with("_here"))
+}
+
+/// A shell export of `name`; .env files never put secrets here (see `env_entries`)
+fn env_override(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !is_placeholder(value))
+}
+
+/// Resolve a secret: process env (shell exports take priority) → secret store
+pub fn get(name: &str) -> Option<String> {
+    if let Some(value) = env_override(name) {
+        return Some(value);
+    }
+
+    let secrets = SECRETS.get()?;
+    if let Ok(cache) = secrets.cache.lock() {
+        if let Some(cached) = cache.get(name) {
+            return cached.clone();
+        }
+    }
+
+    let value = match secrets.store.get(name) {
+        Ok(v) => v.filter(|v| !is_placeholder(v)),
+        Err(e) => {
+            log::warn!("Failed to read secret {}: {}", name, e);
+            return None;
+        }
+    };
+    if let Ok(mut cache) = secrets.cache.lock() {
+        cache.insert(name.to_string(), value.clone());
+    }
+    value
+}
+
+pub fn is_configured(name: &str) -> bool {
+    get(name).is_some()
+}
+
+/// Store a secret (empty value deletes it)
+pub fn set(name: &str, value: &str) -> Result<(), String> {
+    let secrets = SECRETS.get().ok_or("Secret store not initialized")?;
+    let value = value.trim();
+    if value.is_empty() {
+        secrets.store.delete(name)?;
+    } else {
+        secrets.store.set(name, value)?;
+    }
+    if let Ok(mut cache) = secrets.cache.lock() {
+        cache.insert(name.to_string(), (!value.is_empty()).then(|| value.to_string()));
+    }
+    Ok(())
+}
+
+/// Masked form safe to show in the webview, e.g. "AIza…9xQk"
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.is_empty() {
+        String::new()
+    } else if chars.len() <= 12 {
+        "•".repeat(8)
+    } else {
+        let head: String = chars[..4].iter().collect();
+        let tail: String = chars[chars.len() - 4..].iter().collect();
+        format!("{}…{}", head, tail)
+    }
+}
+
+/// Whether a value coming back from the webview is just the mask we sent out
+pub fn is_mask_of(candidate: &str, name: &str) -> bool {
+    get(name).is_some_and(|current| mask(&current) == candidate)
+}
+
+/// The non-secret `KEY=value` lines of a .env file. API keys are left out so a stale
+/// plaintext copy can never shadow the store; `migrate_plaintext` moves them in.
+pub fn env_entries(content: &str) -> Vec<(&str, &str)> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter(|(key, _)| !SECRET_NAMES.contains(key) && *key != BACKUP_PASSPHRASE)
+        .collect()
+}
+
+/// Move plaintext keys from the legacy `.env` and `settings.json` into the store,
+/// then strip them from those files. Safe to run on every startup.
+pub fn migrate_plaintext(config_dir: &Path) -> Result<usize, String> {
+    let mut migrated = 0;
+
+    // 1. settings.json (gemini_api_key / cartesia_api_key fields)
+    let settings_path = config_dir.join("settings.json");
+    if let Ok(data) = std::fs::read_to_string(&settings_path) {
+        if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&data) {
+            let mut changed = false;
+            for (field, name) in [("gemini_api_key", GEMINI_API_KEY), ("cartesia_api_key", CARTESIA_API_KEY)] {
+                if let Some(value) = json.get(field).and_then(|v| v.as_str()).map(str::to_string) {
+                    if !value.is_empty() {
+                        if !is_placeholder(&value) {
+                            set(name, &value)?;
+                            migrated += 1;
+                        }
+                        json[field] = serde_json::Value::String(String::new());
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                let data = serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?;
+                write_private(&settings_path, data.as_bytes())?;
+            }
+        }
+    }
+
+    // 2. .env (written by onboarding) — applied last, it used to override settings.json
+    let env_path = config_dir.join(".env");
+    if let Ok(content) = std::fs::read_to_string(&env_path) {
+        let mut kept = Vec::new();
+        for line in content.lines() {
+            let secret = line.split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .filter(|(k, _)| SECRET_NAMES.contains(k));
+            match secret {
+                Some((key, value)) => {
+                    if !is_placeholder(value) {
+                        set(key, value)?;
+                        migrated += 1;
+                    }
+                }
+                None => kept.push(line),
+            }
+        }
+        if kept.len() != content.lines().count() {
+            if kept.iter().all(|l| l.trim().is_empty()) {
+                std::fs::remove_file(&env_path).map_err(|e| e.to_string())?;
+            } else {
+                write_private(&env_path, format!("{}\n", kept.join("\n")).as_bytes())?;
+            }
+        }
+    }
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("holoself-secrets-{}-{}", name, uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn key_file_is_private_and_reused() {
+        let dir = temp_dir("keyfile");
+        let key_path = dir.join(KEY_FILE);
+        let store = EncryptedFileStore::with_source(&dir, KeySource::KeyFile(load_or_create_key(&key_path).unwrap()));
+        store.set(GEMINI_API_KEY, "AIza-secret").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(std::fs::metadata(dir.join(SECRETS_FILE)).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let reopened = EncryptedFileStore::with_source(&dir, KeySource::KeyFile(load_or_create_key(&key_path).unwrap()));
+        assert_eq!(reopened.get(GEMINI_API_KEY).unwrap().as_deref(), Some("AIza-secret"));
+        let stranger = EncryptedFileStore::with_source(&dir, KeySource::KeyFile(vec![7; 32]));
+        assert!(stranger.get(GEMINI_API_KEY).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn passphrase_is_stretched_per_file_salt() {
+        let dir = temp_dir("passphrase");
+        let store = EncryptedFileStore::with_source(&dir, KeySource::Passphrase("correct horse".to_string()));
+        store.set(CARTESIA_API_KEY, "sk-cartesia").unwrap();
+        assert_eq!(store.get(CARTESIA_API_KEY).unwrap().as_deref(), Some("sk-cartesia"));
+
+        let file: EncryptedFile = serde_json::from_str(&std::fs::read_to_string(dir.join(SECRETS_FILE)).unwrap()).unwrap();
+        assert_eq!(file.version, FILE_VERSION);
+        let wrong = EncryptedFileStore::with_source(&dir, KeySource::Passphrase("wrong horse".to_string()));
+        assert!(wrong.get(CARTESIA_API_KEY).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_key_saved_in_the_store_wins_over_an_old_env_file() {
+        let dir = temp_dir("dotenv");
+        let env = "# onboarding\nGEMINI_API_KEY=AIza-old-plaintext\nHOLOSELF_SECRETS_TEST_FLAG = on\n";
+        assert_eq!(env_entries(env), [("HOLOSELF_SECRETS_TEST_FLAG", "on")]);
+
+        // What startup does with the file: only the non-secret entries reach the environment
+        let before = std::env::var(GEMINI_API_KEY).ok();
+        for (key, value) in env_entries(env) {
+            std::env::set_var(key, value);
+        }
+        assert_eq!(std::env::var(GEMINI_API_KEY).ok(), before);
+
+        let store = EncryptedFileStore::with_source(&dir, KeySource::KeyFile(vec![5; 32]));
+        store.set(GEMINI_API_KEY, "AIza-new-from-settings").unwrap();
+        if before.is_none() {
+            assert_eq!(env_override(GEMINI_API_KEY), None);
+            assert_eq!(store.get(GEMINI_API_KEY).unwrap().as_deref(), Some("AIza-new-from-settings"));
+        }
+        std::env::remove_var("HOLOSELF_SECRETS_TEST_FLAG");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,381 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::sync::Arc;
+use crate::commands::agent::{AgentMessage, PROTOCOLS};
+use crate::commands::gemini::ClinicalResult;
+use crate::commands::health::VitalEntry;
+use crate::commands::settings::AppSettings;
+use crate::services::agent::AgentService;
+use crate::services::clock::{Clock, FrozenClock};
+use crate::services::context::{Config, ServiceContext, ToolPaths};
+use crate::services::health::HealthService;
+use crate::services::http::CannedHttp;
+use crate::services::time;
+
+// Agent simulation: a synthetic user lives through one or more days in accelerated time
+// while the real services run against a frozen clock, an in-memory database and no network.
+// Gemini is never called, so every message comes from the rules and templates and the same
+// seed always gives the same transcript. Diff two transcripts to see what a change did.
+
+/// Longest run: a month
+pub const MAX_DAYS: u32 = 31;
+
+/// The agent is polled at this interval, like the HUD does
+const TICK_MINUTES: i64 = 15;
+/// First and last poll of the day, local time
+const DAY_START_HOUR: u32 = 7;
+const DAY_END_HOUR: u32 = 23;
+/// The synthetic user leaves the screen for lunch
+const LUNCH_HOURS: std::ops::Range<u32> = 13..14;
+/// Daily exam check, local time
+const EXAM_CHECK_HOUR: u32 = 9;
+/// Evening voice question, asked on some days
+const VOICE_HOUR: u32 = 18;
+const VOICE_PROBABILITY: f64 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct SimulationOptions {
+    /// First simulated day, in the user's zone
+    pub start: NaiveDate,
+    pub days: u32,
+    pub seed: u64,
+    /// Chance that the user takes each protocol when reminded, 0 to 1
+    pub adherence: f64,
+}
+
+impl SimulationOptions {
+    pub fn new(start: NaiveDate) -> Self {
+        SimulationOptions { start, days: 1, seed: 1, adherence: 0.8 }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_DAYS).contains(&self.days) {
+            return Err(format!("A simulação tem de ter entre 1 e {} dias.", MAX_DAYS));
+        }
+        if !(0.0..=1.0).contains(&self.adherence) {
+            return Err("A aderência tem de estar entre 0 e 1.".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One thing the agent said or the synthetic user did
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptEntry {
+    pub at: String,    // UTC
+    pub local: String, // "YYYY-MM-DD HH:MM" in the user's zone
+    pub kind: &'static str, // event | message | reminder | deferred | digest | action | exam | summary
+    pub category: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transcript {
+    pub start: String,
+    pub days: u32,
+    pub seed: u64,
+    pub adherence: f64,
+    pub timezone: String,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// One line per entry, for the terminal and plain-text diffs
+    pub fn render(&self) -> String {
+        self.entries.iter()
+            .map(|e| format!("{}  {:<8} {:<20} {}", e.local, e.kind, e.category, e.text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// SplitMix64: tiny, seedable and the same on every platform
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Records the transcript as the simulation moves the clock
+struct Recorder {
+    clock: Arc<FrozenClock>,
+    tz: Tz,
+    entries: Vec<TranscriptEntry>,
+    /// The HUD keeps showing the same card; repeats of it aren't new entries
+    last_shown: Option<String>,
+}
+
+impl Recorder {
+    fn push(&mut self, kind: &'static str, category: &str, text: impl Into<String>) {
+        let now = self.clock.now();
+        self.entries.push(TranscriptEntry {
+            at: time::format_utc(now),
+            local: now.with_timezone(&self.tz).format("%Y-%m-%d %H:%M").to_string(),
+            kind,
+            category: category.to_string(),
+            text: text.into(),
+        });
+    }
+
+    fn shown(&mut self, message: &AgentMessage) {
+        if self.last_shown.as_deref() == Some(message.text.as_str()) {
+            return;
+        }
+        let kind = if message.category == "supplement_reminder" { "reminder" } else { "message" };
+        self.push(kind, &message.category, message.text.clone());
+        self.last_shown = Some(message.text.clone());
+    }
+}
+
+/// Local wall time on `day` as an instant; the earlier one when DST repeats it
+fn local_instant(tz: Tz, day: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+    let naive = day.and_time(NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or_default());
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(|| tz.from_utc_datetime(&naive))
+        .with_timezone(&Utc)
+}
+
+/// Lab history the exam predictions start from, dated relative to the first day
+fn seed_labs(start: NaiveDate) -> Vec<(ClinicalResult, String)> {
+    let marker = |name: &str, value: f64, unit: &str, range: &str, status: &str, days_before: i64| (
+        ClinicalResult {
+            marker: name.to_string(),
+            value,
+            unit: unit.to_string(),
+            reference_range: range.to_string(),
+            status: status.to_string(),
+        },
+        (start - Duration::days(days_before)).format("%Y-%m-%d").to_string(),
+    );
+    vec![
+        marker("Vitamin D", 24.0, "ng/mL", "30-100", "low", 80),
+        marker("Zinc", 82.0, "µg/dL", "70-120", "normal", 60),
+        marker("Magnesium", 2.1, "mg/dL", "1.7-2.4", "normal", 30),
+        marker("Ferritin", 95.0, "ng/mL", "30-400", "normal", 30),
+        marker("ANA", 0.0, "", "negative", "normal", 30),
+        marker("TSH", 1.8, "mIU/L", "0.4-4.0", "normal", 30),
+    ]
+}
+
+/// Replay `options.days` days of the synthetic user against the agent with `settings`
+/// (zone, profile); the user's real data is never touched
+pub async fn run(settings: AppSettings, options: &SimulationOptions) -> Result<Transcript, String> {
+    options.validate()?;
+    let config = Config { settings, gemini_api_key: None, tools: ToolPaths::default() };
+    let tz = config.tz();
+    let clock = Arc::new(FrozenClock::new(local_instant(tz, options.start, DAY_START_HOUR, 0)));
+    let ctx = ServiceContext::in_memory(clock.clone(), config, Arc::new(CannedHttp::offline()))?;
+    let agent = AgentService::new(&ctx);
+    let health = HealthService::new(&ctx);
+    let mut rng = Rng(options.seed);
+    let mut rec = Recorder { clock: clock.clone(), tz, entries: Vec::new(), last_shown: None };
+
+    let labs = seed_labs(options.start);
+    ctx.db.write(move |db| {
+        for (marker, test_date) in &labs {
+            db.insert_lab_result(marker, Some("Simulação"), test_date, None)?;
+        }
+        Ok::<_, rusqlite::Error>(())
+    }).await?;
+
+    for offset in 0..options.days {
+        let day = options.start + Duration::days(offset as i64);
+        // What the user will take today, decided up front so repeated reminders don't reroll
+        let will_take: Vec<&str> = PROTOCOLS.iter()
+            .filter(|_| rng.unit() < options.adherence)
+            .map(|p| p.name)
+            .collect();
+        let asks_by_voice = rng.unit() < VOICE_PROBABILITY;
+
+        clock.set(local_instant(tz, day, DAY_START_HOUR, 0));
+        let resting = 56.0 + (rng.next() % 9) as f64;
+        health.log_vital(VitalEntry {
+            id: None,
+            vital_type: "resting_heart_rate".to_string(),
+            value: resting,
+            unit: "bpm".to_string(),
+            recorded_at: time::format_utc(clock.now()),
+            source: "wearable".to_string(),
+        }, "import").await?;
+        rec.push("event", "wearable", format!("Frequência cardíaca em repouso: {} bpm", resting));
+
+        for hour in DAY_START_HOUR..=DAY_END_HOUR {
+            for minute in (0..60).step_by(TICK_MINUTES as usize) {
+                clock.set(local_instant(tz, day, hour, minute));
+                let away = LUNCH_HOURS.contains(&hour);
+                if minute == 0 && hour == LUNCH_HOURS.start {
+                    rec.push("event", "presence", "Ausente (almoço)");
+                    rec.last_shown = None;
+                }
+                if minute == 0 && hour == LUNCH_HOURS.end {
+                    rec.push("event", "presence", "De volta ao ecrã");
+                    let now = time::format_utc(clock.now());
+                    let digest = ctx.db.write(move |db| db.take_deferred_messages(&now)).await?;
+                    if !digest.is_empty() {
+                        let texts: Vec<&str> = digest.iter().map(|m| m.text.as_str()).collect();
+                        rec.push("digest", "welcome_back", format!("{} em espera: {}", digest.len(), texts.join(" | ")));
+                    }
+                }
+
+                if minute == 0 && hour == EXAM_CHECK_HOUR {
+                    let pending: Vec<String> = ctx.db.read(|db| db.get_upcoming_exams()).await?
+                        .into_iter()
+                        .map(|(_, exam_type, ..)| exam_type)
+                        .collect();
+                    for exam in health.predict_exams().await?.into_iter().filter(|e| !pending.contains(&e.exam_type)) {
+                        rec.push("exam", &exam.exam_type, format!("{} — {}", exam.scheduled_date, exam.reason));
+                        health.store_exam(exam, "agent").await?;
+                    }
+                }
+
+                if minute == 0 && hour == VOICE_HOUR && asks_by_voice {
+                    rec.push("event", "voice", "\"Como estou hoje?\"");
+                    agent.remember_voice_input("Como estou hoje?".to_string()).await?;
+                }
+
+                match agent.next_message(away).await? {
+                    Some(message) => {
+                        rec.shown(&message);
+                        let accepted = message.action.filter(|_| {
+                            will_take.iter().any(|name| message.text.starts_with(name))
+                        });
+                        if let Some(action) = accepted {
+                            let result = agent.execute_action(action.action_type, action.payload, "agent").await?;
+                            rec.push("action", "log_supplement", result);
+                        }
+                    }
+                    None => {
+                        // Only new ones: a message already waiting isn't queued twice
+                        let now = time::format_utc(clock.now());
+                        if let Some(message) = ctx.db.read(move |db| db.deferred_message_at(&now)).await? {
+                            rec.push("deferred", &message.category, message.text);
+                        }
+                    }
+                }
+            }
+        }
+
+        let stats = health.daily_stats().await?;
+        rec.push("summary", "daily_stats", format!(
+            "Aderência {}% ({} de {} protocolos), {} pausas, {} comandos de voz",
+            stats.adherence_percent, stats.protocols_taken, stats.protocols_due, stats.breaks_taken, stats.voice_commands
+        ));
+        rec.last_shown = None;
+    }
+
+    Ok(Transcript {
+        start: options.start.format("%Y-%m-%d").to_string(),
+        days: options.days,
+        seed: options.seed,
+        adherence: options.adherence,
+        timezone: tz.name().to_string(),
+        entries: rec.entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lisbon() -> AppSettings {
+        AppSettings { timezone: "Europe/Lisbon".to_string(), ..AppSettings::default() }
+    }
+
+    fn options(start: &str, days: u32, seed: u64, adherence: f64) -> SimulationOptions {
+        SimulationOptions {
+            start: NaiveDate::parse_from_str(start, "%Y-%m-%d").unwrap(),
+            days,
+            seed,
+            adherence,
+        }
+    }
+
+    #[test]
+    fn the_rng_is_stable_across_runs() {
+        let mut a = Rng(7);
+        let mut b = Rng(7);
+        let draws: Vec<u64> = (0..4).map(|_| a.next()).collect();
+        assert_eq!(draws, (0..4).map(|_| b.next()).collect::<Vec<_>>());
+        assert!((0..1000).map(|_| a.unit()).all(|u| (0.0..1.0).contains(&u)));
+    }
+
+    #[tokio::test]
+    async fn the_same_seed_gives_the_same_transcript() {
+        let first = run(lisbon(), &options("2026-07-01", 2, 42, 0.6)).await.unwrap();
+        let again = run(lisbon(), &options("2026-07-01", 2, 42, 0.6)).await.unwrap();
+        assert_eq!(first, again);
+        assert_eq!(first.entries.iter().filter(|e| e.kind == "summary").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_seeded_week_matches_the_golden_transcript() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/simulation/lisbon-2026-07-01-seed-42.txt");
+        let rendered = run(lisbon(), &options("2026-07-01", 7, 42, 0.6)).await.unwrap().render();
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert!(rendered == expected.trim_end(), "transcript no longer matches {}; rendered:\n{}", path.display(), rendered);
+    }
+
+    #[tokio::test]
+    async fn a_full_day_with_every_protocol_taken() {
+        let transcript = run(lisbon(), &options("2026-07-01", 1, 1, 1.0)).await.unwrap();
+        let actions: Vec<&str> = transcript.entries.iter()
+            .filter(|e| e.kind == "action")
+            .map(|e| e.text.as_str())
+            .collect();
+        assert_eq!(actions.len(), PROTOCOLS.len());
+        assert!(actions[0].starts_with("Winfit"));
+
+        // Lunch: the message of 13:00 waits for the return at 14:00
+        let deferred = transcript.entries.iter().find(|e| e.kind == "deferred").unwrap();
+        assert_eq!(deferred.local, "2026-07-01 13:00");
+        let digest = transcript.entries.iter().find(|e| e.kind == "digest").unwrap();
+        assert_eq!(digest.local, "2026-07-01 14:00");
+        assert!(digest.text.starts_with("1 em espera"));
+
+        // Every seeded lab is recent enough on the first day
+        assert!(!transcript.entries.iter().any(|e| e.kind == "exam"));
+
+        let summary = transcript.entries.last().unwrap();
+        assert_eq!(summary.text, "Aderência 100% (5 de 5 protocolos), 0 pausas, 0 comandos de voz");
+        assert_eq!(summary.local, "2026-07-01 23:45");
+    }
+
+    #[tokio::test]
+    async fn the_vitamin_d_panel_is_scheduled_once_the_labs_are_three_months_old() {
+        let transcript = run(lisbon(), &options("2026-07-01", 11, 5, 0.8)).await.unwrap();
+        let exams: Vec<&TranscriptEntry> = transcript.entries.iter().filter(|e| e.kind == "exam").collect();
+        assert_eq!(exams.len(), 1);
+        assert_eq!(exams[0].category, "vitamin_d_panel");
+        assert_eq!(exams[0].local, "2026-07-11 09:00");
+        assert!(exams[0].text.starts_with("2026-07-18 — "));
+    }
+
+    #[tokio::test]
+    async fn reminders_keep_coming_when_nothing_is_taken() {
+        let transcript = run(lisbon(), &options("2026-07-01", 1, 3, 0.0)).await.unwrap();
+        let at_22 = transcript.entries.iter().find(|e| e.local == "2026-07-01 22:00").unwrap();
+        assert_eq!(at_22.kind, "reminder");
+        assert!(at_22.text.starts_with("Magnésio Bisglicinato"));
+        assert!(!transcript.entries.iter().any(|e| e.kind == "action"));
+        assert!(transcript.entries.last().unwrap().text.starts_with("Aderência 0% (0 de 5"));
+    }
+
+    #[tokio::test]
+    async fn refuses_runs_longer_than_a_month() {
+        let err = run(lisbon(), &options("2026-07-01", 40, 1, 0.5)).await.unwrap_err();
+        assert!(err.contains("31 dias"));
+    }
+}
@@ -0,0 +1,489 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use crate::commands::health::{SupplementEntry, VitalEntry};
+use crate::db::AGENT_MEMORY_RETENTION_DAYS;
+use crate::services::fhir::{self, LabRecord};
+use crate::services::ingest_api::{self, SupplementInput, VitalInput};
+use crate::services::time;
+
+// Spreadsheet-friendly CSV / NDJSON for the record tables.
+//
+// Exports use the stored column names and canonical UTC instants, so a file imports
+// back unchanged. Imports map source columns onto those fields (same name by default,
+// case-insensitive), validate every row on its own and report the failing line instead
+// of rejecting the whole file. Spreadsheet habits are accepted on the way in:
+// `;` or tab separators, decimal commas and `dd/mm/yyyy [hh:mm]` dates (local time).
+
+/// An `agent_memory` row (the embedding is not exported)
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryEntry {
+    pub id: Option<i64>,
+    pub key: String,
+    pub value: String,
+    pub timestamp: String,
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    Supplements,
+    Vitals,
+    LabResults,
+    AgentMemory,
+}
+
+impl Table {
+    pub const ALL: [Table; 4] = [Self::Supplements, Self::Vitals, Self::LabResults, Self::AgentMemory];
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        Self::ALL.into_iter()
+            .find(|table| table.name() == raw)
+            .ok_or_else(|| format!(
+                "Tabela desconhecida '{}' (aceites: {})",
+                raw,
+                Self::ALL.map(Self::name).join(", "),
+            ))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Supplements => "supplements",
+            Self::Vitals => "vitals",
+            Self::LabResults => "lab_results",
+            Self::AgentMemory => "agent_memory",
+        }
+    }
+
+    /// Exported columns, in file order; `id` is ignored on import
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::Supplements => &["id", "name", "dosage", "taken_at", "category", "notes"],
+            Self::Vitals => &["id", "vital_type", "value", "unit", "recorded_at", "source"],
+            Self::LabResults => &[
+                "id", "marker", "value", "unit", "reference_range", "status", "lab_name", "test_date", "pdf_source",
+            ],
+            Self::AgentMemory => &["id", "key", "value", "timestamp", "category"],
+        }
+    }
+
+    /// Fields every imported row must provide
+    fn required(self) -> &'static [&'static str] {
+        match self {
+            Self::Supplements => &["name", "dosage", "taken_at"],
+            Self::Vitals => &["vital_type", "value", "recorded_at"],
+            Self::LabResults => &["marker", "value"],
+            Self::AgentMemory => &["key", "value", "timestamp"],
+        }
+    }
+
+    fn importable(self) -> impl Iterator<Item = &'static str> {
+        self.columns().iter().copied().filter(|c| *c != "id")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw {
+            "csv" => Ok(Self::Csv),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            other => Err(format!("Formato desconhecido '{}' (aceites: csv, ndjson)", other)),
+        }
+    }
+
+    /// Guess from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "csv" | "tsv" | "txt" => Some(Self::Csv),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+// ── Export ──────────────────────────────────────────────────────────────
+
+/// One exported row, in `Table::columns` order
+pub type Row = Vec<Value>;
+
+pub fn supplement_row(e: &SupplementEntry) -> Row {
+    vec![json!(e.id), json!(e.name), json!(e.dosage), json!(e.taken_at), json!(e.category), json!(e.notes)]
+}
+
+pub fn vital_row(e: &VitalEntry) -> Row {
+    vec![json!(e.id), json!(e.vital_type), json!(e.value), json!(e.unit), json!(e.recorded_at), json!(e.source)]
+}
+
+pub fn lab_row(e: &LabRecord) -> Row {
+    vec![
+        json!(e.id), json!(e.marker), json!(e.value), json!(e.unit), json!(e.reference_range),
+        json!(e.status), json!(e.lab_name), json!(e.test_date), json!(e.pdf_source),
+    ]
+}
+
+pub fn memory_row(e: &MemoryEntry) -> Row {
+    vec![json!(e.id), json!(e.key), json!(e.value), json!(e.timestamp), json!(e.category)]
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Write a header (CSV) and `rows`; returns how many rows were written
+pub fn write_rows<W: Write>(out: W, table: Table, format: Format, rows: &[Row]) -> Result<usize, String> {
+    let columns = table.columns();
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(columns).map_err(|e| e.to_string())?;
+            for row in rows {
+                writer.write_record(row.iter().map(cell)).map_err(|e| e.to_string())?;
+            }
+            writer.flush().map_err(|e| e.to_string())?;
+        }
+        Format::Ndjson => {
+            let mut out = std::io::BufWriter::new(out);
+            for row in rows {
+                // Written by hand to keep the column order (serde_json maps are sorted)
+                let fields: Vec<String> = columns.iter()
+                    .zip(row)
+                    .map(|(column, value)| format!("{}:{}", json!(column), value))
+                    .collect();
+                writeln!(out, "{{{}}}", fields.join(",")).map_err(|e| e.to_string())?;
+            }
+            out.flush().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(rows.len())
+}
+
+// ── Import ──────────────────────────────────────────────────────────────
+
+/// One source record: column → cell text (empty cells are left out)
+pub type Record = BTreeMap<String, String>;
+
+/// Everything read from an import file, before mapping
+pub struct SourceFile {
+    pub columns: Vec<String>,                          // in order of first appearance
+    pub records: Vec<(u64, Result<Record, String>)>,   // (line number, record or why it is unreadable)
+}
+
+/// Read every record of a CSV or NDJSON file
+pub fn read_file(path: &Path, format: Format) -> Result<SourceFile, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Falha ao abrir {:?}: {}", path, e))?;
+    match format {
+        Format::Csv => read_csv(BufReader::new(file)),
+        Format::Ndjson => read_ndjson(BufReader::new(file)),
+    }
+}
+
+/// The separator used most on the header line (`;` is the Excel default in PT locales)
+fn detect_delimiter(header: &[u8]) -> u8 {
+    [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|d| header.iter().filter(|b| *b == d).count())
+        .unwrap_or(b',')
+}
+
+fn read_csv<R: BufRead>(mut reader: R) -> Result<SourceFile, String> {
+    let delimiter = {
+        let buffered = reader.fill_buf().map_err(|e| e.to_string())?;
+        let header = buffered.split(|b| *b == b'\n').next().unwrap_or_default();
+        detect_delimiter(header)
+    };
+    let mut csv = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let columns: Vec<String> = csv.headers()
+        .map_err(|e| format!("Cabeçalho CSV inválido: {}", e))?
+        .iter()
+        .map(|h| h.trim_start_matches('\u{feff}').to_string())
+        .collect();
+    if columns.iter().all(String::is_empty) {
+        return Err("O ficheiro CSV não tem cabeçalho.".to_string());
+    }
+
+    let mut records = Vec::new();
+    for result in csv.records() {
+        let parsed = match result {
+            Ok(row) => {
+                let line = row.position().map_or(0, |p| p.line());
+                let parsed = if row.len() > columns.len() {
+                    Err(format!("{} colunas, o cabeçalho tem {}", row.len(), columns.len()))
+                } else {
+                    Ok(columns.iter().cloned()
+                        .zip(row.iter())
+                        .filter(|(_, cell)| !cell.is_empty())
+                        .map(|(column, cell)| (column, cell.to_string()))
+                        .collect())
+                };
+                (line, parsed)
+            }
+            Err(e) => (e.position().map_or(0, |p| p.line()), Err(format!("CSV inválido: {}", e))),
+        };
+        records.push(parsed);
+    }
+    Ok(SourceFile { columns, records })
+}
+
+fn read_ndjson<R: BufRead>(reader: R) -> Result<SourceFile, String> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut records = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+        let parsed = serde_json::from_str::<Value>(line)
+            .map_err(|e| format!("JSON inválido: {}", e))
+            .and_then(|value| match value {
+                Value::Object(object) => object.into_iter()
+                    .filter_map(|(key, value)| match value {
+                        Value::Null => None,
+                        Value::String(s) if s.trim().is_empty() => None,
+                        Value::String(s) => Some(Ok((key, s.trim().to_string()))),
+                        Value::Number(n) => Some(Ok((key, n.to_string()))),
+                        Value::Bool(b) => Some(Ok((key, b.to_string()))),
+                        _ => Some(Err(format!("o campo '{}' não pode ser uma lista ou objeto", key))),
+                    })
+                    .collect::<Result<Record, String>>(),
+                _ => Err("cada linha deve ser um objeto JSON".to_string()),
+            });
+        if let Ok(record) = &parsed {
+            for key in record.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+        records.push((index as u64 + 1, parsed));
+    }
+    Ok(SourceFile { columns, records })
+}
+
+/// Which source column feeds each field: `overrides` (field → column) first, then a
+/// column with the field's own name, ignoring case. Fails when a required field has no column.
+pub fn resolve_mapping(
+    table: Table,
+    columns: &[String],
+    overrides: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut mapping = BTreeMap::new();
+    for (field, column) in overrides {
+        if !table.importable().any(|f| f == field) {
+            return Err(format!(
+                "Campo desconhecido '{}' para {} (aceites: {})",
+                field, table.name(), table.importable().collect::<Vec<_>>().join(", "),
+            ));
+        }
+        if !columns.contains(column) {
+            return Err(format!("A coluna '{}' não existe no ficheiro (colunas: {})", column, columns.join(", ")));
+        }
+        mapping.insert(field.clone(), column.clone());
+    }
+    for field in table.importable() {
+        if mapping.contains_key(field) {
+            continue;
+        }
+        if let Some(column) = columns.iter().find(|c| c.trim().eq_ignore_ascii_case(field)) {
+            mapping.insert(field.to_string(), column.clone());
+        }
+    }
+    let missing: Vec<&str> = table.required().iter().copied().filter(|f| !mapping.contains_key(*f)).collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Sem coluna para {} — indique o mapeamento (colunas do ficheiro: {})",
+            missing.join(", "), columns.join(", "),
+        ));
+    }
+    Ok(mapping)
+}
+
+/// One validated row, ready to store
+#[derive(Debug)]
+pub enum Parsed {
+    Supplement(SupplementEntry),
+    Vital(VitalEntry),
+    Lab(LabRecord),
+    Memory(MemoryEntry),
+}
+
+/// Vital types recorded by the app itself (typing, webcam, manual log): accepted on
+/// re-import with the unit given in the file. Everything else goes through the
+/// ingestion API's rules.
+const APP_VITALS: &[(&str, f64, f64)] = &[
+    ("wpm", 0.0, 400.0),
+    ("blink_rate", 0.0, 120.0),
+    ("sleep_score", 0.0, 100.0),
+    ("stress_level", 0.0, 100.0),
+];
+
+/// Decimal comma accepted ("12,5"); thousands separators are not
+fn parse_number(raw: &str) -> Result<f64, String> {
+    let raw = raw.trim();
+    let normalized = if raw.contains(',') && !raw.contains('.') { raw.replace(',', ".") } else { raw.to_string() };
+    normalized.parse::<f64>().ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("número inválido '{}'", raw))
+}
+
+/// `dd/mm/yyyy [hh:mm[:ss]]` rewritten as naive ISO (read as local time downstream);
+/// any other text is passed through for `time::parse_instant`
+fn iso_local(raw: &str) -> String {
+    let raw = raw.trim();
+    ["%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .or_else(|| NaiveDate::parse_from_str(raw, "%d/%m/%Y").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+        .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+/// Lab dates are calendar days: ISO, dd/mm/yyyy or any instant (its local date)
+fn parse_test_date(raw: &str, tz: chrono_tz::Tz) -> Result<String, String> {
+    time::parse_instant(&iso_local(raw), tz)
+        .map(|instant| instant.with_timezone(&tz).format("%Y-%m-%d").to_string())
+        .ok_or_else(|| format!("data inválida '{}'", raw))
+}
+
+fn text_field(value: Option<&str>, field: &str, max: usize) -> Result<Option<String>, String> {
+    match value.map(str::trim) {
+        Some(v) if v.chars().count() > max => Err(format!("{} não pode exceder {} caracteres", field, max)),
+        other => Ok(other.map(str::to_string)),
+    }
+}
+
+/// Validate one mapped record into a row of `table`
+pub fn parse_record(
+    table: Table,
+    record: &Record,
+    mapping: &BTreeMap<String, String>,
+    tz: chrono_tz::Tz,
+    now: DateTime<Utc>,
+) -> Result<Parsed, String> {
+    let get = |field: &str| mapping.get(field).and_then(|column| record.get(column)).map(String::as_str);
+    let require = |field: &str| get(field).ok_or_else(|| format!("{} em falta", field));
+
+    match table {
+        Table::Supplements => {
+            let input = SupplementInput {
+                name: require("name")?.to_string(),
+                dosage: require("dosage")?.to_string(),
+                taken_at: Some(iso_local(require("taken_at")?)),
+                category: get("category").map(str::to_string),
+                notes: get("notes").map(str::to_string),
+            };
+            let (name, dosage, taken_at, category) = ingest_api::validate_supplement(&input, tz, now)?;
+            Ok(Parsed::Supplement(SupplementEntry {
+                id: None,
+                name,
+                dosage,
+                taken_at: time::format_utc(taken_at),
+                category,
+                notes: input.notes,
+            }))
+        }
+        Table::Vitals => {
+            let vital_type = require("vital_type")?.to_string();
+            let value = parse_number(require("value")?)?;
+            let recorded_at = iso_local(require("recorded_at")?);
+            let source = text_field(get("source"), "source", 32)?.unwrap_or_else(|| "manual".to_string());
+
+            let (vital_type, value, unit, recorded_at) = match APP_VITALS.iter().find(|r| r.0 == vital_type) {
+                Some(&(_, min, max)) => {
+                    let unit = require("unit")?.to_string();
+                    if !(min..=max).contains(&value) {
+                        return Err(format!("valor {} fora do intervalo de {} ({}–{})", value, vital_type, min, max));
+                    }
+                    let at = time::parse_instant(&recorded_at, tz)
+                        .ok_or_else(|| format!("data/hora inválida '{}'", recorded_at))?;
+                    if at > now + Duration::minutes(5) {
+                        return Err(format!("data/hora no futuro '{}'", recorded_at));
+                    }
+                    (vital_type, value, unit, at)
+                }
+                None => ingest_api::validate_vital(&VitalInput {
+                    vital_type,
+                    value,
+                    unit: get("unit").map(str::to_string),
+                    recorded_at: Some(recorded_at),
+                }, tz, now)?,
+            };
+            Ok(Parsed::Vital(VitalEntry {
+                id: None,
+                vital_type,
+                value,
+                unit,
+                recorded_at: time::format_utc(recorded_at),
+                source,
+            }))
+        }
+        Table::LabResults => {
+            let marker = text_field(Some(require("marker")?), "marker", 100)?.unwrap_or_default();
+            let value = parse_number(require("value")?)?;
+            let reference_range = text_field(get("reference_range"), "reference_range", 100)?;
+            let status = match get("status") {
+                Some(status) => status.trim().to_lowercase(),
+                None => match reference_range.as_deref().map(fhir::reference_bounds) {
+                    Some((Some(low), _)) if value < low => "low".to_string(),
+                    Some((_, Some(high))) if value > high => "high".to_string(),
+                    Some((None, None)) | None => "unknown".to_string(),
+                    Some(_) => "normal".to_string(),
+                },
+            };
+            Ok(Parsed::Lab(LabRecord {
+                id: None,
+                marker,
+                value,
+                unit: text_field(get("unit"), "unit", 30)?.unwrap_or_default(),
+                reference_range,
+                status,
+                lab_name: text_field(get("lab_name"), "lab_name", 100)?,
+                test_date: get("test_date").map(|d| parse_test_date(d, tz)).transpose()?,
+                pdf_source: text_field(get("pdf_source"), "pdf_source", 255)?,
+            }))
+        }
+        Table::AgentMemory => {
+            let key = text_field(Some(require("key")?), "key", 100)?.unwrap_or_default();
+            let raw = iso_local(require("timestamp")?);
+            let timestamp = time::parse_instant(&raw, tz).ok_or_else(|| format!("data/hora inválida '{}'", raw))?;
+            if timestamp < now - Duration::days(AGENT_MEMORY_RETENTION_DAYS) {
+                return Err(format!(
+                    "entrada com mais de {} dias: a memória do agente não guarda entradas tão antigas",
+                    AGENT_MEMORY_RETENTION_DAYS,
+                ));
+            }
+            Ok(Parsed::Memory(MemoryEntry {
+                id: None,
+                key,
+                value: require("value")?.to_string(),
+                timestamp: time::format_utc(timestamp),
+                category: text_field(get("category"), "category", 50)?,
+            }))
+        }
+    }
+}
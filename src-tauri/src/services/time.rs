@@ -0,0 +1,129 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Offset, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+
+// Storage convention: every instant is written as UTC RFC 3339 with second precision
+// and a `Z` suffix ("2026-03-01T08:15:00Z"). One fixed width means string order is
+// chronological, so SQLite range queries work directly on the stored text.
+// Calendar days ("today", adherence) come from the user's IANA zone in settings.
+
+/// Canonical stored form of an instant
+pub fn format_utc(instant: DateTime<Utc>) -> String {
+    instant.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Parse a timestamp in any of the shapes found in older rows or sent by clients:
+/// - RFC 3339 with offset (`Utc::now().to_rfc3339()`, `Local::now().to_rfc3339()`)
+/// - `YYYY-MM-DD HH:MM:SS` from SQLite `datetime('now')`, which is UTC
+/// - naive `YYYY-MM-DDTHH:MM[:SS]`, read as wall-clock time in `tz`
+/// - bare `YYYY-MM-DD`, read as local midnight in `tz`
+pub fn parse_instant(raw: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(naive.and_utc());
+    }
+    let local = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    local_to_utc(local, tz)
+}
+
+/// Canonical form of a client/legacy timestamp, or a PT error for the UI
+pub fn normalize(raw: &str, tz: Tz) -> Result<String, String> {
+    parse_instant(raw, tz)
+        .map(format_utc)
+        .ok_or_else(|| format!("Data/hora inválida: '{}'", raw))
+}
+
+/// Normalize a query range. A bare date as upper bound covers that whole local day.
+pub fn normalize_range(from: &str, to: &str, tz: Tz) -> Result<(String, String), String> {
+    let from = normalize(from, tz)?;
+    let to = match NaiveDate::parse_from_str(to.trim(), "%Y-%m-%d") {
+        Ok(day) => local_day_bounds(day, tz).1,
+        Err(_) => normalize(to, tz)?,
+    };
+    Ok((from, to))
+}
+
+/// Wall-clock time in `tz` to UTC; DST gaps resolve to the instant just after the gap
+fn local_to_utc(local: NaiveDateTime, tz: Tz) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// [start, end) of a local calendar day as canonical UTC strings
+pub fn local_day_bounds(day: NaiveDate, tz: Tz) -> (String, String) {
+    let bound = |d: NaiveDate| {
+        d.and_hms_opt(0, 0, 0)
+            .and_then(|midnight| local_to_utc(midnight, tz))
+            .map(format_utc)
+            .unwrap_or_default()
+    };
+    (bound(day), bound(day + chrono::Duration::days(1)))
+}
+
+/// UTC offset in effect in `tz` at `instant`, in minutes
+fn offset_minutes(instant: DateTime<Utc>, tz: Tz) -> i32 {
+    instant.with_timezone(&tz).offset().fix().local_minus_utc() / 60
+}
+
+/// The offsets `tz` uses over [from, to] as (start, end, minutes): canonical UTC bounds,
+/// start inclusive, end exclusive, the last one ending just after `to`. Lets SQL find the
+/// local date of any instant in the range, across DST changes.
+pub fn offset_periods(from: DateTime<Utc>, to: DateTime<Utc>, tz: Tz) -> Vec<(String, String, i32)> {
+    let mut periods = Vec::new();
+    let (mut start, mut minutes) = (from, offset_minutes(from, tz));
+    let mut cursor = from;
+    while cursor <= to {
+        let next = cursor + chrono::Duration::days(1);
+        if offset_minutes(next, tz) == minutes {
+            cursor = next;
+            continue;
+        }
+        // First second of the new offset within (cursor, next]
+        let (mut lo, mut hi) = (cursor, next);
+        while hi - lo > chrono::Duration::seconds(1) {
+            let mid = lo + (hi - lo) / 2;
+            if offset_minutes(mid, tz) == minutes { lo = mid } else { hi = mid }
+        }
+        if hi > to {
+            break;
+        }
+        periods.push((format_utc(start), format_utc(hi), minutes));
+        (start, minutes, cursor) = (hi, offset_minutes(hi, tz), hi);
+    }
+    periods.push((format_utc(start), format_utc(to + chrono::Duration::seconds(1)), minutes));
+    periods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(raw: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(raw).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn offset_periods_split_at_each_dst_change() {
+        let periods = offset_periods(at("2026-01-01T00:00:00Z"), at("2026-12-31T23:59:59Z"), chrono_tz::Europe::Lisbon);
+        assert_eq!(periods, vec![
+            ("2026-01-01T00:00:00Z".to_string(), "2026-03-29T01:00:00Z".to_string(), 0),
+            ("2026-03-29T01:00:00Z".to_string(), "2026-10-25T01:00:00Z".to_string(), 60),
+            ("2026-10-25T01:00:00Z".to_string(), "2027-01-01T00:00:00Z".to_string(), 0),
+        ]);
+
+        // A change just past `to` is not part of the range
+        let summer = offset_periods(at("2026-07-01T00:00:00Z"), at("2026-10-25T00:59:59Z"), chrono_tz::Europe::Lisbon);
+        assert_eq!(summer, vec![("2026-07-01T00:00:00Z".to_string(), "2026-10-25T01:00:00Z".to_string(), 60)]);
+        assert_eq!(offset_periods(at("2026-07-01T00:00:00Z"), at("2026-07-01T00:00:00Z"), chrono_tz::UTC).len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// Structured health timeline: event kinds, cursor pagination and day/week aggregates.
+//
+// Stored events are read newest first in one SQL union, each with a JSON payload shaped
+// by its kind. Fatigue episodes and low-blink periods are derived from per-minute data
+// and merged into the same order. Events sort by (timestamp, key) descending; a cursor
+// is the position of the last event on a page.
+
+/// Every event kind, in display order
+pub const EVENT_TYPES: [&str; 8] = [
+    "supplement",
+    "vital",
+    "lab_result",
+    "exam",
+    "voice",
+    "agent_message",
+    "fatigue_episode",
+    "low_blink_period",
+];
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// One timeline event. `payload` fields depend on `event_type`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    pub key: String,       // stable across pages, e.g. "vitals:000000000042"
+    pub timestamp: String, // canonical UTC; local midnight for date-only labs and exams
+    pub event_type: String,
+    pub payload: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelinePage {
+    pub events: Vec<TimelineEvent>,
+    pub next_cursor: Option<String>, // None on the last page
+}
+
+/// What to read from the database for one page
+#[derive(Debug)]
+pub struct TimelineQuery {
+    pub from: String,
+    pub to: String,
+    pub types: Vec<&'static str>,
+    pub vital_types: Option<Vec<String>>, // None: everything except per-minute and per-second series
+    pub cursor: Option<Cursor>,
+    pub limit: u32,
+    pub tz: chrono_tz::Tz, // dates each event in the offset it had, across DST changes
+}
+
+/// Position after the last event of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub timestamp: String,
+    pub key: String,
+}
+
+impl Cursor {
+    pub fn after(event: &TimelineEvent) -> Self {
+        Cursor { timestamp: event.timestamp.clone(), key: event.key.clone() }
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        raw.split_once('|')
+            .filter(|(timestamp, key)| !timestamp.is_empty() && !key.is_empty())
+            .map(|(timestamp, key)| Cursor { timestamp: timestamp.to_string(), key: key.to_string() })
+            .ok_or_else(|| format!("Cursor inválido: '{}'", raw))
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}|{}", self.timestamp, self.key)
+    }
+
+    /// Whether `event` belongs to a later page than the cursor
+    pub fn precedes(&self, event: &TimelineEvent) -> bool {
+        (event.timestamp.as_str(), event.key.as_str()) < (self.timestamp.as_str(), self.key.as_str())
+    }
+}
+
+/// Validate requested event kinds; None means all of them
+pub fn parse_types(types: Option<&[String]>) -> Result<Vec<&'static str>, String> {
+    let Some(types) = types else { return Ok(EVENT_TYPES.to_vec()) };
+    let mut parsed = Vec::new();
+    for raw in types {
+        let kind = EVENT_TYPES.iter().copied()
+            .find(|t| *t == raw.as_str())
+            .ok_or_else(|| format!("Tipo de evento desconhecido '{}' (aceites: {})", raw, EVENT_TYPES.join(", ")))?;
+        if !parsed.contains(&kind) {
+            parsed.push(kind);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Wrap a derived episode or period as an event keyed by its start
+pub fn derived_event<T: Serialize>(event_type: &str, started_at: &str, payload: &T) -> TimelineEvent {
+    TimelineEvent {
+        key: format!("{}:{}", event_type, started_at),
+        timestamp: started_at.to_string(),
+        event_type: event_type.to_string(),
+        payload: serde_json::to_value(payload).unwrap_or(Value::Null),
+    }
+}
+
+/// Merge one page. `stored` holds up to `limit + 1` events after the cursor, newest
+/// first; `derived` holds every derived event in the range.
+pub fn merge_page(stored: Vec<TimelineEvent>, derived: Vec<TimelineEvent>, cursor: Option<&Cursor>, limit: u32) -> TimelinePage {
+    let mut events = stored;
+    events.extend(derived.into_iter().filter(|e| cursor.is_none_or(|c| c.precedes(e))));
+    events.sort_by(|a, b| (b.timestamp.as_str(), b.key.as_str()).cmp(&(a.timestamp.as_str(), a.key.as_str())));
+
+    let limit = limit as usize;
+    let next_cursor = (events.len() > limit).then(|| Cursor::after(&events[limit - 1]).encode());
+    events.truncate(limit);
+    TimelinePage { events, next_cursor }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bucket {
+    Day,
+    Week, // ISO weeks, Monday to Sunday
+}
+
+impl Bucket {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw {
+            "day" => Ok(Bucket::Day),
+            "week" => Ok(Bucket::Week),
+            other => Err(format!("Agrupamento desconhecido '{}' (aceites: day, week)", other)),
+        }
+    }
+
+    /// First local day of the bucket containing `day`
+    pub fn start(self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => day,
+            Bucket::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    /// Last local day of the bucket starting at `start`
+    pub fn end(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => start,
+            Bucket::Week => start + Duration::days(6),
+        }
+    }
+}
+
+/// Events of one kind on one local day; vitals are split per type and unit
+#[derive(Debug, Clone)]
+pub struct DayTally {
+    pub day: NaiveDate,
+    pub event_type: String,
+    pub vital: Option<(String, String)>, // (vital_type, unit)
+    pub count: u32,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Mean, min and max of one vital type within a bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalAggregate {
+    pub vital_type: String,
+    pub unit: String,
+    pub count: u32,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64, // meaningful for additive series like steps and sleep
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineBucket {
+    pub start: String, // local YYYY-MM-DD, inclusive
+    pub end: String,   // local YYYY-MM-DD, inclusive
+    pub counts: BTreeMap<String, u32>, // event_type → events
+    pub vitals: Vec<VitalAggregate>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Fold per-day tallies into buckets, oldest first; empty buckets are omitted
+pub fn fold_buckets(tallies: &[DayTally], bucket: Bucket) -> Vec<TimelineBucket> {
+    struct Acc {
+        counts: BTreeMap<String, u32>,
+        vitals: BTreeMap<(String, String), (u32, f64, f64, f64)>,
+    }
+
+    let mut buckets: BTreeMap<NaiveDate, Acc> = BTreeMap::new();
+    for tally in tallies {
+        let acc = buckets.entry(bucket.start(tally.day))
+            .or_insert_with(|| Acc { counts: BTreeMap::new(), vitals: BTreeMap::new() });
+        *acc.counts.entry(tally.event_type.clone()).or_default() += tally.count;
+        if let Some(vital) = &tally.vital {
+            let stat = acc.vitals.entry(vital.clone()).or_insert((0, 0.0, f64::INFINITY, f64::NEG_INFINITY));
+            stat.0 += tally.count;
+            stat.1 += tally.sum;
+            stat.2 = stat.2.min(tally.min);
+            stat.3 = stat.3.max(tally.max);
+        }
+    }
+
+    buckets.into_iter().map(|(start, acc)| TimelineBucket {
+        start: start.format("%Y-%m-%d").to_string(),
+        end: bucket.end(start).format("%Y-%m-%d").to_string(),
+        counts: acc.counts,
+        vitals: acc.vitals.into_iter()
+            .filter(|(_, (count, ..))| *count > 0)
+            .map(|((vital_type, unit), (count, sum, min, max))| VitalAggregate {
+                vital_type,
+                unit,
+                count,
+                mean: round2(sum / count as f64),
+                min,
+                max,
+                sum: round2(sum),
+            })
+            .collect(),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: &str, key: &str) -> TimelineEvent {
+        TimelineEvent {
+            key: key.to_string(),
+            timestamp: timestamp.to_string(),
+            event_type: "vital".to_string(),
+            payload: Value::Null,
+        }
+    }
+
+    fn keys(page: &TimelinePage) -> Vec<&str> {
+        page.events.iter().map(|e| e.key.as_str()).collect()
+    }
+
+    #[test]
+    fn cursor_orders_by_timestamp_then_key() {
+        let cursor = Cursor::parse("2026-07-01T09:00:00Z|vitals:000000000005").unwrap();
+        assert!(cursor.precedes(&event("2026-07-01T08:59:59Z", "vitals:000000000009")));
+        assert!(cursor.precedes(&event("2026-07-01T09:00:00Z", "vitals:000000000004")));
+        assert!(!cursor.precedes(&event("2026-07-01T09:00:00Z", "vitals:000000000005")));
+        assert!(!cursor.precedes(&event("2026-07-01T09:00:01Z", "supplements:000000000001")));
+
+        assert_eq!(Cursor::parse(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::parse("2026-07-01T09:00:00Z|").is_err());
+    }
+
+    #[test]
+    fn pages_merge_derived_events_without_repeats() {
+        // Stored rows come newest first from SQL with one extra row; derived ones cover the whole range
+        let stored = vec![
+            event("2026-07-01T12:00:00Z", "vitals:000000000003"),
+            event("2026-07-01T10:00:00Z", "vitals:000000000002"),
+            event("2026-07-01T08:00:00Z", "vitals:000000000001"),
+        ];
+        let derived = vec![
+            event("2026-07-01T11:00:00Z", "fatigue_episode:b"),
+            event("2026-07-01T07:00:00Z", "fatigue_episode:a"),
+        ];
+        let first = merge_page(stored, derived.clone(), None, 2);
+        assert_eq!(keys(&first), ["vitals:000000000003", "fatigue_episode:b"]);
+        let cursor = Cursor::parse(first.next_cursor.as_deref().unwrap()).unwrap();
+
+        let stored = vec![
+            event("2026-07-01T10:00:00Z", "vitals:000000000002"),
+            event("2026-07-01T08:00:00Z", "vitals:000000000001"),
+        ];
+        let second = merge_page(stored, derived, Some(&cursor), 3);
+        assert_eq!(keys(&second), ["vitals:000000000002", "vitals:000000000001", "fatigue_episode:a"]);
+        assert_eq!(second.next_cursor, None);
+    }
+
+    fn tally(day: &str, event_type: &str, vital: Option<(f64, f64, f64, u32)>) -> DayTally {
+        let (sum, min, max, count) = vital.unwrap_or((0.0, 0.0, 0.0, 1));
+        DayTally {
+            day: NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap(),
+            event_type: event_type.to_string(),
+            vital: vital.map(|_| ("heart_rate".to_string(), "bpm".to_string())),
+            count,
+            sum,
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn tallies_fold_into_iso_weeks() {
+        let tallies = [
+            tally("2026-06-28", "supplement", None), // Sunday
+            tally("2026-06-29", "vital", Some((120.0, 58.0, 62.0, 2))), // Monday
+            tally("2026-07-05", "vital", Some((195.0, 61.0, 70.0, 3))), // Sunday
+            tally("2026-07-05", "supplement", None),
+        ];
+        let weeks = fold_buckets(&tallies, Bucket::Week);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!((weeks[0].start.as_str(), weeks[0].end.as_str()), ("2026-06-22", "2026-06-28"));
+        assert!(weeks[0].vitals.is_empty());
+
+        let week = &weeks[1];
+        assert_eq!((week.start.as_str(), week.end.as_str()), ("2026-06-29", "2026-07-05"));
+        assert_eq!(week.counts["vital"], 5);
+        assert_eq!(week.counts["supplement"], 1);
+        let heart = &week.vitals[0];
+        assert_eq!((heart.count, heart.mean, heart.min, heart.max, heart.sum), (5, 63.0, 58.0, 70.0, 315.0));
+
+        let days = fold_buckets(&tallies, Bucket::Day);
+        assert_eq!(days.iter().map(|b| b.start.as_str()).collect::<Vec<_>>(), ["2026-06-28", "2026-06-29", "2026-07-05"]);
+    }
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Typing cadence → fatigue, measured against the user's own baseline for the same
+// weekday and hour (people type slower at 8:00 on a Monday than at 15:00 on a Thursday).
+
+pub const MIN_BASELINE_SAMPLES: u32 = 20;   // minutes of typing before a baseline is trusted
+pub const SMOOTHING_MINUTES: i64 = 5;       // rolling mean before comparing to baseline
+pub const EPISODE_MIN_MINUTES: i64 = 10;    // shorter dips are noise
+pub const EPISODE_MAX_GAP_MINUTES: i64 = 5; // pauses that don't split an episode
+
+// Fatigue thresholds (drop relative to baseline) — same levels the HUD shows
+const MILD_DROP: f64 = 0.15;
+const MODERATE_DROP: f64 = 0.30;
+const HIGH_DROP: f64 = 0.50;
+
+/// Running mean/variance (Welford) of WPM for one weekday × hour cell
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct Baseline {
+    pub samples: u32,
+    pub mean: f64,
+    #[serde(skip)]
+    pub m2: f64,
+}
+
+impl Baseline {
+    pub fn add(&mut self, wpm: f64) {
+        self.samples += 1;
+        let delta = wpm - self.mean;
+        self.mean += delta / self.samples as f64;
+        self.m2 += delta * (wpm - self.mean);
+    }
+
+    /// Combine two cells (Chan et al. parallel variance)
+    pub fn merge(&self, other: &Baseline) -> Baseline {
+        let n = self.samples + other.samples;
+        if n == 0 {
+            return Baseline::default();
+        }
+        let delta = other.mean - self.mean;
+        let (na, nb, nf) = (self.samples as f64, other.samples as f64, n as f64);
+        Baseline {
+            samples: n,
+            mean: self.mean + delta * nb / nf,
+            m2: self.m2 + other.m2 + delta * delta * na * nb / nf,
+        }
+    }
+}
+
+/// All baseline cells keyed by (weekday 0 = Monday, local hour)
+#[derive(Debug, Default)]
+pub struct BaselineTable {
+    pub cells: HashMap<(u32, u32), Baseline>,
+}
+
+impl BaselineTable {
+    /// Most specific baseline with enough data: weekday × hour, then the hour on any
+    /// weekday, then everything. None while still calibrating.
+    pub fn lookup(&self, weekday: u32, hour: u32) -> Option<Baseline> {
+        let exact = self.cells.get(&(weekday, hour)).copied().unwrap_or_default();
+        let same_hour = self.cells.iter()
+            .filter(|((_, h), _)| *h == hour)
+            .fold(Baseline::default(), |acc, (_, b)| acc.merge(b));
+        let overall = self.cells.values()
+            .fold(Baseline::default(), |acc, b| acc.merge(b));
+
+        [exact, same_hour, overall]
+            .into_iter()
+            .find(|b| b.samples >= MIN_BASELINE_SAMPLES && b.mean > 0.0)
+    }
+
+    pub fn lookup_at(&self, local: &DateTime<chrono_tz::Tz>) -> Option<Baseline> {
+        self.lookup(local.weekday().num_days_from_monday(), local.hour())
+    }
+}
+
+/// Current fatigue reading for the HUD and the agent
+#[derive(Debug, Serialize, Clone)]
+pub struct FatigueAssessment {
+    pub wpm: f64,                    // smoothed recent WPM
+    pub baseline_wpm: Option<f64>,
+    pub drop_percent: Option<f64>,   // % below baseline (negative = faster than usual)
+    pub score: u32,                  // 0 (fresh) – 100 (≥50% below baseline)
+    pub level: String,               // none | mild | moderate | high
+    pub calibrating: bool,
+}
+
+pub fn fatigue_level(drop: f64) -> &'static str {
+    if drop >= HIGH_DROP {
+        "high"
+    } else if drop >= MODERATE_DROP {
+        "moderate"
+    } else if drop >= MILD_DROP {
+        "mild"
+    } else {
+        "none"
+    }
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level {
+        "high" => 3,
+        "moderate" => 2,
+        "mild" => 1,
+        _ => 0,
+    }
+}
+
+pub fn assess(recent_wpm: f64, baseline: Option<Baseline>) -> FatigueAssessment {
+    match baseline {
+        Some(b) if recent_wpm > 0.0 => {
+            let drop = 1.0 - recent_wpm / b.mean;
+            FatigueAssessment {
+                wpm: recent_wpm,
+                baseline_wpm: Some(b.mean),
+                drop_percent: Some((drop * 1000.0).round() / 10.0),
+                score: (drop / HIGH_DROP * 100.0).clamp(0.0, 100.0).round() as u32,
+                level: fatigue_level(drop).to_string(),
+                calibrating: false,
+            }
+        }
+        _ => FatigueAssessment {
+            wpm: recent_wpm,
+            baseline_wpm: baseline.map(|b| b.mean),
+            drop_percent: None,
+            score: 0,
+            level: "none".to_string(),
+            calibrating: baseline.is_none(),
+        },
+    }
+}
+
+/// One stored per-minute WPM snapshot
+#[derive(Debug, Clone, Copy)]
+pub struct WpmPoint {
+    pub time: DateTime<Utc>,
+    pub wpm: f64,
+    /// Baseline the minute was judged against when stored; None while calibrating
+    pub baseline_wpm: Option<f64>,
+}
+
+/// Mean WPM of the points within `SMOOTHING_MINUTES` up to and including `at`
+pub fn smoothed_wpm(points: &[WpmPoint], at: DateTime<Utc>) -> Option<f64> {
+    let from = at - chrono::Duration::minutes(SMOOTHING_MINUTES);
+    let window: Vec<f64> = points.iter()
+        .filter(|p| p.time > from && p.time <= at)
+        .map(|p| p.wpm)
+        .collect();
+    (!window.is_empty()).then(|| window.iter().sum::<f64>() / window.len() as f64)
+}
+
+/// Fatigue level of a smoothed reading when it is at least `MODERATE_DROP` below baseline.
+/// Such minutes count toward episodes and are kept out of the baseline.
+pub fn fatigued_level(smoothed_wpm: f64, baseline_wpm: f64) -> Option<&'static str> {
+    let level = fatigue_level(1.0 - smoothed_wpm / baseline_wpm);
+    (baseline_wpm > 0.0 && level_rank(level) >= level_rank("moderate")).then_some(level)
+}
+
+/// Sustained stretch of typing at least `MODERATE_DROP` below baseline
+#[derive(Debug, Serialize, Clone)]
+pub struct FatigueEpisode {
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration_minutes: i64,
+    pub peak_level: String,
+    pub mean_wpm: f64,
+    pub baseline_wpm: f64,
+    pub mean_drop_percent: f64,
+}
+
+/// Group fatigued minutes into episodes. `points` must be sorted by time.
+/// Each minute is judged against the baseline stored with it, so later baseline
+/// changes don't rewrite past episodes.
+pub fn detect_episodes(points: &[WpmPoint]) -> Vec<FatigueEpisode> {
+    struct Open {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        wpm: Vec<f64>,
+        baseline: Vec<f64>,
+        peak: &'static str,
+    }
+
+    let close = |open: Open, episodes: &mut Vec<FatigueEpisode>| {
+        let duration = (open.end - open.start).num_minutes() + 1;
+        if duration < EPISODE_MIN_MINUTES {
+            return;
+        }
+        let n = open.wpm.len() as f64;
+        let mean_wpm = open.wpm.iter().sum::<f64>() / n;
+        let baseline_wpm = open.baseline.iter().sum::<f64>() / n;
+        episodes.push(FatigueEpisode {
+            started_at: crate::services::time::format_utc(open.start),
+            ended_at: crate::services::time::format_utc(open.end),
+            duration_minutes: duration,
+            peak_level: open.peak.to_string(),
+            mean_wpm: mean_wpm.round(),
+            baseline_wpm: baseline_wpm.round(),
+            mean_drop_percent: ((1.0 - mean_wpm / baseline_wpm) * 1000.0).round() / 10.0,
+        });
+    };
+
+    let mut episodes = Vec::new();
+    let mut open: Option<Open> = None;
+    // Sliding window for the rolling mean (same definition as `smoothed_wpm`)
+    let (mut window_start, mut window_sum) = (0usize, 0.0);
+
+    for (i, point) in points.iter().enumerate() {
+        window_sum += point.wpm;
+        while points[window_start].time <= point.time - chrono::Duration::minutes(SMOOTHING_MINUTES) {
+            window_sum -= points[window_start].wpm;
+            window_start += 1;
+        }
+        let smoothed = window_sum / (i + 1 - window_start) as f64;
+
+        let fatigued = point.baseline_wpm
+            .and_then(|baseline| Some((smoothed, baseline, fatigued_level(smoothed, baseline)?)));
+
+        // A long pause ends the episode even if typing resumes just as slowly
+        if let Some(o) = open.take() {
+            if (point.time - o.end).num_minutes() > EPISODE_MAX_GAP_MINUTES || fatigued.is_none() {
+                close(o, &mut episodes);
+            } else {
+                open = Some(o);
+            }
+        }
+
+        if let Some((wpm, baseline, level)) = fatigued {
+            let o = open.get_or_insert_with(|| Open {
+                start: point.time,
+                end: point.time,
+                wpm: Vec::new(),
+                baseline: Vec::new(),
+                peak: "none",
+            });
+            o.end = point.time;
+            o.wpm.push(wpm);
+            o.baseline.push(baseline);
+            if level_rank(level) > level_rank(o.peak) {
+                o.peak = level;
+            }
+        }
+    }
+    if let Some(o) = open {
+        close(o, &mut episodes);
+    }
+    episodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::typing::{episodes_between, record_wpm};
+    use crate::services::clock::Clock;
+    use crate::services::context::testing::context_at;
+
+    const START: &str = "2026-07-06T08:00:00Z"; // Monday 09:00 in Lisbon
+
+    fn minute(offset: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(START).unwrap().with_timezone(&Utc) + chrono::Duration::minutes(offset)
+    }
+
+    /// `fast` minutes at 60 WPM, then `slow` minutes at 25 WPM
+    fn series(fast: i64, slow: i64) -> Vec<(DateTime<Utc>, f64)> {
+        (0..fast + slow).map(|i| (minute(i), if i < fast { 60.0 } else { 25.0 })).collect()
+    }
+
+    #[test]
+    fn episodes_need_a_sustained_drop_against_the_stored_baseline() {
+        let points = |slow: i64, baseline: Option<f64>| -> Vec<WpmPoint> {
+            series(20, slow).into_iter().map(|(time, wpm)| WpmPoint { time, wpm, baseline_wpm: baseline }).collect()
+        };
+
+        let episodes = detect_episodes(&points(15, Some(60.0)));
+        assert_eq!(episodes.len(), 1);
+        // The rolling mean crosses 30% below baseline on the third slow minute
+        assert_eq!(episodes[0].started_at, "2026-07-06T08:22:00Z");
+        assert_eq!(episodes[0].duration_minutes, 13);
+        assert_eq!(episodes[0].peak_level, "high");
+
+        assert!(detect_episodes(&points(8, Some(60.0))).is_empty(), "a short dip is noise");
+        assert!(detect_episodes(&points(15, None)).is_empty(), "calibrating minutes are never fatigued");
+    }
+
+    #[tokio::test]
+    async fn fatigued_minutes_stay_out_of_the_baseline() {
+        let (ctx, clock, _) = context_at(START, None);
+        let (tz, now) = (ctx.tz(), clock.now());
+        let readings = series(30, 15);
+        let stored = ctx.db.write(move |db| record_wpm(db, tz, &readings, "keyboard", now)).await.unwrap();
+        assert_eq!(stored, 45);
+        let updated: String = ctx.db.read(|db| db.query_row("SELECT DISTINCT updated_at FROM wpm_baselines", &[], |row| row.get(0))).await.unwrap();
+        assert_eq!(updated, START);
+
+        // Only the two slow minutes before the rolling mean dropped 30% were learned
+        let cell = ctx.db.read(|db| db.get_wpm_baselines()).await.unwrap().cells[&(0, 9)];
+        assert_eq!(cell.samples, 32);
+        assert!((cell.mean - (30.0 * 60.0 + 2.0 * 25.0) / 32.0).abs() < 1e-9);
+
+        let episodes = ctx.db.read(|db| episodes_between(db, "2026-07-06T08:00:00Z", "2026-07-06T09:00:00Z")).await.unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!((episodes[0].started_at.as_str(), episodes[0].duration_minutes), ("2026-07-06T08:32:00Z", 13));
+    }
+
+    #[tokio::test]
+    async fn baseline_changes_do_not_rewrite_past_episodes() {
+        let (ctx, clock, _) = context_at(START, None);
+        let (tz, now) = (ctx.tz(), clock.now());
+        let readings = series(30, 15);
+        ctx.db.write(move |db| record_wpm(db, tz, &readings, "keyboard", now)).await.unwrap();
+        let range = ("2026-07-06T08:00:00Z", "2026-07-06T09:00:00Z");
+        let before = ctx.db.read(move |db| episodes_between(db, range.0, range.1)).await.unwrap();
+
+        // A slower baseline later on would clear the episode if it were re-judged
+        ctx.db.write(|db| db.execute("UPDATE wpm_baselines SET mean = 30", &[])).await.unwrap();
+        let after = ctx.db.read(move |db| episodes_between(db, range.0, range.1)).await.unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(
+            (&after[0].started_at, after[0].baseline_wpm, after[0].mean_drop_percent),
+            (&before[0].started_at, before[0].baseline_wpm, before[0].mean_drop_percent),
+        );
+    }
+}
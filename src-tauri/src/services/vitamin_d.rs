@@ -1,3 +1,4 @@
+use chrono::{Datelike, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike};
 use serde::{Deserialize, Serialize};
 
 /// Vitamin D3 Calculator
@@ -8,9 +9,14 @@ pub struct VitaminDRecommendation {
     pub uv_index: f64,
     pub skin_type: u8,          // Fitzpatrick scale 1-6
     pub latitude: f64,
+    pub longitude: f64,
     pub optimal_minutes: u32,    // Minutes of sun exposure needed
-    pub best_window: String,     // e.g., "11:00 - 14:00"
+    pub best_window: Option<String>, // e.g., "11:00 - 14:00" — None when sun never reaches UVB_MIN_ELEVATION
+    pub solar_noon: String,      // Local time of maximum elevation, "HH:MM"
+    pub max_solar_elevation: f64, // Degrees above horizon at solar noon
     pub d3_iu_supplement: u32,   // Recommended IU if sun is insufficient
+    pub intake: Option<IntakeBudget>, // Rolling sun + supplement intake, when logged
+    pub response: Option<ResponseModel>, // Personal lab response, when ≥2 labs exist
     pub note: String,
 }
 
@@ -21,18 +27,82 @@ const DEFAULT_SKIN_TYPE: u8 = 4;
 /// Portugal latitude (Lisbon)
 const PORTUGAL_LATITUDE: f64 = 38.7;
 
-/// Calculate Vitamin D recommendation
-pub fn calculate(
-    uv_index: f64,
-    skin_type: Option<u8>,
-    latitude: Option<f64>,
-    month: u32,
-) -> VitaminDRecommendation {
-    let skin = skin_type.unwrap_or(DEFAULT_SKIN_TYPE);
-    let lat = latitude.unwrap_or(PORTUGAL_LATITUDE);
+/// Portugal longitude (Lisbon)
+const PORTUGAL_LONGITUDE: f64 = -9.1;
+
+/// Below ~45° solar elevation the atmospheric path absorbs most UVB (290-315nm),
+/// so cutaneous D3 synthesis is negligible regardless of exposure time.
+pub const UVB_MIN_ELEVATION: f64 = 45.0;
+
+/// Solar geometry for one local calendar day
+#[derive(Debug, Clone)]
+pub struct SolarDay {
+    pub solar_noon: NaiveTime,
+    pub max_elevation: f64,
+    /// First and last local minute with elevation ≥ UVB_MIN_ELEVATION
+    pub uvb_window: Option<(NaiveTime, NaiveTime)>,
+}
+
+/// Solar elevation in degrees at a UTC instant (NOAA general solar position equations).
+/// Accurate to well under a degree, which is plenty for exposure planning.
+pub fn solar_elevation(latitude: f64, longitude: f64, at: chrono::DateTime<chrono::Utc>) -> f64 {
+    let day_of_year = at.ordinal() as f64;
+    let hour = at.hour() as f64 + at.minute() as f64 / 60.0 + at.second() as f64 / 3600.0;
+
+    // Fractional year (radians)
+    let gamma = 2.0 * std::f64::consts::PI / 365.0 * (day_of_year - 1.0 + (hour - 12.0) / 24.0);
+
+    // Equation of time (minutes) and solar declination (radians)
+    let eq_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos() - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos() - 0.040849 * (2.0 * gamma).sin());
+    let decl = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos() + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos() + 0.00148 * (3.0 * gamma).sin();
+
+    // True solar time (minutes) → hour angle (degrees)
+    let true_solar_minutes = hour * 60.0 + eq_time + 4.0 * longitude;
+    let hour_angle = (true_solar_minutes / 4.0 - 180.0).to_radians();
+
+    let lat = latitude.to_radians();
+    let cos_zenith = (lat.sin() * decl.sin() + lat.cos() * decl.cos() * hour_angle.cos()).clamp(-1.0, 1.0);
+    90.0 - cos_zenith.acos().to_degrees()
+}
+
+/// Sample the solar elevation curve minute-by-minute over a local day
+pub fn solar_day(latitude: f64, longitude: f64, date: NaiveDate, utc_offset: FixedOffset) -> SolarDay {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+
+    let mut solar_noon = NaiveTime::MIN;
+    let mut max_elevation = f64::MIN;
+    let mut first_above: Option<NaiveTime> = None;
+    let mut last_above: Option<NaiveTime> = None;
+
+    for minute in 0..(24 * 60) {
+        let local = midnight + chrono::Duration::minutes(minute);
+        let Some(instant) = utc_offset.from_local_datetime(&local).single() else { continue };
+        let elevation = solar_elevation(latitude, longitude, instant.with_timezone(&chrono::Utc));
+
+        if elevation > max_elevation {
+            max_elevation = elevation;
+            solar_noon = local.time();
+        }
+        if elevation >= UVB_MIN_ELEVATION {
+            first_above.get_or_insert(local.time());
+            last_above = Some(local.time());
+        }
+    }
 
-    // Base MED (Minimal Erythemal Dose) by Fitzpatrick type — in minutes at UV 6
-    let base_med_minutes: f64 = match skin {
+    SolarDay {
+        solar_noon,
+        max_elevation,
+        uvb_window: first_above.zip(last_above),
+    }
+}
+
+/// Base MED (Minimal Erythemal Dose) by Fitzpatrick type — in minutes at UV 6
+fn base_med_minutes(skin_type: u8) -> f64 {
+    match skin_type {
         1 => 10.0,   // Very fair — burns easily
         2 => 15.0,   // Fair
         3 => 20.0,   // Medium
@@ -40,33 +110,31 @@ pub fn calculate(
         5 => 45.0,   // Brown
         6 => 60.0,   // Dark
         _ => 30.0,
-    };
+    }
+}
 
-    // Adjust for UV index (inversely proportional)
-    // At UV 1, need ~6x more time than UV 6
+/// Minutes to reach one MED at a given UV index (inversely proportional)
+/// At UV 1, need ~6x more time than UV 6
+fn med_minutes(uv_index: f64, skin_type: u8) -> f64 {
     let uv_factor = if uv_index > 0.0 { 6.0 / uv_index } else { 10.0 };
-    let adjusted_minutes = (base_med_minutes * uv_factor * 0.5).round() as u32; // 50% of MED = safe
+    base_med_minutes(skin_type) * uv_factor
+}
 
-    // Clamp to reasonable range
-    let optimal_minutes = adjusted_minutes.clamp(10, 120);
+/// Unclamped safe exposure: 50% of MED
+fn safe_exposure_minutes(uv_index: f64, skin_type: u8) -> f64 {
+    med_minutes(uv_index, skin_type) * 0.5
+}
 
-    // Best sun window depends on latitude and season
-    let best_window = if lat > 45.0 {
-        // Northern latitudes — narrower window
-        "11:30 - 13:30".to_string()
-    } else if lat > 35.0 {
-        // Portugal latitude
-        match month {
-            11 | 12 | 1 | 2 => "11:00 - 14:00".to_string(),  // Winter — wider window needed
-            3 | 4 | 9 | 10 => "11:00 - 15:00".to_string(),    // Spring/Autumn
-            _ => "10:00 - 16:00".to_string(),                   // Summer
-        }
-    } else {
-        "10:00 - 16:00".to_string()
-    };
+/// Logged history that personalises the supplement dose (both empty = static tiers)
+#[derive(Debug, Default)]
+pub struct IntakeHistory {
+    pub budget: Option<IntakeBudget>,
+    pub response: Option<ResponseModel>,
+}
 
-    // Supplement recommendation when UV is too low
-    let d3_iu_supplement = if uv_index < 3.0 {
+/// Static supplement tiers by UV index and skin type
+fn tiered_supplement_iu(uv_index: f64, skin: u8, has_uvb_window: bool) -> u32 {
+    if uv_index < 3.0 || !has_uvb_window {
         // Low UV — need significant supplementation
         match skin {
             1..=2 => 2000,
@@ -85,32 +153,595 @@ pub fn calculate(
             1..=3 => 0,
             _ => 1000, // Darker skin may still benefit
         }
+    }
+}
+
+/// Calculate Vitamin D recommendation
+pub fn calculate(
+    uv_index: f64,
+    skin_type: Option<u8>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    date: NaiveDate,
+    utc_offset: FixedOffset,
+    history: &IntakeHistory,
+) -> VitaminDRecommendation {
+    let budget = history.budget.as_ref();
+    let response = history.response.as_ref();
+    let skin = skin_type.unwrap_or(DEFAULT_SKIN_TYPE);
+    let lat = latitude.unwrap_or(PORTUGAL_LATITUDE);
+    let lon = longitude.unwrap_or(PORTUGAL_LONGITUDE);
+
+    let adjusted_minutes = safe_exposure_minutes(uv_index, skin).round() as u32;
+
+    // Clamp to reasonable range
+    let optimal_minutes = adjusted_minutes.clamp(10, 120);
+
+    // Best sun window: the span when the sun is high enough for UVB to reach the skin
+    let sun = solar_day(lat, lon, date, utc_offset);
+    let best_window = sun.uvb_window
+        .map(|(start, end)| format!("{} - {}", start.format("%H:%M"), end.format("%H:%M")));
+
+    // Supplement recommendation, most personal first: the user's measured lab response,
+    // then closing the rolling intake budget, then the UV/skin-type tiers
+    let sun_daily_iu = budget.map(|b| b.sun_iu as f64 / b.window_days as f64).unwrap_or(0.0);
+    let d3_iu_supplement = match (response, budget) {
+        (Some(model), _) => personal_supplement_iu(model, sun_daily_iu),
+        (None, Some(b)) => budget_supplement_iu(b),
+        (None, None) => tiered_supplement_iu(uv_index, skin, best_window.is_some()),
     };
 
-    let note = if uv_index < 2.0 {
-        format!(
-            "UV muito baixo ({:.0}). Em Portugal no inverno, é quase impossível sintetizar Vitamina D suficiente. Suplementação de {}IU/dia é essencial para fototipo {}.",
-            uv_index, d3_iu_supplement, skin
-        )
-    } else if uv_index < 4.0 {
-        format!(
-            "UV moderado ({:.0}). {} minutos de exposição solar diária no período {} com braços e rosto expostos. Suplementar {}IU/dia como apoio.",
-            uv_index, optimal_minutes, best_window, d3_iu_supplement
-        )
+    let note = if let Some(window) = &best_window {
+        if uv_index < 2.0 {
+            format!(
+                "UV muito baixo ({:.0}). Mesmo na janela {}, a síntese de Vitamina D será mínima. Suplementação de {}IU/dia é essencial para fototipo {}.",
+                uv_index, window, d3_iu_supplement, skin
+            )
+        } else if uv_index < 4.0 {
+            format!(
+                "UV moderado ({:.0}). {} minutos de exposição solar diária no período {} com braços e rosto expostos. Suplementar {}IU/dia como apoio.",
+                uv_index, optimal_minutes, window, d3_iu_supplement
+            )
+        } else if d3_iu_supplement == 0 {
+            format!(
+                "UV bom ({:.0}). {} minutos de exposição solar no período {} são suficientes. Sem necessidade de suplementação extra.",
+                uv_index, optimal_minutes, window
+            )
+        } else {
+            format!(
+                "UV bom ({:.0}). {} minutos de exposição solar no período {}. Suplementar {}IU/dia para completar.",
+                uv_index, optimal_minutes, window, d3_iu_supplement
+            )
+        }
     } else {
         format!(
-            "UV bom ({:.0}). {} minutos de exposição solar no período {} são suficientes. Sem necessidade de suplementação extra.",
-            uv_index, optimal_minutes, best_window
+            "Sem janela de síntese hoje: o sol atinge no máximo {:.0}° às {} (são precisos ≥{:.0}° para UVB). Exposição solar não produz Vitamina D hoje — suplementar {}IU/dia.",
+            sun.max_elevation.max(0.0), sun.solar_noon.format("%H:%M"), UVB_MIN_ELEVATION, d3_iu_supplement
         )
     };
 
+    let note = match budget {
+        Some(b) => format!(
+            "{} Últimos {} dias: média {} IU/dia (sol {} IU, suplemento {} IU) para um alvo de {} IU/dia.",
+            note, b.window_days, b.daily_average_iu, b.sun_iu, b.supplement_iu, b.daily_target_iu
+        ),
+        None => note,
+    };
+    let note = match response {
+        Some(m) => format!(
+            "{} Resposta pessoal ({} pares de análises): +{:.1} ng/mL por 1000 IU/dia. Último 25-OH-D {:.0} ng/mL ({}), alvo {:.0} ng/mL.",
+            note, m.lab_pairs, m.ng_ml_per_1000_iu, m.latest_ng_ml, m.latest_date, TARGET_SERUM_NG_ML
+        ),
+        None => note,
+    };
+
     VitaminDRecommendation {
         uv_index,
         skin_type: skin,
         latitude: lat,
+        longitude: lon,
         optimal_minutes,
         best_window,
+        solar_noon: sun.solar_noon.format("%H:%M").to_string(),
+        max_solar_elevation: (sun.max_elevation * 10.0).round() / 10.0,
         d3_iu_supplement,
+        intake: budget.cloned(),
+        response: response.cloned(),
         note,
     }
 }
+
+// === Multi-day exposure planning (hourly forecast) ===
+
+/// Whole-body exposure to 1 MED yields roughly 10,000-20,000 IU; use the conservative end
+const FULL_BODY_MED_IU: f64 = 10_000.0;
+
+/// Below UV 3 synthesis is too slow to be worth planning around
+pub const MIN_SYNTHESIS_UV: f64 = 3.0;
+
+/// Below UV 1 there is effectively no UVB reaching the skin
+const NEGLIGIBLE_UV: f64 = 1.0;
+
+/// Slots kept per day in the plan
+const SLOTS_PER_DAY: usize = 3;
+
+/// One forecast hour (UTC). Open-Meteo's `uv_index` already includes cloud attenuation;
+/// cloud cover is kept so nudges can mention it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UvHour {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub uv_index: f64,
+    pub cloud_cover: f64, // %
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExposureSlot {
+    pub start: String,        // Local "HH:MM"
+    pub end: String,
+    pub uv_index: f64,
+    pub cloud_cover: f64,
+    pub minutes: u32,         // Safe exposure (50% MED), capped to the slot
+    pub estimated_iu: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyExposurePlan {
+    pub date: String,         // Local "YYYY-MM-DD"
+    pub max_uv_index: f64,
+    pub slots: Vec<ExposureSlot>, // Best first
+    pub best_estimated_iu: u32,
+    pub nudge: String,        // Ready-to-speak summary for the agent
+}
+
+/// Fraction of body surface exposed (rule of nines, rounded)
+/// face_hands | face_arms | arms_legs | swimsuit
+pub fn body_area_fraction(body_area: &str) -> f64 {
+    match body_area {
+        "face_hands" => 0.10,
+        "face_arms" => 0.25,
+        "arms_legs" => 0.50,
+        "swimsuit" => 0.80,
+        _ => 0.25,
+    }
+}
+
+fn body_area_label(body_area: &str) -> &'static str {
+    match body_area {
+        "face_hands" => "rosto e mãos",
+        "arms_legs" => "braços e pernas",
+        "swimsuit" => "corpo quase todo",
+        _ => "rosto e braços",
+    }
+}
+
+/// Estimated IU synthesized for `minutes` in the sun. Production plateaus at ~1 MED,
+/// after which previtamin D3 photodegrades instead of accumulating.
+pub fn estimate_iu(uv_index: f64, skin_type: u8, minutes: f64, body_area_fraction: f64) -> u32 {
+    if uv_index < NEGLIGIBLE_UV || minutes <= 0.0 {
+        return 0;
+    }
+    let med_fraction = (minutes / med_minutes(uv_index, skin_type)).min(1.0);
+    (FULL_BODY_MED_IU * med_fraction * body_area_fraction).round() as u32
+}
+
+/// Build a per-day exposure plan from an hourly forecast. Hours are placed in `tz` one by
+/// one, so a DST change inside the plan shifts the slots with the wall clock.
+/// `today` anchors the "Hoje"/"Amanhã" labels in the nudges.
+pub fn plan_exposure(
+    forecast: &[UvHour],
+    skin_type: u8,
+    body_area: &str,
+    tz: chrono_tz::Tz,
+    today: NaiveDate,
+) -> Vec<DailyExposurePlan> {
+    let fraction = body_area_fraction(body_area);
+
+    let mut days: std::collections::BTreeMap<NaiveDate, Vec<&UvHour>> = Default::default();
+    for hour in forecast {
+        let local_date = hour.time.with_timezone(&tz).date_naive();
+        if local_date >= today {
+            days.entry(local_date).or_default().push(hour);
+        }
+    }
+
+    days.into_iter()
+        .map(|(date, hours)| {
+            let max_uv_index = hours.iter().map(|h| h.uv_index).fold(0.0, f64::max);
+
+            let mut slots: Vec<ExposureSlot> = hours.iter()
+                .filter(|h| h.uv_index >= MIN_SYNTHESIS_UV)
+                .map(|h| {
+                    let minutes = safe_exposure_minutes(h.uv_index, skin_type).clamp(5.0, 60.0);
+                    let start = h.time.with_timezone(&tz);
+                    ExposureSlot {
+                        start: start.format("%H:%M").to_string(),
+                        end: (start + chrono::Duration::hours(1)).format("%H:%M").to_string(),
+                        uv_index: h.uv_index,
+                        cloud_cover: h.cloud_cover,
+                        minutes: minutes.round() as u32,
+                        estimated_iu: estimate_iu(h.uv_index, skin_type, minutes, fraction),
+                    }
+                })
+                .collect();
+
+            // Highest yield first, then the shortest exposure, then fewer clouds (more stable UV)
+            slots.sort_by(|a, b| {
+                b.estimated_iu.cmp(&a.estimated_iu)
+                    .then(a.minutes.cmp(&b.minutes))
+                    .then(a.cloud_cover.total_cmp(&b.cloud_cover))
+            });
+            slots.truncate(SLOTS_PER_DAY);
+
+            let best_estimated_iu = slots.first().map(|s| s.estimated_iu).unwrap_or(0);
+
+            let day_label = match (date - today).num_days() {
+                0 => "Hoje".to_string(),
+                1 => "Amanhã".to_string(),
+                _ => date.format("%d/%m").to_string(),
+            };
+            let nudge = match slots.first() {
+                Some(best) => format!(
+                    "{}: melhor janela às {} (UV {:.0}, {:.0}% nuvens). {} min com {} expostos ≈ {} IU.",
+                    day_label, best.start, best.uv_index, best.cloud_cover,
+                    best.minutes, body_area_label(body_area), best.estimated_iu
+                ),
+                None => format!(
+                    "{}: UV máximo {:.0} — insuficiente para sintetizar Vitamina D. Manter suplemento.",
+                    day_label, max_uv_index
+                ),
+            };
+
+            DailyExposurePlan {
+                date: date.format("%Y-%m-%d").to_string(),
+                max_uv_index,
+                slots,
+                best_estimated_iu,
+                nudge,
+            }
+        })
+        .collect()
+}
+
+// === Sun exposure log + rolling intake budget ===
+
+/// Rolling window for the intake budget. D3 is fat-soluble (25-OH-D half-life ~2-3 weeks),
+/// so a weekly budget is as meaningful as a daily one.
+pub const BUDGET_WINDOW_DAYS: i64 = 7;
+
+/// Tolerable upper intake level for adults — never recommend more than this per day
+const D3_UPPER_LIMIT_IU: u32 = 4000;
+
+/// IU logged for one sun exposure session. Sunscreen divides effective UVB by its SPF,
+/// which overstates real-world protection (thin application) — a conservative estimate.
+pub fn estimate_session_iu(
+    uv_index: f64,
+    skin_type: u8,
+    duration_minutes: u32,
+    body_area: &str,
+    spf: Option<f64>,
+) -> u32 {
+    let iu = estimate_iu(uv_index, skin_type, duration_minutes as f64, body_area_fraction(body_area));
+    match spf {
+        Some(spf) if spf > 1.0 => (iu as f64 / spf).round() as u32,
+        _ => iu,
+    }
+}
+
+/// Extract IU from a logged D3 supplement, e.g. ("Vitamina D3", "2000 IU") → 2000.
+/// Returns None for anything that isn't vitamin D or has no IU amount.
+pub fn parse_d3_iu(name: &str, dosage: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if !(name.contains("d3") || name.contains("vitamina d") || name.contains("vitamin d")) {
+        return None;
+    }
+    let lower = dosage.to_lowercase();
+    if !(lower.contains("iu") || lower.contains("ui")) {
+        return None;
+    }
+    let digits: String = lower.chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | ' '))
+        .filter(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|iu| *iu > 0)
+}
+
+/// Daily D3 target by Fitzpatrick type — darker skin synthesizes less per minute of sun
+fn daily_target_iu(skin_type: u8) -> u32 {
+    match skin_type {
+        1..=2 => 1500,
+        3..=4 => 2000,
+        _ => 2500,
+    }
+}
+
+/// Rolling intake estimate over the last BUDGET_WINDOW_DAYS (sun + supplements)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntakeBudget {
+    pub window_days: u32,
+    pub sun_iu: u32,
+    pub supplement_iu: u32,
+    pub daily_average_iu: u32,
+    pub daily_target_iu: u32,
+    /// IU still missing to reach the window target (0 when on budget)
+    pub deficit_iu: u32,
+}
+
+pub fn intake_budget(sun_iu: u32, supplement_iu: u32, skin_type: u8) -> IntakeBudget {
+    let window_days = BUDGET_WINDOW_DAYS as u32;
+    let daily_target_iu = daily_target_iu(skin_type);
+    let total = sun_iu + supplement_iu;
+    IntakeBudget {
+        window_days,
+        sun_iu,
+        supplement_iu,
+        daily_average_iu: total / window_days,
+        daily_target_iu,
+        deficit_iu: (daily_target_iu * window_days).saturating_sub(total),
+    }
+}
+
+/// Daily supplement dose that closes the rolling deficit over the next window rather than
+/// in one bolus: the average daily shortfall, rounded up to 500 IU and capped at the upper limit
+fn budget_supplement_iu(budget: &IntakeBudget) -> u32 {
+    dose_step(budget.deficit_iu.div_ceil(budget.window_days.max(1)))
+}
+
+/// Daily doses come in 500 IU steps, rounded up so the target is reached, and never past the upper limit
+fn dose_step(iu: u32) -> u32 {
+    iu.div_ceil(500).saturating_mul(500).min(D3_UPPER_LIMIT_IU)
+}
+
+// === Personal response model (25-OH-D labs vs. intake) ===
+
+/// Lab marker name fragments that identify serum 25-hydroxyvitamin D
+pub const VITAMIN_D_LAB_MARKERS: &[&str] = &["vitamin d", "vitamina d", "25-oh", "25(oh)", "25 oh", "calcidiol"];
+
+/// Middle of the 30-50 ng/mL sufficiency range
+const TARGET_SERUM_NG_ML: f64 = 40.0;
+
+/// 25-OH-D takes ~8-12 weeks to plateau after a dose change
+const STEADY_STATE_WEEKS: f64 = 12.0;
+
+/// Plausible serum response; a fit outside this range is noise, not a model
+const MIN_RESPONSE_PER_1000_IU: f64 = 1.0;
+const MAX_RESPONSE_PER_1000_IU: f64 = 30.0;
+
+/// Intake has to have varied by at least this much (weighted SD, IU/day) to fit a slope
+const MIN_INTAKE_SPREAD_IU: f64 = 250.0;
+
+/// Serum level in ng/mL; nmol/L results are converted (1 ng/mL = 2.496 nmol/L)
+pub fn to_ng_ml(value: f64, unit: &str) -> f64 {
+    if unit.to_lowercase().contains("nmol") { value / 2.496 } else { value }
+}
+
+/// Two consecutive 25-OH-D labs and the average daily intake between them
+#[derive(Debug, Clone)]
+pub struct LabResponseSample {
+    pub weeks: f64,
+    pub start_ng_ml: f64,
+    pub end_ng_ml: f64,
+    pub avg_daily_iu: f64,    // Supplement + sun over the last ≤12 weeks of the interval
+    pub prior_daily_iu: f64,  // Same, over the ≤12 weeks before the first lab
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResponseModel {
+    pub ng_ml_per_1000_iu: f64,
+    pub lab_pairs: usize,
+    pub latest_ng_ml: f64,
+    pub latest_date: String,
+    /// Average daily intake that produced the latest level
+    pub latest_daily_iu: f64,
+}
+
+/// Fit the serum level at each lab against the daily intake that preceded it
+/// (weighted least squares with an intercept, i.e. the steady-state dose response).
+/// Pairs shorter than the steady-state period weigh proportionally less.
+/// A single pair is fitted through its starting level instead: the level change against the intake change.
+/// Returns None when intake never varied enough to estimate a slope, or when the
+/// slope falls outside the plausible range (e.g. the level fell while intake rose).
+pub fn fit_response_model(samples: &[LabResponseSample], latest_date: &str) -> Option<ResponseModel> {
+    let last = samples.last()?;
+    let slope = match samples {
+        [only] => single_pair_slope(only)?,
+        _ => regression_slope(samples)?,
+    };
+    if !(MIN_RESPONSE_PER_1000_IU..=MAX_RESPONSE_PER_1000_IU).contains(&slope) {
+        return None;
+    }
+
+    Some(ResponseModel {
+        ng_ml_per_1000_iu: slope,
+        lab_pairs: samples.len(),
+        latest_ng_ml: last.end_ng_ml,
+        latest_date: latest_date.to_string(),
+        latest_daily_iu: last.avg_daily_iu,
+    })
+}
+
+/// ng/mL per 1000 IU from one pair: only the share of the steady state reached by the second lab shows up
+fn single_pair_slope(sample: &LabResponseSample) -> Option<f64> {
+    let weight = (sample.weeks / STEADY_STATE_WEEKS).clamp(0.0, 1.0);
+    let intake_change = sample.avg_daily_iu - sample.prior_daily_iu;
+    if weight <= 0.0 || intake_change.abs() < MIN_INTAKE_SPREAD_IU {
+        return None;
+    }
+    Some((sample.end_ng_ml - sample.start_ng_ml) / (weight * intake_change / 1000.0))
+}
+
+fn regression_slope(samples: &[LabResponseSample]) -> Option<f64> {
+    let points: Vec<(f64, f64, f64)> = samples.iter()
+        .map(|s| ((s.weeks / STEADY_STATE_WEEKS).clamp(0.0, 1.0), s.avg_daily_iu / 1000.0, s.end_ng_ml))
+        .filter(|(weight, ..)| *weight > 0.0)
+        .collect();
+    let total_weight: f64 = points.iter().map(|(w, ..)| w).sum();
+    if total_weight <= 0.0 {
+        return None;
+    }
+    let mean_x = points.iter().map(|(w, x, _)| w * x).sum::<f64>() / total_weight;
+    let mean_y = points.iter().map(|(w, _, y)| w * y).sum::<f64>() / total_weight;
+
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (w, x, y) in &points {
+        sxy += w * (x - mean_x) * (y - mean_y);
+        sxx += w * (x - mean_x) * (x - mean_x);
+    }
+    let min_spread = MIN_INTAKE_SPREAD_IU / 1000.0;
+    if sxx / total_weight < min_spread * min_spread {
+        return None;
+    }
+    Some(sxy / sxx)
+}
+
+/// Daily supplement dose that should move serum from the latest level to the target,
+/// given expected daily IU from sun. Rounded up to 500 IU, capped at the upper limit.
+fn personal_supplement_iu(model: &ResponseModel, sun_daily_iu: f64) -> u32 {
+    let needed_total = model.latest_daily_iu
+        + (TARGET_SERUM_NG_ML - model.latest_ng_ml) / model.ng_ml_per_1000_iu * 1000.0;
+    let supplement = (needed_total - sun_daily_iu).max(0.0);
+    // Float noise must not push an exact step up to the next one
+    dose_step((supplement - 1e-6).max(0.0).ceil() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(raw: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(raw).unwrap().with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn elevation_matches_noaa_reference_values() {
+        // NOAA solar calculator spreadsheet (Meeus), Lisbon and Oslo, no refraction. The
+        // fractional-year declination drifts most around the equinoxes (~0.4°)
+        let cases = [
+            (38.7, -9.1, "2026-06-21T12:38:00Z", 74.738),
+            (38.7, -9.1, "2026-03-20T09:00:00Z", 25.834),
+            (38.7, -9.1, "2026-12-21T16:00:00Z", 11.445),
+            (38.7, -9.1, "2026-06-21T00:00:00Z", -27.224),
+            (59.91, 10.75, "2026-12-21T11:15:00Z", 6.653),
+        ];
+        for (lat, lon, at, expected) in cases {
+            let elevation = solar_elevation(lat, lon, utc(at));
+            assert!((elevation - expected).abs() < 0.5, "{} at {}: {} vs {}", lat, at, elevation, expected);
+        }
+    }
+
+    #[test]
+    fn lisbon_midsummer_has_a_window_around_solar_noon() {
+        // NOAA: solar noon 12:38 UTC (13:38 WEST) at 74.7°
+        let sun = solar_day(38.7, -9.1, NaiveDate::from_ymd_opt(2026, 6, 21).unwrap(), FixedOffset::east_opt(3600).unwrap());
+        let noon = NaiveTime::from_hms_opt(13, 38, 0).unwrap();
+        assert!((sun.solar_noon - noon).num_minutes().abs() <= 2, "{}", sun.solar_noon);
+        assert!((sun.max_elevation - 74.738).abs() < 0.3);
+
+        let (start, end) = sun.uvb_window.unwrap();
+        let (before, after) = ((sun.solar_noon - start).num_minutes(), (end - sun.solar_noon).num_minutes());
+        assert!((before - after).abs() <= 2 && before > 180, "{} - {}", start, end);
+    }
+
+    #[test]
+    fn high_latitude_winter_has_no_window() {
+        // Oslo at the winter solstice: the sun peaks at ~6.7° (NOAA), far below the UVB threshold
+        let date = NaiveDate::from_ymd_opt(2026, 12, 21).unwrap();
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        let sun = solar_day(59.91, 10.75, date, cet);
+        assert!(sun.uvb_window.is_none());
+        assert!((sun.max_elevation - 6.653).abs() < 0.3);
+
+        let rec = calculate(4.0, Some(2), Some(59.91), Some(10.75), date, cet, &IntakeHistory::default());
+        assert_eq!(rec.best_window, None);
+        assert_eq!(rec.solar_noon, "12:15");
+        assert!(rec.note.starts_with("Sem janela de síntese hoje: o sol atinge no máximo 7° às 12:15"), "{}", rec.note);
+        assert!(rec.d3_iu_supplement > 0);
+    }
+
+    #[test]
+    fn plan_follows_the_wall_clock_across_a_dst_change() {
+        // Lisbon leaves summer time on 2026-10-25: the same UTC hour is an hour earlier locally
+        let forecast = ["2026-10-24T12:00:00Z", "2026-10-25T12:00:00Z"]
+            .map(|t| UvHour { time: utc(t), uv_index: 4.0, cloud_cover: 0.0 });
+        let today = NaiveDate::from_ymd_opt(2026, 10, 24).unwrap();
+
+        let plan = plan_exposure(&forecast, 3, "face_arms", chrono_tz::Europe::Lisbon, today);
+        let starts: Vec<_> = plan.iter().map(|day| (day.date.as_str(), day.slots[0].start.as_str())).collect();
+        assert_eq!(starts, [("2026-10-24", "13:00"), ("2026-10-25", "12:00")]);
+    }
+
+    #[test]
+    fn budget_deficit_is_spread_over_the_window() {
+        // Skin type 4: 2000 IU/day target, 14000 IU over the week
+        let missed_week = intake_budget(0, 0, 4);
+        assert_eq!(missed_week.deficit_iu, 14_000);
+        assert_eq!(budget_supplement_iu(&missed_week), 2000);
+
+        // 3000 IU short: ~430 IU/day, rounded up
+        assert_eq!(budget_supplement_iu(&intake_budget(5000, 6000, 4)), 500);
+        assert_eq!(budget_supplement_iu(&intake_budget(0, 14_000, 4)), 0);
+    }
+
+    fn lab_pair(weeks: f64, start_ng_ml: f64, end_ng_ml: f64, avg_daily_iu: f64) -> LabResponseSample {
+        LabResponseSample { weeks, start_ng_ml, end_ng_ml, avg_daily_iu, prior_daily_iu: 0.0 }
+    }
+
+    #[test]
+    fn steady_dose_gives_no_response_model() {
+        // 2000 IU/day at a stable 30 ng/mL says nothing about the dose response
+        let samples = [lab_pair(12.0, 30.0, 30.5, 2000.0), lab_pair(12.0, 30.5, 29.8, 2000.0)];
+        assert!(fit_response_model(&samples, "2026-06-01").is_none());
+    }
+
+    #[test]
+    fn dose_increase_fits_the_response_and_the_dose_to_target() {
+        // 1000 IU/day → 22 ng/mL, then 3000 IU/day → 32 ng/mL: +5 ng/mL per 1000 IU
+        let samples = [lab_pair(12.0, 20.0, 22.0, 1000.0), lab_pair(16.0, 22.0, 32.0, 3000.0)];
+        let model = fit_response_model(&samples, "2026-06-01").unwrap();
+        assert!((model.ng_ml_per_1000_iu - 5.0).abs() < 1e-9);
+        assert_eq!((model.latest_ng_ml, model.latest_daily_iu), (32.0, 3000.0));
+
+        // 8 ng/mL short of 40 at 5 per 1000 IU: 4600 IU/day total, 600 of it from the sun
+        assert_eq!(personal_supplement_iu(&model, 600.0), 4000);
+        // 3100 IU rounds up, like the budget dose
+        assert_eq!(personal_supplement_iu(&model, 1500.0), 3500);
+    }
+
+    #[test]
+    fn one_pair_fits_through_the_starting_level() {
+        // 1000 → 3000 IU/day for the full 12 weeks took 22 → 32 ng/mL
+        let pair = LabResponseSample { prior_daily_iu: 1000.0, ..lab_pair(12.0, 22.0, 32.0, 3000.0) };
+        let model = fit_response_model(std::slice::from_ref(&pair), "2026-06-01").unwrap();
+        assert!((model.ng_ml_per_1000_iu - 5.0).abs() < 1e-9);
+        assert_eq!(model.lab_pairs, 1);
+
+        // Six weeks in only half the rise has shown up
+        let early = LabResponseSample { weeks: 6.0, end_ng_ml: 27.0, ..pair.clone() };
+        assert!((fit_response_model(&[early], "2026-06-01").unwrap().ng_ml_per_1000_iu - 5.0).abs() < 1e-9);
+
+        // Same intake on both sides: nothing to fit
+        let steady = LabResponseSample { prior_daily_iu: 3000.0, ..pair };
+        assert!(fit_response_model(&[steady], "2026-06-01").is_none());
+    }
+
+    #[test]
+    fn falling_level_gives_no_response_model() {
+        // Intake unchanged: no slope to fit
+        let flat = [lab_pair(12.0, 40.0, 34.0, 1000.0), lab_pair(12.0, 34.0, 28.0, 1000.0)];
+        assert!(fit_response_model(&flat, "2026-06-01").is_none());
+
+        // Intake went up while the level went down: a negative slope is not a model
+        let inverse = [lab_pair(12.0, 40.0, 35.0, 1000.0), lab_pair(12.0, 35.0, 28.0, 3000.0)];
+        assert!(fit_response_model(&inverse, "2026-06-01").is_none());
+
+        // Dose cut: the level falls with it, which is a normal response
+        let cut = [lab_pair(12.0, 40.0, 42.0, 4000.0), lab_pair(12.0, 42.0, 30.0, 1000.0)];
+        assert!((fit_response_model(&cut, "2026-06-01").unwrap().ng_ml_per_1000_iu - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn budget_dose_stays_under_the_upper_limit() {
+        let budget = IntakeBudget { deficit_iu: 70_000, ..intake_budget(0, 0, 6) };
+        assert_eq!(budget_supplement_iu(&budget), D3_UPPER_LIMIT_IU);
+    }
+}
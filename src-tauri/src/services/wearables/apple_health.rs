@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::BufRead;
+use super::WearableSample;
+
+// Apple Health `export.xml` (Health app → Exportar todos os dados de saúde, unzipped).
+// Only `<Record>` elements matter; they are read one event at a time with a reused buffer.
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z"; // e.g. 2024-03-02 08:15:00 +0000
+
+/// Attributes of a `<Record>` we care about
+#[derive(Default)]
+struct Record {
+    kind: String,
+    value: String,
+    start: String,
+    end: String,
+}
+
+impl Record {
+    fn from_element(element: &BytesStart) -> Self {
+        let mut record = Record::default();
+        for attr in element.attributes().flatten() {
+            let slot = match attr.key.as_ref() {
+                b"type" => &mut record.kind,
+                b"value" => &mut record.value,
+                b"startDate" => &mut record.start,
+                b"endDate" => &mut record.end,
+                _ => continue,
+            };
+            // Values we read are numbers, dates and identifiers: never escaped
+            *slot = String::from_utf8_lossy(&attr.value).into_owned();
+        }
+        record
+    }
+
+    fn to_sample(&self) -> Option<WearableSample> {
+        let start = parse_date(&self.start)?;
+        let number = || self.value.parse::<f64>().ok();
+        match self.kind.as_str() {
+            "HKQuantityTypeIdentifierHeartRate" => Some(WearableSample::heart_rate(start, number()?)),
+            "HKQuantityTypeIdentifierRestingHeartRate" => Some(WearableSample::resting_heart_rate(start, number()?)),
+            "HKQuantityTypeIdentifierHeartRateVariabilitySDNN" => Some(WearableSample::hrv(start, number()?)),
+            "HKQuantityTypeIdentifierStepCount" => Some(WearableSample::steps(start, number()?)),
+            // Asleep, AsleepUnspecified, AsleepCore, AsleepDeep, AsleepREM — not InBed / Awake
+            "HKCategoryTypeIdentifierSleepAnalysis" if self.value.starts_with("HKCategoryValueSleepAnalysisAsleep") => {
+                Some(WearableSample::sleep(start, parse_date(&self.end)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(raw, DATE_FORMAT).ok().map(|d| d.with_timezone(&Utc))
+}
+
+pub fn parse<R, F>(reader: R, mut emit: F) -> Result<(), String>
+where
+    R: BufRead,
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    let mut xml = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut seen_health_data = false;
+    loop {
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(e) | Event::Empty(e)) => match e.name().as_ref() {
+                b"HealthData" => seen_health_data = true,
+                b"Record" => {
+                    if let Some(sample) = Record::from_element(&e).to_sample() {
+                        emit(sample)?;
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!("XML inválido na posição {}: {}", xml.error_position(), e));
+            }
+        }
+        buf.clear();
+    }
+    if !seen_health_data {
+        return Err("O ficheiro não é um export.xml do Apple Health.".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::wearables::tests::{fixture, summary};
+
+    fn collect(bytes: &[u8]) -> Result<Vec<WearableSample>, String> {
+        let mut samples = Vec::new();
+        parse(bytes, |s| {
+            samples.push(s);
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+
+    #[test]
+    fn records_map_to_vitals_and_the_rest_is_skipped() {
+        let samples = collect(&fixture("export.xml")).unwrap();
+        assert_eq!(summary(&samples), [
+            ("heart_rate", 64.0, "2026-03-02T08:15:00Z".to_string()),
+            ("resting_heart_rate", 55.0, "2026-03-02T00:00:00Z".to_string()),
+            // Offsets in the export are honoured
+            ("hrv", 48.5, "2026-03-02T06:00:00Z".to_string()),
+            ("steps", 812.0, "2026-03-02T09:00:00Z".to_string()),
+            // InBed is not sleep; AsleepCore is, as its length in minutes
+            ("sleep", 90.0, "2026-03-02T00:00:00Z".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn other_xml_and_broken_xml_are_errors() {
+        assert_eq!(
+            collect(b"<?xml version=\"1.0\"?><gpx><trk/></gpx>").unwrap_err(),
+            "O ficheiro não é um export.xml do Apple Health."
+        );
+        let broken = collect(b"<HealthData><Record type=\"x\" value=\"1\"></HealthData>").unwrap_err();
+        assert!(broken.starts_with("XML inválido"), "{}", broken);
+    }
+}
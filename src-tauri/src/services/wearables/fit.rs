@@ -0,0 +1,481 @@
+use chrono::{DateTime, Timelike, Utc};
+use std::io::Read;
+use super::WearableSample;
+
+// Minimal streaming decoder for Garmin FIT files (activity, monitoring/wellness and sleep).
+// Only the messages that map to vitals are interpreted; everything else is skipped by size.
+// Reference: FIT SDK protocol description and Profile.xlsx.
+
+const FIT_EPOCH_OFFSET: i64 = 631_065_600; // 1989-12-31T00:00:00Z as a Unix timestamp
+const MIN_RR_INTERVALS_PER_MINUTE: usize = 10;
+
+// Global message numbers
+const MESG_RECORD: u16 = 20;
+const MESG_MONITORING: u16 = 55;
+const MESG_HRV: u16 = 78;
+const MESG_MONITORING_HR_DATA: u16 = 211;
+const MESG_SLEEP_LEVEL: u16 = 275;
+
+const FIELD_TIMESTAMP: u8 = 253;
+
+#[derive(Clone)]
+struct FieldDef {
+    num: u8,
+    size: usize,
+    base_type: u8,
+}
+
+#[derive(Clone)]
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    fields: Vec<FieldDef>,
+    developer_size: usize,
+}
+
+/// One decoded data message: field bytes addressed through its definition
+struct Message<'a> {
+    def: &'a Definition,
+    data: &'a [u8],
+}
+
+impl Message<'_> {
+    fn raw(&self, num: u8) -> Option<(&FieldDef, &[u8])> {
+        let mut offset = 0;
+        for field in &self.def.fields {
+            if field.num == num {
+                return Some((field, &self.data[offset..offset + field.size]));
+            }
+            offset += field.size;
+        }
+        None
+    }
+
+    /// Unsigned field value, None when absent or set to the base type's invalid marker
+    fn uint(&self, num: u8) -> Option<u64> {
+        let (field, bytes) = self.raw(num)?;
+        let size = base_type_size(field.base_type).min(bytes.len());
+        decode_uint(&bytes[..size], field.base_type, self.def.big_endian)
+    }
+
+    fn uint_array(&self, num: u8) -> Vec<u64> {
+        let Some((field, bytes)) = self.raw(num) else { return Vec::new() };
+        bytes.chunks_exact(base_type_size(field.base_type))
+            .filter_map(|chunk| decode_uint(chunk, field.base_type, self.def.big_endian))
+            .collect()
+    }
+}
+
+fn base_type_size(base_type: u8) -> usize {
+    match base_type & 0x1F {
+        0x03 | 0x04 | 0x0B => 2,
+        0x05 | 0x06 | 0x08 | 0x0C => 4,
+        0x09 | 0x0E | 0x0F | 0x10 => 8,
+        _ => 1,
+    }
+}
+
+fn decode_uint(bytes: &[u8], base_type: u8, big_endian: bool) -> Option<u64> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+    let value = if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    };
+    let invalid = match base_type & 0x1F {
+        0x0A | 0x0B | 0x0C | 0x10 => 0, // uint8z, uint16z, uint32z, uint64z
+        _ => u64::MAX >> (64 - 8 * bytes.len()),
+    };
+    (value != invalid).then_some(value)
+}
+
+fn fit_time(seconds: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(seconds as i64 + FIT_EPOCH_OFFSET, 0)
+}
+
+/// Turns decoded messages into samples; keeps the little state FIT relies on
+#[derive(Default)]
+struct Interpreter {
+    last_timestamp: Option<u64>,                       // FIT seconds
+    cycles_by_activity: std::collections::HashMap<u64, u64>, // cumulative monitoring steps
+    rr_minute: Option<DateTime<Utc>>,
+    rr_intervals: Vec<f64>,                           // ms, for the current minute
+    sleep_since: Option<DateTime<Utc>>,               // start of the open asleep stretch
+}
+
+impl Interpreter {
+    fn now(&self) -> Option<DateTime<Utc>> {
+        self.last_timestamp.and_then(fit_time)
+    }
+
+    fn handle(&mut self, msg: &Message, out: &mut Vec<WearableSample>) {
+        if let Some(ts) = msg.uint(FIELD_TIMESTAMP) {
+            self.last_timestamp = Some(ts);
+        }
+
+        match msg.def.global {
+            MESG_RECORD => {
+                if let (Some(at), Some(bpm)) = (self.now(), msg.uint(3)) {
+                    out.push(WearableSample::heart_rate(at, bpm as f64));
+                }
+            }
+            MESG_MONITORING => {
+                // timestamp_16: low 16 bits of the timestamp, relative to the last full one
+                if let (Some(ts16), Some(last)) = (msg.uint(26), self.last_timestamp) {
+                    self.last_timestamp = Some(last + ((ts16.wrapping_sub(last & 0xFFFF)) & 0xFFFF));
+                }
+                let Some(at) = self.now() else { return };
+                if let Some(bpm) = msg.uint(27) {
+                    out.push(WearableSample::heart_rate(at, bpm as f64));
+                }
+                // cycles are steps for walking (6) and running (1); cumulative per day
+                let activity = msg.uint(5).or_else(|| msg.uint(24).map(|v| v & 0x1F));
+                if let (Some(activity @ (1 | 6)), Some(cycles)) = (activity, msg.uint(3)) {
+                    let previous = self.cycles_by_activity.insert(activity, cycles);
+                    let delta = match previous {
+                        Some(p) if cycles >= p => cycles - p,
+                        _ => cycles, // first reading or daily reset
+                    };
+                    if delta > 0 {
+                        out.push(WearableSample::steps(at, delta as f64));
+                    }
+                }
+            }
+            MESG_MONITORING_HR_DATA => {
+                // current_day_resting_heart_rate, else the 7-day resting_heart_rate
+                if let (Some(at), Some(bpm)) = (self.now(), msg.uint(1).or_else(|| msg.uint(0))) {
+                    out.push(WearableSample::resting_heart_rate(at, bpm as f64));
+                }
+            }
+            MESG_HRV => {
+                let Some(at) = self.now() else { return };
+                let minute = at.with_second(0).unwrap_or(at);
+                if self.rr_minute != Some(minute) {
+                    self.flush_rr(out);
+                    self.rr_minute = Some(minute);
+                }
+                // RR intervals in 1/1000 s
+                self.rr_intervals.extend(msg.uint_array(0).into_iter().map(|rr| rr as f64));
+            }
+            MESG_SLEEP_LEVEL => {
+                let (Some(at), Some(level)) = (self.now(), msg.uint(0)) else { return };
+                let asleep = matches!(level, 2..=4); // light, deep, REM
+                match (asleep, self.sleep_since) {
+                    (true, None) => self.sleep_since = Some(at),
+                    (false, Some(start)) => {
+                        out.push(WearableSample::sleep(start, at));
+                        self.sleep_since = None;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// HRV as RMSSD of the RR intervals collected during one minute
+    fn flush_rr(&mut self, out: &mut Vec<WearableSample>) {
+        let intervals = std::mem::take(&mut self.rr_intervals);
+        let Some(minute) = self.rr_minute.take() else { return };
+        if intervals.len() < MIN_RR_INTERVALS_PER_MINUTE {
+            return;
+        }
+        let squares: f64 = intervals.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum();
+        let rmssd = (squares / (intervals.len() - 1) as f64).sqrt();
+        out.push(WearableSample::hrv(minute, rmssd.round()));
+    }
+}
+
+/// Byte source that knows how much of the current file's data section is left
+struct Input<R> {
+    reader: R,
+    remaining: u64,
+}
+
+impl<R: Read> Input<R> {
+    fn bytes(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        if (buf.len() as u64) > self.remaining {
+            return Err("Ficheiro FIT truncado ou corrompido.".to_string());
+        }
+        self.reader.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => "Ficheiro FIT truncado ou corrompido.".to_string(),
+            _ => format!("Falha ao ler ficheiro FIT: {}", e),
+        })?;
+        self.remaining -= buf.len() as u64;
+        Ok(())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let mut b = [0u8; 1];
+        self.bytes(&mut b)?;
+        Ok(b[0])
+    }
+}
+
+/// FIT's CRC-16 (polynomial 0xA001, computed a nibble at a time as in the SDK)
+fn crc16(mut crc: u16, bytes: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401,
+        0xA001, 0x6C00, 0x7800, 0xB401, 0x5000, 0x9C01, 0x8801, 0x4400,
+    ];
+    for &byte in bytes {
+        for nibble in [byte & 0x0F, byte >> 4] {
+            let tmp = TABLE[(crc & 0x0F) as usize];
+            crc = ((crc >> 4) & 0x0FFF) ^ tmp ^ TABLE[nibble as usize];
+        }
+    }
+    crc
+}
+
+/// Keeps the CRC of every byte read since the last reset
+struct Checksummed<R> {
+    reader: R,
+    crc: u16,
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.crc = crc16(self.crc, &buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Read the file header; None at a clean end of input (after the last chained file)
+fn read_header<R: Read>(reader: &mut R) -> Result<Option<u64>, String> {
+    let mut size = [0u8; 1];
+    if reader.read(&mut size).map_err(|e| e.to_string())? == 0 {
+        return Ok(None);
+    }
+    let header_size = size[0] as usize;
+    if header_size < 12 {
+        return Err("Cabeçalho FIT inválido.".to_string());
+    }
+    let mut header = vec![0u8; header_size - 1];
+    reader.read_exact(&mut header).map_err(|_| "Cabeçalho FIT truncado.".to_string())?;
+    if &header[7..11] != b".FIT" {
+        return Err("O ficheiro não é um ficheiro FIT.".to_string());
+    }
+    // 14-byte headers carry their own CRC; 0 means the writer left it out
+    if header_size >= 14 {
+        let stored = u16::from_le_bytes([header[11], header[12]]);
+        if stored != 0 && stored != crc16(crc16(0, &size), &header[..11]) {
+            return Err("Cabeçalho FIT corrompido (CRC inválido).".to_string());
+        }
+    }
+    Ok(Some(u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as u64))
+}
+
+pub fn parse<R, F>(reader: R, mut emit: F) -> Result<(), String>
+where
+    R: Read,
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    let mut files = 0;
+    let mut interpreter = Interpreter::default();
+    let mut samples = Vec::new();
+    let mut reader = Checksummed { reader, crc: 0 };
+
+    // The file CRC covers header and data; samples of a chained file are only
+    // emitted once its CRC matched, so a corrupt file imports nothing
+    loop {
+        reader.crc = 0;
+        let Some(data_size) = read_header(&mut reader)? else { break };
+        files += 1;
+        let mut input = Input { reader: &mut reader, remaining: data_size };
+        let mut definitions: [Option<Definition>; 16] = Default::default();
+        let mut data = Vec::new();
+
+        while input.remaining > 0 {
+            let header = input.byte()?;
+            let local = if header & 0x80 != 0 {
+                // Compressed timestamp header: 5-bit offset from the last full timestamp
+                let offset = (header & 0x1F) as u64;
+                if let Some(last) = interpreter.last_timestamp {
+                    let mut ts = (last & !0x1F) + offset;
+                    if offset < (last & 0x1F) {
+                        ts += 0x20;
+                    }
+                    interpreter.last_timestamp = Some(ts);
+                }
+                ((header >> 5) & 0x03) as usize
+            } else if header & 0x40 != 0 {
+                let mut fixed = [0u8; 5];
+                input.bytes(&mut fixed)?;
+                let big_endian = fixed[1] == 1;
+                let global = if big_endian {
+                    u16::from_be_bytes([fixed[2], fixed[3]])
+                } else {
+                    u16::from_le_bytes([fixed[2], fixed[3]])
+                };
+                let mut fields = Vec::with_capacity(fixed[4] as usize);
+                for _ in 0..fixed[4] {
+                    let mut f = [0u8; 3];
+                    input.bytes(&mut f)?;
+                    fields.push(FieldDef { num: f[0], size: f[1] as usize, base_type: f[2] });
+                }
+                let mut developer_size = 0;
+                if header & 0x20 != 0 {
+                    for _ in 0..input.byte()? {
+                        let mut f = [0u8; 3];
+                        input.bytes(&mut f)?;
+                        developer_size += f[1] as usize;
+                    }
+                }
+                definitions[(header & 0x0F) as usize] = Some(Definition { global, big_endian, fields, developer_size });
+                continue;
+            } else {
+                (header & 0x0F) as usize
+            };
+
+            let def = definitions[local].as_ref()
+                .ok_or_else(|| "Mensagem FIT sem definição.".to_string())?;
+            let size: usize = def.fields.iter().map(|f| f.size).sum();
+            data.resize(size + def.developer_size, 0);
+            input.bytes(&mut data)?;
+            interpreter.handle(&Message { def, data: &data[..size] }, &mut samples);
+        }
+
+        let expected = reader.crc;
+        let mut crc = [0u8; 2];
+        reader.read_exact(&mut crc).map_err(|_| "Ficheiro FIT sem CRC final.".to_string())?;
+        if u16::from_le_bytes(crc) != expected {
+            return Err("Ficheiro FIT corrompido (CRC não confere).".to_string());
+        }
+        for sample in samples.drain(..) {
+            emit(sample)?;
+        }
+    }
+
+    if files == 0 {
+        return Err("Ficheiro FIT vazio.".to_string());
+    }
+    interpreter.flush_rr(&mut samples);
+    for sample in samples {
+        emit(sample)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::wearables::tests::{fixture, summary};
+
+    fn collect(bytes: &[u8]) -> Result<Vec<WearableSample>, String> {
+        let mut samples = Vec::new();
+        parse(bytes, |s| {
+            samples.push(s);
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+
+    /// Re-seal `data` (the bytes after the 14-byte header) as a FIT file with valid CRCs
+    fn fit_file(data: &[u8]) -> Vec<u8> {
+        let mut file = vec![14, 0x20, 0x54, 0x08];
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(b".FIT");
+        file.extend(crc16(0, &file).to_le_bytes());
+        file.extend(data);
+        file.extend(crc16(0, &file).to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn crc_matches_the_sdk_check_value() {
+        assert_eq!(crc16(0, b"123456789"), 0xBB3D);
+    }
+
+    /// monitoring.fit: monitoring, record, sleep level, monitoring HR data and HRV messages
+    /// starting 2026-03-02T06:00:28Z, with a compressed-timestamp record and a
+    /// timestamp_16-only monitoring message
+    #[test]
+    fn definitions_and_data_messages_become_samples() {
+        let samples = collect(&fixture("monitoring.fit")).unwrap();
+        assert_eq!(summary(&samples), [
+            ("heart_rate", 60.0, "2026-03-02T06:00:28Z".to_string()),
+            ("steps", 100.0, "2026-03-02T06:00:28Z".to_string()),
+            ("heart_rate", 70.0, "2026-03-02T06:00:28Z".to_string()),
+            // Compressed header: offset 6 is below the last timestamp's 28, so it rolls over
+            ("heart_rate", 72.0, "2026-03-02T06:00:38Z".to_string()),
+            // timestamp_16 only; cycles are cumulative, so 150 → 50 more steps
+            ("heart_rate", 62.0, "2026-03-02T06:01:28Z".to_string()),
+            ("steps", 50.0, "2026-03-02T06:01:28Z".to_string()),
+            ("sleep", 30.0, "2026-03-02T06:02:08Z".to_string()),
+            ("resting_heart_rate", 52.0, "2026-03-02T06:33:48Z".to_string()),
+            // Ten RR intervals alternating 800/810 ms, flushed at the end
+            ("hrv", 10.0, "2026-03-02T06:33:00Z".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn chained_files_share_the_timestamp_state() {
+        let one = fixture("monitoring.fit");
+        let two = fit_file(&[0x40, 0, 0, 20, 0, 1, 3, 1, 0x02, 0x80 | 10, 75]);
+        let mut both = one.clone();
+        both.extend(&two);
+
+        let samples = collect(&both).unwrap();
+        assert_eq!(samples.len(), 10);
+        // 06:33:48 has offset 12 within its 32 s block; offset 10 lands in the next one.
+        // The open HRV minute is only flushed after the last file.
+        let tail = summary(&samples[8..]);
+        assert_eq!(tail[0], ("heart_rate", 75.0, "2026-03-02T06:34:18Z".to_string()));
+        assert_eq!(tail[1].0, "hrv");
+    }
+
+    #[test]
+    fn corrupt_bytes_fail_the_crc_and_emit_nothing() {
+        let good = fixture("monitoring.fit");
+
+        let mut data = good.clone();
+        data[40] ^= 0x01;
+        let mut emitted = 0;
+        let result = parse(&data[..], |_| {
+            emitted += 1;
+            Ok(())
+        });
+        assert_eq!(result.unwrap_err(), "Ficheiro FIT corrompido (CRC não confere).");
+        assert_eq!(emitted, 0);
+
+        let mut header = good.clone();
+        header[2] ^= 0x01;
+        assert_eq!(collect(&header).unwrap_err(), "Cabeçalho FIT corrompido (CRC inválido).");
+
+        // A header CRC of 0 is allowed (not computed by the writer)
+        let mut unchecked = good.clone();
+        unchecked[12..14].copy_from_slice(&[0, 0]);
+        let len = unchecked.len();
+        let crc = crc16(0, &unchecked[..len - 2]);
+        unchecked[len - 2..].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(collect(&unchecked).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error_at_every_length() {
+        let good = fixture("monitoring.fit");
+        for len in 0..good.len() {
+            assert!(collect(&good[..len]).is_err(), "{} of {} bytes", len, good.len());
+        }
+        assert!(collect(&good).is_ok());
+    }
+
+    #[test]
+    fn malformed_messages_are_errors_not_panics() {
+        // Data message for a local type that was never defined
+        assert_eq!(collect(&fit_file(&[0x01, 0x00])).unwrap_err(), "Mensagem FIT sem definição.");
+        // Field sizes pointing past the declared data size
+        assert!(collect(&fit_file(&[0x40, 0, 0, 20, 0, 1, 3, 200, 0x02, 0x00, 1])).is_err());
+        // Invalid markers and odd sizes decode to nothing instead of out-of-range reads
+        let odd = fit_file(&[0x40, 0, 0, 20, 0, 2, 253, 3, 0x86, 3, 1, 0x02, 0x00, 1, 2, 3, 0xFF]);
+        assert!(collect(&odd).unwrap().is_empty());
+        let mut not_fit = fit_file(&[]);
+        not_fit[8..12].copy_from_slice(b"FITX");
+        assert_eq!(collect(&not_fit).unwrap_err(), "O ficheiro não é um ficheiro FIT.");
+        assert_eq!(collect(b"").unwrap_err(), "Ficheiro FIT vazio.");
+    }
+}
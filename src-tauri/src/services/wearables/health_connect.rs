@@ -0,0 +1,250 @@
+use chrono::{DateTime, Utc};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserializer;
+use serde_json::Value;
+use std::fmt;
+use std::io::Read;
+use super::WearableSample;
+
+// Google Fit (Takeout "All Data" files: {"Data Points": [...]}) and Health Connect record
+// dumps ({"records": [...]} or a bare array). The outer array is walked element by element,
+// so only one data point is ever held as a `Value`.
+
+// Sleep stage codes shared by Google Fit and Health Connect
+const SLEEP_STAGES_ASLEEP: [i64; 4] = [2, 4, 5, 6]; // sleeping, light, deep, REM
+
+pub fn parse<R, F>(reader: R, mut emit: F) -> Result<(), String>
+where
+    R: Read,
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    let mut aborted = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let found = deserializer
+        .deserialize_any(Root { emit: &mut emit, aborted: &mut aborted })
+        .map_err(|e| match aborted.take() {
+            Some(reason) => reason,
+            None => format!("JSON inválido: {}", e),
+        })?;
+    if !found {
+        return Err("JSON sem \"Data Points\" (Google Fit) nem \"records\" (Health Connect).".to_string());
+    }
+    Ok(())
+}
+
+/// Top level: finds the data array, skipping everything else without buffering it
+struct Root<'a, F> {
+    emit: &'a mut F,
+    aborted: &'a mut Option<String>,
+}
+
+impl<'de, F> Visitor<'de> for Root<'_, F>
+where
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    type Value = bool; // whether a data array was found
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Google Fit or Health Connect export")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        let mut found = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "Data Points" || key == "records" {
+                map.next_value_seed(Items { emit: &mut *self.emit, aborted: &mut *self.aborted })?;
+                found = true;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(found)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<bool, A::Error> {
+        Items { emit: self.emit, aborted: self.aborted }.visit_seq(seq)?;
+        Ok(true)
+    }
+}
+
+struct Items<'a, F> {
+    emit: &'a mut F,
+    aborted: &'a mut Option<String>,
+}
+
+impl<'de, F> DeserializeSeed<'de> for Items<'_, F>
+where
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F> Visitor<'de> for Items<'_, F>
+where
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of data points")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut samples = Vec::new();
+        while let Some(item) = seq.next_element::<Value>()? {
+            samples.clear();
+            if item.get("dataTypeName").is_some() {
+                google_fit_point(&item, &mut samples);
+            } else {
+                health_connect_record(&item, &mut samples);
+            }
+            for sample in samples.drain(..) {
+                if let Err(reason) = (self.emit)(sample) {
+                    *self.aborted = Some(reason);
+                    return Err(de::Error::custom("import aborted"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn nanos(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let value = value?;
+    let n = value.as_i64().or_else(|| value.as_str()?.parse().ok())?;
+    Some(DateTime::from_timestamp_nanos(n))
+}
+
+fn instant(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    crate::services::time::parse_instant(value?.as_str()?, chrono_tz::UTC)
+}
+
+/// {"dataTypeName": "...", "startTimeNanos": .., "endTimeNanos": .., "fitValue": [{"value": {"fpVal"|"intVal": ..}}]}
+fn google_fit_point(point: &Value, out: &mut Vec<WearableSample>) {
+    let (Some(start), Some(end)) = (nanos(point.get("startTimeNanos")), nanos(point.get("endTimeNanos"))) else {
+        return;
+    };
+    let Some(value) = point.pointer("/fitValue/0/value") else { return };
+    let fp = value.get("fpVal").and_then(Value::as_f64);
+    let int = value.get("intVal").and_then(Value::as_i64);
+
+    match point["dataTypeName"].as_str().unwrap_or_default() {
+        "com.google.heart_rate.bpm" => out.extend(fp.map(|bpm| WearableSample::heart_rate(start, bpm))),
+        "com.google.step_count.delta" => out.extend(int.map(|n| WearableSample::steps(start, n as f64))),
+        "com.google.sleep.segment" if int.is_some_and(|s| SLEEP_STAGES_ASLEEP.contains(&s)) => {
+            out.push(WearableSample::sleep(start, end));
+        }
+        _ => {}
+    }
+}
+
+/// Health Connect records as serialized by export apps: the record type plus the
+/// Jetpack field names (`beatsPerMinute`, `heartRateVariabilityMillis`, ...)
+fn health_connect_record(record: &Value, out: &mut Vec<WearableSample>) {
+    let kind = record.get("type").or_else(|| record.get("recordType")).and_then(Value::as_str).unwrap_or_default();
+    let number = |key: &str| record.get(key).and_then(Value::as_f64);
+
+    match kind.trim_end_matches("Record") {
+        "HeartRate" => {
+            for sample in record.get("samples").and_then(Value::as_array).into_iter().flatten() {
+                if let (Some(at), Some(bpm)) = (instant(sample.get("time")), sample.get("beatsPerMinute").and_then(Value::as_f64)) {
+                    out.push(WearableSample::heart_rate(at, bpm));
+                }
+            }
+        }
+        "RestingHeartRate" => {
+            if let (Some(at), Some(bpm)) = (instant(record.get("time")), number("beatsPerMinute")) {
+                out.push(WearableSample::resting_heart_rate(at, bpm));
+            }
+        }
+        "HeartRateVariabilityRmssd" => {
+            if let (Some(at), Some(ms)) = (instant(record.get("time")), number("heartRateVariabilityMillis")) {
+                out.push(WearableSample::hrv(at, ms));
+            }
+        }
+        "Steps" => {
+            if let (Some(at), Some(count)) = (instant(record.get("startTime")), number("count")) {
+                out.push(WearableSample::steps(at, count));
+            }
+        }
+        "SleepSession" => {
+            let stages = record.get("stages").and_then(Value::as_array).filter(|s| !s.is_empty());
+            match stages {
+                Some(stages) => {
+                    for stage in stages {
+                        let asleep = stage.get("stage").and_then(Value::as_i64).is_some_and(|s| SLEEP_STAGES_ASLEEP.contains(&s));
+                        if let (true, Some(start), Some(end)) = (asleep, instant(stage.get("startTime")), instant(stage.get("endTime"))) {
+                            out.push(WearableSample::sleep(start, end));
+                        }
+                    }
+                }
+                // No stage data: the whole session counts as sleep
+                None => {
+                    if let (Some(start), Some(end)) = (instant(record.get("startTime")), instant(record.get("endTime"))) {
+                        out.push(WearableSample::sleep(start, end));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::wearables::tests::{fixture, summary};
+
+    fn collect(bytes: &[u8]) -> Result<Vec<WearableSample>, String> {
+        let mut samples = Vec::new();
+        parse(bytes, |s| {
+            samples.push(s);
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+
+    #[test]
+    fn health_connect_records_map_to_vitals() {
+        let samples = collect(&fixture("health_connect.json")).unwrap();
+        assert_eq!(summary(&samples), [
+            ("heart_rate", 66.0, "2026-03-02T10:00:00Z".to_string()),
+            ("heart_rate", 68.0, "2026-03-02T09:00:30Z".to_string()),
+            ("resting_heart_rate", 54.0, "2026-03-02T06:00:00Z".to_string()),
+            ("hrv", 42.0, "2026-03-02T06:05:00Z".to_string()),
+            ("steps", 1200.0, "2026-03-02T11:00:00Z".to_string()),
+            // Asleep stages only (light, REM), not the awake one
+            ("sleep", 180.0, "2026-03-01T23:20:00Z".to_string()),
+            ("sleep", 40.0, "2026-03-02T02:20:00Z".to_string()),
+            // A session without stages counts whole
+            ("sleep", 25.0, "2026-03-02T14:00:00Z".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn google_fit_points_map_to_vitals() {
+        let samples = collect(&fixture("google_fit.json")).unwrap();
+        assert_eq!(summary(&samples), [
+            ("heart_rate", 71.0, "2026-03-02T08:00:00Z".to_string()),
+            // Nanos as strings too
+            ("steps", 430.0, "2026-03-02T08:01:00Z".to_string()),
+            // The awake segment is skipped, deep sleep kept
+            ("sleep", 120.0, "2026-03-02T01:00:00Z".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn bare_arrays_are_records_and_other_json_is_an_error() {
+        let bare = br#"[{"type": "StepsRecord", "startTime": "2026-03-02T11:00:00Z", "count": 10}]"#;
+        assert_eq!(collect(bare).unwrap().len(), 1);
+        assert_eq!(
+            collect(br#"{"workouts": []}"#).unwrap_err(),
+            "JSON sem \"Data Points\" (Google Fit) nem \"records\" (Health Connect)."
+        );
+        assert!(collect(br#"{"records": [{"type": "StepsRecord""#).unwrap_err().starts_with("JSON inválido"));
+    }
+}
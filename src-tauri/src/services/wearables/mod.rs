@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+
+// Wearable exports → vitals. Every parser streams its input and hands samples to a
+// callback one at a time, so a multi-GB Apple Health export never sits in memory.
+
+pub mod apple_health;
+pub mod fit;
+pub mod health_connect;
+
+/// One normalized wearable measurement
+#[derive(Debug, Clone, PartialEq)]
+pub struct WearableSample {
+    pub vital_type: &'static str, // heart_rate | resting_heart_rate | hrv | steps | sleep
+    pub value: f64,
+    pub unit: &'static str,       // bpm | ms | steps | min
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl WearableSample {
+    pub fn heart_rate(at: DateTime<Utc>, bpm: f64) -> Self {
+        Self { vital_type: "heart_rate", value: bpm, unit: "bpm", recorded_at: at }
+    }
+
+    pub fn resting_heart_rate(at: DateTime<Utc>, bpm: f64) -> Self {
+        Self { vital_type: "resting_heart_rate", value: bpm, unit: "bpm", recorded_at: at }
+    }
+
+    pub fn hrv(at: DateTime<Utc>, ms: f64) -> Self {
+        Self { vital_type: "hrv", value: ms, unit: "ms", recorded_at: at }
+    }
+
+    pub fn steps(at: DateTime<Utc>, count: f64) -> Self {
+        Self { vital_type: "steps", value: count, unit: "steps", recorded_at: at }
+    }
+
+    /// A stretch of sleep starting at `start`, stored as its length in minutes
+    pub fn sleep(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let minutes = (end - start).num_seconds() as f64 / 60.0;
+        Self { vital_type: "sleep", value: minutes.round(), unit: "min", recorded_at: start }
+    }
+
+    /// Drop readings no sensor could produce (corrupt rows, unit mix-ups)
+    pub fn is_plausible(&self) -> bool {
+        let range = match self.vital_type {
+            "heart_rate" | "resting_heart_rate" => 20.0..=250.0,
+            "hrv" => 1.0..=500.0,
+            "steps" => 1.0..=100_000.0,
+            "sleep" => 1.0..=24.0 * 60.0,
+            _ => return false,
+        };
+        self.value.is_finite() && range.contains(&self.value)
+    }
+}
+
+/// Supported export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    AppleHealth,
+    HealthConnect,
+    Fit,
+}
+
+impl Format {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "apple_health" => Some(Self::AppleHealth),
+            "health_connect" | "google_fit" => Some(Self::HealthConnect),
+            "fit" | "garmin" => Some(Self::Fit),
+            _ => None,
+        }
+    }
+
+    /// Guess from the file extension
+    pub fn detect(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "xml" => Some(Self::AppleHealth),
+            "json" => Some(Self::HealthConnect),
+            "fit" => Some(Self::Fit),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AppleHealth => "apple_health",
+            Self::HealthConnect => "health_connect",
+            Self::Fit => "fit",
+        }
+    }
+}
+
+/// Stream `path` through the matching parser. `emit` returning an error aborts the import.
+pub fn parse_file<F>(path: &std::path::Path, format: Format, emit: F) -> Result<(), String>
+where
+    F: FnMut(WearableSample) -> Result<(), String>,
+{
+    let file = std::fs::File::open(path).map_err(|e| format!("Falha ao abrir {:?}: {}", path, e))?;
+    let reader = std::io::BufReader::with_capacity(1 << 16, file);
+    match format {
+        Format::AppleHealth => apple_health::parse(reader, emit),
+        Format::HealthConnect => health_connect::parse(reader, emit),
+        Format::Fit => fit::parse(reader, emit),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Path of an export under `tests/fixtures/wearables`
+    pub fn fixture_path(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wearables").join(name)
+    }
+
+    pub fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(fixture_path(name)).unwrap()
+    }
+
+    /// Every sample as (type, value, instant), in emission order
+    pub fn summary(samples: &[WearableSample]) -> Vec<(&'static str, f64, String)> {
+        samples.iter()
+            .map(|s| (s.vital_type, s.value, crate::services::time::format_utc(s.recorded_at)))
+            .collect()
+    }
+
+    #[test]
+    fn files_are_routed_to_their_parser_by_extension() {
+        for (name, expected) in [("export.xml", 5), ("health_connect.json", 8), ("google_fit.json", 3), ("monitoring.fit", 9)] {
+            let path = fixture_path(name);
+            let format = Format::detect(&path).unwrap();
+            let mut count = 0;
+            parse_file(&path, format, |sample| {
+                assert!(sample.is_plausible(), "{}: {:?}", name, sample);
+                count += 1;
+                Ok(())
+            }).unwrap();
+            assert_eq!(count, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn an_emit_error_aborts_the_import_with_its_reason() {
+        for name in ["export.xml", "health_connect.json", "monitoring.fit"] {
+            let path = fixture_path(name);
+            let result = parse_file(&path, Format::detect(&path).unwrap(), |_| Err("disco cheio".to_string()));
+            assert_eq!(result.unwrap_err(), "disco cheio", "{}", name);
+        }
+    }
+}
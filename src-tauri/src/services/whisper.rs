@@ -1,29 +1,27 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use anyhow::{Context, Result, bail};
-
-/// Whisper.cpp STT Service
-///
-/// Uses whisper.cpp CLI binary for speech-to-text transcription.
-/// The binary must be installed at one of the expected paths,
-/// and a GGML model must be available.
-///
-/// Setup:
-/// 1. Build whisper.cpp: `git clone https://github.com/ggerganov/whisper.cpp && cd whisper.cpp && make`
-/// 2. Download model: `bash ./models/download-ggml-model.sh base`
-/// 3. Set WHISPER_CPP_PATH env var to the binary location
-/// 4. Set WHISPER_MODEL_PATH env var to the model file (.bin)
+use crate::services::context::ToolPaths;
+
+// Whisper.cpp STT Service
+//
+// Uses whisper.cpp CLI binary for speech-to-text transcription.
+// The binary must be installed at one of the expected paths,
+// and a GGML model must be available.
+//
+// Setup:
+// 1. Build whisper.cpp: `git clone https://github.com/ggerganov/whisper.cpp && cd whisper.cpp && make`
+// 2. Download model: `bash ./models/download-ggml-model.sh base`
+// 3. Put the binary and model paths in settings.json (whisper_binary_path, whisper_model_path),
+//    or leave them empty to search ~/.holoself, Homebrew and PATH
 
 const DEFAULT_BINARY_NAMES: &[&str] = &["whisper-cli", "whisper", "main"];
 
 /// Find the whisper.cpp binary
-fn find_whisper_binary() -> Result<PathBuf> {
-    // 1. Check env var
-    if let Ok(path) = std::env::var("WHISPER_CPP_PATH") {
-        let p = PathBuf::from(&path);
-        if p.exists() {
-            return Ok(p);
-        }
+fn find_whisper_binary(configured: Option<&Path>) -> Result<PathBuf> {
+    // 1. Path recorded by setup
+    if let Some(p) = configured.filter(|p| p.exists()) {
+        return Ok(p.to_path_buf());
     }
 
     // 2. Check common locations
@@ -64,13 +62,10 @@ fn find_whisper_binary() -> Result<PathBuf> {
 }
 
 /// Find the GGML model file
-fn find_model() -> Result<PathBuf> {
-    // 1. Check env var
-    if let Ok(path) = std::env::var("WHISPER_MODEL_PATH") {
-        let p = PathBuf::from(&path);
-        if p.exists() {
-            return Ok(p);
-        }
+fn find_model(configured: Option<&Path>) -> Result<PathBuf> {
+    // 1. Path recorded by setup
+    if let Some(p) = configured.filter(|p| p.exists()) {
+        return Ok(p.to_path_buf());
     }
 
     // 2. Check common locations
@@ -109,9 +104,9 @@ fn find_model() -> Result<PathBuf> {
 ///
 /// Accepts WAV (16-bit, 16kHz mono preferred) or any ffmpeg-compatible format.
 /// Returns the transcribed text.
-pub fn transcribe(audio_path: &str, language: Option<&str>) -> Result<String> {
-    let binary = find_whisper_binary()?;
-    let model = find_model()?;
+pub fn transcribe(tools: &ToolPaths, audio_path: &str, language: Option<&str>) -> Result<String> {
+    let binary = find_whisper_binary(tools.whisper_binary.as_deref())?;
+    let model = find_model(tools.whisper_model.as_deref())?;
     let lang = language.unwrap_or("pt"); // Default to Portuguese
 
     let audio = PathBuf::from(audio_path);
@@ -146,14 +141,14 @@ pub fn transcribe(audio_path: &str, language: Option<&str>) -> Result<String> {
 
 /// Check if whisper.cpp is available
 #[allow(dead_code)]
-pub fn is_available() -> bool {
-    find_whisper_binary().is_ok() && find_model().is_ok()
+pub fn is_available(tools: &ToolPaths) -> bool {
+    find_whisper_binary(tools.whisper_binary.as_deref()).is_ok() && find_model(tools.whisper_model.as_deref()).is_ok()
 }
 
 /// Get whisper.cpp status info
-pub fn status() -> WhisperStatus {
-    let binary = find_whisper_binary();
-    let model = find_model();
+pub fn status(tools: &ToolPaths) -> WhisperStatus {
+    let binary = find_whisper_binary(tools.whisper_binary.as_deref());
+    let model = find_model(tools.whisper_model.as_deref());
 
     WhisperStatus {
         binary_found: binary.is_ok(),
@@ -170,3 +165,26 @@ pub struct WhisperStatus {
     pub model_found: bool,
     pub model_path: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_paths_win_when_they_exist() {
+        let dir = std::env::temp_dir().join(format!("holoself-whisper-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (binary, model) = (dir.join("whisper-cli"), dir.join("ggml-test.bin"));
+        std::fs::write(&binary, b"").unwrap();
+        std::fs::write(&model, b"").unwrap();
+
+        let tools = ToolPaths { whisper_binary: Some(binary.clone()), whisper_model: Some(model.clone()), ffmpeg: None };
+        let found = status(&tools);
+        assert_eq!(found.binary_path.as_deref(), binary.to_str());
+        assert_eq!(found.model_path.as_deref(), model.to_str());
+
+        // A stale path from settings falls through to the usual places
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_ne!(status(&tools).binary_path.as_deref(), binary.to_str());
+    }
+}
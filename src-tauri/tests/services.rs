@@ -0,0 +1,114 @@
+// The core services end to end, as the app and the CLI drive them: an in-memory database,
+// a frozen clock moved by hand and canned Gemini replies instead of the network.
+
+use chrono::Duration;
+use holoself_os_lib::commands::health::VitalEntry;
+use holoself_os_lib::services::agent::AgentService;
+use holoself_os_lib::services::context::testing::{context_in, gemini_reply, supplement};
+use holoself_os_lib::services::health::HealthService;
+use holoself_os_lib::services::labs::LabService;
+
+#[tokio::test]
+async fn a_morning_with_the_agent() {
+    // 08:30 in Lisbon
+    let (ctx, clock, _) = context_in("2026-07-01T07:30:00Z", "Europe/Lisbon", None);
+    let agent = AgentService::new(&ctx);
+    let health = HealthService::new(&ctx);
+
+    // Before any window opens there is nothing pending yet
+    let stats = health.daily_stats().await.unwrap();
+    assert_eq!((stats.protocols_taken, stats.protocols_due), (0, 2));
+
+    // The reminder comes with an action; confirming it logs the supplement at the frozen time
+    let reminder = agent.next_message(false).await.unwrap().unwrap();
+    assert!(reminder.text.starts_with("Winfit"));
+    let action = reminder.action.unwrap();
+    assert_eq!(agent.execute_action(action.action_type, action.payload, "agent").await.unwrap(), "Winfit registado com sucesso.");
+    let log = health.supplement_log("2026-07-01", "2026-07-01").await.unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].taken_at, "2026-07-01T07:30:00Z");
+
+    // Voice: "tomei a vitamina d3" suggests logging it, even while away
+    clock.advance(Duration::minutes(5));
+    agent.remember_voice_input("Tomei a Vitamina D3".to_string()).await.unwrap();
+    let reply = agent.next_message(true).await.unwrap().unwrap();
+    assert_eq!(reply.text, "Registando Vitamina D3 — 2000 IU.");
+    let action = reply.action.unwrap();
+    agent.execute_action(action.action_type, action.payload, "voice").await.unwrap();
+
+    let stats = health.daily_stats().await.unwrap();
+    assert_eq!((stats.protocols_taken, stats.protocols_due, stats.adherence_percent), (2, 2, 100));
+    assert_eq!(stats.voice_commands, 1);
+
+    // Delivered messages land on the timeline at the frozen instants
+    let query = health.timeline_query("2026-07-01", "2026-07-01", Some(&["agent_message".to_string()]), None, None, None).unwrap();
+    let page = health.timeline(query).await.unwrap();
+    let stamps: Vec<&str> = page.events.iter().map(|e| e.timestamp.as_str()).collect();
+    assert_eq!(stamps, ["2026-07-01T07:35:00Z", "2026-07-01T07:30:00Z"]);
+}
+
+#[tokio::test]
+async fn away_messages_wait_and_the_day_rolls_over_in_the_users_zone() {
+    // 23:10 in São Paulo is already the next day in UTC
+    let (ctx, clock, _) = context_in("2026-03-02T02:10:00Z", "America/Sao_Paulo", None);
+    let agent = AgentService::new(&ctx);
+    let health = HealthService::new(&ctx);
+
+    health.log_supplement(supplement("Magnésio Bisglicinato", "2026-03-01T22:15"), "ui").await.unwrap();
+    assert!(agent.next_message(true).await.unwrap().is_none());
+    let deferred: i64 = ctx.db.read(|db| db.query_row("SELECT COUNT(*) FROM deferred_messages", &[], |row| row.get(0))).await.unwrap();
+    assert_eq!(deferred, 1);
+
+    // Melatonin is the one still pending on 1 March local
+    let message = agent.next_message(false).await.unwrap().unwrap();
+    assert!(message.text.starts_with("Noxarem"));
+
+    // An hour later it is 2 March locally and nothing from the night before counts
+    clock.advance(Duration::hours(1));
+    let stats = health.daily_stats().await.unwrap();
+    assert_eq!((stats.protocols_taken, stats.protocols_due), (0, 0));
+}
+
+#[tokio::test]
+async fn lab_import_feeds_exam_predictions() {
+    let (ctx, clock, http) = context_in("2026-07-01T10:00:00Z", "Europe/Lisbon", Some("test-key"));
+    let health = HealthService::new(&ctx);
+    health.log_supplement(supplement("Winfit", "2026-06-15T09:00:00Z"), "ui").await.unwrap();
+    health.log_vital(VitalEntry {
+        id: None,
+        vital_type: "heart_rate".to_string(),
+        value: 58.0,
+        unit: "bpm".to_string(),
+        recorded_at: "2026-06-30T21:00".to_string(),
+        source: "manual".to_string(),
+    }, "ui").await.unwrap();
+
+    let before: Vec<String> = health.predict_exams().await.unwrap().into_iter().map(|e| e.exam_type).collect();
+    assert!(before.contains(&"zinc_copper_panel".to_string()));
+    assert!(before.contains(&"vitamin_d_panel".to_string()));
+
+    http.reply(Ok(gemini_reply(r#"{"patient_name": null, "date": "2026-06-28", "lab": "Synlab", "markers": [
+        {"marker": "Zinc", "value": 80, "unit": "ug/dL", "reference_range": "60-120", "status": "normal"},
+        {"marker": "Vitamin D", "value": 31, "unit": "ng/mL", "reference_range": "30-100", "status": "normal"}
+    ]}"#)));
+    let pdf = std::env::temp_dir().join(format!("holoself-it-labs-{}.pdf", std::process::id()));
+    std::fs::write(&pdf, b"%PDF-1.4\n%%EOF\n").unwrap();
+    let report = LabService::new(&ctx).import_pdf(pdf.to_str().unwrap()).await;
+    std::fs::remove_file(&pdf).unwrap();
+    assert_eq!(report.unwrap().markers.len(), 2);
+
+    // Fresh zinc and vitamin D results push those panels out; saved exams are audited
+    let after = health.predict_exams().await.unwrap();
+    assert!(after.iter().all(|e| e.exam_type != "zinc_copper_panel" && e.exam_type != "vitamin_d_panel"));
+    let thyroid = after.into_iter().find(|e| e.exam_type == "thyroid_panel").unwrap();
+    assert_eq!(thyroid.scheduled_date, "2026-07-15");
+    health.store_exam(thyroid, "cli").await.unwrap();
+
+    // Three months on, vitamin D is due again
+    clock.advance(Duration::days(92));
+    let later: Vec<String> = health.predict_exams().await.unwrap().into_iter().map(|e| e.exam_type).collect();
+    assert!(later.contains(&"vitamin_d_panel".to_string()));
+
+    let buckets = health.timeline_buckets("2026-06-01", "2026-07-31", "week", None, None).await.unwrap();
+    assert!(!buckets.is_empty());
+}
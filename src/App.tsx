@@ -18,8 +18,9 @@ import { usePresenceDetector } from "./hooks/usePresenceDetector";
 import { usePostureMonitor } from "./hooks/usePostureMonitor";
 import { useHealthContext, type ProactiveAlert } from "./hooks/useHealthContext";
 import { useDailySummary } from "./hooks/useDailySummary";
+import { useActivityLog } from "./hooks/useActivityLog";
 import { SetupWizard } from "./components/setup/SetupWizard";
-import type { OcrResult } from "./types/health";
+import type { FhirImportSummary, OcrResult, WelcomeBackDigest, WearableImportSummary } from "./types/health";
 
 export default function App() {
   const [isDragging, setIsDragging] = useState(false);
@@ -122,7 +123,10 @@ export default function App() {
     onReturn: (awayMs) => {
       const mins = Math.floor(awayMs / 60_000);
       if (mins >= 1) {
-        toast(`Bem-vindo de volta. Passaram ${mins} minuto${mins !== 1 ? "s" : ""}.`, "info");
+        // Under Tauri the backend sends a welcome-back digest instead
+        if (typeof window.__TAURI__ === "undefined") {
+          toast(`Bem-vindo de volta. Passaram ${mins} minuto${mins !== 1 ? "s" : ""}.`, "info");
+        }
         breakCountRef.current += 1;
         focusStartRef.current = Date.now(); // reset focus timer
       }
@@ -140,6 +144,69 @@ export default function App() {
     },
   });
 
+  // Persist sessions, breaks and posture for daily stats
+  useActivityLog(presence, posture);
+
+  // 20-20-20 reminders from the backend eye-care engine
+  useEffect(() => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    let unlisten: (() => void) | undefined;
+    import("@tauri-apps/api/event").then(({ listen }) =>
+      listen<{ text: string }>("eye-care://reminder", (event) => toast(event.payload.text, "info"))
+        .then((fn) => { unlisten = fn; })
+    );
+    return () => unlisten?.();
+  }, [toast]);
+
+  // Welcome-back digest: one message with everything deferred while away
+  useEffect(() => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    let unlisten: (() => void) | undefined;
+    import("@tauri-apps/api/event").then(({ listen }) =>
+      listen<WelcomeBackDigest>("presence://welcome-back", (event) => {
+        toast(event.payload.text, "info");
+        if (autoSpeak) speakText(event.payload.text);
+      }).then((fn) => { unlisten = fn; })
+    );
+    return () => unlisten?.();
+  }, [toast, autoSpeak, speakText]);
+
+  // Wearable exports and FHIR bundles dropped on the window. The native drop event carries real paths,
+  // which the HTML drop handler below doesn't get.
+  useEffect(() => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    let unlisten: (() => void) | undefined;
+    import("@tauri-apps/api/event").then(({ listen }) =>
+      listen<{ paths: string[] }>("tauri://drag-drop", async (event) => {
+        // FHIR bundles (as written by the export: holoself-fhir-*.json) restore the record
+        const isFhir = (p: string) => /fhir[^/\\]*\.json$/i.test(p);
+        const fhirFiles = event.payload.paths.filter(isFhir);
+        const wearableFiles = event.payload.paths.filter((p) => !isFhir(p) && /\.(xml|json|fit)$/i.test(p));
+        if (fhirFiles.length === 0 && wearableFiles.length === 0) return;
+        const { invoke } = await import("@tauri-apps/api/core");
+        for (const path of fhirFiles) {
+          try {
+            const summary = await invoke<FhirImportSummary>("import_fhir_bundle", { path });
+            const total = summary.vitals + summary.lab_results + summary.supplements + summary.exams;
+            toast(`FHIR: ${total} registos importados (${summary.duplicates} já existiam)`, "success");
+          } catch (err) {
+            toast(`Erro na importação FHIR: ${err instanceof Error ? err.message : String(err)}`, "error");
+          }
+        }
+        for (const path of wearableFiles) {
+          toast("A importar dados do wearable...", "info");
+          try {
+            const summary = await invoke<WearableImportSummary>("import_wearable_file", { path });
+            toast(`${summary.inserted} registos importados (${summary.duplicates} já existiam)`, "success");
+          } catch (err) {
+            toast(`Erro na importação: ${err instanceof Error ? err.message : String(err)}`, "error");
+          }
+        }
+      }).then((fn) => { unlisten = fn; })
+    );
+    return () => unlisten?.();
+  }, [toast]);
+
   // === PROACTIVE ALERTS (Feature 4) ===
   const handleAlert = useCallback((alert: ProactiveAlert) => {
     toast(alert.message, alert.priority === 1 ? "error" : "info");
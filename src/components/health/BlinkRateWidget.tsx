@@ -11,8 +11,28 @@ import { IconEye } from "../hud/Icons";
  * Blink rate > 20/min may indicate eye strain or stress.
  *
  * Privacy-first: all processing is local, no data sent anywhere.
+ * Blink timestamps are flushed to the local backend each minute for low-blink
+ * detection and eye-care stats.
  */
 
+const FLUSH_MS = 60_000;
+
+async function flushBlinks(from: number, to: number, events: number[]) {
+  if (typeof window.__TAURI__ === "undefined") return;
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("log_blink_events", {
+      input: {
+        from: new Date(from).toISOString(),
+        to: new Date(to).toISOString(),
+        events: events.map((t) => new Date(t).toISOString()),
+      },
+    });
+  } catch (err) {
+    console.warn("[HoloSelf] log_blink_events failed:", err);
+  }
+}
+
 interface BlinkStats {
   blinksPerMinute: number;
   status: "normal" | "low" | "high";
@@ -30,9 +50,13 @@ export function BlinkRateWidget() {
   const lastBlinkTimeRef = useRef(0);
   const trackingRef = useRef(false);
   const intervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
+  const blinkEventsRef = useRef<number[]>([]);
+  const windowStartRef = useRef<number | null>(null); // null while calibrating
 
   const stopTracking = useCallback(() => {
     trackingRef.current = false;
+    windowStartRef.current = null;
+    blinkEventsRef.current = [];
     if (streamRef.current) {
       streamRef.current.getTracks().forEach((t) => t.stop());
       streamRef.current = null;
@@ -107,6 +131,7 @@ export function BlinkRateWidget() {
           const avg = brightnessHistory.reduce((a, b) => a + b, 0) / brightnessHistory.length;
           blinkThreshold = avg * 0.03; // 3% brightness drop = blink
           calibrating = false;
+          windowStartRef.current = Date.now();
         }
 
         // Detect blink: sudden brightness drop in eye region
@@ -114,6 +139,7 @@ export function BlinkRateWidget() {
         if (diff > blinkThreshold && Date.now() - lastBlinkTimeRef.current > 200) {
           blinkCountRef.current++;
           lastBlinkTimeRef.current = Date.now();
+          blinkEventsRef.current.push(lastBlinkTimeRef.current);
         }
 
         prevBrightness = brightness;
@@ -122,6 +148,22 @@ export function BlinkRateWidget() {
       // Run detection at ~15fps
       intervalRef.current = setInterval(detectBlink, 66);
 
+      // Send whole wall-clock minutes to the backend; the partial minute waits for the next flush
+      const flushInterval = setInterval(() => {
+        const from = windowStartRef.current;
+        if (!trackingRef.current) {
+          clearInterval(flushInterval);
+          return;
+        }
+        if (from === null) return;
+        const to = Math.floor(Date.now() / 60_000) * 60_000;
+        if (to <= from) return;
+        const events = blinkEventsRef.current.filter((t) => t < to);
+        blinkEventsRef.current = blinkEventsRef.current.filter((t) => t >= to);
+        windowStartRef.current = to;
+        flushBlinks(from, to, events);
+      }, FLUSH_MS);
+
       // Update stats every 5 seconds
       const statsInterval = setInterval(() => {
         if (!trackingRef.current) {
@@ -6,7 +6,8 @@ interface VitDData {
   uv_index: number;
   optimal_minutes: number;
   d3_iu_supplement: number;
-  best_window: string;
+  best_window: string | null;
+  max_solar_elevation: number;
   note: string;
 }
 
@@ -16,7 +17,18 @@ export function VitaminDWidget() {
   useEffect(() => {
     fetchVitD();
     const interval = setInterval(fetchVitD, 2 * 60 * 60 * 1000);
-    return () => clearInterval(interval);
+
+    // Location / skin type changes apply immediately
+    let unlisten: (() => void) | undefined;
+    if (typeof window.__TAURI__ !== "undefined") {
+      import("@tauri-apps/api/event").then(({ listen }) =>
+        listen("settings://changed", fetchVitD).then((fn) => { unlisten = fn; })
+      );
+    }
+    return () => {
+      clearInterval(interval);
+      unlisten?.();
+    };
   }, []);
 
   const fetchVitD = async () => {
@@ -32,6 +44,7 @@ export function VitaminDWidget() {
           optimal_minutes: 25,
           d3_iu_supplement: 2000,
           best_window: "11:00 - 14:00",
+          max_solar_elevation: 52,
           note: "UV moderado. 25 min exposição.",
         });
       }
@@ -73,7 +86,7 @@ export function VitaminDWidget() {
         )}
       </div>
       <div style={{ marginTop: 4, fontSize: 8, color: "var(--holo-text-dim)", borderLeft: `1px solid ${uvColor}`, paddingLeft: 4 }}>
-        {data.best_window}
+        {data.best_window ?? `Sem janela UVB (sol máx. ${data.max_solar_elevation.toFixed(0)}°)`}
       </div>
     </div>
   );
@@ -12,6 +12,7 @@ export function AgentPanel() {
   const { message, isLoading } = useAgentStore();
   const [actionStatus, setActionStatus] = useState<string | null>(null);
   const [executing, setExecuting] = useState(false);
+  const [canUndo, setCanUndo] = useState(false);
   const toast = useToastStore((s) => s.add);
 
   if (isLoading) {
@@ -37,13 +38,18 @@ export function AgentPanel() {
           payload: action.payload,
         });
         setActionStatus(result);
+        setCanUndo(true);
         toast(result, "success");
       } else {
         const name = (action.payload as Record<string, string>).name ?? "Item";
         setActionStatus(`${name} registado (dev mode)`);
         toast(`${name} registado`, "success");
       }
-      setTimeout(() => setActionStatus(null), 3000);
+      // Longer window while the undo button is offered
+      setTimeout(() => {
+        setActionStatus(null);
+        setCanUndo(false);
+      }, 8000);
     } catch (err) {
       const errMsg = err instanceof Error ? err.message : String(err);
       setActionStatus(`Erro: ${errMsg}`);
@@ -54,6 +60,18 @@ export function AgentPanel() {
     }
   };
 
+  const handleUndo = async () => {
+    setCanUndo(false);
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const result = await invoke<{ message: string }>("undo_last_agent_action");
+      setActionStatus(result.message);
+      toast(result.message, "info");
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao desfazer", "error");
+    }
+  };
+
   return (
     <div className="slide-up">
       <div className="holo-card" style={{ padding: "12px 16px" }}>
@@ -87,6 +105,11 @@ export function AgentPanel() {
         {actionStatus ? (
           <p style={{ marginTop: 10, fontSize: 11, color: "var(--holo-accent)" }}>
             {actionStatus}
+            {canUndo && (
+              <button className="holo-btn" onClick={handleUndo} style={{ marginLeft: 8, fontSize: 10, padding: "2px 8px" }}>
+                Desfazer
+              </button>
+            )}
           </p>
         ) : message.action ? (
           <button
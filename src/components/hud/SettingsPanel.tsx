@@ -3,6 +3,7 @@ import { useToastStore } from "./Toast";
 import { IconX, IconEye, IconEyeOff } from "./Icons";
 
 interface AppSettings {
+  version?: number;
   gemini_api_key: string;
   cartesia_api_key: string;
   cartesia_voice_id: string;
@@ -11,6 +12,28 @@ interface AppSettings {
   longitude: number;
   timezone: string;
   sleep_anchor_hour: number;
+  exposed_body_area: string;
+  keyboard_cadence: boolean;
+  eye_care_reminders: boolean;
+  ingest_api_enabled: boolean;
+  ingest_api_port: number;
+  backup_enabled: boolean;
+  backup_interval_hours: number;
+  backup_keep: number;
+  backup_encrypt: boolean;
+  backup_dir: string;
+  whisper_binary_path?: string;
+  whisper_model_path?: string;
+  ffmpeg_path?: string;
+  corrupt_backup?: string | null;
+}
+
+interface IngestClient {
+  id: number;
+  name: string;
+  created_at: string;
+  last_used_at: string | null;
+  revoked_at: string | null;
 }
 
 const DEFAULT_SETTINGS: AppSettings = {
@@ -22,8 +45,44 @@ const DEFAULT_SETTINGS: AppSettings = {
   longitude: -9.14,
   timezone: "Europe/Lisbon",
   sleep_anchor_hour: 23,
+  exposed_body_area: "face_arms",
+  keyboard_cadence: false,
+  eye_care_reminders: true,
+  ingest_api_enabled: false,
+  ingest_api_port: 47830,
+  backup_enabled: false,
+  backup_interval_hours: 24,
+  backup_keep: 7,
+  backup_encrypt: false,
+  backup_dir: "",
 };
 
+interface BackupInfo {
+  path: string;
+  created_at: string | null;
+  reason: string;
+  size_bytes: number;
+  encrypted: boolean;
+}
+
+interface BackupStatus {
+  directory: string;
+  passphrase_set: boolean;
+  last_error: string | null;
+  backups: BackupInfo[];
+}
+
+interface TabularImportReport {
+  dry_run: boolean;
+  columns: string[];
+  mapping: Record<string, string>;
+  read: number;
+  inserted: number;
+  duplicates: number;
+  invalid: number;
+  errors: { line: number; message: string }[];
+}
+
 interface SettingsPanelProps {
   visible: boolean;
   onClose: () => void;
@@ -46,13 +105,16 @@ export function SettingsPanel({ visible, onClose }: SettingsPanelProps) {
         const { invoke } = await import("@tauri-apps/api/core");
         const s = await invoke<AppSettings>("get_settings");
         setSettings(s);
+        if (s.corrupt_backup) {
+          toast(`settings.json estava danificado e foi guardado em ${s.corrupt_backup}; a usar valores padrão`, "error");
+        }
       }
     } catch (err) {
       console.error("Failed to load settings:", err);
     } finally {
       setLoading(false);
     }
-  }, []);
+  }, [toast]);
 
   useEffect(() => {
     if (visible) {
@@ -75,12 +137,47 @@ export function SettingsPanel({ visible, onClose }: SettingsPanelProps) {
       }
     } catch (err) {
       console.error("Failed to save settings:", err);
-      toast("Erro ao salvar configurações", "error");
+      // Validation errors come back as a readable PT message
+      toast(typeof err === "string" ? err : "Erro ao salvar configurações", "error");
     } finally {
       setSaving(false);
     }
   };
 
+  const generateReport = async (language: "pt" | "en") => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const summary = await invoke<{ path: string; pages: number }>("generate_health_report", { language });
+      toast(`Relatório (${summary.pages} pág.) gravado em ${summary.path}`, "success");
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao gerar relatório", "error");
+    }
+  };
+
+  const exportFhir = async () => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const summary = await invoke<{ path: string }>("export_fhir_bundle");
+      toast(`Exportado para ${summary.path}`, "success");
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao exportar", "error");
+    }
+  };
+
+  const exportRecords = async (format: "csv" | "ndjson") => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const files = await invoke<{ table: string; rows: number }[]>("export_records", { format });
+      const rows = files.reduce((sum, f) => sum + f.rows, 0);
+      toast(`${files.length} ficheiros (${rows} linhas) gravados em Transferências`, "success");
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao exportar", "error");
+    }
+  };
+
   const update = <K extends keyof AppSettings>(key: K, value: AppSettings[K]) => {
     setSettings((prev) => ({ ...prev, [key]: value }));
   };
@@ -208,6 +305,7 @@ export function SettingsPanel({ visible, onClose }: SettingsPanelProps) {
                   />
 
                   <p style={hintStyle}>
+                    As chaves ficam no cofre do sistema (keychain) e só são mostradas mascaradas.
                     Variáveis de ambiente (GEMINI_API_KEY, CARTESIA_API_KEY) têm prioridade sobre estes campos.
                   </p>
                 </div>
@@ -246,9 +344,145 @@ export function SettingsPanel({ visible, onClose }: SettingsPanelProps) {
                     style={inputStyle}
                   />
 
+                  <label style={{ ...labelStyle, marginTop: 16 }}>Área Exposta ao Sol</label>
+                  <select
+                    value={settings.exposed_body_area}
+                    onChange={(e) => update("exposed_body_area", e.target.value)}
+                    style={inputStyle}
+                  >
+                    <option value="face_hands">Rosto e mãos</option>
+                    <option value="face_arms">Rosto e braços</option>
+                    <option value="arms_legs">Braços e pernas</option>
+                    <option value="swimsuit">Fato de banho</option>
+                  </select>
+
                   <p style={hintStyle}>
                     Latitude/longitude são usadas para calcular o índice UV e recomendação de Vitamina D.
                   </p>
+
+                  <label style={{ ...labelStyle, marginTop: 16, display: "flex", alignItems: "center", gap: 8 }}>
+                    <input
+                      type="checkbox"
+                      checked={settings.keyboard_cadence}
+                      onChange={(e) => update("keyboard_cadence", e.target.checked)}
+                    />
+                    Ritmo de escrita em todo o sistema (Linux)
+                  </label>
+                  <p style={hintStyle}>
+                    Conta apenas quantas teclas são premidas por minuto para detetar fadiga. Nenhuma tecla
+                    ou texto é gravado. Requer acesso a /dev/input (grupo "input").
+                  </p>
+
+                  <label style={{ ...labelStyle, marginTop: 16, display: "flex", alignItems: "center", gap: 8 }}>
+                    <input
+                      type="checkbox"
+                      checked={settings.eye_care_reminders}
+                      onChange={(e) => update("eye_care_reminders", e.target.checked)}
+                    />
+                    Lembretes 20-20-20 (descanso visual)
+                  </label>
+
+                  <label style={{ ...labelStyle, marginTop: 16, display: "flex", alignItems: "center", gap: 8 }}>
+                    <input
+                      type="checkbox"
+                      checked={settings.ingest_api_enabled}
+                      onChange={(e) => update("ingest_api_enabled", e.target.checked)}
+                    />
+                    API local para sensores (balanças, tensiómetros)
+                  </label>
+                  {settings.ingest_api_enabled && (
+                    <>
+                      <label style={{ ...labelStyle, marginTop: 12 }}>Porta (127.0.0.1)</label>
+                      <input
+                        type="number"
+                        min={1024}
+                        max={65535}
+                        value={settings.ingest_api_port}
+                        onChange={(e) => update("ingest_api_port", Number(e.target.value))}
+                        style={inputStyle}
+                      />
+                      <IngestClients />
+                    </>
+                  )}
+                  <p style={hintStyle}>
+                    Aceita POST /v1/vitals e /v1/supplements em JSON com "Authorization: Bearer &lt;token&gt;".
+                    Só escuta neste computador e regista cada escrita por cliente.
+                  </p>
+
+                  <label style={{ ...labelStyle, marginTop: 16 }}>Dados</label>
+                  <div style={{ display: "flex", gap: 8, flexWrap: "wrap" }}>
+                    <button className="holo-btn" onClick={() => generateReport("pt")} style={{ fontSize: 11, padding: "6px 12px" }}>
+                      Relatório médico (PDF)
+                    </button>
+                    <button className="holo-btn" onClick={() => generateReport("en")} style={{ fontSize: 11, padding: "6px 12px" }}>
+                      Report (EN)
+                    </button>
+                    <button className="holo-btn" onClick={exportFhir} style={{ fontSize: 11, padding: "6px 12px" }}>
+                      Exportar registo (FHIR R4)
+                    </button>
+                    <button className="holo-btn" onClick={() => exportRecords("csv")} style={{ fontSize: 11, padding: "6px 12px" }}>
+                      Exportar CSV
+                    </button>
+                    <button className="holo-btn" onClick={() => exportRecords("ndjson")} style={{ fontSize: 11, padding: "6px 12px" }}>
+                      Exportar NDJSON
+                    </button>
+                  </div>
+                  <p style={hintStyle}>
+                    Os ficheiros são gravados em Transferências. Arraste um Bundle FHIR para a janela para o importar.
+                  </p>
+                  <RecordImport />
+
+                  <label style={{ ...labelStyle, marginTop: 16, display: "flex", alignItems: "center", gap: 8 }}>
+                    <input
+                      type="checkbox"
+                      checked={settings.backup_enabled}
+                      onChange={(e) => update("backup_enabled", e.target.checked)}
+                    />
+                    Cópias de segurança automáticas
+                  </label>
+                  {settings.backup_enabled && (
+                    <div style={{ display: "flex", gap: 8 }}>
+                      <div style={{ flex: 1 }}>
+                        <label style={{ ...labelStyle, marginTop: 12 }}>A cada (horas)</label>
+                        <input
+                          type="number"
+                          min={1}
+                          max={720}
+                          value={settings.backup_interval_hours}
+                          onChange={(e) => update("backup_interval_hours", Number(e.target.value))}
+                          style={inputStyle}
+                        />
+                      </div>
+                      <div style={{ flex: 1 }}>
+                        <label style={{ ...labelStyle, marginTop: 12 }}>Manter</label>
+                        <input
+                          type="number"
+                          min={1}
+                          max={100}
+                          value={settings.backup_keep}
+                          onChange={(e) => update("backup_keep", Number(e.target.value))}
+                          style={inputStyle}
+                        />
+                      </div>
+                    </div>
+                  )}
+                  <label style={{ ...labelStyle, marginTop: 12, display: "flex", alignItems: "center", gap: 8 }}>
+                    <input
+                      type="checkbox"
+                      checked={settings.backup_encrypt}
+                      onChange={(e) => update("backup_encrypt", e.target.checked)}
+                    />
+                    Cifrar cópias com palavra-passe
+                  </label>
+                  <label style={{ ...labelStyle, marginTop: 12 }}>Pasta (vazio = pasta da aplicação)</label>
+                  <input
+                    type="text"
+                    value={settings.backup_dir}
+                    onChange={(e) => update("backup_dir", e.target.value)}
+                    placeholder="/caminho/absoluto"
+                    style={inputStyle}
+                  />
+                  <Backups encrypt={settings.backup_encrypt} />
                 </div>
               )}
 
@@ -301,6 +535,280 @@ export function SettingsPanel({ visible, onClose }: SettingsPanelProps) {
   );
 }
 
+/** Tokens for the local ingestion API: create (shown once) and revoke */
+function IngestClients() {
+  const [clients, setClients] = useState<IngestClient[]>([]);
+  const [name, setName] = useState("");
+  const [newToken, setNewToken] = useState<string | null>(null);
+  const toast = useToastStore((s) => s.add);
+
+  const loadClients = useCallback(async () => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      setClients(await invoke<IngestClient[]>("list_ingest_clients"));
+    } catch (err) {
+      console.error("Failed to load API clients:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadClients();
+  }, [loadClients]);
+
+  const createClient = async () => {
+    if (!name.trim() || typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const created = await invoke<{ client: IngestClient; token: string }>("create_ingest_client", { name });
+      setNewToken(created.token);
+      setName("");
+      loadClients();
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao criar token", "error");
+    }
+  };
+
+  const revokeClient = async (id: number) => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("revoke_ingest_client", { id });
+      loadClients();
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao revogar token", "error");
+    }
+  };
+
+  return (
+    <div style={{ marginTop: 12 }}>
+      <label style={labelStyle}>Clientes</label>
+      {clients.map((c) => (
+        <div key={c.id} style={{ display: "flex", justifyContent: "space-between", fontSize: 11, padding: "4px 0", opacity: c.revoked_at ? 0.4 : 0.8 }}>
+          <span>
+            {c.name}
+            {c.last_used_at ? ` · usado ${new Date(c.last_used_at).toLocaleString()}` : " · nunca usado"}
+          </span>
+          {!c.revoked_at && (
+            <button className="holo-btn" onClick={() => revokeClient(c.id)} style={{ fontSize: 10, padding: "2px 8px" }}>
+              Revogar
+            </button>
+          )}
+        </div>
+      ))}
+      <div style={{ display: "flex", gap: 8, marginTop: 8 }}>
+        <input
+          type="text"
+          value={name}
+          onChange={(e) => setName(e.target.value)}
+          placeholder="ex. balança-wifi"
+          style={inputStyle}
+        />
+        <button className="holo-btn" onClick={createClient} style={{ fontSize: 11, padding: "6px 12px" }}>
+          Criar token
+        </button>
+      </div>
+      {newToken && (
+        <p style={{ ...hintStyle, color: "var(--holo-primary)", wordBreak: "break-all" }}>
+          Copie agora, não volta a ser mostrado: <code>{newToken}</code>
+        </p>
+      )}
+    </div>
+  );
+}
+
+/** Spreadsheet import: validate first (dry run), adjust the column mapping, then import */
+function RecordImport() {
+  const [table, setTable] = useState("supplements");
+  const [path, setPath] = useState("");
+  const [mapping, setMapping] = useState("");
+  const [report, setReport] = useState<TabularImportReport | null>(null);
+  const toast = useToastStore((s) => s.add);
+
+  const run = async (dryRun: boolean) => {
+    if (!path.trim() || typeof window.__TAURI__ === "undefined") return;
+    // "campo=coluna" pairs, comma separated
+    const pairs = mapping.split(",").map((p) => p.split("=").map((x) => x.trim())).filter((p) => p.length === 2 && p[0] && p[1]);
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const result = await invoke<TabularImportReport>("import_records", {
+        table,
+        path,
+        mapping: pairs.length ? Object.fromEntries(pairs) : null,
+        dryRun,
+      });
+      setReport(result);
+      if (!dryRun) toast(`${result.inserted} linhas importadas`, "success");
+    } catch (err) {
+      setReport(null);
+      toast(typeof err === "string" ? err : "Erro ao importar", "error");
+    }
+  };
+
+  return (
+    <div style={{ marginTop: 12 }}>
+      <label style={labelStyle}>Importar CSV / NDJSON</label>
+      <div style={{ display: "flex", gap: 8 }}>
+        <select value={table} onChange={(e) => setTable(e.target.value)} style={{ ...inputStyle, width: 140 }}>
+          <option value="supplements">Suplementos</option>
+          <option value="vitals">Sinais vitais</option>
+          <option value="lab_results">Análises</option>
+          <option value="agent_memory">Memória do agente</option>
+        </select>
+        <input type="text" value={path} onChange={(e) => setPath(e.target.value)} placeholder="/caminho/ficheiro.csv" style={inputStyle} />
+      </div>
+      <input
+        type="text"
+        value={mapping}
+        onChange={(e) => setMapping(e.target.value)}
+        placeholder="Mapeamento opcional: name=Nome, taken_at=Data"
+        style={{ ...inputStyle, marginTop: 8 }}
+      />
+      <div style={{ display: "flex", gap: 8, marginTop: 8 }}>
+        <button className="holo-btn" onClick={() => run(true)} style={{ fontSize: 11, padding: "6px 12px" }}>
+          Validar
+        </button>
+        <button className="holo-btn" onClick={() => run(false)} style={{ fontSize: 11, padding: "6px 12px" }}>
+          Importar
+        </button>
+      </div>
+      {report && (
+        <div style={{ ...hintStyle, marginTop: 8 }}>
+          {report.dry_run ? "Simulação: " : ""}
+          {report.read} linhas · {report.inserted} novas · {report.duplicates} repetidas · {report.invalid} inválidas
+          <br />
+          Colunas: {Object.entries(report.mapping).map(([field, column]) => `${field}←${column}`).join(", ")}
+          {report.errors.slice(0, 10).map((e) => (
+            <div key={e.line} style={{ color: "var(--holo-warn)" }}>
+              Linha {e.line}: {e.message}
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
+
+/** Manual backups, the stored passphrase and restore from the list or a path */
+function Backups({ encrypt }: { encrypt: boolean }) {
+  const [status, setStatus] = useState<BackupStatus | null>(null);
+  const [passphrase, setPassphrase] = useState("");
+  const [restorePath, setRestorePath] = useState("");
+  const [busy, setBusy] = useState(false);
+  const toast = useToastStore((s) => s.add);
+
+  const loadStatus = useCallback(async () => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      setStatus(await invoke<BackupStatus>("get_backup_status"));
+    } catch (err) {
+      console.error("Failed to load backups:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadStatus();
+  }, [loadStatus]);
+
+  const savePassphrase = async () => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("set_backup_passphrase", { passphrase });
+      setPassphrase("");
+      toast(passphrase ? "Palavra-passe guardada" : "Palavra-passe removida", "success");
+      loadStatus();
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao guardar palavra-passe", "error");
+    }
+  };
+
+  const createBackup = async () => {
+    if (typeof window.__TAURI__ === "undefined") return;
+    setBusy(true);
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const info = await invoke<BackupInfo>("create_backup");
+      toast(`Cópia gravada em ${info.path}`, "success");
+      loadStatus();
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao criar cópia", "error");
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const restoreBackup = async (path: string) => {
+    if (!path.trim() || typeof window.__TAURI__ === "undefined") return;
+    if (!window.confirm("Substituir os dados atuais por esta cópia? O estado atual é guardado antes.")) return;
+    setBusy(true);
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const summary = await invoke<{ created_at: string }>("restore_backup", {
+        path,
+        passphrase: passphrase || null,
+      });
+      toast(`Cópia de ${new Date(summary.created_at).toLocaleString()} reposta`, "success");
+      setRestorePath("");
+      loadStatus();
+    } catch (err) {
+      toast(typeof err === "string" ? err : "Erro ao repor cópia", "error");
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  return (
+    <div style={{ marginTop: 12 }}>
+      {encrypt && (
+        <div style={{ display: "flex", gap: 8, marginBottom: 8 }}>
+          <input
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            placeholder={status?.passphrase_set ? "Palavra-passe guardada" : "Palavra-passe (mín. 8)"}
+            style={inputStyle}
+          />
+          <button className="holo-btn" onClick={savePassphrase} style={{ fontSize: 11, padding: "6px 12px" }}>
+            Guardar
+          </button>
+        </div>
+      )}
+      <button className="holo-btn" onClick={createBackup} disabled={busy} style={{ fontSize: 11, padding: "6px 12px" }}>
+        {busy ? "A trabalhar..." : "Criar cópia agora"}
+      </button>
+      {status?.last_error && (
+        <p style={{ ...hintStyle, color: "var(--holo-warn)" }}>Última cópia automática falhou: {status.last_error}</p>
+      )}
+      {status?.backups.slice(0, 5).map((b) => (
+        <div key={b.path} style={{ display: "flex", justifyContent: "space-between", fontSize: 11, padding: "4px 0", opacity: 0.8 }}>
+          <span>
+            {b.created_at ? new Date(b.created_at).toLocaleString() : b.path}
+            {` · ${b.reason} · ${(b.size_bytes / 1024).toFixed(0)} KB`}
+            {b.encrypted ? " · cifrada" : ""}
+          </span>
+          <button className="holo-btn" onClick={() => restoreBackup(b.path)} disabled={busy} style={{ fontSize: 10, padding: "2px 8px" }}>
+            Repor
+          </button>
+        </div>
+      ))}
+      <div style={{ display: "flex", gap: 8, marginTop: 8 }}>
+        <input
+          type="text"
+          value={restorePath}
+          onChange={(e) => setRestorePath(e.target.value)}
+          placeholder="Repor a partir de ficheiro .hsbak"
+          style={inputStyle}
+        />
+        <button className="holo-btn" onClick={() => restoreBackup(restorePath)} disabled={busy} style={{ fontSize: 11, padding: "6px 12px" }}>
+          Repor
+        </button>
+      </div>
+      {status && <p style={hintStyle}>Cópias em {status.directory}</p>}
+    </div>
+  );
+}
+
 function skinTypeLabel(type: number): string {
   const labels: Record<number, string> = {
     1: "Muito clara, sempre queima",
@@ -0,0 +1,63 @@
+import { useEffect, useRef } from "react";
+import type { PresenceState } from "./usePresenceDetector";
+import type { PostureState } from "./usePostureMonitor";
+
+const HEARTBEAT_MS = 60_000; // presence heartbeat + one posture sample per minute
+
+async function invokeTauri<T>(cmd: string, args: Record<string, unknown>): Promise<T | null> {
+  if (typeof window.__TAURI__ === "undefined") return null;
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<T>(cmd, args);
+  } catch (err) {
+    console.warn(`[HoloSelf] ${cmd} failed:`, err);
+    return null;
+  }
+}
+
+/**
+ * useActivityLog — feeds the backend presence state machine (which records sessions,
+ * breaks and eye rests) and persists a posture sample per minute.
+ * Only reports while the camera is available.
+ */
+export function useActivityLog(presence: PresenceState, posture: PostureState) {
+  const postureRef = useRef(posture);
+  postureRef.current = posture;
+  const presentRef = useRef(presence.isPresent);
+  presentRef.current = presence.isPresent;
+
+  // Presence transitions → backend state machine
+  useEffect(() => {
+    if (!presence.cameraAvailable) return;
+    invokeTauri("report_presence", {
+      event: presence.isPresent
+        ? { state: "present", at: new Date().toISOString(), source: "webcam" }
+        // The detector reports absence after a threshold; the user really left at lastSeenAt
+        : { state: "away", at: new Date(presence.lastSeenAt).toISOString(), source: "webcam" },
+    });
+    // lastSeenAt changes every frame while present; only transitions matter here
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [presence.isPresent, presence.cameraAvailable]);
+
+  // Heartbeat (keeps idle detection quiet) + posture sample, once a minute while present
+  useEffect(() => {
+    if (!presence.cameraAvailable) return;
+    const interval = setInterval(() => {
+      if (!presentRef.current) return;
+      const now = new Date().toISOString();
+      invokeTauri("report_presence", { event: { state: "present", at: now, source: "webcam" } });
+
+      const p = postureRef.current;
+      if (!p.headPosition) return;
+      invokeTauri("log_posture_samples", {
+        samples: [{
+          recorded_at: now,
+          score: p.score,
+          head_x: p.headPosition.x,
+          head_y: p.headPosition.y,
+        }],
+      });
+    }, HEARTBEAT_MS);
+    return () => clearInterval(interval);
+  }, [presence.cameraAvailable]);
+}
@@ -8,10 +8,13 @@ interface DailySummaryOptions {
 
 interface DayStats {
   adherencePercent: number;
+  protocolsTaken: number;
+  protocolsDue: number;
   breaksTaken: number;
-  avgPostureScore: number;
+  avgPostureScore: number | null; // null when no posture samples today
   focusMinutes: number;
   voiceCommands: number;
+  eyeCareCompliance: number | null; // % of 20-20-20 reminders followed, null if none
 }
 
 /**
@@ -33,10 +36,13 @@ export function useDailySummary(options: DailySummaryOptions) {
       // Fallback for browser dev
       return {
         adherencePercent: 80,
+        protocolsTaken: 4,
+        protocolsDue: 5,
         breaksTaken: 3,
         avgPostureScore: 72,
         focusMinutes: 360,
         voiceCommands: 8,
+        eyeCareCompliance: 75,
       };
     }
 
@@ -55,19 +61,22 @@ export function useDailySummary(options: DailySummaryOptions) {
     parts.push(`Relatório do dia.`);
 
     // Adherence
+    const protocols = `${stats.protocolsTaken} de ${stats.protocolsDue} suplementos`;
     if (stats.adherencePercent >= 90) {
-      parts.push(`Aderência excelente: ${stats.adherencePercent}%.`);
+      parts.push(`Aderência excelente: ${stats.adherencePercent}%, ${protocols}.`);
     } else if (stats.adherencePercent >= 70) {
-      parts.push(`Aderência boa: ${stats.adherencePercent}%.`);
+      parts.push(`Aderência boa: ${stats.adherencePercent}%, ${protocols}.`);
     } else {
-      parts.push(`Aderência precisa melhorar: ${stats.adherencePercent}%.`);
+      parts.push(`Aderência precisa melhorar: ${stats.adherencePercent}%, ${protocols}.`);
     }
 
     // Breaks
     parts.push(`Fizeste ${stats.breaksTaken} pausa${stats.breaksTaken !== 1 ? "s" : ""} hoje.`);
 
-    // Posture
-    if (stats.avgPostureScore >= 80) {
+    // Posture (skipped when the camera collected no samples)
+    if (stats.avgPostureScore === null) {
+      // nothing to report
+    } else if (stats.avgPostureScore >= 80) {
       parts.push(`Postura média ótima: ${stats.avgPostureScore} de 100.`);
     } else if (stats.avgPostureScore >= 60) {
       parts.push(`Postura média razoável: ${stats.avgPostureScore} de 100. Tenta manter as costas retas amanhã.`);
@@ -80,6 +89,11 @@ export function useDailySummary(options: DailySummaryOptions) {
     const mins = stats.focusMinutes % 60;
     parts.push(`Tempo de foco total: ${hours}h${mins > 0 ? ` ${mins}min` : ""}.`);
 
+    // Eye care
+    if (stats.eyeCareCompliance !== null) {
+      parts.push(`Pausas visuais 20-20-20 cumpridas: ${stats.eyeCareCompliance}%.`);
+    }
+
     // Voice
     if (stats.voiceCommands > 0) {
       parts.push(`Usaste ${stats.voiceCommands} comando${stats.voiceCommands !== 1 ? "s" : ""} de voz.`);
@@ -96,7 +110,7 @@ export function useDailySummary(options: DailySummaryOptions) {
     if (!enabled || firedTodayRef.current) return;
 
     const now = new Date();
-    const todayStr = now.toISOString().slice(0, 10); // "YYYY-MM-DD"
+    const todayStr = now.toLocaleDateString("sv-SE"); // local "YYYY-MM-DD" (toISOString is UTC)
 
     if (now.getHours() === targetHour && now.getMinutes() < 5) {
       // Prevent multiple fires on same day
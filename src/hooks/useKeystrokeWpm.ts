@@ -10,11 +10,30 @@ export interface WpmSnapshot {
 const WINDOW_MS = 60_000; // 1-minute rolling window
 const CHARS_PER_WORD = 5;
 const SAMPLE_INTERVAL = 5_000; // recalc every 5s
+const SNAPSHOT_EVERY = 12;     // send one snapshot per minute (12 × 5s)
 
-// Fatigue thresholds (relative to personal baseline)
-const MILD_DROP = 0.15;    // 15% below baseline
-const MODERATE_DROP = 0.30; // 30% below baseline
-const HIGH_DROP = 0.50;     // 50% below baseline
+interface FatigueAssessment {
+  level: WpmSnapshot["fatigue"];
+  calibrating: boolean;
+}
+
+/** Send the minute's mean WPM and get fatigue vs. the personal weekday/hour baseline (backend). */
+async function syncWithBackend(wpm: number): Promise<WpmSnapshot["fatigue"]> {
+  if (typeof window.__TAURI__ === "undefined") return "none";
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    if (wpm > 0) {
+      await invoke("record_wpm_snapshots", {
+        snapshots: [{ recorded_at: new Date().toISOString(), wpm, source: "keyboard" }],
+      });
+    }
+    const assessment = await invoke<FatigueAssessment>("get_typing_fatigue");
+    return assessment.calibrating ? "none" : assessment.level;
+  } catch (err) {
+    console.warn("[HoloSelf] WPM sync failed:", err);
+    return "none";
+  }
+}
 
 export function useKeystrokeWpm(): WpmSnapshot {
   const [snapshot, setSnapshot] = useState<WpmSnapshot>({
@@ -25,10 +44,10 @@ export function useKeystrokeWpm(): WpmSnapshot {
   });
 
   const keystrokesRef = useRef<number[]>([]); // timestamps
-  const baselineRef = useRef<number>(0);       // peak WPM (first 5 min)
   const historyRef = useRef<number[]>([]);     // WPM samples for trend
   const sessionStartRef = useRef<number>(Date.now());
-  const calibratingRef = useRef(true);
+  const fatigueRef = useRef<WpmSnapshot["fatigue"]>("none");
+  const tickRef = useRef(0);
 
   const handleKeydown = useCallback((e: KeyboardEvent) => {
     // Only count actual character keys (ignore modifiers, nav, function keys)
@@ -53,16 +72,6 @@ export function useKeystrokeWpm(): WpmSnapshot {
       // Session time
       const sessionMinutes = Math.round((now - sessionStartRef.current) / 60_000);
 
-      // Calibration: first 5 minutes, track peak WPM
-      if (calibratingRef.current) {
-        if (currentWpm > baselineRef.current) {
-          baselineRef.current = currentWpm;
-        }
-        if (sessionMinutes >= 5 && baselineRef.current > 0) {
-          calibratingRef.current = false;
-        }
-      }
-
       // Track history for trend (keep last 12 samples = 1 min)
       historyRef.current.push(currentWpm);
       if (historyRef.current.length > 12) {
@@ -81,16 +90,17 @@ export function useKeystrokeWpm(): WpmSnapshot {
         else if (avgSecond < avgFirst * 0.9) trend = "declining";
       }
 
-      // Fatigue detection (only after calibration)
-      let fatigue: WpmSnapshot["fatigue"] = "none";
-      if (!calibratingRef.current && baselineRef.current > 0 && currentWpm > 0) {
-        const drop = 1 - (currentWpm / baselineRef.current);
-        if (drop >= HIGH_DROP) fatigue = "high";
-        else if (drop >= MODERATE_DROP) fatigue = "moderate";
-        else if (drop >= MILD_DROP) fatigue = "mild";
+      // Once a minute: persist the mean WPM, refresh fatigue from the backend baseline
+      tickRef.current += 1;
+      if (tickRef.current % SNAPSHOT_EVERY === 0) {
+        const minuteWpm = Math.round(hist.reduce((a, b) => a + b, 0) / hist.length);
+        syncWithBackend(minuteWpm).then((fatigue) => {
+          fatigueRef.current = fatigue;
+          setSnapshot((s) => ({ ...s, fatigue }));
+        });
       }
 
-      setSnapshot({ wpm: currentWpm, trend, fatigue, sessionMinutes });
+      setSnapshot({ wpm: currentWpm, trend, fatigue: fatigueRef.current, sessionMinutes });
     }, SAMPLE_INTERVAL);
 
     return () => {
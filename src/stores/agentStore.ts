@@ -91,7 +91,12 @@ export const useAgentStore = create<AgentState>((set, get) => ({
       }
 
       const { invoke } = await import("@tauri-apps/api/core");
-      const message = await invoke<AgentMessage>("get_agent_message");
+      const message = await invoke<AgentMessage | null>("get_agent_message");
+      if (!message) {
+        // User is away — the backend keeps it for the welcome-back digest
+        set({ isLoading: false });
+        return;
+      }
       const msgId = Date.now(); // Unique ID per fetch
       set({ message, isLoading: false });
 
@@ -11,11 +11,34 @@ export interface SupplementEntry {
 
 export interface VitalEntry {
   id?: number;
-  vital_type: "heart_rate" | "hrv" | "sleep_score" | "stress_level" | "wpm";
+  vital_type:
+    | "heart_rate" | "resting_heart_rate" | "hrv" | "sleep" | "sleep_score"
+    | "steps" | "stress_level" | "wpm" | "blink_rate";
   value: number;
   unit: string;
   recorded_at: string; // ISO 8601
-  source: "manual" | "wearable" | "webcam";
+  source: "manual" | "wearable" | "webcam" | "api";
+}
+
+/** Result of importing a FHIR R4 Bundle */
+export interface FhirImportSummary {
+  vitals: number;
+  lab_results: number;
+  supplements: number;
+  exams: number;
+  duplicates: number;
+  ignored: number;
+  errors: string[];
+}
+
+/** Result of importing an Apple Health / Health Connect / Garmin FIT file */
+export interface WearableImportSummary {
+  format: "apple_health" | "health_connect" | "fit";
+  read: number;
+  inserted: number;
+  duplicates: number;
+  skipped: number;
+  by_type: Record<string, number>;
 }
 
 export interface ClinicalResult {
@@ -36,21 +59,52 @@ export interface OcrResult {
 
 export interface AgentMessage {
   text: string;
-  category: "supplement_reminder" | "health_insight" | "calm_nudge" | "schedule";
+  category: "supplement_reminder" | "health_insight" | "calm_nudge" | "schedule" | "sun_exposure" | "fatigue" | "eye_care";
   priority: "low" | "medium" | "high";
   action: AgentAction | null;
 }
 
+/** Delivered once when the user returns: what the agent held back while they were away */
+export interface WelcomeBackDigest {
+  away_minutes: number;
+  text: string;
+  messages: { created_at: string; category: string; priority: string; text: string }[];
+}
+
 export interface AgentAction {
   action_type: "log_supplement" | "schedule_exam" | "open_timer";
   payload: Record<string, unknown>;
 }
 
-export interface HealthTimelineEntry {
+export type TimelineEventType =
+  | "supplement"
+  | "vital"
+  | "lab_result"
+  | "exam"
+  | "voice"
+  | "agent_message"
+  | "fatigue_episode"
+  | "low_blink_period";
+
+/** One `get_health_timeline` event; `payload` fields depend on `event_type` */
+export interface TimelineEvent {
+  key: string;
   timestamp: string;
-  event_type: string;
-  label: string;
-  value?: number;
+  event_type: TimelineEventType;
+  payload: Record<string, unknown>;
+}
+
+export interface TimelinePage {
+  events: TimelineEvent[];
+  next_cursor: string | null;
+}
+
+/** One local day or ISO week from `get_timeline_buckets` */
+export interface TimelineBucket {
+  start: string;
+  end: string;
+  counts: Partial<Record<TimelineEventType, number>>;
+  vitals: { vital_type: string; unit: string; count: number; mean: number; min: number; max: number; sum: number }[];
 }
 
 export interface HealthScheduleEntry {
@@ -66,12 +120,61 @@ export interface VitaminDRecommendation {
   uv_index: number;
   skin_type: number;
   latitude: number;
+  longitude: number;
   optimal_minutes: number;
-  best_window: string;
+  best_window: string | null;
+  solar_noon: string;
+  max_solar_elevation: number;
   d3_iu_supplement: number;
+  intake: IntakeBudget | null;
+  response: VitaminDResponseModel | null;
   note: string;
 }
 
+export interface VitaminDResponseModel {
+  ng_ml_per_1000_iu: number;
+  lab_pairs: number;
+  latest_ng_ml: number;
+  latest_date: string;
+  latest_daily_iu: number;
+}
+
+export interface SunExposureEntry {
+  id?: number;
+  started_at: string; // ISO 8601
+  duration_minutes: number;
+  body_area: "face_hands" | "face_arms" | "arms_legs" | "swimsuit";
+  spf?: number;
+  uv_index?: number; // filled from forecast cache when omitted
+  estimated_iu?: number;
+}
+
+export interface IntakeBudget {
+  window_days: number;
+  sun_iu: number;
+  supplement_iu: number;
+  daily_average_iu: number;
+  daily_target_iu: number;
+  deficit_iu: number;
+}
+
+export interface ExposureSlot {
+  start: string; // local HH:MM
+  end: string;
+  uv_index: number;
+  cloud_cover: number;
+  minutes: number;
+  estimated_iu: number;
+}
+
+export interface DailyExposurePlan {
+  date: string; // YYYY-MM-DD
+  max_uv_index: number;
+  slots: ExposureSlot[];
+  best_estimated_iu: number;
+  nudge: string;
+}
+
 export interface ScheduledExam {
   exam_type: string;
   reason: string;
@@ -87,3 +190,36 @@ export interface SystemStatus {
   timezone: string;
   uptime_seconds: number;
 }
+
+export interface TypingFatigue {
+  wpm: number;
+  baseline_wpm: number | null;
+  drop_percent: number | null; // % below the weekday/hour baseline
+  score: number;               // 0-100
+  level: "none" | "mild" | "moderate" | "high";
+  calibrating: boolean;
+}
+
+export interface FatigueEpisode {
+  started_at: string;
+  ended_at: string;
+  duration_minutes: number;
+  peak_level: "moderate" | "high";
+  mean_wpm: number;
+  baseline_wpm: number;
+  mean_drop_percent: number;
+}
+
+export type RecordType = "supplements" | "vitals" | "lab_results" | "health_schedule";
+
+export interface AuditEntry {
+  id: number;
+  changed_at: string;
+  record_type: RecordType;
+  record_id: number | null; // null for bulk imports
+  action: "insert" | "update" | "delete" | "restore" | "import" | "undo";
+  source: "ui" | "voice" | "agent" | "import" | "cli";
+  before: Record<string, unknown> | null;
+  after: Record<string, unknown> | null;
+  undone_at: string | null;
+}